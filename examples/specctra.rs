@@ -14,7 +14,7 @@ fn main() -> Result<(), std::io::Error> {
     let invoker = Invoker::new(Autorouter::new(board).unwrap());
 
     let mut file = File::create("example.ses").unwrap();
-    design.write_ses(invoker.autorouter().board(), &mut file);
+    design.write_ses(invoker.autorouter().board(), 1.0, &mut file);
 
     let filename = design.get_name();
     Ok(())
@@ -21,7 +21,9 @@ tr-menu-place-place-via = Platziere DuKo
 tr-menu-edit-remove-bands = Entferne Bänder
 tr-menu-inspect-compare-detours = Vergleiche Umwege
 tr-menu-route-options-presort-by-pairwise-detours = Nach paarweisen Umwegen vorsortieren
+tr-menu-route-options-ratline-ordering-pairwise-detours = Nach paarweisen Umwegen vorsortieren
 tr-menu-route-options-squeeze-through-under-bands = Presse unter Bänder durch
+tr-menu-route-options-place-vias = Durchkontaktierungen setzen
 tr-menu-route-options-wrap-around-bands = Wickle um Bänder
 tr-menu-view-zoom-to-fit = Einpassen
 tr-menu-view-show-ratsnest = Zeige Ratsnest
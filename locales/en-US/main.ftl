@@ -4,6 +4,7 @@ tr-menu-file = File
 tr-menu-file-open = Open
 tr-menu-open-specctra-session-file = Specctra session file
 tr-menu-file-export-session-file = Export Session File
+tr-menu-file-max-arc-deviation = Max Arc Deviation
 tr-menu-file-import-history = Import History
 tr-menu-file-export-history = Export History
 tr-menu-file-quit = Quit
@@ -25,10 +26,15 @@ tr-menu-view-frame-timestep = Frame Timestep
 
 tr-menu-place = Place
 tr-menu-place-place-via = Place Via
+tr-menu-place-move-component = Move Component
 
 tr-menu-route = Route
+tr-menu-route-route-track = Route Track
 tr-menu-route-autoroute = Autoroute
+tr-menu-route-tune-length = Tune Length
 tr-menu-route-routed-band-width = Routed Band Width
+tr-menu-route-target-length = Target Length
+tr-menu-route-length-tolerance = Length Tolerance
 
 tr-menu-help = Help
 tr-menu-help-online-documentation = Online Documentation
@@ -36,19 +42,30 @@ tr-menu-help-online-documentation = Online Documentation
 # Misnamed tag, TODO fix this.
 tr-menu-options = Options
 
-tr-menu-route-options-presort-by-pairwise-detours = Presort by Pairwise Detours
+tr-menu-route-options-ratline-ordering = Ratline Ordering
+tr-menu-route-options-ratline-ordering-unordered = As Selected
+tr-menu-route-options-ratline-ordering-shortest-first = Shortest First
+tr-menu-route-options-ratline-ordering-longest-first = Longest First
+tr-menu-route-options-ratline-ordering-net-class-priority = By Net Class Priority
+tr-menu-route-options-ratline-ordering-least-congested-first = Least Congested First
+tr-menu-route-options-ratline-ordering-pairwise-detours = By Pairwise Detours
+tr-menu-route-options-rip-up-and-reroute = Rip up and Reroute
+tr-menu-route-options-skip-failed-ratlines = Skip Failed Ratlines
 
 ## Continuously applied, so use imperfective aspect if possible, e.g. in Polish
 ## it should be "przeciskaj pod taśmami" instead of "przeciśnij pod taśmami".
 
 tr-menu-route-options-squeeze-through-under-bands = Squeeze through under Bands
+tr-menu-route-options-place-vias = Place Vias
 tr-menu-route-options-wrap-around-bands = Wrap around Bands
+tr-menu-route-options-shove-bands = Shove Bands
 
 ##
 
 tr-menu-inspect = Inspect
 tr-menu-inspect-compare-detours = Compare Detours
 tr-menu-inspect-measure-length = Measure Length
+tr-menu-inspect-check-design-rules = Check Design Rules
 
 tr-menu-preferences = Preferences
 tr-menu-preferences-set-language = Set Language
@@ -20,6 +20,7 @@ tr-menu-view-show-origin-destination = Pokaż pochodzenie i destynację
 tr-menu-options = Opcje
 tr-menu-route-options-wrap-around-bands = Owiń wokół taśm
 tr-menu-route-options-squeeze-through-under-bands = Ściśnij pod taśmami
+tr-menu-route-options-place-vias = Umieszczaj przelotki
 tr-menu-edit = Edytuj
 tr-menu-view = Widok
 tr-menu-place = Umieść
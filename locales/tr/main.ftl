@@ -24,7 +24,9 @@ tr-menu-help = Yardım
 tr-menu-help-online-documentation = Çevrimiçi Belgeler
 tr-menu-options = Seçenekler
 tr-menu-route-options-presort-by-pairwise-detours = Pairwise Detours ile Ön Sırala
+tr-menu-route-options-ratline-ordering-pairwise-detours = Pairwise Detours ile Ön Sırala
 tr-menu-route-options-squeeze-through-under-bands = Bantların Altından Sıkışarak Geç
+tr-menu-route-options-place-vias = Via Yerleştir
 tr-menu-route-options-wrap-around-bands = Bantların çevresine sar
 tr-menu-inspect = İncele
 tr-menu-inspect-compare-detours = Tali Yolları Karşılaştır
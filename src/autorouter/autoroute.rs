@@ -1,19 +1,38 @@
 //! Manages autorouting of ratlines in a layout, tracking status and processed
 //! routing steps.
 
-use std::ops::ControlFlow;
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::ControlFlow,
+};
 
+use derive_getters::Getters;
+use geo::EuclideanDistance;
 use petgraph::graph::EdgeIndex;
 
 use crate::{
     board::mesadata::AccessMesadata,
-    drawing::{band::BandTermsegIndex, graph::PrimitiveIndex},
-    geometry::primitive::PrimitiveShape,
-    router::{navcord::NavcordStepper, navmesh::Navmesh, route::RouteStepper, Router},
+    drawing::{
+        band::BandTermsegIndex,
+        dot::FixedDotIndex,
+        graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex},
+        loose::LooseIndex,
+        primitive::MakePrimitiveShape,
+    },
+    geometry::{
+        compound::ManageCompounds,
+        primitive::PrimitiveShape,
+        shape::{AccessShape, MeasureLength},
+    },
+    graph::MakeRef,
+    router::{
+        couple::Coupler, navcord::NavcordStepper, navmesh::Navmesh, route::RouteStepper, Router,
+    },
     stepper::Step,
 };
 
 use super::{
+    diff_pair,
     invoker::{GetGhosts, GetMaybeNavcord, GetMaybeNavmesh, GetObstacles},
     Autorouter, AutorouterError, AutorouterOptions,
 };
@@ -26,10 +45,49 @@ pub enum AutorouteContinueStatus {
     Routed(BandTermsegIndex),
 }
 
+/// The outcome of an autoroute: which ratlines got routed and which failed, and why.
+#[derive(Debug, Clone, Default, Getters)]
+pub struct AutorouteReport {
+    /// The ratlines that were routed, each with the terminating seg of its band.
+    routed_ratlines: Vec<(EdgeIndex<usize>, BandTermsegIndex)>,
+    /// The ratlines that failed to route, each with the error it failed with.
+    failed_ratlines: Vec<(EdgeIndex<usize>, AutorouterError)>,
+    /// The summed length of the bands of the routed ratlines, through vias included.
+    total_length: f64,
+    /// The ratlines of differential pairs that were routed together, each pair with the skew
+    /// between the lengths of its bands.
+    pair_skews: Vec<(EdgeIndex<usize>, EdgeIndex<usize>, f64)>,
+}
+
 /// Manages the autorouting process across multiple ratlines.
+///
+/// When a ratline fails to route, the bands of the ratlines routed earlier that blocked it may
+/// be ripped up, as configured in the [`AutorouterOptions`], so that the failed ratline is
+/// routed first and the ripped-up ones are rerouted after it.
+///
+/// Once a ratline of a net that forms a differential pair is routed, the ratline of the other
+/// net of the pair that joins the nearest pins is taken out of the queue and drawn alongside
+/// the band just routed, within the limits set by the [`AutorouterOptions`]. If it can't be drawn
+/// there, the band just routed is ripped up and the pair is routed the other way round.
 pub struct AutorouteExecutionStepper {
-    /// An iterator over ratlines that tracks which segments still need to be routed.
-    ratlines_iter: Box<dyn Iterator<Item = EdgeIndex<usize>>>,
+    /// The ratlines that still need to be routed, in the order they will be.
+    ratlines: VecDeque<EdgeIndex<usize>>,
+    /// The ratlines routed so far, whose bands may be ripped up.
+    routed_ratlines: Vec<EdgeIndex<usize>>,
+    /// The routed ratlines whose bands or vias the current route has run into.
+    blocking_ratlines: Vec<EdgeIndex<usize>>,
+    /// The rip-up costs of the ratlines that have failed to route or been ripped up so far.
+    /// The other ratlines cost one.
+    ripup_costs: HashMap<EdgeIndex<usize>, f64>,
+    /// How many times bands have been ripped up so far.
+    ripup_count: usize,
+    /// The ratlines that failed to route and were skipped, with the errors they failed with.
+    failed_ratlines: Vec<(EdgeIndex<usize>, AutorouterError)>,
+    /// The ratlines of differential pairs routed together, with the skews between their bands.
+    pair_skews: Vec<(EdgeIndex<usize>, EdgeIndex<usize>, f64)>,
+    /// The ratlines of differential pairs that were made to be routed first after the other
+    /// ratline of their pair failed to be coupled with, so that no pair is swapped twice.
+    swapped_pair_leaders: Vec<EdgeIndex<usize>>,
     /// The options for the autorouting process, defining how routing should be carried out.
     options: AutorouterOptions,
     /// Stores the current route being processed, if any.
@@ -46,32 +104,399 @@ impl AutorouteExecutionStepper {
     /// and stores the associated data for future routing steps.
     pub fn new(
         autorouter: &mut Autorouter<impl AccessMesadata>,
-        ratlines: impl IntoIterator<Item = EdgeIndex<usize>> + 'static,
+        ratlines: impl IntoIterator<Item = EdgeIndex<usize>>,
         options: AutorouterOptions,
     ) -> Result<Self, AutorouterError> {
-        let mut ratlines_iter = Box::new(ratlines.into_iter());
+        let ratlines: VecDeque<EdgeIndex<usize>> = ratlines.into_iter().collect();
 
-        let Some(curr_ratline) = ratlines_iter.next() else {
+        if ratlines.is_empty() {
             return Err(AutorouterError::NothingToRoute);
+        }
+
+        let mut this = Self {
+            ratlines,
+            routed_ratlines: vec![],
+            blocking_ratlines: vec![],
+            ripup_costs: HashMap::new(),
+            ripup_count: 0,
+            failed_ratlines: vec![],
+            pair_skews: vec![],
+            swapped_pair_leaders: vec![],
+            options,
+            route: None,
+            curr_ratline: None,
         };
 
-        let (origin, destination) = autorouter.ratline_endpoints(curr_ratline);
-        let mut router = Router::new(autorouter.board.layout_mut(), options.router_options);
+        this.start_next_route(autorouter)?;
+        Ok(this)
+    }
 
-        Ok(Self {
-            ratlines_iter,
-            options,
-            route: Some(router.route(
-                origin,
-                destination,
-                options.router_options.routed_band_width,
-            )?),
-            curr_ratline: Some(curr_ratline),
-        })
+    /// Starts routing the next ratline in the queue, if there is any left.
+    ///
+    /// Ratlines whose routes can't be started are skipped if the options say so.
+    fn start_next_route(
+        &mut self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+    ) -> Result<(), AutorouterError> {
+        while let Some(ratline) = self.ratlines.pop_front() {
+            match self.start_route(autorouter, ratline) {
+                Ok(()) => return Ok(()),
+                Err(err) => self.fail_ratline(ratline, err)?,
+            }
+        }
+
+        self.curr_ratline = None;
+        self.route = None;
+        Ok(())
+    }
+
+    /// Records the ratline as failed if the options say to skip failed ratlines, or returns
+    /// the error it failed with otherwise.
+    fn fail_ratline(
+        &mut self,
+        ratline: EdgeIndex<usize>,
+        error: AutorouterError,
+    ) -> Result<(), AutorouterError> {
+        if !self.options.skip_failed_ratlines {
+            return Err(error);
+        }
+
+        self.failed_ratlines.push((ratline, error));
+        Ok(())
+    }
+
+    fn report(&self, autorouter: &Autorouter<impl AccessMesadata>) -> AutorouteReport {
+        let drawing = autorouter.board.layout().drawing();
+        let mut routed_ratlines = vec![];
+        let mut total_length = 0.0;
+
+        for ratline in self.routed_ratlines.iter() {
+            let weight = autorouter.ratsnest.graph().edge_weight(*ratline).unwrap();
+            let Some(band_termseg) = weight.band_termseg else {
+                continue;
+            };
+
+            routed_ratlines.push((*ratline, band_termseg));
+            total_length += band_termseg.ref_(drawing).length();
+
+            for (via_band, _) in weight.vias.iter() {
+                total_length += via_band.ref_(drawing).length();
+            }
+        }
+
+        AutorouteReport {
+            routed_ratlines,
+            failed_ratlines: self.failed_ratlines.clone(),
+            total_length,
+            pair_skews: self.pair_skews.clone(),
+        }
+    }
+
+    fn start_route(
+        &mut self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+        ratline: EdgeIndex<usize>,
+    ) -> Result<(), AutorouterError> {
+        let (source, target) = autorouter.ratline_endpoints(ratline);
+        let width = autorouter.band_width(source, &self.options.router_options);
+        let mut router = Router::new(autorouter.board.layout_mut(), self.options.router_options);
+
+        self.curr_ratline = Some(ratline);
+        self.blocking_ratlines.clear();
+        self.route = Some(router.route(source, target, width)?);
+        Ok(())
+    }
+
+    fn ripup_cost(&self, ratline: EdgeIndex<usize>) -> f64 {
+        self.ripup_costs.get(&ratline).copied().unwrap_or(1.0)
+    }
+
+    fn escalate_ripup_cost(&mut self, ratline: EdgeIndex<usize>) {
+        let cost = self.ripup_cost(ratline) * self.options.ripup_cost_escalation;
+        self.ripup_costs.insert(ratline, cost);
+    }
+
+    /// Adds the routed ratlines whose bands or vias are among the obstacles the last step of
+    /// the current route ran into to the blocking ones.
+    fn note_blocking_ratlines(&mut self, autorouter: &Autorouter<impl AccessMesadata>) {
+        let Some(ref route) = self.route else {
+            return;
+        };
+
+        let blocking_ratlines: Vec<EdgeIndex<usize>> = route
+            .obstacles()
+            .iter()
+            .filter_map(|obstacle| self.routed_ratline_of(autorouter, *obstacle))
+            .collect();
+
+        for ratline in blocking_ratlines {
+            if !self.blocking_ratlines.contains(&ratline) {
+                self.blocking_ratlines.push(ratline);
+            }
+        }
+    }
+
+    /// Finds the routed ratline whose band or via the primitive belongs to, if any.
+    fn routed_ratline_of(
+        &self,
+        autorouter: &Autorouter<impl AccessMesadata>,
+        primitive: PrimitiveIndex,
+    ) -> Option<EdgeIndex<usize>> {
+        let drawing = autorouter.board.layout().drawing();
+
+        if let Ok(loose) = LooseIndex::try_from(primitive) {
+            let band = drawing.collect().loose_band_uid(loose);
+
+            self.routed_ratlines.iter().copied().find(|ratline| {
+                let weight = autorouter.ratsnest.graph().edge_weight(*ratline).unwrap();
+
+                weight
+                    .band_termseg
+                    .into_iter()
+                    .chain(weight.vias.iter().map(|(via_band, _)| *via_band))
+                    .any(|termseg| drawing.collect().loose_band_uid(termseg.into()) == band)
+            })
+        } else if let PrimitiveIndex::FixedDot(dot) = primitive {
+            let compounds: Vec<_> = drawing.compounds(dot).collect();
+
+            self.routed_ratlines.iter().copied().find(|ratline| {
+                let weight = autorouter.ratsnest.graph().edge_weight(*ratline).unwrap();
+
+                weight
+                    .vias
+                    .iter()
+                    .any(|(_, via)| compounds.contains(&(*via).into()))
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Rips up the bands blocking the current ratline, which failed to route, if the options
+    /// allow it, and starts routing the ratline anew, followed by the ripped-up ones.
+    /// Otherwise, returns the error the route failed with.
+    fn rip_up_and_reroute(
+        &mut self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+        failed_ratline: EdgeIndex<usize>,
+        error: AutorouterError,
+    ) -> Result<(), AutorouterError> {
+        if let Some(ref mut route) = self.route {
+            let mut router =
+                Router::new(autorouter.board.layout_mut(), self.options.router_options);
+            route.abandon(&mut router);
+        }
+
+        self.escalate_ripup_cost(failed_ratline);
+
+        let cost = self.ripup_cost(failed_ratline);
+        let blocking_ratlines = std::mem::take(&mut self.blocking_ratlines);
+
+        if blocking_ratlines.is_empty()
+            || self.ripup_count >= self.options.max_ripups
+            || blocking_ratlines
+                .iter()
+                .any(|ratline| self.ripup_cost(*ratline) >= cost)
+        {
+            return Err(error);
+        }
+
+        let recording = autorouter.board.layout_mut().start_recording();
+
+        for ratline in blocking_ratlines.iter() {
+            if autorouter.rip_up_ratline(*ratline).is_err() {
+                // Removing a band re-bows the bends that were around it, which may then collide
+                // with something, so put everything back as it was.
+                let edit = autorouter.board.layout_mut().stop_recording(recording);
+                autorouter.board.layout_mut().apply_edit(&edit.reverse());
+                return Err(error);
+            }
+        }
+
+        autorouter.board.layout_mut().stop_recording(recording);
+
+        self.ripup_count += 1;
+
+        for ratline in blocking_ratlines.into_iter().rev() {
+            autorouter.ratsnest.unassign_ratline(ratline);
+            self.escalate_ripup_cost(ratline);
+            self.routed_ratlines.retain(|routed| *routed != ratline);
+            self.ratlines.push_front(ratline);
+        }
+
+        self.start_route(autorouter, failed_ratline)
+    }
+
+    /// Draws the ratline of the other net of the differential pair the routed ratline belongs
+    /// to alongside its band, if such a ratline is still queued and joins pins on the same
+    /// layer. Ratlines routed through vias aren't coupled with.
+    fn couple_pair(
+        &mut self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+        leader: EdgeIndex<usize>,
+        leader_termseg: BandTermsegIndex,
+    ) -> Result<(), AutorouterError> {
+        let weight = autorouter.ratsnest.graph().edge_weight(leader).unwrap();
+
+        if !weight.vias.is_empty() {
+            return Ok(());
+        }
+
+        let Some((partner, (source, target))) = self.find_pair_ratline(autorouter, leader) else {
+            return Ok(());
+        };
+
+        self.ratlines.retain(|ratline| *ratline != partner);
+
+        let (leader_source, _) = autorouter.ratline_endpoints(leader);
+        let width = autorouter.band_width(source, &self.options.router_options);
+        let recording = autorouter.board.layout_mut().start_recording();
+
+        let result = Coupler::new(autorouter.board.layout_mut())
+            .couple(leader_termseg, leader_source, source, target, width)
+            .map_err(AutorouterError::CouldNotCouple)
+            .and_then(|band| {
+                let drawing = autorouter.board.layout().drawing();
+                let leader_band = leader_termseg.ref_(drawing);
+                let band_termseg = band.0.ref_(drawing);
+
+                let mut leader_path = leader_band.path();
+                let mut path = band_termseg.path();
+
+                if leader_path.source != leader_source {
+                    leader_path = leader_path.reversed();
+                }
+
+                if path.source != source {
+                    path = path.reversed();
+                }
+
+                let skew = (leader_band.length() - band_termseg.length()).abs();
+                self.options
+                    .diff_pair_options
+                    .check(skew, diff_pair::uncoupled_length(&leader_path, &path))?;
+
+                Ok((band, skew))
+            });
+
+        let edit = autorouter.board.layout_mut().stop_recording(recording);
+
+        match result {
+            Ok((band, skew)) => {
+                autorouter
+                    .ratsnest
+                    .assign_band_termseg_to_ratline(partner, band.1);
+                autorouter
+                    .board
+                    .try_set_band_between_nodes(source, target, band);
+
+                self.routed_ratlines.push(partner);
+                self.pair_skews.push((leader, partner, skew));
+                Ok(())
+            }
+            Err(err) => {
+                autorouter.board.layout_mut().apply_edit(&edit.reverse());
+
+                // Swapping the pair around turns it the same way, so it's only worth trying when
+                // the band couldn't be drawn at all, not when it exceeded the limits.
+                if matches!(err, AutorouterError::CouldNotCouple(..))
+                    && self.swap_pair_leader(autorouter, leader, partner)
+                {
+                    Ok(())
+                } else {
+                    self.fail_ratline(partner, err)
+                }
+            }
+        }
+    }
+
+    /// Rips up the band of the leader of a pair whose other ratline failed to be coupled with
+    /// it, and queues the pair to be routed the other way round, which may well succeed, e.g.
+    /// when the leader went straight past something the other band can't get past without
+    /// turning. Returns whether the pair was swapped, which it is only once.
+    fn swap_pair_leader(
+        &mut self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+        leader: EdgeIndex<usize>,
+        partner: EdgeIndex<usize>,
+    ) -> bool {
+        if self.swapped_pair_leaders.contains(&leader) {
+            return false;
+        }
+
+        let recording = autorouter.board.layout_mut().start_recording();
+        let result = autorouter.rip_up_ratline(leader);
+        let edit = autorouter.board.layout_mut().stop_recording(recording);
+
+        if result.is_err() {
+            autorouter.board.layout_mut().apply_edit(&edit.reverse());
+            return false;
+        }
+
+        autorouter.ratsnest.unassign_ratline(leader);
+        self.routed_ratlines.retain(|routed| *routed != leader);
+        self.swapped_pair_leaders.push(partner);
+        self.ratlines.push_front(leader);
+        self.ratlines.push_front(partner);
+        true
+    }
+
+    /// Finds the queued ratline of the net that forms a differential pair with the net of the
+    /// given ratline, choosing the one whose endpoints lie nearest to the ratline's. The
+    /// endpoints are returned in the order matching the given ratline's.
+    fn find_pair_ratline(
+        &self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+        leader: EdgeIndex<usize>,
+    ) -> Option<(EdgeIndex<usize>, (FixedDotIndex, FixedDotIndex))> {
+        let (leader_source, leader_target) = autorouter.ratline_endpoints(leader);
+        let drawing = autorouter.board.layout().drawing();
+        let layer = drawing.primitive(leader_source).layer();
+        let pair_net = drawing
+            .rules()
+            .net_pair(drawing.primitive(leader_source).maybe_net()?)?;
+
+        let mut best: Option<(f64, EdgeIndex<usize>, (FixedDotIndex, FixedDotIndex))> = None;
+
+        for ratline in self.ratlines.iter().copied() {
+            if autorouter.ratsnest.requires_layer_change(ratline) {
+                continue;
+            }
+
+            let (source, target) = autorouter.ratline_endpoints(ratline);
+            let drawing = autorouter.board.layout().drawing();
+
+            if drawing.primitive(source).maybe_net() != Some(pair_net)
+                || drawing.primitive(source).layer() != layer
+            {
+                continue;
+            }
+
+            let distance = |dot1: FixedDotIndex, dot2: FixedDotIndex| {
+                dot1.primitive(drawing)
+                    .shape()
+                    .center()
+                    .euclidean_distance(&dot2.primitive(drawing).shape().center())
+            };
+
+            let straight = distance(leader_source, source) + distance(leader_target, target);
+            let crossed = distance(leader_source, target) + distance(leader_target, source);
+            let candidate = if straight <= crossed {
+                (straight, ratline, (source, target))
+            } else {
+                (crossed, ratline, (target, source))
+            };
+
+            if best.is_none_or(|(best_distance, ..)| candidate.0 < best_distance) {
+                best = Some(candidate);
+            }
+        }
+
+        best.map(|(_, ratline, endpoints)| (ratline, endpoints))
     }
 }
 
-impl<M: AccessMesadata> Step<Autorouter<M>, (), AutorouteContinueStatus>
+impl<M: AccessMesadata> Step<Autorouter<M>, AutorouteReport, AutorouteContinueStatus>
     for AutorouteExecutionStepper
 {
     type Error = AutorouterError;
@@ -79,28 +504,42 @@ impl<M: AccessMesadata> Step<Autorouter<M>, (), AutorouteContinueStatus>
     fn step(
         &mut self,
         autorouter: &mut Autorouter<M>,
-    ) -> Result<ControlFlow<(), AutorouteContinueStatus>, AutorouterError> {
+    ) -> Result<ControlFlow<AutorouteReport, AutorouteContinueStatus>, AutorouterError> {
         let Some(curr_ratline) = self.curr_ratline else {
-            return Ok(ControlFlow::Break(()));
+            return Ok(ControlFlow::Break(self.report(autorouter)));
         };
 
         let Some(ref mut route) = self.route else {
             // Shouldn't happen.
-            return Ok(ControlFlow::Break(()));
+            return Ok(ControlFlow::Break(self.report(autorouter)));
         };
 
         let (source, target) = autorouter.ratline_endpoints(curr_ratline);
 
-        let band_termseg = {
+        let result = {
             let mut router =
                 Router::new(autorouter.board.layout_mut(), self.options.router_options);
+            route.step(&mut router)
+        };
 
-            let ControlFlow::Break(band_termseg) = route.step(&mut router)? else {
+        let band_termseg = match result {
+            Ok(ControlFlow::Break(band_termseg)) => band_termseg,
+            Ok(ControlFlow::Continue(())) => {
+                self.note_blocking_ratlines(autorouter);
                 return Ok(ControlFlow::Continue(AutorouteContinueStatus::Running));
-            };
-            band_termseg
+            }
+            Err(err) => {
+                if let Err(err) = self.rip_up_and_reroute(autorouter, curr_ratline, err.into()) {
+                    self.fail_ratline(curr_ratline, err)?;
+                    self.start_next_route(autorouter)?;
+                }
+
+                return Ok(ControlFlow::Continue(AutorouteContinueStatus::Running));
+            }
         };
 
+        let vias = route.navcord().vias().collect();
+
         let band = autorouter
             .board
             .layout()
@@ -110,27 +549,19 @@ impl<M: AccessMesadata> Step<Autorouter<M>, (), AutorouteContinueStatus>
 
         autorouter
             .ratsnest
-            .assign_band_termseg_to_ratline(self.curr_ratline.unwrap(), band_termseg);
+            .assign_band_termseg_to_ratline(curr_ratline, band_termseg);
+        autorouter
+            .ratsnest
+            .assign_vias_to_ratline(curr_ratline, vias);
 
         autorouter
             .board
             .try_set_band_between_nodes(source, target, band);
 
-        if let Some(new_ratline) = self.ratlines_iter.next() {
-            let (source, target) = autorouter.ratline_endpoints(new_ratline);
-            let mut router =
-                Router::new(autorouter.board.layout_mut(), self.options.router_options);
+        self.routed_ratlines.push(curr_ratline);
 
-            self.curr_ratline = Some(new_ratline);
-            self.route = Some(router.route(
-                source,
-                target,
-                self.options.router_options.routed_band_width,
-            )?);
-        } else {
-            self.curr_ratline = None;
-            //return Ok(AutorouteStatus::Finished);
-        }
+        self.couple_pair(autorouter, curr_ratline, band_termseg)?;
+        self.start_next_route(autorouter)?;
 
         Ok(ControlFlow::Continue(AutorouteContinueStatus::Routed(
             band_termseg,
@@ -1,33 +1,92 @@
 use derive_getters::Getters;
 use geo::Point;
 use petgraph::graph::{EdgeIndex, NodeIndex};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use spade::InsertionError;
 use thiserror::Error;
 
 use crate::{
-    board::{mesadata::AccessMesadata, Board},
-    drawing::{band::BandTermsegIndex, dot::FixedDotIndex, Infringement},
-    layout::via::ViaWeight,
-    router::{astar::AstarError, navmesh::NavmeshError, RouterOptions},
+    board::{mesadata::AccessMesadata, BandName, Board},
+    drawing::{
+        band::BandTermsegIndex,
+        dot::FixedDotIndex,
+        rules::{Conditions, GetConditions, ObjectKind},
+    },
+    layout::{via::ViaWeight, LayoutException},
+    router::{astar::AstarError, draw::DrawException, navmesh::NavmeshError, RouterOptions},
     triangulation::GetTrianvertexNodeIndex,
 };
 
 use super::{
     autoroute::AutorouteExecutionStepper,
+    check_design_rules::CheckDesignRulesExecutionStepper,
     compare_detours::CompareDetoursExecutionStepper,
+    diff_pair::DiffPairOptions,
+    edit::{AutorouterEdit, AutorouterRecording},
     measure_length::MeasureLengthExecutionStepper,
+    move_component::MoveComponentExecutionStepper,
     place_via::PlaceViaExecutionStepper,
     pointroute::PointrouteExecutionStepper,
+    ratline_ordering::RatlineOrdering,
     ratsnest::{Ratsnest, RatvertexIndex},
     remove_bands::RemoveBandsExecutionStepper,
     selection::{BandSelection, PinSelection},
+    tune_length::TuneLengthExecutionStepper,
 };
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct AutorouterOptions {
-    pub presort_by_pairwise_detours: bool,
+    /// The order in which the selected ratlines are routed.
+    ///
+    /// Options saved before the orderings were added instead tell whether to presort by pairwise
+    /// detours, which is read as the corresponding ordering.
+    #[serde(
+        default,
+        alias = "presort_by_pairwise_detours",
+        deserialize_with = "deserialize_ratline_ordering"
+    )]
+    pub ratline_ordering: RatlineOrdering,
     pub router_options: RouterOptions,
+    /// How many times an autoroute may rip up the bands blocking a ratline it failed to route,
+    /// to route that ratline first and the ripped-up ones after it. Zero disables rip-up and
+    /// reroute.
+    #[serde(default)]
+    pub max_ripups: usize,
+    /// Factor by which the rip-up cost of a ratline grows each time it fails to route or its
+    /// band is ripped up. Every ratline starts at a cost of one, and the bands blocking a failed
+    /// ratline are only ripped up if each of their costs is lower than the ratline's.
+    #[serde(default = "default_ripup_cost_escalation")]
+    pub ripup_cost_escalation: f64,
+    /// Whether to go on with the next ratline when one fails to route, listing it in the
+    /// report of the autoroute, instead of failing the whole autoroute.
+    #[serde(default)]
+    pub skip_failed_ratlines: bool,
+    /// The limits within which the ratlines of differential pairs are routed together.
+    #[serde(default)]
+    pub diff_pair_options: DiffPairOptions,
+}
+
+fn default_ripup_cost_escalation() -> f64 {
+    2.0
+}
+
+fn deserialize_ratline_ordering<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<RatlineOrdering, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RatlineOrderingOrPresort {
+        RatlineOrdering(RatlineOrdering),
+        PresortByPairwiseDetours(bool),
+    }
+
+    Ok(match RatlineOrderingOrPresort::deserialize(deserializer)? {
+        RatlineOrderingOrPresort::RatlineOrdering(ordering) => ordering,
+        RatlineOrderingOrPresort::PresortByPairwiseDetours(true) => {
+            RatlineOrdering::PairwiseDetours
+        }
+        RatlineOrderingOrPresort::PresortByPairwiseDetours(false) => RatlineOrdering::Unordered,
+    })
 }
 
 #[derive(Error, Debug, Clone)]
@@ -39,11 +98,23 @@ pub enum AutorouterError {
     #[error("routing failed: {0}")]
     Astar(#[from] AstarError),
     #[error("could not place via")]
-    CouldNotPlaceVia(#[from] Infringement),
+    CouldNotPlaceVia(#[from] LayoutException),
     #[error("could not remove band")]
     CouldNotRemoveBand(BandTermsegIndex),
     #[error("need exactly two ratlines")]
     NeedExactlyTwoRatlines,
+    #[error("could not tune length of band {0:?}")]
+    CouldNotTuneLength(BandName),
+    #[error("could not couple band with its differential pair")]
+    CouldNotCouple(#[source] DrawException),
+    #[error("skew of {0} between bands of differential pair exceeds limit")]
+    PairSkewTooLarge(f64),
+    #[error("uncoupled length of {0} of differential pair exceeds limit")]
+    UncoupledLengthTooLong(f64),
+    #[error("no component named {0}")]
+    NoSuchComponent(String),
+    #[error("could not move component {0}")]
+    CouldNotMoveComponent(String),
 }
 
 #[derive(Getters)]
@@ -54,7 +125,7 @@ pub struct Autorouter<M: AccessMesadata> {
 
 impl<M: AccessMesadata> Autorouter<M> {
     pub fn new(board: Board<M>) -> Result<Self, InsertionError> {
-        let ratsnest = Ratsnest::new(board.layout())?;
+        let ratsnest = Ratsnest::new(&board)?;
         Ok(Self { board, ratsnest })
     }
 
@@ -64,7 +135,9 @@ impl<M: AccessMesadata> Autorouter<M> {
         point: Point,
         options: AutorouterOptions,
     ) -> Result<PointrouteExecutionStepper, AutorouterError> {
-        let ratvertex = self.find_selected_ratvertex(selection).unwrap();
+        let ratvertex = self
+            .find_selected_ratvertex(selection)
+            .ok_or(AutorouterError::NothingToRoute)?;
         let origin_dot = match self
             .ratsnest
             .graph()
@@ -79,6 +152,92 @@ impl<M: AccessMesadata> Autorouter<M> {
         PointrouteExecutionStepper::new(self, origin_dot, point, options)
     }
 
+    /// Starts recording the edits made to the board and the ratsnest, to later find the edit
+    /// they make up with [`Autorouter::edit_since`].
+    pub fn start_recording(&mut self) -> AutorouterRecording {
+        AutorouterRecording {
+            layout: self.board.layout_mut().start_recording(),
+            ratlines: self.ratsnest.graph().edge_weights().cloned().collect(),
+            band_names: self.board.band_bandnames().clone(),
+        }
+    }
+
+    /// Finds the edit made to the board and the ratsnest since the recording was started.
+    pub fn edit_since(&self, recording: &AutorouterRecording) -> AutorouterEdit {
+        let ratlines = self
+            .ratsnest
+            .graph()
+            .edge_indices()
+            .zip(recording.ratlines.iter())
+            .filter_map(|(ratline, before)| {
+                let after = self.ratsnest.graph().edge_weight(ratline).unwrap();
+                (after != before).then(|| (ratline, before.clone(), after.clone()))
+            })
+            .collect();
+
+        let band_names = self.board.band_bandnames();
+        let mut band_name_changes: Vec<_> = recording
+            .band_names
+            .iter()
+            .filter_map(|(band, before)| {
+                let maybe_after = band_names.get_by_left(band);
+                (maybe_after != Some(before))
+                    .then(|| (*band, Some(before.clone()), maybe_after.cloned()))
+            })
+            .collect();
+        band_name_changes.extend(
+            band_names
+                .iter()
+                .filter(|(band, _)| !recording.band_names.contains_left(band))
+                .map(|(band, after)| (*band, None, Some(after.clone()))),
+        );
+
+        AutorouterEdit {
+            layout: self.board.layout().edit_since(&recording.layout),
+            ratlines,
+            band_names: band_name_changes,
+        }
+    }
+
+    /// Stops the recording, returning the edit made to the board and the ratsnest since it was
+    /// started.
+    pub fn stop_recording(&mut self, recording: AutorouterRecording) -> AutorouterEdit {
+        let edit = self.edit_since(&recording);
+        self.board.layout_mut().stop_recording(recording.layout);
+        edit
+    }
+
+    /// Applies an edit, or the reverse of one, to bring the board and the ratsnest to the state
+    /// it leads to.
+    pub fn apply_edit(&mut self, edit: &AutorouterEdit) {
+        self.board.layout_mut().apply_edit(&edit.layout);
+
+        for (ratline, _, after) in edit.ratlines.iter() {
+            self.ratsnest.set_ratline_weight(*ratline, after.clone());
+        }
+
+        // A name may pass from one band to another, so all names are taken away before any is
+        // given.
+        for (band, _, _) in edit.band_names.iter() {
+            self.board.set_band_bandname(*band, None);
+        }
+
+        for (band, _, maybe_after) in edit.band_names.iter() {
+            if maybe_after.is_some() {
+                self.board.set_band_bandname(*band, maybe_after.clone());
+            }
+        }
+
+        self.ratsnest.update_positions(self.board.layout());
+    }
+
+    /// Brings the board and the ratsnest back to the state they were in when the recording was
+    /// started, which goes on recording from there.
+    pub fn revert_to(&mut self, recording: &AutorouterRecording) {
+        let edit = self.edit_since(recording);
+        self.apply_edit(&edit.reverse());
+    }
+
     pub fn undo_pointroute(&mut self, band: BandTermsegIndex) -> Result<(), AutorouterError> {
         self.board
             .layout_mut()
@@ -91,7 +250,9 @@ impl<M: AccessMesadata> Autorouter<M> {
         selection: &PinSelection,
         options: AutorouterOptions,
     ) -> Result<AutorouteExecutionStepper, AutorouterError> {
-        self.autoroute_ratlines(self.selected_ratlines(selection), options)
+        let mut ratlines = self.selected_ratlines(selection);
+        options.ratline_ordering.sort(self, &mut ratlines, &options);
+        self.autoroute_ratlines(ratlines, options)
     }
 
     pub(super) fn autoroute_ratlines(
@@ -102,28 +263,32 @@ impl<M: AccessMesadata> Autorouter<M> {
         AutorouteExecutionStepper::new(self, ratlines, options)
     }
 
-    pub fn undo_autoroute(&mut self, selection: &PinSelection) -> Result<(), AutorouterError> {
-        self.undo_autoroute_ratlines(self.selected_ratlines(selection))
-    }
-
-    pub(super) fn undo_autoroute_ratlines(
+    /// Removes the band the ratline was routed with, along with the vias it was routed through
+    /// and the bands leading into them, so that the ratline can be routed anew.
+    ///
+    /// The ratline stays assigned to them in the ratsnest until it's unassigned.
+    pub(super) fn rip_up_ratline(
         &mut self,
-        ratlines: Vec<EdgeIndex<usize>>,
+        ratline: EdgeIndex<usize>,
     ) -> Result<(), AutorouterError> {
-        for ratline in ratlines.iter() {
-            let band = self
-                .ratsnest
-                .graph()
-                .edge_weight(*ratline)
-                .unwrap()
-                .band_termseg
-                .unwrap();
+        let weight = self.ratsnest.graph().edge_weight(ratline).unwrap().clone();
+
+        if let Some(band) = weight.band_termseg {
             self.board
                 .layout_mut()
                 .remove_band(band)
                 .map_err(|_| AutorouterError::CouldNotRemoveBand(band))?;
         }
 
+        // The bands leading into the vias must go before the vias they're joined to.
+        for (via_band, via) in weight.vias.iter().rev() {
+            self.board
+                .layout_mut()
+                .remove_band(*via_band)
+                .map_err(|_| AutorouterError::CouldNotRemoveBand(*via_band))?;
+            self.board.layout_mut().remove_via(*via);
+        }
+
         Ok(())
     }
 
@@ -134,10 +299,6 @@ impl<M: AccessMesadata> Autorouter<M> {
         PlaceViaExecutionStepper::new(weight)
     }
 
-    pub fn undo_place_via(&mut self, _weight: ViaWeight) {
-        todo!();
-    }
-
     pub fn remove_bands(
         &self,
         selection: &BandSelection,
@@ -145,10 +306,6 @@ impl<M: AccessMesadata> Autorouter<M> {
         RemoveBandsExecutionStepper::new(selection)
     }
 
-    pub fn undo_remove_bands(&mut self, _selection: &BandSelection) {
-        todo!();
-    }
-
     pub fn compare_detours(
         &mut self,
         selection: &PinSelection,
@@ -177,6 +334,30 @@ impl<M: AccessMesadata> Autorouter<M> {
         MeasureLengthExecutionStepper::new(selection)
     }
 
+    pub fn check_design_rules(
+        &mut self,
+    ) -> Result<CheckDesignRulesExecutionStepper, AutorouterError> {
+        CheckDesignRulesExecutionStepper::new()
+    }
+
+    pub fn tune_length(
+        &mut self,
+        selection: &BandSelection,
+        target_length: f64,
+        tolerance: f64,
+    ) -> Result<TuneLengthExecutionStepper, AutorouterError> {
+        TuneLengthExecutionStepper::new(selection, target_length, tolerance)
+    }
+
+    pub fn move_component(
+        &mut self,
+        component: &str,
+        offset: Point,
+        options: AutorouterOptions,
+    ) -> Result<MoveComponentExecutionStepper, AutorouterError> {
+        MoveComponentExecutionStepper::new(self, component, offset, options)
+    }
+
     pub fn ratline_endpoints(
         &mut self,
         ratline: EdgeIndex<usize>,
@@ -208,6 +389,22 @@ impl<M: AccessMesadata> Autorouter<M> {
         (source_dot, target_dot)
     }
 
+    /// Returns the width to route a band starting at the given dot with: the one the rules set
+    /// for wires of its net on its layer where it lies or, if they set none, the one given in
+    /// the router options.
+    pub fn band_width(&self, origin: FixedDotIndex, router_options: &RouterOptions) -> f64 {
+        let drawing = self.board.layout().drawing();
+        let origin_conditions = drawing.primitive(origin).conditions();
+
+        drawing
+            .rules()
+            .maybe_width(&Conditions {
+                kind: ObjectKind::Wire,
+                ..origin_conditions
+            })
+            .unwrap_or(router_options.routed_band_width)
+    }
+
     pub(super) fn selected_ratlines(&self, selection: &PinSelection) -> Vec<EdgeIndex<usize>> {
         self.ratsnest
             .graph()
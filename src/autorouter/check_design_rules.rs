@@ -0,0 +1,385 @@
+//! Checks a whole board against its design rules, reporting the clearance and width
+//! violations, including those of the fills of pours, the shorts between nets and the ratlines
+//! that are left unconnected.
+
+use std::collections::HashMap;
+
+use geo::{point, Point};
+use petgraph::{
+    stable_graph::NodeIndex,
+    unionfind::UnionFind,
+    visit::{EdgeRef, IntoEdgeReferences, NodeIndexable},
+};
+
+use crate::{
+    board::mesadata::AccessMesadata,
+    drawing::{
+        graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex},
+        primitive::MakePrimitiveShape,
+        rules::{GetConditions, GetObjectKind, ObjectKind},
+        Infringement,
+    },
+    geometry::{
+        compound::ManageCompounds,
+        primitive::{AccessPrimitiveShape, PrimitiveShape},
+        shape::AccessShape,
+        GenericNode, GeometryLabel, GetWidth,
+    },
+    graph::GetPetgraphIndex,
+    router::{navcord::NavcordStepper, navmesh::Navmesh},
+    triangulation::GetTrianvertexNodeIndex,
+};
+
+use super::{
+    invoker::{GetGhosts, GetMaybeNavcord, GetMaybeNavmesh, GetObstacles},
+    ratsnest::{MultilayerRatvertexKey, Ratsnest, RatvertexIndex},
+    Autorouter, AutorouterError,
+};
+
+/// A place where a board breaks its design rules.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DesignRuleViolation {
+    /// Two primitives that aren't of the same net lie closer to each other than the clearance
+    /// between them.
+    Clearance {
+        primitives: (PrimitiveIndex, PrimitiveIndex),
+        netnames: (Option<String>, Option<String>),
+        layername: Option<String>,
+        pos: Point,
+        clearance: f64,
+    },
+    /// Two primitives of different nets overlap.
+    Short {
+        primitives: (PrimitiveIndex, PrimitiveIndex),
+        netnames: (Option<String>, Option<String>),
+        layername: Option<String>,
+        pos: Point,
+    },
+    /// A wire is narrower than the width the rules set for its net.
+    Width {
+        primitive: PrimitiveIndex,
+        netname: Option<String>,
+        layername: Option<String>,
+        pos: Point,
+        width: f64,
+        required_width: f64,
+    },
+    /// The fill of a pour comes closer to a primitive of another net than the clearance
+    /// between them, as it may when the layout has changed since the pour was last filled.
+    PourClearance {
+        pour: usize,
+        primitive: PrimitiveIndex,
+        netnames: (Option<String>, Option<String>),
+        layername: Option<String>,
+        pos: Point,
+        clearance: f64,
+    },
+    /// The endpoints of a ratline aren't connected by any copper.
+    UnconnectedRatline {
+        netname: Option<String>,
+        layernames: (Option<String>, Option<String>),
+        positions: (Point, Point),
+    },
+}
+
+pub struct CheckDesignRulesExecutionStepper {
+    maybe_violations: Option<Vec<DesignRuleViolation>>,
+}
+
+impl CheckDesignRulesExecutionStepper {
+    pub fn new() -> Result<Self, AutorouterError> {
+        Ok(Self {
+            maybe_violations: None,
+        })
+    }
+
+    pub fn doit(
+        &mut self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+    ) -> Result<Vec<DesignRuleViolation>, AutorouterError> {
+        if let Some(ref violations) = self.maybe_violations {
+            return Ok(violations.clone());
+        }
+
+        let mut violations = vec![];
+        Self::check_clearances(autorouter, &mut violations);
+        Self::check_pour_clearances(autorouter, &mut violations);
+        Self::check_widths(autorouter, &mut violations);
+        Self::check_ratlines(autorouter, &mut violations);
+
+        self.maybe_violations = Some(violations.clone());
+        Ok(violations)
+    }
+
+    fn check_clearances(
+        autorouter: &Autorouter<impl AccessMesadata>,
+        violations: &mut Vec<DesignRuleViolation>,
+    ) {
+        let drawing = autorouter.board.layout().drawing();
+
+        for (node, Infringement(inflated_shape, infringee)) in drawing.find_all_infringements() {
+            let shape = node.primitive(drawing).shape();
+            let infringee_shape = infringee.primitive(drawing).shape();
+            let maybe_nets = (
+                node.primitive(drawing).maybe_net(),
+                infringee.primitive(drawing).maybe_net(),
+            );
+            let primitives = (node, infringee);
+            let netnames = (
+                netname(autorouter, maybe_nets.0),
+                netname(autorouter, maybe_nets.1),
+            );
+            let layername = layername(autorouter, node.primitive(drawing).layer());
+
+            if maybe_nets.0.is_some()
+                && maybe_nets.1.is_some()
+                && shape.intersects(&infringee_shape)
+            {
+                violations.push(DesignRuleViolation::Short {
+                    primitives,
+                    netnames,
+                    layername,
+                    pos: overlap_center(&shape, &infringee_shape),
+                });
+            } else {
+                violations.push(DesignRuleViolation::Clearance {
+                    primitives,
+                    netnames,
+                    layername,
+                    pos: overlap_center(&inflated_shape, &infringee_shape),
+                    clearance: drawing.rules().clearance(
+                        &node.primitive(drawing).conditions(),
+                        &infringee.primitive(drawing).conditions(),
+                    ),
+                });
+            }
+        }
+    }
+
+    fn check_pour_clearances(
+        autorouter: &Autorouter<impl AccessMesadata>,
+        violations: &mut Vec<DesignRuleViolation>,
+    ) {
+        let layout = autorouter.board.layout();
+
+        for (pour_index, pour) in layout.pours().iter().enumerate() {
+            for infringement in pour.find_fill_infringements(layout) {
+                violations.push(DesignRuleViolation::PourClearance {
+                    pour: pour_index,
+                    primitive: infringement.primitive,
+                    netnames: (
+                        netname(autorouter, pour.maybe_net()),
+                        netname(
+                            autorouter,
+                            infringement
+                                .primitive
+                                .primitive(layout.drawing())
+                                .maybe_net(),
+                        ),
+                    ),
+                    layername: layername(autorouter, pour.layer()),
+                    pos: infringement.pos,
+                    clearance: infringement.clearance,
+                });
+            }
+        }
+    }
+
+    fn check_widths(
+        autorouter: &Autorouter<impl AccessMesadata>,
+        violations: &mut Vec<DesignRuleViolation>,
+    ) {
+        let drawing = autorouter.board.layout().drawing();
+
+        for node in drawing.primitive_nodes() {
+            // Only the segs and bends of wires have a width that rules set, as dots are as wide
+            // as the wires they join and pads are as large as their footprints make them.
+            if matches!(
+                node,
+                PrimitiveIndex::FixedDot(..) | PrimitiveIndex::LooseDot(..)
+            ) || node.primitive(drawing).object_kind() != ObjectKind::Wire
+            {
+                continue;
+            }
+
+            let Some(net) = node.primitive(drawing).maybe_net() else {
+                continue;
+            };
+            let Some(required_width) = drawing
+                .rules()
+                .maybe_width(&node.primitive(drawing).conditions())
+            else {
+                continue;
+            };
+            let width = node.primitive(drawing).width();
+
+            if width < required_width {
+                violations.push(DesignRuleViolation::Width {
+                    primitive: node,
+                    netname: netname(autorouter, Some(net)),
+                    layername: layername(autorouter, node.primitive(drawing).layer()),
+                    pos: node.primitive(drawing).shape().center(),
+                    width,
+                    required_width,
+                });
+            }
+        }
+    }
+
+    fn check_ratlines(
+        autorouter: &Autorouter<impl AccessMesadata>,
+        violations: &mut Vec<DesignRuleViolation>,
+    ) {
+        let layout = autorouter.board.layout();
+        let drawing = layout.drawing();
+        let graph = drawing.geometry().graph();
+        let mut unionfind = UnionFind::new(graph.node_bound());
+
+        // Loose bends have their cores and inner bends as neighbors too, but these aren't
+        // connected to them.
+        for edge in graph.edge_references() {
+            if matches!(
+                edge.weight(),
+                GeometryLabel::Joined | GeometryLabel::Compound
+            ) {
+                unionfind.union(edge.source(), edge.target());
+            }
+        }
+
+        // Copper of the same net is connected wherever it overlaps, even if it isn't joined,
+        // as is the case for the fixed wires read from design files.
+        for node in drawing.primitive_nodes() {
+            let Some(net) = node.primitive(drawing).maybe_net() else {
+                continue;
+            };
+            let shape = node.primitive(drawing).shape();
+
+            for other in drawing
+                .rtree()
+                .locate_in_envelope_intersecting(
+                    &shape.envelope_3d(0.0, node.primitive(drawing).layer()),
+                )
+                .filter_map(|wrapper| {
+                    if let GenericNode::Primitive(other) = wrapper.data {
+                        Some(other)
+                    } else {
+                        None
+                    }
+                })
+            {
+                if other.primitive(drawing).maybe_net() == Some(net)
+                    && shape.intersects(&other.primitive(drawing).shape())
+                {
+                    unionfind.union(node.petgraph_index(), other.petgraph_index());
+                }
+            }
+        }
+
+        let ratsnest = autorouter.ratsnest.graph();
+
+        // The copies of a through-hole pad on each layer are connected through its hole, like
+        // the ratsnest merges them into one vertex.
+        let mut key_ratvertices: HashMap<MultilayerRatvertexKey, Vec<NodeIndex<usize>>> =
+            HashMap::new();
+
+        for weight in ratsnest.node_weights() {
+            key_ratvertices
+                .entry(Ratsnest::multilayer_ratvertex_key(
+                    &autorouter.board,
+                    weight.node_index(),
+                ))
+                .or_default()
+                .push(weight.node_index().petgraph_index());
+        }
+
+        for copies in key_ratvertices.values() {
+            for copy in copies.iter().skip(1) {
+                unionfind.union(copies[0], *copy);
+            }
+        }
+
+        for ratline in ratsnest.edge_indices() {
+            let (source, target) = ratsnest.edge_endpoints(ratline).unwrap();
+            let source = ratsnest.node_weight(source).unwrap();
+            let target = ratsnest.node_weight(target).unwrap();
+
+            if unionfind.equiv(
+                source.node_index().petgraph_index(),
+                target.node_index().petgraph_index(),
+            ) {
+                continue;
+            }
+
+            let maybe_net = match source.node_index() {
+                RatvertexIndex::FixedDot(dot) => drawing.primitive(dot).maybe_net(),
+                RatvertexIndex::Poly(poly) => drawing.compound_weight(poly.into()).maybe_net(),
+            };
+            violations.push(DesignRuleViolation::UnconnectedRatline {
+                netname: netname(autorouter, maybe_net),
+                layernames: (
+                    layername(autorouter, source.layer),
+                    layername(autorouter, target.layer),
+                ),
+                positions: (source.pos, target.pos),
+            });
+        }
+    }
+}
+
+fn netname(
+    autorouter: &Autorouter<impl AccessMesadata>,
+    maybe_net: Option<usize>,
+) -> Option<String> {
+    maybe_net
+        .and_then(|net| autorouter.board.layout().rules().net_netname(net))
+        .map(str::to_string)
+}
+
+fn layername(autorouter: &Autorouter<impl AccessMesadata>, layer: usize) -> Option<String> {
+    autorouter
+        .board
+        .layout()
+        .rules()
+        .layer_layername(layer)
+        .map(str::to_string)
+}
+
+/// The center of the region where the bounding boxes of two shapes overlap, as the place to
+/// point at when reporting that the shapes come too close.
+fn overlap_center(shape1: &PrimitiveShape, shape2: &PrimitiveShape) -> Point {
+    let (bbox1, bbox2) = (shape1.bbox(0.0), shape2.bbox(0.0));
+    let lower = [
+        bbox1.lower()[0].max(bbox2.lower()[0]),
+        bbox1.lower()[1].max(bbox2.lower()[1]),
+    ];
+    let upper = [
+        bbox1.upper()[0].min(bbox2.upper()[0]),
+        bbox1.upper()[1].min(bbox2.upper()[1]),
+    ];
+
+    point! {x: (lower[0] + upper[0]) / 2.0, y: (lower[1] + upper[1]) / 2.0}
+}
+
+impl GetMaybeNavmesh for CheckDesignRulesExecutionStepper {
+    fn maybe_navmesh(&self) -> Option<&Navmesh> {
+        None
+    }
+}
+
+impl GetMaybeNavcord for CheckDesignRulesExecutionStepper {
+    fn maybe_navcord(&self) -> Option<&NavcordStepper> {
+        None
+    }
+}
+
+impl GetGhosts for CheckDesignRulesExecutionStepper {
+    fn ghosts(&self) -> &[PrimitiveShape] {
+        &[]
+    }
+}
+
+impl GetObstacles for CheckDesignRulesExecutionStepper {
+    fn obstacles(&self) -> &[PrimitiveIndex] {
+        &[]
+    }
+}
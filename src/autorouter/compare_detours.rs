@@ -16,6 +16,7 @@ use crate::{
 
 use super::{
     autoroute::{AutorouteContinueStatus, AutorouteExecutionStepper},
+    edit::AutorouterRecording,
     invoker::{GetGhosts, GetMaybeNavcord, GetMaybeNavmesh, GetObstacles},
     Autorouter, AutorouterError, AutorouterOptions,
 };
@@ -23,8 +24,9 @@ use super::{
 pub struct CompareDetoursExecutionStepper {
     autoroute: AutorouteExecutionStepper,
     next_autoroute: Option<AutorouteExecutionStepper>,
-    ratline1: EdgeIndex<usize>,
-    ratline2: EdgeIndex<usize>,
+    /// The recording of the routing, by which the layout and the ratsnest are brought back to
+    /// how they were before each order. It's stopped once the comparison ends.
+    maybe_recording: Option<AutorouterRecording>,
     total_length1: f64,
     total_length2: f64,
     done: bool,
@@ -40,8 +42,7 @@ impl CompareDetoursExecutionStepper {
         Ok(Self {
             autoroute: autorouter.autoroute_ratlines(vec![ratline1, ratline2], options)?,
             next_autoroute: Some(autorouter.autoroute_ratlines(vec![ratline2, ratline1], options)?),
-            ratline1,
-            ratline2,
+            maybe_recording: Some(autorouter.start_recording()),
             total_length1: 0.0,
             total_length2: 0.0,
             done: false,
@@ -62,7 +63,18 @@ impl<M: AccessMesadata> Step<Autorouter<M>, (f64, f64)> for CompareDetoursExecut
             return Ok(ControlFlow::Break((self.total_length1, self.total_length2)));
         }
 
-        match self.autoroute.step(autorouter)? {
+        let status = match self.autoroute.step(autorouter) {
+            Ok(status) => status,
+            Err(err) => {
+                if let Some(recording) = self.maybe_recording.take() {
+                    autorouter.stop_recording(recording);
+                }
+
+                return Err(err);
+            }
+        };
+
+        match status {
             ControlFlow::Continue(AutorouteContinueStatus::Running) => {
                 Ok(ControlFlow::Continue(()))
             }
@@ -79,15 +91,21 @@ impl<M: AccessMesadata> Step<Autorouter<M>, (f64, f64)> for CompareDetoursExecut
 
                 Ok(ControlFlow::Continue(()))
             }
-            ControlFlow::Break(()) => {
+            ControlFlow::Break(..) => {
+                if let Some(recording) = self.maybe_recording.as_ref() {
+                    autorouter.revert_to(recording);
+                }
+
                 if let Some(next_autoroute) = self.next_autoroute.take() {
-                    autorouter.undo_autoroute_ratlines(vec![self.ratline1, self.ratline2])?;
                     self.autoroute = next_autoroute;
 
                     Ok(ControlFlow::Continue(()))
                 } else {
                     self.done = true;
-                    autorouter.undo_autoroute_ratlines(vec![self.ratline2, self.ratline1])?;
+
+                    if let Some(recording) = self.maybe_recording.take() {
+                        autorouter.stop_recording(recording);
+                    }
 
                     Ok(ControlFlow::Break((self.total_length1, self.total_length2)))
                 }
@@ -0,0 +1,68 @@
+//! Provides the options and measures of the differential pairs an autoroute routes together.
+//! Once the ratline of one net of a pair is routed, the ratline of the other net is not routed
+//! on its own, but drawn alongside the band of the first one.
+
+use geo::EuclideanLength;
+use serde::{Deserialize, Serialize};
+
+use crate::drawing::band::BandPath;
+
+use super::AutorouterError;
+
+/// The limits the bands of differential pairs are routed within.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct DiffPairOptions {
+    /// The longest the bands of a pair may run apart from each other where they fan out to
+    /// their pins, summed over both of their ends. `None` sets no limit.
+    #[serde(default)]
+    pub max_uncoupled_length: Option<f64>,
+    /// The largest difference allowed between the lengths of the bands of a pair. `None` sets
+    /// no limit.
+    #[serde(default)]
+    pub max_skew: Option<f64>,
+}
+
+impl DiffPairOptions {
+    /// Checks the skew and uncoupled length of a pair against the limits.
+    pub fn check(&self, skew: f64, uncoupled_length: f64) -> Result<(), AutorouterError> {
+        if self.max_skew.is_some_and(|max_skew| skew > max_skew) {
+            return Err(AutorouterError::PairSkewTooLarge(skew));
+        }
+
+        if self
+            .max_uncoupled_length
+            .is_some_and(|max_uncoupled_length| uncoupled_length > max_uncoupled_length)
+        {
+            return Err(AutorouterError::UncoupledLengthTooLong(uncoupled_length));
+        }
+
+        Ok(())
+    }
+}
+
+/// Estimates the length over which the bands of a pair, going in the same direction, run apart
+/// from each other.
+///
+/// A band coupled with another one turns wherever the other one does, so the two run in
+/// parallel between their first and last bends. Past these, each band makes for its own pin,
+/// and the differences between the lengths of their first segs and of their last segs are
+/// taken for how far they run apart. Straight bands are taken to run apart over the difference
+/// of their lengths.
+pub fn uncoupled_length(path1: &BandPath, path2: &BandPath) -> f64 {
+    let leg_length_difference = |i: usize, j: usize| {
+        (path1.legs[i].euclidean_length() - path2.legs[j].euclidean_length()).abs()
+    };
+
+    if path1.legs.len() == 1 || path2.legs.len() == 1 {
+        let length = |path: &BandPath| {
+            path.legs
+                .iter()
+                .map(|leg| leg.euclidean_length())
+                .sum::<f64>()
+        };
+
+        return (length(path1) - length(path2)).abs();
+    }
+
+    leg_length_difference(0, 0) + leg_length_difference(path1.legs.len() - 1, path2.legs.len() - 1)
+}
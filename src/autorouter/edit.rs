@@ -0,0 +1,63 @@
+//! Records the edits commands make to the board along with the ratsnest, so that commands can
+//! be undone and redone by reverting and applying these edits.
+
+use bimap::BiHashMap;
+use petgraph::graph::EdgeIndex;
+
+use crate::{
+    board::BandName, drawing::band::BandUid, geometry::edit::Recording, layout::LayoutEdit,
+};
+
+use super::ratsnest::RatlineWeight;
+
+/// A recording of the edits made through the autorouter, started with
+/// [`super::Autorouter::start_recording`].
+///
+/// The layout records its own changes, while the ratlines and the band names, of which there are
+/// few, are copied when the recording is started and compared against when it's stopped.
+#[derive(Debug)]
+pub struct AutorouterRecording {
+    pub(super) layout: Recording,
+    pub(super) ratlines: Vec<RatlineWeight>,
+    pub(super) band_names: BiHashMap<BandUid, BandName>,
+}
+
+/// The edit made to the layout, along with the bands and vias the ratlines were assigned and
+/// the names the bands were given, which can be reverted to undo it.
+#[derive(Debug, Clone)]
+pub struct AutorouterEdit {
+    pub(super) layout: LayoutEdit,
+    /// The ratlines whose assignments changed, with their assignments before and after.
+    pub(super) ratlines: Vec<(EdgeIndex<usize>, RatlineWeight, RatlineWeight)>,
+    /// The bands whose names changed, with their names before and after.
+    pub(super) band_names: Vec<(BandUid, Option<BandName>, Option<BandName>)>,
+}
+
+impl AutorouterEdit {
+    /// The edit that undoes this one.
+    pub fn reverse(&self) -> Self {
+        Self {
+            layout: self.layout.reverse(),
+            ratlines: self
+                .ratlines
+                .iter()
+                .map(|(ratline, before, after)| (*ratline, after.clone(), before.clone()))
+                .collect(),
+            band_names: self
+                .band_names
+                .iter()
+                .map(|(band, before, after)| (*band, after.clone(), before.clone()))
+                .collect(),
+        }
+    }
+
+    /// Whether the edit leaves everything as it was.
+    pub fn is_empty(&self) -> bool {
+        self.layout.is_empty() && self.ratlines.is_empty() && self.band_names.is_empty()
+    }
+
+    /// Whether the edit changes the layout, as opposed to only the ratsnest or the band names.
+    pub fn edits_layout(&self) -> bool {
+        !self.layout.is_empty()
+    }
+}
@@ -1,18 +1,30 @@
-use std::ops::ControlFlow;
+use std::{fmt, ops::ControlFlow};
 
 use enum_dispatch::enum_dispatch;
 use serde::{Deserialize, Serialize};
 
-use crate::{board::mesadata::AccessMesadata, layout::via::ViaWeight, stepper::Step};
+use geo::Point;
+
+use crate::{
+    board::{mesadata::AccessMesadata, BandName},
+    drawing::band::BandTermsegIndex,
+    graph::GenericIndex,
+    layout::via::ViaWeight,
+    stepper::Step,
+};
 
 use super::{
-    autoroute::AutorouteExecutionStepper,
+    autoroute::{AutorouteExecutionStepper, AutorouteReport},
+    check_design_rules::{CheckDesignRulesExecutionStepper, DesignRuleViolation},
     compare_detours::CompareDetoursExecutionStepper,
     invoker::{Invoker, InvokerError},
     measure_length::MeasureLengthExecutionStepper,
+    move_component::MoveComponentExecutionStepper,
     place_via::PlaceViaExecutionStepper,
+    pointroute::PointrouteExecutionStepper,
     remove_bands::RemoveBandsExecutionStepper,
     selection::{BandSelection, PinSelection},
+    tune_length::TuneLengthExecutionStepper,
     Autorouter, AutorouterOptions,
 };
 
@@ -25,6 +37,76 @@ pub enum Command {
     RemoveBands(BandSelection),
     CompareDetours(Type, AutorouterOptions),
     MeasureLength(BandSelection),
+    /// Checks the whole board against its design rules.
+    CheckDesignRules,
+    /// Tunes the selected bands to a target length, give or take a tolerance. Each band is
+    /// tuned on its own, as tuning groups of bands to a length of their own isn't implemented.
+    TuneLength(BandSelection, f64, f64),
+    /// Routes a band from the selected pin to a point, as is done when routing a track
+    /// interactively.
+    Pointroute(PinSelection, Point, AutorouterOptions),
+    /// Moves the named component by an offset, routing the bands that were joined to or
+    /// wrapped around it anew.
+    MoveComponent(String, Point, AutorouterOptions),
+}
+
+/// What a command yielded once it finished executing.
+#[derive(Debug, Clone)]
+pub enum ExecutionOutcome {
+    /// The ratlines that got routed and the ones that failed.
+    Autoroute(AutorouteReport),
+    /// The via that was placed.
+    PlaceVia(GenericIndex<ViaWeight>),
+    RemoveBands,
+    /// The total lengths of the bands routed in either order.
+    CompareDetours(f64, f64),
+    /// The total length of the selected bands.
+    MeasureLength(f64),
+    /// The places where the board breaks its design rules.
+    CheckDesignRules(Vec<DesignRuleViolation>),
+    /// The lengths the selected bands were tuned to.
+    TuneLength(Vec<(BandName, f64)>),
+    /// The band that was routed to the point.
+    Pointroute(BandTermsegIndex),
+    /// The ratlines whose bands were routed anew after the component was moved.
+    MoveComponent(AutorouteReport),
+}
+
+impl fmt::Display for ExecutionOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExecutionOutcome::Autoroute(report) => write!(
+                f,
+                "finished autorouting: routed {} ratlines of total length {}, {} failed",
+                report.routed_ratlines().len(),
+                report.total_length(),
+                report.failed_ratlines().len()
+            ),
+            ExecutionOutcome::PlaceVia(..) => write!(f, "finished placing via"),
+            ExecutionOutcome::RemoveBands => write!(f, "finished removing bands"),
+            ExecutionOutcome::CompareDetours(total_length1, total_length2) => write!(
+                f,
+                "total detour lengths are {} and {}",
+                total_length1, total_length2
+            ),
+            ExecutionOutcome::MeasureLength(length) => {
+                write!(f, "Total length of selected bands: {}", length)
+            }
+            ExecutionOutcome::CheckDesignRules(violations) => {
+                write!(f, "found {} design rule violations", violations.len())
+            }
+            ExecutionOutcome::TuneLength(lengths) => {
+                write!(f, "finished tuning length of {} bands", lengths.len())
+            }
+            ExecutionOutcome::Pointroute(..) => write!(f, "finished routing track"),
+            ExecutionOutcome::MoveComponent(report) => write!(
+                f,
+                "finished moving component: rerouted {} bands, {} failed",
+                report.routed_ratlines().len(),
+                report.failed_ratlines().len()
+            ),
+        }
+    }
 }
 
 #[enum_dispatch(GetMaybeNavmesh, GetMaybeNavcord, GetGhosts, GetObstacles)]
@@ -34,60 +116,79 @@ pub enum ExecutionStepper {
     RemoveBands(RemoveBandsExecutionStepper),
     CompareDetours(CompareDetoursExecutionStepper),
     MeasureLength(MeasureLengthExecutionStepper),
+    CheckDesignRules(CheckDesignRulesExecutionStepper),
+    TuneLength(TuneLengthExecutionStepper),
+    Pointroute(PointrouteExecutionStepper),
+    MoveComponent(MoveComponentExecutionStepper),
 }
 
 impl ExecutionStepper {
     fn step_catch_err<M: AccessMesadata>(
         &mut self,
         autorouter: &mut Autorouter<M>,
-    ) -> Result<ControlFlow<String>, InvokerError> {
+    ) -> Result<ControlFlow<ExecutionOutcome>, InvokerError> {
         Ok(match self {
             ExecutionStepper::Autoroute(autoroute) => match autoroute.step(autorouter)? {
                 ControlFlow::Continue(..) => ControlFlow::Continue(()),
-                ControlFlow::Break(..) => ControlFlow::Break("finished autorouting".to_string()),
+                ControlFlow::Break(report) => {
+                    ControlFlow::Break(ExecutionOutcome::Autoroute(report))
+                }
             },
             ExecutionStepper::PlaceVia(place_via) => {
-                place_via.doit(autorouter)?;
-                ControlFlow::Break("finished placing via".to_string())
+                ControlFlow::Break(ExecutionOutcome::PlaceVia(place_via.doit(autorouter)?))
             }
             ExecutionStepper::RemoveBands(remove_bands) => {
                 remove_bands.doit(autorouter)?;
-                ControlFlow::Break("finished removing bands".to_string())
+                ControlFlow::Break(ExecutionOutcome::RemoveBands)
             }
             ExecutionStepper::CompareDetours(compare_detours) => {
                 match compare_detours.step(autorouter)? {
                     ControlFlow::Continue(()) => ControlFlow::Continue(()),
-                    ControlFlow::Break((total_length1, total_length2)) => {
-                        ControlFlow::Break(format!(
-                            "total detour lengths are {} and {}",
-                            total_length1, total_length2
-                        ))
-                    }
+                    ControlFlow::Break((total_length1, total_length2)) => ControlFlow::Break(
+                        ExecutionOutcome::CompareDetours(total_length1, total_length2),
+                    ),
                 }
             }
-            ExecutionStepper::MeasureLength(measure_length) => {
-                let length = measure_length.doit(autorouter)?;
-                ControlFlow::Break(format!("Total length of selected bands: {}", length))
+            ExecutionStepper::MeasureLength(measure_length) => ControlFlow::Break(
+                ExecutionOutcome::MeasureLength(measure_length.doit(autorouter)?),
+            ),
+            ExecutionStepper::CheckDesignRules(check_design_rules) => ControlFlow::Break(
+                ExecutionOutcome::CheckDesignRules(check_design_rules.doit(autorouter)?),
+            ),
+            ExecutionStepper::TuneLength(tune_length) => {
+                ControlFlow::Break(ExecutionOutcome::TuneLength(tune_length.doit(autorouter)?))
+            }
+            ExecutionStepper::Pointroute(pointroute) => match pointroute.step(autorouter)? {
+                ControlFlow::Continue(()) => ControlFlow::Continue(()),
+                ControlFlow::Break(band) => ControlFlow::Break(ExecutionOutcome::Pointroute(band)),
+            },
+            ExecutionStepper::MoveComponent(move_component) => {
+                match move_component.step(autorouter)? {
+                    ControlFlow::Continue(()) => ControlFlow::Continue(()),
+                    ControlFlow::Break(report) => {
+                        ControlFlow::Break(ExecutionOutcome::MoveComponent(report))
+                    }
+                }
             }
         })
     }
 }
 
-impl<M: AccessMesadata> Step<Invoker<M>, String> for ExecutionStepper {
+impl<M: AccessMesadata> Step<Invoker<M>, ExecutionOutcome> for ExecutionStepper {
     type Error = InvokerError;
 
-    fn step(&mut self, invoker: &mut Invoker<M>) -> Result<ControlFlow<String>, InvokerError> {
+    fn step(
+        &mut self,
+        invoker: &mut Invoker<M>,
+    ) -> Result<ControlFlow<ExecutionOutcome>, InvokerError> {
         match self.step_catch_err(&mut invoker.autorouter) {
             Ok(ControlFlow::Continue(())) => Ok(ControlFlow::Continue(())),
-            Ok(ControlFlow::Break(msg)) => {
-                if let Some(command) = invoker.ongoing_command.take() {
-                    invoker.history.do_(command);
-                }
-
-                Ok(ControlFlow::Break(msg))
+            Ok(ControlFlow::Break(outcome)) => {
+                invoker.end_ongoing_command(true);
+                Ok(ControlFlow::Break(outcome))
             }
             Err(err) => {
-                invoker.ongoing_command = None;
+                invoker.end_ongoing_command(false);
                 Err(err)
             }
         }
@@ -6,7 +6,7 @@ use derive_getters::{Dissolve, Getters};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::autorouter::execution::Command;
+use crate::autorouter::{edit::AutorouterEdit, execution::Command};
 
 #[derive(Error, Debug, Clone)]
 pub enum HistoryError {
@@ -14,12 +14,36 @@ pub enum HistoryError {
     NoPreviousCommand,
     #[error("no next command")]
     NoNextCommand,
+    #[error("previous command was never executed, so there is no edit to undo")]
+    NoPreviousEdit,
+}
+
+/// A command in the history, along with the edit its execution made to the board and the
+/// ratsnest.
+///
+/// Only the command is saved. The edit is missing for commands loaded from a file until they are
+/// executed.
+#[derive(Debug, Clone, Getters, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HistoryEntry {
+    command: Command,
+    #[serde(skip)]
+    maybe_edit: Option<AutorouterEdit>,
+}
+
+impl From<Command> for HistoryEntry {
+    fn from(command: Command) -> Self {
+        Self {
+            command,
+            maybe_edit: None,
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Getters, Dissolve, Serialize, Deserialize)]
 pub struct History {
-    done: Vec<Command>,
-    undone: Vec<Command>,
+    done: Vec<HistoryEntry>,
+    undone: Vec<HistoryEntry>,
 }
 
 impl History {
@@ -28,36 +52,53 @@ impl History {
     }
 
     pub fn do_(&mut self, command: Command) {
-        self.done.push(command);
+        self.done.push(command.into());
+    }
+
+    pub fn do_with_edit(&mut self, command: Command, edit: AutorouterEdit) {
+        self.done.push(HistoryEntry {
+            command,
+            maybe_edit: Some(edit),
+        });
     }
 
     pub fn undo(&mut self) -> Result<(), HistoryError> {
-        let Some(command) = self.done.pop() else {
+        let Some(entry) = self.done.pop() else {
             return Err(HistoryError::NoPreviousCommand);
         };
 
-        self.undone.push(command);
+        self.undone.push(entry);
         Ok(())
     }
 
     pub fn redo(&mut self) -> Result<(), HistoryError> {
-        let Some(command) = self.undone.pop() else {
+        let Some(entry) = self.undone.pop() else {
+            return Err(HistoryError::NoNextCommand);
+        };
+
+        self.done.push(entry);
+        Ok(())
+    }
+
+    /// Redoes the next command, which was executed anew and made the given edit.
+    pub fn redo_with_edit(&mut self, edit: AutorouterEdit) -> Result<(), HistoryError> {
+        let Some(entry) = self.undone.pop() else {
             return Err(HistoryError::NoNextCommand);
         };
 
-        self.done.push(command);
+        self.do_with_edit(entry.command, edit);
         Ok(())
     }
 
     pub fn set_undone(&mut self, iter: impl IntoIterator<Item = Command>) {
-        self.undone = Vec::from_iter(iter);
+        self.undone = iter.into_iter().map(HistoryEntry::from).collect();
     }
 
-    pub fn last_done(&self) -> Result<&Command, HistoryError> {
+    pub fn last_done(&self) -> Result<&HistoryEntry, HistoryError> {
         self.done.last().ok_or(HistoryError::NoPreviousCommand)
     }
 
-    pub fn last_undone(&self) -> Result<&Command, HistoryError> {
+    pub fn last_undone(&self) -> Result<&HistoryEntry, HistoryError> {
         self.undone.last().ok_or(HistoryError::NoNextCommand)
     }
 }
@@ -1,6 +1,6 @@
 //! Manages the execution of routing commands within the autorouting system.
 
-use std::{cmp::Ordering, ops::ControlFlow};
+use std::ops::ControlFlow;
 
 use contracts_try::debug_requires;
 use derive_getters::{Dissolve, Getters};
@@ -17,12 +17,17 @@ use crate::{
 
 use super::{
     autoroute::AutorouteExecutionStepper,
+    check_design_rules::CheckDesignRulesExecutionStepper,
     compare_detours::CompareDetoursExecutionStepper,
-    execution::{Command, ExecutionStepper},
+    edit::{AutorouterEdit, AutorouterRecording},
+    execution::{Command, ExecutionOutcome, ExecutionStepper},
     history::{History, HistoryError},
     measure_length::MeasureLengthExecutionStepper,
+    move_component::MoveComponentExecutionStepper,
     place_via::PlaceViaExecutionStepper,
+    pointroute::PointrouteExecutionStepper,
     remove_bands::RemoveBandsExecutionStepper,
+    tune_length::TuneLengthExecutionStepper,
     Autorouter, AutorouterError,
 };
 
@@ -59,6 +64,9 @@ pub struct Invoker<M: AccessMesadata> {
     pub(super) autorouter: Autorouter<M>,
     pub(super) history: History,
     pub(super) ongoing_command: Option<Command>,
+    /// The recording of the edits the ongoing command makes.
+    #[getter(skip)]
+    pub(super) ongoing_recording: Option<AutorouterRecording>,
 }
 
 impl<M: AccessMesadata> Invoker<M> {
@@ -71,51 +79,83 @@ impl<M: AccessMesadata> Invoker<M> {
             autorouter,
             history,
             ongoing_command: None,
+            ongoing_recording: None,
         }
     }
 
+    /// Gives mutable access to the autorouter to interactions, which change the layout bit by
+    /// bit as the user goes and only record their command once they're committed.
+    pub(crate) fn autorouter_mut(&mut self) -> &mut Autorouter<M> {
+        &mut self.autorouter
+    }
+
+    /// Records a command that was carried out outside of the invoker, such as one done
+    /// interactively, along with the edit it made.
+    #[debug_requires(self.ongoing_command.is_none())]
+    pub fn record(&mut self, command: Command, edit: AutorouterEdit) {
+        if edit.edits_layout() {
+            self.autorouter.board.layout_mut().fill_pours();
+        }
+
+        self.history.do_with_edit(command, edit);
+        self.history.set_undone(std::iter::empty());
+    }
+
     //#[debug_requires(self.ongoing_command.is_none())]
-    pub fn execute(&mut self, command: Command) -> Result<(), InvokerError> {
+    pub fn execute(&mut self, command: Command) -> Result<ExecutionOutcome, InvokerError> {
         let mut execute = self.execute_stepper(command)?;
 
         loop {
             let status = execute.step(self)?;
 
-            if let ControlFlow::Break(..) = status {
-                self.history.set_undone(std::iter::empty());
-                return Ok(());
+            if let ControlFlow::Break(outcome) = status {
+                return Ok(outcome);
             }
         }
     }
 
     #[debug_requires(self.ongoing_command.is_none())]
     pub fn execute_stepper(&mut self, command: Command) -> Result<ExecutionStepper, InvokerError> {
-        let execute = self.dispatch_command(&command);
+        self.ongoing_recording = Some(self.autorouter.start_recording());
+        let result = self.dispatch_command(&command);
         self.ongoing_command = Some(command);
-        execute
+
+        if result.is_err() {
+            self.end_ongoing_command(false);
+        }
+
+        result
+    }
+
+    /// Ends the ongoing command, recording it in the history along with the edit it made.
+    ///
+    /// Whatever a failed command managed to change is still recorded, so that it can be undone
+    /// like a command that finished. A failed command that changed nothing isn't recorded.
+    pub(super) fn end_ongoing_command(&mut self, finished: bool) {
+        let (Some(command), Some(recording)) =
+            (self.ongoing_command.take(), self.ongoing_recording.take())
+        else {
+            return;
+        };
+        let edit = self.autorouter.stop_recording(recording);
+
+        // Pours are filled around the layout, so they're filled anew whenever a command
+        // changes it.
+        if edit.edits_layout() {
+            self.autorouter.board.layout_mut().fill_pours();
+        }
+
+        if finished || !edit.is_empty() {
+            self.history.do_with_edit(command, edit);
+            self.history.set_undone(std::iter::empty());
+        }
     }
 
     #[debug_requires(self.ongoing_command.is_none())]
     fn dispatch_command(&mut self, command: &Command) -> Result<ExecutionStepper, InvokerError> {
         Ok(match command {
             Command::Autoroute(selection, options) => {
-                let mut ratlines = self.autorouter.selected_ratlines(selection);
-
-                if options.presort_by_pairwise_detours {
-                    ratlines.sort_unstable_by(|a, b| {
-                        let mut compare_detours = self
-                            .autorouter
-                            .compare_detours_ratlines(*a, *b, *options)
-                            .unwrap();
-                        if let Ok((al, bl)) = compare_detours.finish(&mut self.autorouter) {
-                            PartialOrd::partial_cmp(&al, &bl).unwrap()
-                        } else {
-                            Ordering::Equal
-                        }
-                    });
-                }
-
-                ExecutionStepper::Autoroute(self.autorouter.autoroute_ratlines(ratlines, *options)?)
+                ExecutionStepper::Autoroute(self.autorouter.autoroute(selection, *options)?)
             }
             Command::PlaceVia(weight) => {
                 ExecutionStepper::PlaceVia(self.autorouter.place_via(*weight)?)
@@ -129,55 +169,113 @@ impl<M: AccessMesadata> Invoker<M> {
             Command::MeasureLength(selection) => {
                 ExecutionStepper::MeasureLength(self.autorouter.measure_length(selection)?)
             }
+            Command::CheckDesignRules => {
+                ExecutionStepper::CheckDesignRules(self.autorouter.check_design_rules()?)
+            }
+            Command::TuneLength(selection, target_length, tolerance) => {
+                ExecutionStepper::TuneLength(self.autorouter.tune_length(
+                    selection,
+                    *target_length,
+                    *tolerance,
+                )?)
+            }
+            Command::Pointroute(selection, point, options) => ExecutionStepper::Pointroute(
+                self.autorouter.pointroute(selection, *point, *options)?,
+            ),
+            Command::MoveComponent(component, offset, options) => ExecutionStepper::MoveComponent(
+                self.autorouter
+                    .move_component(component, *offset, *options)?,
+            ),
         })
     }
 
     #[debug_requires(self.ongoing_command.is_none())]
     pub fn undo(&mut self) -> Result<(), InvokerError> {
-        let command = self.history.last_done()?;
-
-        match command {
-            Command::Autoroute(ref selection, ..) => {
-                self.autorouter.undo_autoroute(selection)?;
-            }
-            Command::PlaceVia(weight) => {
-                self.autorouter.undo_place_via(*weight);
-            }
-            Command::RemoveBands(ref selection) => {
-                self.autorouter.undo_remove_bands(selection);
-            }
-            Command::CompareDetours(..) => {}
-            Command::MeasureLength(..) => {}
-        }
+        // A command in a history the invoker was given but never executed itself has no edit,
+        // and what it did can't be told from the board, so it can't be undone.
+        let edit = self
+            .history
+            .last_done()?
+            .maybe_edit()
+            .as_ref()
+            .ok_or(HistoryError::NoPreviousEdit)?
+            .reverse();
+        self.apply_edit(&edit);
 
         Ok(self.history.undo()?)
     }
 
-    //#[debug_requires(self.ongoing.is_none())]
+    //#[debug_requires(self.ongoing_command.is_none())]
     pub fn redo(&mut self) -> Result<(), InvokerError> {
-        let command = self.history.last_undone()?.clone();
-        let mut execute = self.execute_stepper(command)?;
+        let entry = self.history.last_undone()?;
 
-        loop {
-            let status = match execute.step(self) {
-                Ok(status) => status,
-                Err(err) => return Err(err),
-            };
+        if let Some(edit) = entry.maybe_edit().clone() {
+            self.apply_edit(&edit);
+            return Ok(self.history.redo()?);
+        }
+
+        // Commands loaded from a file haven't been executed yet, so they have no edit to apply
+        // and are executed anew instead.
+        let command = entry.command().clone();
+        let recording = self.autorouter.start_recording();
+        let mut execute = match self.dispatch_command(&command) {
+            Ok(execute) => execute,
+            Err(err) => {
+                self.end_redo(recording, false)?;
+                return Err(err);
+            }
+        };
 
-            if let ControlFlow::Break(..) = status {
-                return Ok(self.history.redo()?);
+        loop {
+            match execute.step(self) {
+                Ok(ControlFlow::Break(..)) => return self.end_redo(recording, true),
+                Ok(ControlFlow::Continue(())) => (),
+                Err(err) => {
+                    self.end_redo(recording, false)?;
+                    return Err(err);
+                }
             }
         }
     }
 
+    /// Ends redoing a command that was executed anew, moving it back to the done commands
+    /// along with the edit it made, as [`Invoker::end_ongoing_command`] does for executed
+    /// commands. A failed command that changed nothing stays undone.
+    fn end_redo(
+        &mut self,
+        recording: AutorouterRecording,
+        finished: bool,
+    ) -> Result<(), InvokerError> {
+        let edit = self.autorouter.stop_recording(recording);
+
+        if edit.edits_layout() {
+            self.autorouter.board.layout_mut().fill_pours();
+        }
+
+        if finished || !edit.is_empty() {
+            self.history.redo_with_edit(edit)?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_edit(&mut self, edit: &AutorouterEdit) {
+        self.autorouter.apply_edit(edit);
+
+        if edit.edits_layout() {
+            self.autorouter.board.layout_mut().fill_pours();
+        }
+    }
+
     #[debug_requires(self.ongoing_command.is_none())]
     pub fn replay(&mut self, history: History) {
         let (done, undone) = history.dissolve();
 
-        for command in done {
-            self.execute(command);
+        for entry in done {
+            self.execute(entry.command().clone());
         }
 
-        self.history.set_undone(undone.into_iter());
+        self.history
+            .set_undone(undone.iter().map(|entry| entry.command().clone()));
     }
 }
@@ -2,15 +2,21 @@
 
 pub mod autoroute;
 mod autorouter;
+pub mod check_design_rules;
 pub mod compare_detours;
+pub mod diff_pair;
+pub mod edit;
 pub mod execution;
 pub mod history;
 pub mod invoker;
 pub mod measure_length;
+pub mod move_component;
 pub mod place_via;
 pub mod pointroute;
+pub mod ratline_ordering;
 pub mod ratsnest;
 pub mod remove_bands;
 pub mod selection;
+pub mod tune_length;
 
 pub use autorouter::*;
@@ -0,0 +1,287 @@
+//! Provides functionality for moving a component, i.e. the pins of a footprint placed on the
+//! board, with the bands joined to or wrapped around it ripped up and routed anew, so that they
+//! follow the component to where it's moved.
+
+use std::ops::ControlFlow;
+
+use geo::Point;
+use petgraph::graph::EdgeIndex;
+
+use crate::{
+    board::mesadata::AccessMesadata,
+    drawing::{
+        band::BandUid,
+        dot::FixedDotIndex,
+        gear::{GearIndex, GetNextGear},
+        graph::PrimitiveIndex,
+        loose::LooseIndex,
+        Infringement,
+    },
+    geometry::{primitive::PrimitiveShape, GenericNode},
+    graph::MakeRef,
+    router::{navcord::NavcordStepper, navmesh::Navmesh},
+    stepper::Step,
+    triangulation::GetTrianvertexNodeIndex,
+};
+
+use super::{
+    autoroute::{AutorouteExecutionStepper, AutorouteReport},
+    invoker::{GetGhosts, GetMaybeNavcord, GetMaybeNavmesh, GetObstacles},
+    Autorouter, AutorouterError, AutorouterOptions,
+};
+
+pub struct MoveComponentExecutionStepper {
+    component: String,
+    offset: Point,
+    options: AutorouterOptions,
+    /// Whether the component has been moved already.
+    moved: bool,
+    /// The rerouting of the ratlines whose bands were ripped up to move the component.
+    maybe_reroute: Option<AutorouteExecutionStepper>,
+}
+
+impl MoveComponentExecutionStepper {
+    pub fn new(
+        autorouter: &Autorouter<impl AccessMesadata>,
+        component: &str,
+        offset: Point,
+        options: AutorouterOptions,
+    ) -> Result<Self, AutorouterError> {
+        if autorouter.board.component_nodes(component).next().is_none() {
+            return Err(AutorouterError::NoSuchComponent(component.to_string()));
+        }
+
+        Ok(Self {
+            component: component.to_string(),
+            offset,
+            options,
+            moved: false,
+            maybe_reroute: None,
+        })
+    }
+
+    /// Moves the component, ripping up the routed ratlines whose bands are joined to it, wrapped
+    /// around it or in its way, and returns these ratlines. If the component can't be moved,
+    /// the layout is left as it was.
+    fn move_component(
+        &self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+    ) -> Result<Vec<EdgeIndex<usize>>, AutorouterError> {
+        let recording = autorouter.start_recording();
+        let result = self.rip_up_and_move(autorouter);
+
+        if result.is_err() {
+            autorouter.revert_to(&recording);
+        }
+
+        autorouter.stop_recording(recording);
+        result
+    }
+
+    fn rip_up_and_move(
+        &self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+    ) -> Result<Vec<EdgeIndex<usize>>, AutorouterError> {
+        let mut ratlines = self.attached_ratlines(autorouter);
+
+        for ratline in self.wrapping_ratlines(autorouter) {
+            if !ratlines.contains(&ratline) {
+                ratlines.push(ratline);
+            }
+        }
+
+        for ratline in ratlines.iter() {
+            autorouter.rip_up_ratline(*ratline)?;
+        }
+
+        // Bands that aren't routed along any ratline can't be routed anew, and their bends
+        // wouldn't follow the component, so these keep it in place.
+        if self.component_dots(autorouter).iter().any(|dot| {
+            GearIndex::FixedDot(*dot)
+                .ref_(autorouter.board.layout().drawing())
+                .next_gear()
+                .is_some()
+        }) {
+            return Err(AutorouterError::CouldNotMoveComponent(
+                self.component.clone(),
+            ));
+        }
+
+        loop {
+            let Err(Infringement(_, infringee)) = autorouter
+                .board
+                .move_component(&self.component, self.offset)
+            else {
+                return Ok(ratlines);
+            };
+
+            // A band in the way of the component is ripped up to be routed anew after it.
+            match self.routed_ratline_of(autorouter, infringee) {
+                Some(ratline) if !ratlines.contains(&ratline) => {
+                    autorouter.rip_up_ratline(ratline)?;
+                    ratlines.push(ratline);
+                }
+                _ => {
+                    return Err(AutorouterError::CouldNotMoveComponent(
+                        self.component.clone(),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn component_dots(&self, autorouter: &Autorouter<impl AccessMesadata>) -> Vec<FixedDotIndex> {
+        autorouter
+            .board
+            .component_nodes(&self.component)
+            .filter_map(|node| match node {
+                GenericNode::Primitive(PrimitiveIndex::FixedDot(dot)) => Some(dot),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Finds the routed ratlines with an endpoint on a pin of the component.
+    fn attached_ratlines(
+        &self,
+        autorouter: &Autorouter<impl AccessMesadata>,
+    ) -> Vec<EdgeIndex<usize>> {
+        let graph = autorouter.ratsnest.graph();
+        let is_on_component = |ratvertex| {
+            autorouter
+                .board
+                .node_pinname(&graph.node_weight(ratvertex).unwrap().node_index().into())
+                .and_then(|pinname| autorouter.board.pinname_component(pinname))
+                .is_some_and(|component| *component == self.component)
+        };
+
+        graph
+            .edge_indices()
+            .filter(|ratline| graph.edge_weight(*ratline).unwrap().band_termseg.is_some())
+            .filter(|ratline| {
+                let (source, target) = graph.edge_endpoints(*ratline).unwrap();
+                is_on_component(source) || is_on_component(target)
+            })
+            .collect()
+    }
+
+    /// Finds the routed ratlines whose bands are wrapped around the dots of the component.
+    fn wrapping_ratlines(
+        &self,
+        autorouter: &Autorouter<impl AccessMesadata>,
+    ) -> Vec<EdgeIndex<usize>> {
+        let drawing = autorouter.board.layout().drawing();
+        let mut ratlines = vec![];
+
+        for dot in self.component_dots(autorouter) {
+            let mut maybe_gear = GearIndex::FixedDot(dot).ref_(drawing).next_gear();
+
+            while let Some(bend) = maybe_gear {
+                if let Some(ratline) = self.routed_ratline_of(autorouter, bend.into()) {
+                    if !ratlines.contains(&ratline) {
+                        ratlines.push(ratline);
+                    }
+                }
+
+                maybe_gear = GearIndex::LooseBend(bend).ref_(drawing).next_gear();
+            }
+        }
+
+        ratlines
+    }
+
+    /// Finds the routed ratline whose band, or one of the bands leading into its vias, the
+    /// primitive belongs to, if any.
+    fn routed_ratline_of(
+        &self,
+        autorouter: &Autorouter<impl AccessMesadata>,
+        primitive: PrimitiveIndex,
+    ) -> Option<EdgeIndex<usize>> {
+        let drawing = autorouter.board.layout().drawing();
+        let loose = LooseIndex::try_from(primitive).ok()?;
+        let band: BandUid = drawing.collect().loose_band_uid(loose);
+        let graph = autorouter.ratsnest.graph();
+
+        graph.edge_indices().find(|ratline| {
+            let weight = graph.edge_weight(*ratline).unwrap();
+
+            weight
+                .band_termseg
+                .into_iter()
+                .chain(weight.vias.iter().map(|(via_band, _)| *via_band))
+                .any(|termseg| drawing.collect().loose_band_uid(termseg.into()) == band)
+        })
+    }
+}
+
+impl<M: AccessMesadata> Step<Autorouter<M>, AutorouteReport> for MoveComponentExecutionStepper {
+    type Error = AutorouterError;
+
+    fn step(
+        &mut self,
+        autorouter: &mut Autorouter<M>,
+    ) -> Result<ControlFlow<AutorouteReport>, AutorouterError> {
+        if !self.moved {
+            let mut ratlines = self.move_component(autorouter)?;
+            self.moved = true;
+
+            for ratline in ratlines.iter() {
+                autorouter.ratsnest.unassign_ratline(*ratline);
+            }
+
+            autorouter
+                .ratsnest
+                .update_positions(autorouter.board.layout());
+
+            if !ratlines.is_empty() {
+                self.options
+                    .ratline_ordering
+                    .sort(autorouter, &mut ratlines, &self.options);
+                self.maybe_reroute = Some(autorouter.autoroute_ratlines(ratlines, self.options)?);
+            }
+
+            return Ok(ControlFlow::Continue(()));
+        }
+
+        let Some(ref mut reroute) = self.maybe_reroute else {
+            return Ok(ControlFlow::Break(AutorouteReport::default()));
+        };
+
+        Ok(match reroute.step(autorouter)? {
+            ControlFlow::Continue(..) => ControlFlow::Continue(()),
+            ControlFlow::Break(report) => ControlFlow::Break(report),
+        })
+    }
+}
+
+impl GetMaybeNavmesh for MoveComponentExecutionStepper {
+    fn maybe_navmesh(&self) -> Option<&Navmesh> {
+        self.maybe_reroute
+            .as_ref()
+            .and_then(|reroute| reroute.maybe_navmesh())
+    }
+}
+
+impl GetMaybeNavcord for MoveComponentExecutionStepper {
+    fn maybe_navcord(&self) -> Option<&NavcordStepper> {
+        self.maybe_reroute
+            .as_ref()
+            .and_then(|reroute| reroute.maybe_navcord())
+    }
+}
+
+impl GetGhosts for MoveComponentExecutionStepper {
+    fn ghosts(&self) -> &[PrimitiveShape] {
+        self.maybe_reroute
+            .as_ref()
+            .map_or(&[], |reroute| reroute.ghosts())
+    }
+}
+
+impl GetObstacles for MoveComponentExecutionStepper {
+    fn obstacles(&self) -> &[PrimitiveIndex] {
+        self.maybe_reroute
+            .as_ref()
+            .map_or(&[], |reroute| reroute.obstacles())
+    }
+}
@@ -6,6 +6,7 @@ use crate::{
     board::mesadata::AccessMesadata,
     drawing::graph::PrimitiveIndex,
     geometry::primitive::PrimitiveShape,
+    graph::GenericIndex,
     layout::via::ViaWeight,
     router::{navcord::NavcordStepper, navmesh::Navmesh},
 };
@@ -18,27 +19,27 @@ use super::{
 #[derive(Debug)]
 pub struct PlaceViaExecutionStepper {
     weight: ViaWeight,
-    done: bool,
+    maybe_via: Option<GenericIndex<ViaWeight>>,
 }
 
 impl PlaceViaExecutionStepper {
     pub fn new(weight: ViaWeight) -> Result<Self, AutorouterError> {
         Ok(Self {
             weight,
-            done: false,
+            maybe_via: None,
         })
     }
 
     pub fn doit(
         &mut self,
         autorouter: &mut Autorouter<impl AccessMesadata>,
-    ) -> Result<(), AutorouterError> {
-        if !self.done {
-            self.done = true;
-            autorouter.board.layout_mut().add_via(self.weight)?;
-            Ok(())
+    ) -> Result<GenericIndex<ViaWeight>, AutorouterError> {
+        if let Some(via) = self.maybe_via {
+            Ok(via)
         } else {
-            Ok(())
+            let via = autorouter.board.layout_mut().add_via(self.weight)?;
+            self.maybe_via = Some(via);
+            Ok(via)
         }
     }
 }
@@ -7,13 +7,19 @@ use crate::{
     drawing::{
         band::BandTermsegIndex,
         dot::{FixedDotIndex, FixedDotWeight},
+        graph::{GetLayer, GetMaybeNet, PrimitiveIndex},
+        rules::ObjectKind,
     },
+    geometry::primitive::PrimitiveShape,
     math::Circle,
-    router::{route::RouteStepper, Router},
+    router::{navcord::NavcordStepper, navmesh::Navmesh, route::RouteStepper, Router},
     stepper::Step,
 };
 
-use super::{Autorouter, AutorouterError, AutorouterOptions};
+use super::{
+    invoker::{GetGhosts, GetMaybeNavcord, GetMaybeNavmesh, GetObstacles},
+    Autorouter, AutorouterError, AutorouterOptions,
+};
 
 pub struct PointrouteExecutionStepper {
     point: Point,
@@ -28,14 +34,22 @@ impl PointrouteExecutionStepper {
         point: Point,
         options: AutorouterOptions,
     ) -> Result<Self, AutorouterError> {
+        let width = autorouter.band_width(origin, &options.router_options);
+
+        // The destination belongs to the net and layer of the origin, as a netless dot would be
+        // an obstacle to the band routed to it.
+        let origin_primitive = autorouter.board.layout().drawing().primitive(origin);
+        let maybe_net = origin_primitive.maybe_net();
+        let layer = origin_primitive.layer();
         let destination = autorouter.board.add_fixed_dot_infringably(
             FixedDotWeight {
                 circle: Circle {
                     pos: point,
-                    r: options.router_options.routed_band_width / 2.0,
+                    r: width / 2.0,
                 },
-                layer: 0,
-                maybe_net: None,
+                layer,
+                maybe_net,
+                kind: ObjectKind::Wire,
             },
             None,
         );
@@ -44,11 +58,7 @@ impl PointrouteExecutionStepper {
 
         Ok(Self {
             point,
-            route: router.route(
-                origin,
-                destination,
-                options.router_options.routed_band_width,
-            )?,
+            route: router.route(origin, destination, width)?,
             options,
         })
     }
@@ -65,3 +75,27 @@ impl<M: AccessMesadata> Step<Autorouter<M>, BandTermsegIndex> for PointrouteExec
         Ok(self.route.step(&mut router)?)
     }
 }
+
+impl GetMaybeNavmesh for PointrouteExecutionStepper {
+    fn maybe_navmesh(&self) -> Option<&Navmesh> {
+        Some(self.route.navmesh())
+    }
+}
+
+impl GetMaybeNavcord for PointrouteExecutionStepper {
+    fn maybe_navcord(&self) -> Option<&NavcordStepper> {
+        Some(self.route.navcord())
+    }
+}
+
+impl GetGhosts for PointrouteExecutionStepper {
+    fn ghosts(&self) -> &[PrimitiveShape] {
+        self.route.ghosts()
+    }
+}
+
+impl GetObstacles for PointrouteExecutionStepper {
+    fn obstacles(&self) -> &[PrimitiveIndex] {
+        self.route.obstacles()
+    }
+}
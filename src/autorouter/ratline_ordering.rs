@@ -0,0 +1,177 @@
+//! Provides the strategies by which an autoroute orders the ratlines it routes, as the bands
+//! routed first are the ones the later ones have to go around.
+
+use std::collections::HashMap;
+
+use geo::{
+    line_intersection::{line_intersection, LineIntersection},
+    EuclideanLength, Line,
+};
+use petgraph::graph::EdgeIndex;
+use serde::{Deserialize, Serialize};
+
+use crate::{board::mesadata::AccessMesadata, drawing::graph::GetMaybeNet, stepper::Step};
+
+use super::{Autorouter, AutorouterOptions};
+
+/// The order in which an autoroute routes the ratlines of its selection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RatlineOrdering {
+    /// Route the ratlines in the order they're selected in.
+    #[default]
+    Unordered,
+    /// Route the shortest ratlines first.
+    ShortestFirst,
+    /// Route the longest ratlines first.
+    LongestFirst,
+    /// Route the ratlines of the nets of higher priority first, as given by their net classes.
+    NetClassPriority,
+    /// Route the ratlines that cross the fewest other ones first, as these crossings estimate
+    /// how congested the area the ratline spans will be.
+    LeastCongestedFirst,
+    /// Route first the ratlines that make for shorter detours when routed before the others.
+    ///
+    /// Every pair of ratlines is routed once in each order, and each ratline scores a point for
+    /// every other one that is better routed after it. That is n(n - 1) autoroutes of two
+    /// ratlines for n selected ratlines, so this is only practical for small selections.
+    PairwiseDetours,
+}
+
+impl RatlineOrdering {
+    /// Sorts the ratlines in the order they're to be routed in. Ratlines the strategy doesn't
+    /// tell apart keep their order.
+    pub fn sort(
+        self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+        ratlines: &mut [EdgeIndex<usize>],
+        options: &AutorouterOptions,
+    ) {
+        let keys: HashMap<EdgeIndex<usize>, f64> = match self {
+            RatlineOrdering::Unordered => return,
+            RatlineOrdering::ShortestFirst => ratlines
+                .iter()
+                .map(|ratline| {
+                    (
+                        *ratline,
+                        ratline_line(autorouter, *ratline).euclidean_length(),
+                    )
+                })
+                .collect(),
+            RatlineOrdering::LongestFirst => ratlines
+                .iter()
+                .map(|ratline| {
+                    (
+                        *ratline,
+                        -ratline_line(autorouter, *ratline).euclidean_length(),
+                    )
+                })
+                .collect(),
+            RatlineOrdering::NetClassPriority => ratlines
+                .iter()
+                .map(|ratline| (*ratline, -(ratline_priority(autorouter, *ratline) as f64)))
+                .collect(),
+            RatlineOrdering::LeastCongestedFirst => crossing_counts(autorouter, ratlines),
+            RatlineOrdering::PairwiseDetours => detour_scores(autorouter, ratlines, options),
+        };
+
+        ratlines.sort_by(|ratline1, ratline2| keys[ratline1].total_cmp(&keys[ratline2]));
+    }
+}
+
+fn ratline_line(autorouter: &Autorouter<impl AccessMesadata>, ratline: EdgeIndex<usize>) -> Line {
+    let graph = autorouter.ratsnest.graph();
+    let (source, target) = graph.edge_endpoints(ratline).unwrap();
+
+    Line::new(
+        graph.node_weight(source).unwrap().pos,
+        graph.node_weight(target).unwrap().pos,
+    )
+}
+
+fn ratline_priority(
+    autorouter: &mut Autorouter<impl AccessMesadata>,
+    ratline: EdgeIndex<usize>,
+) -> i64 {
+    let (source, _) = autorouter.ratline_endpoints(ratline);
+    let layout = autorouter.board.layout();
+
+    layout
+        .drawing()
+        .primitive(source)
+        .maybe_net()
+        .map_or(0, |net| layout.rules().net_priority(net))
+}
+
+/// Counts, for each ratline, how many of the other ones it properly crosses.
+fn crossing_counts(
+    autorouter: &Autorouter<impl AccessMesadata>,
+    ratlines: &[EdgeIndex<usize>],
+) -> HashMap<EdgeIndex<usize>, f64> {
+    let lines: Vec<Line> = ratlines
+        .iter()
+        .map(|ratline| ratline_line(autorouter, *ratline))
+        .collect();
+
+    ratlines
+        .iter()
+        .zip(lines.iter())
+        .map(|(ratline, line)| {
+            let crossings = lines
+                .iter()
+                .filter(|other| {
+                    matches!(
+                        line_intersection(*line, **other),
+                        Some(LineIntersection::SinglePoint {
+                            is_proper: true,
+                            ..
+                        })
+                    )
+                })
+                .count();
+
+            (*ratline, crossings as f64)
+        })
+        .collect()
+}
+
+/// Routes every pair of ratlines in both orders and scores each ratline by how many of the
+/// other ones are better routed after it, negated so that the highest scores sort first.
+///
+/// Pairs that fail to route in either order score half a point to each of their ratlines.
+///
+/// The number of comparisons grows quadratically with the number of ratlines, each of them
+/// routing two ratlines twice and reverting the layout in between, which makes this by far the
+/// costliest of the orderings.
+fn detour_scores(
+    autorouter: &mut Autorouter<impl AccessMesadata>,
+    ratlines: &[EdgeIndex<usize>],
+    options: &AutorouterOptions,
+) -> HashMap<EdgeIndex<usize>, f64> {
+    let mut scores: HashMap<EdgeIndex<usize>, f64> =
+        ratlines.iter().map(|ratline| (*ratline, 0.0)).collect();
+
+    for (i, ratline1) in ratlines.iter().enumerate() {
+        for ratline2 in ratlines[i + 1..].iter() {
+            let recording = autorouter.start_recording();
+            let result = autorouter
+                .compare_detours_ratlines(*ratline1, *ratline2, *options)
+                .and_then(|mut compare_detours| compare_detours.finish(autorouter));
+
+            // A failed comparison leaves behind whatever it routed before failing, along with
+            // the ratlines it assigned bands to.
+            let edit = autorouter.stop_recording(recording);
+            autorouter.apply_edit(&edit.reverse());
+
+            let (score1, score2) = match result {
+                Ok((length1, length2)) if length1 < length2 => (1.0, 0.0),
+                Ok((length1, length2)) if length1 > length2 => (0.0, 1.0),
+                _ => (0.5, 0.5),
+            };
+
+            *scores.get_mut(ratline1).unwrap() -= score1;
+            *scores.get_mut(ratline2).unwrap() -= score2;
+        }
+    }
+
+    scores
+}
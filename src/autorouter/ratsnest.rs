@@ -16,24 +16,26 @@ use petgraph::{
 use spade::{HasPosition, InsertionError, Point2};
 
 use crate::{
+    board::{mesadata::AccessMesadata, Board},
     drawing::{
-        band::BandTermsegIndex,
+        band::{BandTermsegIndex, BandUid},
         dot::FixedDotIndex,
         graph::{GetMaybeNet, MakePrimitive, PrimitiveIndex},
         primitive::MakePrimitiveShape,
         rules::AccessRules,
     },
-    geometry::{compound::ManageCompounds, shape::AccessShape},
+    geometry::{compound::ManageCompounds, shape::AccessShape, GeometryLabel},
     graph::{GenericIndex, GetPetgraphIndex},
     layout::{
         poly::{MakePolyShape, PolyWeight},
-        Layout,
+        via::ViaWeight,
+        CompoundWeight, Layout,
     },
     triangulation::{GetTrianvertexNodeIndex, Triangulation},
 };
 
 #[enum_dispatch(GetPetgraphIndex)]
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RatvertexIndex {
     FixedDot(FixedDotIndex),
     Poly(GenericIndex<PolyWeight>),
@@ -48,10 +50,19 @@ impl From<RatvertexIndex> for crate::layout::NodeIndex {
     }
 }
 
+/// What the copies of a multi-layer ratvertex, one per layer, all belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum MultilayerRatvertexKey<'a> {
+    Pin(&'a str),
+    Via(GenericIndex<ViaWeight>),
+    Lone(RatvertexIndex),
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct RatvertexWeight {
     vertex: RatvertexIndex,
     pub pos: Point,
+    pub layer: usize,
 }
 
 impl GetTrianvertexNodeIndex<RatvertexIndex> for RatvertexWeight {
@@ -67,49 +78,64 @@ impl HasPosition for RatvertexWeight {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct RatlineWeight {
     pub band_termseg: Option<BandTermsegIndex>,
+    /// The vias the ratline was routed through, each with the terminating seg of the band that
+    /// leads into it, in the order they were placed.
+    pub vias: Vec<(BandTermsegIndex, GenericIndex<ViaWeight>)>,
 }
 
+#[derive(Clone)]
 pub struct Ratsnest {
     graph: UnGraph<RatvertexWeight, RatlineWeight, usize>,
 }
 
 impl Ratsnest {
-    pub fn new(layout: &Layout<impl AccessRules>) -> Result<Self, InsertionError> {
+    pub fn new(board: &Board<impl AccessMesadata>) -> Result<Self, InsertionError> {
+        let layout = board.layout();
         let mut unionfind = UnionFind::new(layout.drawing().geometry().graph().node_bound());
 
+        // Loose bends are also linked to what they wrap around, which they aren't connected to.
         for edge in layout.drawing().geometry().graph().edge_references() {
-            unionfind.union(edge.source(), edge.target());
+            if matches!(
+                edge.weight(),
+                GeometryLabel::Joined | GeometryLabel::Compound
+            ) {
+                unionfind.union(edge.source(), edge.target());
+            }
         }
 
         let mut this = Self {
             graph: UnGraph::default(),
         };
 
-        let mut triangulations = HashMap::new();
-        let node_bound = layout.drawing().geometry().graph().node_bound();
+        let mut net_ratvertices: HashMap<usize, Vec<RatvertexWeight>> = HashMap::new();
 
         for layer in 0..layout.drawing().layer_count() {
             let mut handle_rvw = |maybe_net: Option<usize>, vertex: RatvertexIndex, pos: Point| {
                 if let Some(net) = maybe_net {
-                    triangulations
-                        .entry((layer, net))
-                        .or_insert_with(|| Triangulation::new(node_bound))
-                        .add_vertex(RatvertexWeight { vertex, pos })?;
+                    net_ratvertices
+                        .entry(net)
+                        .or_default()
+                        .push(RatvertexWeight { vertex, pos, layer });
                 }
-                Ok(())
             };
 
             for node in layout.drawing().layer_primitive_nodes(layer) {
                 if let PrimitiveIndex::FixedDot(dot) = node {
-                    if layout.polys(dot).next().is_none() {
+                    // The dots of polygons are accounted for by the polygons themselves.
+                    if !layout.polys(dot).any(|compound| {
+                        matches!(
+                            layout.drawing().compound_weight(compound),
+                            CompoundWeight::Poly(..)
+                        )
+                    }) {
                         handle_rvw(
                             layout.drawing().primitive(dot).maybe_net(),
                             RatvertexIndex::FixedDot(dot),
                             node.primitive(layout.drawing()).shape().center(),
-                        )?;
+                        );
                     }
                 }
             }
@@ -119,24 +145,65 @@ impl Ratsnest {
                     layout.drawing().compound_weight(poly.into()).maybe_net(),
                     RatvertexIndex::Poly(poly),
                     layout.poly(poly).shape().center(),
-                )?;
+                );
             }
         }
 
-        for ((_layer, _net), triangulation) in triangulations {
+        let node_bound = layout.drawing().geometry().graph().node_bound();
+
+        for (_net, ratvertices) in net_ratvertices {
+            // Through-hole pads and vias have copper on several layers. The copies of a pin or a
+            // via are merged into one multi-layer vertex, so that a single spanning tree is
+            // computed for the whole net across all layers.
+            let mut multilayer_ratvertices: Vec<Vec<NodeIndex<usize>>> = vec![];
+            let mut key_to_multilayer_ratvertex: HashMap<MultilayerRatvertexKey, usize> =
+                HashMap::new();
+            // The triangulation is indexed by the ratvertex of the first copy.
+            let mut first_copy_to_multilayer_ratvertex: HashMap<RatvertexIndex, usize> =
+                HashMap::new();
+            let mut triangulation =
+                Triangulation::<RatvertexIndex, RatvertexWeight, ()>::new(node_bound);
+
+            for weight in ratvertices {
+                let node = this.graph.add_node(weight);
+                let key = Self::multilayer_ratvertex_key(board, weight.vertex);
+
+                if let Some(&multilayer_ratvertex) = key_to_multilayer_ratvertex.get(&key) {
+                    // The copies are connected through their pin or via, so a connection
+                    // already made on any layer counts as made on all of them.
+                    let first_copy = multilayer_ratvertices[multilayer_ratvertex][0];
+                    unionfind.union(
+                        this.graph[first_copy].vertex.petgraph_index(),
+                        weight.vertex.petgraph_index(),
+                    );
+                    multilayer_ratvertices[multilayer_ratvertex].push(node);
+                } else {
+                    key_to_multilayer_ratvertex.insert(key, multilayer_ratvertices.len());
+                    first_copy_to_multilayer_ratvertex
+                        .insert(weight.vertex, multilayer_ratvertices.len());
+                    multilayer_ratvertices.push(vec![node]);
+                    triangulation.add_vertex(weight)?;
+                }
+            }
+
             let mut map = Vec::new();
 
             for element in petgraph::algo::min_spanning_tree(&triangulation) {
                 match element {
                     Element::Node { weight } => {
-                        map.push(this.graph.add_node(weight));
+                        map.push(first_copy_to_multilayer_ratvertex[&weight.vertex]);
                     }
                     Element::Edge {
                         source,
                         target,
-                        weight,
+                        weight: _,
                     } => {
-                        this.graph.add_edge(map[source], map[target], weight.weight);
+                        let (source, target) = this.multilayer_ratline_endpoints(
+                            &multilayer_ratvertices[map[source]],
+                            &multilayer_ratvertices[map[target]],
+                        );
+                        this.graph
+                            .add_edge(source, target, RatlineWeight::default());
                     }
                 }
             }
@@ -155,6 +222,60 @@ impl Ratsnest {
         Ok(this)
     }
 
+    /// Finds what the ratvertex is a copy of: the pin of its pad or the via of its dot.
+    pub(crate) fn multilayer_ratvertex_key(
+        board: &Board<impl AccessMesadata>,
+        vertex: RatvertexIndex,
+    ) -> MultilayerRatvertexKey<'_> {
+        if let Some(pinname) = board.node_pinname(&vertex.into()) {
+            return MultilayerRatvertexKey::Pin(pinname);
+        }
+
+        if let RatvertexIndex::FixedDot(dot) = vertex {
+            let layout = board.layout();
+
+            if let Some(via) = layout.drawing().compounds(dot).find_map(|compound| {
+                matches!(
+                    layout.drawing().compound_weight(compound),
+                    CompoundWeight::Via(..)
+                )
+                .then(|| GenericIndex::<ViaWeight>::new(compound.petgraph_index()))
+            }) {
+                return MultilayerRatvertexKey::Via(via);
+            }
+        }
+
+        MultilayerRatvertexKey::Lone(vertex)
+    }
+
+    /// Picks the copies of two multi-layer vertices to join with a ratline: the ones on the
+    /// lowest layer both have copper on or, if they have no layer in common, their first copies,
+    /// between which the ratline then requires a layer change.
+    fn multilayer_ratline_endpoints(
+        &self,
+        source_copies: &[NodeIndex<usize>],
+        target_copies: &[NodeIndex<usize>],
+    ) -> (NodeIndex<usize>, NodeIndex<usize>) {
+        source_copies
+            .iter()
+            .flat_map(|source| target_copies.iter().map(move |target| (*source, *target)))
+            .filter(|(source, target)| {
+                self.graph.node_weight(*source).unwrap().layer
+                    == self.graph.node_weight(*target).unwrap().layer
+            })
+            .min_by_key(|(source, _)| self.graph.node_weight(*source).unwrap().layer)
+            .unwrap_or((source_copies[0], target_copies[0]))
+    }
+
+    /// Whether the endpoints of the ratline lie on different layers, so that it can only be
+    /// routed through vias.
+    pub fn requires_layer_change(&self, ratline: EdgeIndex<usize>) -> bool {
+        let (source, target) = self.graph.edge_endpoints(ratline).unwrap();
+
+        self.graph.node_weight(source).unwrap().layer
+            != self.graph.node_weight(target).unwrap().layer
+    }
+
     pub fn assign_band_termseg_to_ratline(
         &mut self,
         ratline: EdgeIndex<usize>,
@@ -163,6 +284,56 @@ impl Ratsnest {
         self.graph.edge_weight_mut(ratline).unwrap().band_termseg = Some(termseg);
     }
 
+    pub fn assign_vias_to_ratline(
+        &mut self,
+        ratline: EdgeIndex<usize>,
+        vias: Vec<(BandTermsegIndex, GenericIndex<ViaWeight>)>,
+    ) {
+        self.graph.edge_weight_mut(ratline).unwrap().vias = vias;
+    }
+
+    /// Makes the ratline that was routed with the given band refer to the band it was redrawn
+    /// as, be it the ratline's own band or one leading into one of its vias.
+    pub fn reassign_band(&mut self, band: BandUid, termseg: BandTermsegIndex) {
+        let is_of_band = |seg: &BandTermsegIndex| {
+            seg.petgraph_index() == band.0.petgraph_index()
+                || seg.petgraph_index() == band.1.petgraph_index()
+        };
+
+        for weight in self.graph.edge_weights_mut() {
+            if weight.band_termseg.as_ref().is_some_and(is_of_band) {
+                weight.band_termseg = Some(termseg);
+            }
+
+            for (via_band, _) in weight.vias.iter_mut() {
+                if is_of_band(via_band) {
+                    *via_band = termseg;
+                }
+            }
+        }
+    }
+
+    /// Moves the ratvertices to where their dots and polygons now are, after these were moved
+    /// along with their component.
+    pub fn update_positions(&mut self, layout: &Layout<impl AccessRules>) {
+        for weight in self.graph.node_weights_mut() {
+            weight.pos = match weight.vertex {
+                RatvertexIndex::FixedDot(dot) => dot.primitive(layout.drawing()).shape().center(),
+                RatvertexIndex::Poly(poly) => layout.poly(poly).shape().center(),
+            };
+        }
+    }
+
+    /// Forgets the band and vias the ratline was routed with, once they've been removed.
+    pub fn unassign_ratline(&mut self, ratline: EdgeIndex<usize>) {
+        *self.graph.edge_weight_mut(ratline).unwrap() = RatlineWeight::default();
+    }
+
+    /// Assigns the ratline the band and vias it had when an edit was recorded.
+    pub fn set_ratline_weight(&mut self, ratline: EdgeIndex<usize>, weight: RatlineWeight) {
+        *self.graph.edge_weight_mut(ratline).unwrap() = weight;
+    }
+
     pub fn graph(&self) -> &UnGraph<RatvertexWeight, RatlineWeight, usize> {
         &self.graph
     }
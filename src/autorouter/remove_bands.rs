@@ -35,8 +35,11 @@ impl RemoveBandsExecutionStepper {
             self.done = true;
 
             for selector in self.selection.selectors() {
-                let band = autorouter.board.bandname_band(&selector.band).unwrap().0;
-                autorouter.board.layout_mut().remove_band(band);
+                let band = *autorouter.board.bandname_band(&selector.band).unwrap();
+                autorouter
+                    .board
+                    .remove_band(band)
+                    .map_err(|_| AutorouterError::CouldNotRemoveBand(band.0))?;
             }
             Ok(())
         } else {
@@ -0,0 +1,236 @@
+//! Provides functionality for tuning the lengths of selected bands to a target length by laying
+//! meanders into them, as is needed to match the lengths of the bands of a bus.
+//!
+//! All the selected bands are tuned to the same target length, one by one. Tuning groups, in
+//! which bands are matched to a length of their group, such as that of its longest band, aren't
+//! implemented.
+
+use crate::{
+    board::{mesadata::AccessMesadata, BandName},
+    drawing::{band::BandUid, graph::PrimitiveIndex},
+    geometry::{primitive::PrimitiveShape, shape::MeasureLength},
+    graph::MakeRef,
+    router::{
+        meander::{Meander, Meanderer},
+        navcord::NavcordStepper,
+        navmesh::Navmesh,
+    },
+};
+
+use super::{
+    invoker::{GetGhosts, GetMaybeNavcord, GetMaybeNavmesh, GetObstacles},
+    selection::BandSelection,
+    Autorouter, AutorouterError,
+};
+
+/// How many times the amplitude of a meander is doubled in search of one long enough.
+const MAX_AMPLITUDE_DOUBLINGS: usize = 16;
+/// How many times the amplitude of a meander is bisected in search of one of the right length.
+const MAX_AMPLITUDE_BISECTIONS: usize = 32;
+
+pub struct TuneLengthExecutionStepper {
+    selection: BandSelection,
+    target_length: f64,
+    tolerance: f64,
+    maybe_lengths: Option<Vec<(BandName, f64)>>,
+}
+
+impl TuneLengthExecutionStepper {
+    pub fn new(
+        selection: &BandSelection,
+        target_length: f64,
+        tolerance: f64,
+    ) -> Result<Self, AutorouterError> {
+        Ok(Self {
+            selection: selection.clone(),
+            target_length,
+            tolerance,
+            maybe_lengths: None,
+        })
+    }
+
+    /// Tunes each selected band to the target length, returning the lengths they ended up
+    /// with.
+    pub fn doit(
+        &mut self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+    ) -> Result<Vec<(BandName, f64)>, AutorouterError> {
+        if let Some(lengths) = &self.maybe_lengths {
+            return Ok(lengths.clone());
+        }
+
+        let mut lengths = vec![];
+
+        for selector in self.selection.selectors() {
+            let band = *autorouter.board.bandname_band(&selector.band).unwrap();
+            let length = self
+                .tune_band(autorouter, band)
+                .ok_or_else(|| AutorouterError::CouldNotTuneLength(selector.band.clone()))?;
+            lengths.push((selector.band.clone(), length));
+        }
+
+        self.maybe_lengths = Some(lengths.clone());
+        Ok(lengths)
+    }
+
+    /// Lays a meander into the band that brings it within the tolerance of the target length,
+    /// returning the length it was brought to.
+    ///
+    /// Meanders spanning the whole seg they're laid along are tried first, as these get long
+    /// enough at the smallest amplitude, and ones with fewer cores only when they can't. Bands
+    /// that are already longer than the target can't be shortened.
+    fn tune_band(
+        &self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+        band: BandUid,
+    ) -> Option<f64> {
+        let length = band.0.ref_(autorouter.board.layout().drawing()).length();
+
+        if self.is_within_tolerance(length) {
+            return Some(length);
+        } else if length > self.target_length {
+            return None;
+        }
+
+        let meanderer = Meanderer::new(autorouter.board.layout_mut());
+        let radius = meanderer.bend_radius(band.0);
+        let max_core_count = meanderer.max_core_count(band.0);
+
+        for core_count in (1..=max_core_count).rev() {
+            // The band is straight at the lower bound, so it's too short there.
+            let mut lower = -radius;
+            let mut upper = radius;
+            let mut is_upper_found = false;
+
+            for _ in 0..MAX_AMPLITUDE_DOUBLINGS {
+                match self.try_meander(autorouter, band, core_count, upper) {
+                    Some(length) if self.is_within_tolerance(length) => {
+                        return self.lay_meander(autorouter, band, core_count, upper);
+                    }
+                    Some(length) if length < self.target_length => {
+                        lower = upper;
+                        upper *= 2.0;
+                    }
+                    _ => {
+                        is_upper_found = true;
+                        break;
+                    }
+                }
+            }
+
+            if !is_upper_found {
+                continue;
+            }
+
+            // The amplitude is now somewhere between a meander that's too short and one that's
+            // too long or can't be drawn.
+            for _ in 0..MAX_AMPLITUDE_BISECTIONS {
+                let amplitude = (lower + upper) / 2.0;
+
+                match self.try_meander(autorouter, band, core_count, amplitude) {
+                    Some(length) if self.is_within_tolerance(length) => {
+                        return self.lay_meander(autorouter, band, core_count, amplitude);
+                    }
+                    Some(length) if length < self.target_length => lower = amplitude,
+                    _ => upper = amplitude,
+                }
+            }
+        }
+
+        None
+    }
+
+    fn is_within_tolerance(&self, length: f64) -> bool {
+        (length - self.target_length).abs() <= self.tolerance
+    }
+
+    /// Measures the length the band would have with the given meander laid into it, leaving the
+    /// layout as it was.
+    fn try_meander(
+        &self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+        band: BandUid,
+        core_count: usize,
+        amplitude: f64,
+    ) -> Option<f64> {
+        let recording = autorouter.board.layout_mut().start_recording();
+        let maybe_length = Meanderer::new(autorouter.board.layout_mut())
+            .redraw_with_meander(
+                band.0,
+                Meander {
+                    core_count,
+                    amplitude,
+                },
+            )
+            .ok()
+            .map(|new_band| {
+                new_band
+                    .0
+                    .ref_(autorouter.board.layout().drawing())
+                    .length()
+            });
+
+        let edit = autorouter.board.layout_mut().stop_recording(recording);
+        autorouter.board.layout_mut().apply_edit(&edit.reverse());
+
+        maybe_length
+    }
+
+    /// Lays the given meander into the band for good, updating the ratsnest and the band names
+    /// to refer to the band as it was redrawn.
+    fn lay_meander(
+        &self,
+        autorouter: &mut Autorouter<impl AccessMesadata>,
+        band: BandUid,
+        core_count: usize,
+        amplitude: f64,
+    ) -> Option<f64> {
+        let mut meanderer = Meanderer::new(autorouter.board.layout_mut());
+        let (source, target) = meanderer.band_endpoints(band.0);
+        let new_band = meanderer
+            .redraw_with_meander(
+                band.0,
+                Meander {
+                    core_count,
+                    amplitude,
+                },
+            )
+            .ok()?;
+
+        autorouter.ratsnest.reassign_band(band, new_band.1);
+        autorouter
+            .board
+            .try_set_band_between_nodes(source, target, new_band);
+
+        Some(
+            new_band
+                .0
+                .ref_(autorouter.board.layout().drawing())
+                .length(),
+        )
+    }
+}
+
+impl GetMaybeNavmesh for TuneLengthExecutionStepper {
+    fn maybe_navmesh(&self) -> Option<&Navmesh> {
+        None
+    }
+}
+
+impl GetMaybeNavcord for TuneLengthExecutionStepper {
+    fn maybe_navcord(&self) -> Option<&NavcordStepper> {
+        None
+    }
+}
+
+impl GetGhosts for TuneLengthExecutionStepper {
+    fn ghosts(&self) -> &[PrimitiveShape] {
+        &[]
+    }
+}
+
+impl GetObstacles for TuneLengthExecutionStepper {
+    fn obstacles(&self) -> &[PrimitiveIndex] {
+        &[]
+    }
+}
@@ -79,6 +79,7 @@ impl EditActions {
 
 pub struct PlaceActions {
     pub place_via: Switch,
+    pub move_component: Trigger,
 }
 
 impl PlaceActions {
@@ -89,22 +90,39 @@ impl PlaceActions {
                 egui::Modifiers::CTRL,
                 egui::Key::P,
             )),
+            move_component: Trigger::new(Action::new(
+                tr.text("tr-menu-place-move-component"),
+                egui::Modifiers::CTRL,
+                egui::Key::M,
+            )),
         }
     }
 }
 
 pub struct RouteActions {
+    pub route_track: Trigger,
     pub autoroute: Trigger,
+    pub tune_length: Trigger,
 }
 
 impl RouteActions {
     pub fn new(tr: &Translator) -> Self {
         Self {
+            route_track: Trigger::new(Action::new(
+                tr.text("tr-menu-route-route-track"),
+                egui::Modifiers::CTRL,
+                egui::Key::R,
+            )),
             autoroute: Trigger::new(Action::new(
                 tr.text("tr-menu-route-autoroute"),
                 egui::Modifiers::CTRL,
                 egui::Key::A,
             )),
+            tune_length: Trigger::new(Action::new(
+                tr.text("tr-menu-route-tune-length"),
+                egui::Modifiers::CTRL,
+                egui::Key::T,
+            )),
         }
     }
 }
@@ -112,6 +130,7 @@ impl RouteActions {
 pub struct InspectActions {
     pub compare_detours: Trigger,
     pub measure_length: Trigger,
+    pub check_design_rules: Trigger,
 }
 
 impl InspectActions {
@@ -127,6 +146,11 @@ impl InspectActions {
                 egui::Modifiers::NONE,
                 egui::Key::Plus,
             )),
+            check_design_rules: Trigger::new(Action::new(
+                tr.text("tr-menu-inspect-check-design-rules"),
+                egui::Modifiers::CTRL,
+                egui::Key::D,
+            )),
         }
     }
 }
@@ -147,7 +147,9 @@ impl eframe::App for App {
             * ctx.input(|i| i.pointer.latest_pos().unwrap_or_default());
 
         self.advance_state_by_dt(&InteractiveInput {
-            pointer_pos: point! {x: pointer_pos.x as f64, y: pointer_pos.y as f64},
+            // The board's y axis points up, unlike the screen's.
+            pointer_pos: point! {x: pointer_pos.x as f64, y: -pointer_pos.y as f64},
+            is_pointer_clicked: ctx.input(|i| i.pointer.primary_clicked()),
             dt: ctx.input(|i| i.stable_dt),
         });
 
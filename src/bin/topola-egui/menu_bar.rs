@@ -6,7 +6,8 @@ use std::{borrow::Cow, ops::ControlFlow, path::Path, sync::mpsc::Sender};
 
 use topola::{
     autorouter::{
-        execution::Command, invoker::InvokerError, selection::Selection, AutorouterOptions,
+        diff_pair::DiffPairOptions, execution::Command, invoker::InvokerError,
+        ratline_ordering::RatlineOrdering, selection::Selection, AutorouterOptions,
     },
     interactor::activity::{ActivityContext, ActivityStepperWithStatus, InteractiveInput},
     router::RouterOptions,
@@ -25,6 +26,9 @@ use crate::{
 
 pub struct MenuBar {
     pub autorouter_options: AutorouterOptions,
+    pub max_arc_deviation: f64,
+    pub target_length: f64,
+    pub length_tolerance: f64,
     pub is_placing_via: bool,
     pub show_ratsnest: bool,
     pub show_navmesh: bool,
@@ -38,13 +42,22 @@ impl MenuBar {
     pub fn new() -> Self {
         Self {
             autorouter_options: AutorouterOptions {
-                presort_by_pairwise_detours: false,
+                ratline_ordering: RatlineOrdering::Unordered,
                 router_options: RouterOptions {
                     routed_band_width: 100.0,
                     wrap_around_bands: true,
                     squeeze_through_under_bands: true,
+                    via_cost: None,
+                    shove_depth: 0,
                 },
+                max_ripups: 100,
+                ripup_cost_escalation: 2.0,
+                skip_failed_ratlines: false,
+                diff_pair_options: DiffPairOptions::default(),
             },
+            max_arc_deviation: 1.0,
+            target_length: 10000.0,
+            length_tolerance: 10.0,
             is_placing_via: false,
             show_ratsnest: false,
             show_navmesh: false,
@@ -85,6 +98,13 @@ impl MenuBar {
                         //ui.add_enabled_ui(maybe_workspace.is_some(), |ui| {
                         actions.file.export_session.button(ctx, ui);
 
+                        ui.label(tr.text("tr-menu-file-max-arc-deviation"));
+                        ui.add(
+                            egui::widgets::DragValue::new(&mut self.max_arc_deviation)
+                                .range(0.001..=f64::INFINITY)
+                                .speed(0.1),
+                        );
+
                         ui.separator();
 
                         actions.file.import_history.button(ctx, ui);
@@ -130,13 +150,16 @@ impl MenuBar {
                                 ui,
                                 &mut self.is_placing_via,
                             );
+                            actions.place.move_component.button(ctx, ui);
                         });
                     });
 
                     ui.menu_button(tr.text("tr-menu-route"), |ui| {
                         ui.add_enabled_ui(maybe_workspace.is_some(), |ui| {
                             //ui.add_enabled_ui(workspace_activities_enabled, |ui| {
+                            actions.route.route_track.button(ctx, ui);
                             actions.route.autoroute.button(ctx, ui);
+                            actions.route.tune_length.button(ctx, ui);
                             //});
                             ui.separator();
 
@@ -152,11 +175,53 @@ impl MenuBar {
 
                             ui.separator();
 
+                            ui.label(tr.text("tr-menu-route-target-length"));
+                            ui.add(egui::widgets::DragValue::new(&mut self.target_length));
+
+                            ui.label(tr.text("tr-menu-route-length-tolerance"));
+                            ui.add(egui::widgets::DragValue::new(&mut self.length_tolerance));
+
+                            ui.separator();
+
                             ui.menu_button(tr.text("tr-menu-options"), |ui| {
+                                ui.menu_button(
+                                    tr.text("tr-menu-route-options-ratline-ordering"),
+                                    |ui| {
+                                        for ordering in [
+                                            RatlineOrdering::Unordered,
+                                            RatlineOrdering::ShortestFirst,
+                                            RatlineOrdering::LongestFirst,
+                                            RatlineOrdering::NetClassPriority,
+                                            RatlineOrdering::LeastCongestedFirst,
+                                            RatlineOrdering::PairwiseDetours,
+                                        ] {
+                                            ui.radio_value(
+                                                &mut self.autorouter_options.ratline_ordering,
+                                                ordering,
+                                                tr.text(ratline_ordering_tr_key(ordering)),
+                                            );
+                                        }
+                                    },
+                                );
+
+                                let mut rip_up_and_reroute = self.autorouter_options.max_ripups > 0;
+
+                                if ui
+                                    .checkbox(
+                                        &mut rip_up_and_reroute,
+                                        tr.text("tr-menu-route-options-rip-up-and-reroute"),
+                                    )
+                                    .changed()
+                                {
+                                    self.autorouter_options.max_ripups =
+                                        if rip_up_and_reroute { 100 } else { 0 };
+                                }
+
                                 ui.checkbox(
-                                    &mut self.autorouter_options.presort_by_pairwise_detours,
-                                    tr.text("tr-menu-route-options-presort-by-pairwise-detours"),
+                                    &mut self.autorouter_options.skip_failed_ratlines,
+                                    tr.text("tr-menu-route-options-skip-failed-ratlines"),
                                 );
+
                                 ui.checkbox(
                                     &mut self
                                         .autorouter_options
@@ -168,6 +233,34 @@ impl MenuBar {
                                     &mut self.autorouter_options.router_options.wrap_around_bands,
                                     tr.text("tr-menu-route-options-wrap-around-bands"),
                                 );
+
+                                let mut shove_bands =
+                                    self.autorouter_options.router_options.shove_depth > 0;
+
+                                if ui
+                                    .checkbox(
+                                        &mut shove_bands,
+                                        tr.text("tr-menu-route-options-shove-bands"),
+                                    )
+                                    .changed()
+                                {
+                                    self.autorouter_options.router_options.shove_depth =
+                                        if shove_bands { 3 } else { 0 };
+                                }
+
+                                let mut place_vias =
+                                    self.autorouter_options.router_options.via_cost.is_some();
+
+                                if ui
+                                    .checkbox(
+                                        &mut place_vias,
+                                        tr.text("tr-menu-route-options-place-vias"),
+                                    )
+                                    .changed()
+                                {
+                                    self.autorouter_options.router_options.via_cost =
+                                        place_vias.then_some(1000.0);
+                                }
                             });
                         });
                     });
@@ -176,6 +269,7 @@ impl MenuBar {
                         ui.add_enabled_ui(workspace_activities_enabled, |ui| {
                             actions.inspect.compare_detours.button(ctx, ui);
                             actions.inspect.measure_length.button(ctx, ui);
+                            actions.inspect.check_design_rules.button(ctx, ui);
                         });
                     });
 
@@ -228,7 +322,9 @@ impl MenuBar {
                         // FIXME: I don't know how to avoid buffering the entire exported file
                         let mut writebuf = vec![];
 
-                        workspace.design.write_ses(board, &mut writebuf);
+                        workspace
+                            .design
+                            .write_ses(board, self.max_arc_deviation, &mut writebuf);
 
                         let mut dialog = rfd::AsyncFileDialog::new();
                         if let Some(filename) = Path::new(workspace.design.get_name()).file_stem() {
@@ -305,6 +401,16 @@ impl MenuBar {
                             schedule(|selection, _| {
                                 Command::RemoveBands(selection.band_selection)
                             })?;
+                        } else if actions.route.route_track.consume_key_triggered(ctx, ui) {
+                            let selection = workspace.overlay.take_selection();
+                            let _ = workspace
+                                .interactor
+                                .route_track(selection.pin_selection, self.autorouter_options);
+                        } else if actions.place.move_component.consume_key_triggered(ctx, ui) {
+                            let selection = workspace.overlay.take_selection();
+                            let _ = workspace
+                                .interactor
+                                .move_component(selection.pin_selection, self.autorouter_options);
                         } else if actions.route.autoroute.consume_key_triggered(ctx, ui) {
                             schedule(|selection, opts| {
                                 Command::Autoroute(selection.pin_selection, opts)
@@ -325,6 +431,19 @@ impl MenuBar {
                             schedule(|selection, _| {
                                 Command::MeasureLength(selection.band_selection)
                             })?;
+                        } else if actions
+                            .inspect
+                            .check_design_rules
+                            .consume_key_triggered(ctx, ui)
+                        {
+                            workspace.interactor.schedule(Command::CheckDesignRules);
+                        } else if actions.route.tune_length.consume_key_triggered(ctx, ui) {
+                            let selection = workspace.overlay.take_selection();
+                            workspace.interactor.schedule(Command::TuneLength(
+                                selection.band_selection,
+                                self.target_length,
+                                self.length_tolerance,
+                            ));
                         }
                     }
                 }
@@ -407,3 +526,20 @@ impl MenuBar {
         });
     }
 }
+
+fn ratline_ordering_tr_key(ordering: RatlineOrdering) -> &'static str {
+    match ordering {
+        RatlineOrdering::Unordered => "tr-menu-route-options-ratline-ordering-unordered",
+        RatlineOrdering::ShortestFirst => "tr-menu-route-options-ratline-ordering-shortest-first",
+        RatlineOrdering::LongestFirst => "tr-menu-route-options-ratline-ordering-longest-first",
+        RatlineOrdering::NetClassPriority => {
+            "tr-menu-route-options-ratline-ordering-net-class-priority"
+        }
+        RatlineOrdering::LeastCongestedFirst => {
+            "tr-menu-route-options-ratline-ordering-least-congested-first"
+        }
+        RatlineOrdering::PairwiseDetours => {
+            "tr-menu-route-options-ratline-ordering-pairwise-detours"
+        }
+    }
+}
@@ -30,7 +30,7 @@ pub struct Overlay {
 impl Overlay {
     pub fn new(board: &Board<impl AccessMesadata>) -> Result<Self, InsertionError> {
         Ok(Self {
-            ratsnest: Ratsnest::new(board.layout())?,
+            ratsnest: Ratsnest::new(board)?,
             selection: Selection::new(),
             active_layer: 0,
         })
@@ -76,6 +76,8 @@ impl Overlay {
                                 weight.from_layer >= self.active_layer
                                     && weight.to_layer <= self.active_layer
                             }
+                            // Meanders are selected through the bands laid around them.
+                            CompoundWeight::Meander(..) => false,
                         }
                     }
                 }
@@ -106,6 +108,7 @@ impl Overlay {
                         .via(GenericIndex::<ViaWeight>::new(compound.petgraph_index()))
                         .shape()
                         .into(),
+                    CompoundWeight::Meander(..) => return false,
                 }
             }
         };
@@ -113,6 +116,10 @@ impl Overlay {
         shape.contains_point(p)
     }
 
+    pub fn update_ratsnest(&mut self, board: &Board<impl AccessMesadata>) {
+        self.ratsnest.update_positions(board.layout());
+    }
+
     pub fn ratsnest(&self) -> &Ratsnest {
         &self.ratsnest
     }
@@ -1,4 +1,4 @@
-use geo::{CoordsIter, Point, Polygon};
+use geo::{CoordsIter, MultiPolygon, Point, Polygon, TriangulateSpade};
 use rstar::AABB;
 use topola::{
     geometry::primitive::{AccessPrimitiveShape, PrimitiveShape},
@@ -104,6 +104,33 @@ impl<'a> Painter<'a> {
         ));
     }
 
+    pub fn paint_multipolygon(
+        &mut self,
+        multipolygon: &MultiPolygon,
+        color: egui::epaint::Color32,
+    ) {
+        // egui only fills convex polygons, so polygons that may be concave or have holes are
+        // split into triangles first.
+        let Ok(triangles) = multipolygon.constrained_triangulation(Default::default()) else {
+            return;
+        };
+
+        for triangle in triangles {
+            self.ui.painter().add(egui::Shape::convex_polygon(
+                triangle
+                    .to_array()
+                    .iter()
+                    .map(|coords| {
+                        self.transform
+                            .mul_pos([coords.x as f32, -coords.y as f32].into())
+                    })
+                    .collect(),
+                color,
+                egui::Stroke::default(),
+            ));
+        }
+    }
+
     pub fn paint_edge(&mut self, from: Point, to: Point, stroke: egui::Stroke) {
         self.ui.painter().add(egui::Shape::line_segment(
             [
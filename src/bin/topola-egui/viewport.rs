@@ -10,7 +10,7 @@ use topola::{
         invoker::{GetGhosts, GetMaybeNavcord, GetMaybeNavmesh, GetObstacles, Invoker},
     },
     drawing::{
-        graph::{MakePrimitive, PrimitiveIndex},
+        graph::{GetLayer, MakePrimitive, PrimitiveIndex},
         primitive::MakePrimitiveShape,
     },
     geometry::{shape::AccessShape, GenericNode},
@@ -60,7 +60,9 @@ impl Viewport {
                     let overlay = &mut workspace.overlay;
 
                     if ctx.input(|i| i.pointer.any_click()) {
-                        if menu_bar.is_placing_via {
+                        if workspace.interactor.is_interacting() {
+                            // The interaction takes the click for itself.
+                        } else if menu_bar.is_placing_via {
                             workspace.interactor.execute(
                                 Command::PlaceVia(ViaWeight {
                                     from_layer: 0,
@@ -84,6 +86,10 @@ impl Viewport {
 
                     for i in (0..layers.visible.len()).rev() {
                         if layers.visible[i] {
+                            for pour in board.layout().pours().iter().filter(|pour| pour.layer() == i) {
+                                painter.paint_multipolygon(&pour.fill, layers.colors[i].gamma_multiply(0.5));
+                            }
+
                             for primitive in board.layout().drawing().layer_primitive_nodes(i) {
                                 let shape = primitive.primitive(board.layout().drawing()).shape();
 
@@ -121,6 +127,8 @@ impl Viewport {
                     }
 
                     if menu_bar.show_ratsnest {
+                        // Components may have been moved since the ratsnest was made.
+                        overlay.update_ratsnest(board);
                         let graph = overlay.ratsnest().graph();
                         for edge in graph.edge_references() {
                             let from = graph
@@ -144,14 +152,10 @@ impl Viewport {
                         if let Some(activity) = workspace.interactor.maybe_activity() {
                             if let Some(navmesh) = activity.maybe_navmesh() {
                                 for edge in navmesh.edge_references() {
-                                    let mut from = PrimitiveIndex::from(navmesh.node_weight(edge.source()).unwrap().node)
-                                        .primitive(board.layout().drawing())
-                                        .shape()
-                                        .center();
-                                    let mut to = PrimitiveIndex::from(navmesh.node_weight(edge.target()).unwrap().node)
-                                        .primitive(board.layout().drawing())
-                                        .shape()
-                                        .center();
+                                    let mut from = navmesh.node_weight(edge.source()).unwrap().node
+                                        .pos(board.layout().drawing());
+                                    let mut to = navmesh.node_weight(edge.target()).unwrap().node
+                                        .pos(board.layout().drawing());
 
                                     if let Some(from_cw) = navmesh.node_weight(edge.source()).unwrap().maybe_cw {
                                         if from_cw {
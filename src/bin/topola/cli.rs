@@ -5,15 +5,15 @@ use std::path::PathBuf;
 #[command(about, version)]
 pub struct Cli {
     #[arg(
-        value_name = "SPECCTRA DESIGN FILE",
-        help = "Specify the Specctra Design (*.dsn) input file for the Topola autorouter"
+        value_name = "DESIGN FILE",
+        help = "Specify the Specctra Design (*.dsn) or KiCad board (*.kicad_pcb) input file for the Topola autorouter"
     )]
     pub input: PathBuf,
     #[arg(
         short,
         long,
-        value_name = "SPECCTRA SESSION FILE",
-        help = "Specify the output session file in Specctra-compatible format (*.ses). The input filename is used by default, with the extension changed to Specctra Session File extension"
+        value_name = "OUTPUT FILE",
+        help = "Specify the output file: a session file in Specctra-compatible format (*.ses) for Specctra designs, a KiCad board (*.kicad_pcb) for KiCad boards. The input filename is used by default, with the extension changed to Specctra Session File extension, or to .routed.kicad_pcb for KiCad boards"
     )]
     pub output: Option<PathBuf>,
     #[arg(
@@ -23,4 +23,23 @@ pub struct Cli {
         help = "JSON-like file with .cmd extension, containing sequence of available commands "
     )]
     pub commands: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "DEVIATION",
+        default_value_t = 1.0,
+        value_parser = parse_max_arc_deviation,
+        help = "Maximum distance, in board units, by which the straight segments exported in place of arcs may deviate from them"
+    )]
+    pub max_arc_deviation: f64,
+}
+
+fn parse_max_arc_deviation(arg: &str) -> Result<f64, String> {
+    let max_arc_deviation: f64 = arg.parse().map_err(|err| format!("{err}"))?;
+
+    // Also rejects NaN.
+    if max_arc_deviation > 0.0 {
+        Ok(max_arc_deviation)
+    } else {
+        Err("must be greater than 0".to_string())
+    }
 }
@@ -1,12 +1,18 @@
 use clap::Parser;
 use std::fs::File;
 use std::io::BufReader;
+use std::path::PathBuf;
+use topola::autorouter::diff_pair::DiffPairOptions;
 use topola::autorouter::execution::Command;
 use topola::autorouter::history::History;
 use topola::autorouter::invoker::Invoker;
+use topola::autorouter::ratline_ordering::RatlineOrdering;
 use topola::autorouter::selection::PinSelection;
 use topola::autorouter::Autorouter;
 use topola::autorouter::AutorouterOptions;
+use topola::board::mesadata::AccessMesadata;
+use topola::board::Board;
+use topola::kicad::design::KicadDesign;
 use topola::router::RouterOptions;
 use topola::specctra::design::SpecctraDesign;
 
@@ -18,10 +24,48 @@ fn main() -> Result<(), std::io::Error> {
     let design_file = File::open(&args.input)?;
     let mut design_bufread = BufReader::new(design_file);
 
-    let design = SpecctraDesign::load(design_bufread).unwrap();
-    let board = design.make_board();
+    if args
+        .input
+        .extension()
+        .is_some_and(|extension| extension == "kicad_pcb")
+    {
+        let mut design = KicadDesign::load(design_bufread).unwrap();
 
-    let history = if let Some(commands_filename) = args.commands {
+        // KiCad keeps net classes in the project file lying next to the board file.
+        if let Ok(project_file) = File::open(args.input.with_extension("kicad_pro")) {
+            design.load_project(BufReader::new(project_file)).unwrap();
+        }
+
+        let invoker = route(design.make_board(), args.commands)?;
+
+        let output_filename = args
+            .output
+            .unwrap_or_else(|| args.input.clone().with_extension("routed.kicad_pcb"));
+        let mut file = File::create(output_filename).unwrap();
+        design.write_kicad_pcb(invoker.autorouter().board(), &mut file)?;
+    } else {
+        let design = SpecctraDesign::load(design_bufread).unwrap();
+        let invoker = route(design.make_board(), args.commands)?;
+
+        let output_filename = args
+            .output
+            .unwrap_or_else(|| args.input.clone().with_extension("ses"));
+        let mut file = File::create(output_filename).unwrap();
+        design.write_ses(
+            invoker.autorouter().board(),
+            args.max_arc_deviation,
+            &mut file,
+        );
+    }
+
+    Ok(())
+}
+
+fn route<M: AccessMesadata>(
+    board: Board<M>,
+    maybe_commands_filename: Option<PathBuf>,
+) -> Result<Invoker<M>, std::io::Error> {
+    let history = if let Some(commands_filename) = maybe_commands_filename {
         let command_file = File::open(commands_filename)?;
         let commands_bufread = BufReader::new(command_file);
         serde_json::from_reader(commands_bufread)?
@@ -30,12 +74,18 @@ fn main() -> Result<(), std::io::Error> {
         history.do_(Command::Autoroute(
             PinSelection::new_select_layer(&board, 0),
             AutorouterOptions {
-                presort_by_pairwise_detours: false,
+                ratline_ordering: RatlineOrdering::Unordered,
                 router_options: RouterOptions {
                     wrap_around_bands: true,
                     squeeze_through_under_bands: false,
                     routed_band_width: 100.0,
+                    via_cost: None,
+                    shove_depth: 0,
                 },
+                max_ripups: 100,
+                ripup_cost_escalation: 2.0,
+                skip_failed_ratlines: false,
+                diff_pair_options: DiffPairOptions::default(),
             },
         ));
         history
@@ -44,11 +94,5 @@ fn main() -> Result<(), std::io::Error> {
     let mut invoker = Invoker::new(Autorouter::new(board).unwrap());
     invoker.replay(history);
 
-    let output_filename = args
-        .output
-        .unwrap_or_else(|| args.input.clone().with_extension("ses"));
-    let mut file = File::create(output_filename).unwrap();
-    design.write_ses(invoker.autorouter().board(), &mut file);
-
-    Ok(())
+    Ok(invoker)
 }
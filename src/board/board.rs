@@ -2,21 +2,25 @@ use std::{cmp::Ordering, collections::HashMap};
 
 use bimap::BiHashMap;
 use derive_getters::Getters;
+use geo::Point;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     board::mesadata::AccessMesadata,
     drawing::{
-        band::BandUid,
-        dot::{FixedDotIndex, FixedDotWeight},
-        graph::{GetLayer, GetMaybeNet},
+        band::{BandTermsegIndex, BandUid},
+        dot::{DotIndex, FixedDotIndex, FixedDotWeight},
+        graph::{GetLayer, GetMaybeNet, PrimitiveIndex},
+        primitive::GetJoints,
+        rules::GetObjectKind,
         seg::{FixedSegIndex, FixedSegWeight},
+        DrawingException, Infringement,
     },
-    geometry::{shape::AccessShape, GenericNode},
-    graph::GenericIndex,
+    geometry::{compound::ManageCompounds, shape::AccessShape, GenericNode},
+    graph::{GenericIndex, GetPetgraphIndex},
     layout::{
         poly::{GetMaybeApex, MakePolyShape, PolyWeight},
-        Layout, NodeIndex,
+        CompoundWeight, Layout, NodeIndex,
     },
     math::Circle,
 };
@@ -50,6 +54,8 @@ pub struct Board<M: AccessMesadata> {
     node_to_pinname: HashMap<NodeIndex, String>,
     #[getter(skip)]
     band_bandname: BiHashMap<BandUid, BandName>,
+    #[getter(skip)]
+    pinname_to_component: HashMap<String, String>,
 }
 
 impl<M: AccessMesadata> Board<M> {
@@ -59,6 +65,7 @@ impl<M: AccessMesadata> Board<M> {
             layout,
             node_to_pinname: HashMap::new(),
             band_bandname: BiHashMap::new(),
+            pinname_to_component: HashMap::new(),
         }
     }
 
@@ -173,6 +180,7 @@ impl<M: AccessMesadata> Board<M> {
                     },
                     layer: self.layout.poly(poly).layer(),
                     maybe_net: self.layout.poly(poly).maybe_net(),
+                    kind: self.layout.poly(poly).object_kind(),
                 },
                 poly,
             )
@@ -184,6 +192,64 @@ impl<M: AccessMesadata> Board<M> {
         self.node_to_pinname.get(node)
     }
 
+    /// Makes the pin of the given name part of the given component, e.g. the pin of a footprint
+    /// placed on the board, so that it's moved along with the component.
+    pub fn add_pin_to_component(&mut self, pinname: String, component: String) {
+        self.pinname_to_component.insert(pinname, component);
+    }
+
+    /// Returns the name of the component the pin of the given name is part of.
+    pub fn pinname_component(&self, pinname: &str) -> Option<&String> {
+        self.pinname_to_component.get(pinname)
+    }
+
+    /// Returns the nodes of the pins of the component of the given name.
+    pub fn component_nodes<'a>(
+        &'a self,
+        component: &'a str,
+    ) -> impl Iterator<Item = NodeIndex> + 'a {
+        self.node_to_pinname
+            .iter()
+            .filter(move |(_, pinname)| {
+                self.pinname_component(pinname)
+                    .is_some_and(|pin_component| pin_component == component)
+            })
+            .map(|(node, _)| *node)
+    }
+
+    /// Moves the component of the given name by the given offset, along with the ends of the
+    /// bands joined to its pins.
+    ///
+    /// If the moved component would infringe on anything, nothing is moved.
+    pub fn move_component(&mut self, component: &str, offset: Point) -> Result<(), Infringement> {
+        let dots: Vec<FixedDotIndex> = self
+            .component_nodes(component)
+            .filter_map(|node| match node {
+                GenericNode::Primitive(PrimitiveIndex::FixedDot(dot)) => Some(dot),
+                _ => None,
+            })
+            .collect();
+
+        self.layout.move_dots_by(&dots, offset)
+    }
+
+    /// Returns all the named bands along with their names.
+    pub fn band_bandnames(&self) -> &BiHashMap<BandUid, BandName> {
+        &self.band_bandname
+    }
+
+    /// Names the band, or takes its name away.
+    pub fn set_band_bandname(&mut self, band: BandUid, maybe_bandname: Option<BandName>) {
+        match maybe_bandname {
+            Some(bandname) => {
+                self.band_bandname.insert(band, bandname);
+            }
+            None => {
+                self.band_bandname.remove_by_left(&band);
+            }
+        }
+    }
+
     /// Returns the band name associated with a given band.
     pub fn band_bandname(&self, band: &BandUid) -> Option<&BandName> {
         self.band_bandname.get_by_left(band)
@@ -195,22 +261,70 @@ impl<M: AccessMesadata> Board<M> {
     }
 
     /// Creates band between the two nodes
+    ///
+    /// Bands are named after the pins they join, so a band ending in anything else, such as a
+    /// via, is left unnamed.
     pub fn try_set_band_between_nodes(
         &mut self,
         source: FixedDotIndex,
         target: FixedDotIndex,
         band: BandUid,
     ) {
-        let source_pinname = self
-            .node_pinname(&GenericNode::Primitive(source.into()))
-            .unwrap()
-            .to_string();
-        let target_pinname = self
-            .node_pinname(&GenericNode::Primitive(target.into()))
-            .unwrap()
-            .to_string();
-        self.band_bandname
-            .insert(band, BandName::new(source_pinname, target_pinname));
+        let (Some(source_pinname), Some(target_pinname)) = (
+            self.node_pinname(&GenericNode::Primitive(source.into())),
+            self.node_pinname(&GenericNode::Primitive(target.into())),
+        ) else {
+            return;
+        };
+        let bandname = BandName::new(source_pinname.to_string(), target_pinname.to_string());
+        self.band_bandname.insert(band, bandname);
+    }
+
+    /// Removes the band along with its name.
+    ///
+    /// The apexes of the polygons the band ended at are removed too once no other band ends at
+    /// them, as they're only made for bands to end at.
+    pub fn remove_band(&mut self, band: BandUid) -> Result<(), DrawingException> {
+        let (source, target) = self.band_endpoints(band);
+        self.layout.remove_band(band.0)?;
+        self.band_bandname.remove_by_left(&band);
+
+        for dot in [source, target] {
+            let polys: Vec<_> = self
+                .layout
+                .polys(dot)
+                .filter(|compound| {
+                    matches!(
+                        self.layout.drawing().compound_weight(*compound),
+                        CompoundWeight::Poly(..)
+                    )
+                })
+                .collect();
+
+            for poly in polys {
+                self.layout
+                    .remove_unjoined_poly_apex(GenericIndex::new(poly.petgraph_index()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn band_endpoints(&self, band: BandUid) -> (FixedDotIndex, FixedDotIndex) {
+        let endpoint = |termseg: BandTermsegIndex| match termseg {
+            BandTermsegIndex::Straight(seg) => self.layout.drawing().primitive(seg).joints().0,
+            BandTermsegIndex::Bended(seg) => {
+                let DotIndex::Fixed(dot) = self.layout.drawing().primitive(seg).joints().0 else {
+                    unreachable!();
+                };
+                dot
+            }
+        };
+
+        match band.0 {
+            BandTermsegIndex::Straight(seg) => self.layout.drawing().primitive(seg).joints(),
+            BandTermsegIndex::Bended(..) => (endpoint(band.0), endpoint(band.1)),
+        }
     }
 
     /// Finds a band between two pin names.
@@ -1,4 +1,6 @@
 //! Module implementing the logic behind board metadata
+use std::collections::HashMap;
+
 use crate::drawing::rules::AccessRules;
 
 /// Trait for managing the Specctra's mesadata
@@ -23,4 +25,70 @@ pub trait AccessMesadata: AccessRules {
 
     /// Retrieves the index of a net by its name.
     fn netname_net(&self, netname: &str) -> Option<usize>;
+
+    /// Retrieves the routing priority of a net, given by its net class. Nets of higher
+    /// priority are routed first when ordering ratlines by it.
+    fn net_priority(&self, net: usize) -> i64;
+
+    /// Declares two nets a differential pair, optionally with the gap their bands are to be
+    /// kept at, which then takes the place of the clearance between them.
+    fn pair_nets(&mut self, net1: usize, net2: usize, maybe_gap: Option<f64>);
+
+    /// Retrieves the net the given net forms a differential pair with, if any. Pairs are
+    /// declared explicitly or, failing that, by net names that differ only in their `_P` and
+    /// `_N` suffixes.
+    fn net_pair(&self, net: usize) -> Option<usize>;
+}
+
+/// The differential pairs of nets declared for a board, each with the gap between its nets, if
+/// one is set.
+#[derive(Debug, Clone, Default)]
+pub struct NetPairs {
+    // net -> (paired net, gap)
+    pairs: HashMap<usize, (usize, Option<f64>)>,
+}
+
+impl NetPairs {
+    /// Declares the two nets a pair, undoing any pairs either of them was in before.
+    pub fn insert(&mut self, net1: usize, net2: usize, maybe_gap: Option<f64>) {
+        for net in [net1, net2] {
+            if let Some((old_pair, _)) = self.pairs.remove(&net) {
+                self.pairs.remove(&old_pair);
+            }
+        }
+
+        self.pairs.insert(net1, (net2, maybe_gap));
+        self.pairs.insert(net2, (net1, maybe_gap));
+    }
+
+    /// Retrieves the net the given net has been declared a pair with.
+    pub fn pair(&self, net: usize) -> Option<usize> {
+        self.pairs.get(&net).map(|(pair, _)| *pair)
+    }
+
+    /// Retrieves the gap declared between the two nets, if they form a pair with one.
+    pub fn gap(&self, net1: usize, net2: usize) -> Option<f64> {
+        self.pairs
+            .get(&net1)
+            .filter(|(pair, _)| *pair == net2)
+            .and_then(|(_, maybe_gap)| *maybe_gap)
+    }
+
+    /// The largest gap declared for any pair, or zero if none is.
+    pub fn largest_gap(&self) -> f64 {
+        self.pairs
+            .values()
+            .filter_map(|(_, maybe_gap)| *maybe_gap)
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Returns the name of the net that forms a differential pair with the named one by the naming
+/// convention, where the names of the two nets differ only in their `_P` and `_N` suffixes.
+pub fn complementary_netname(netname: &str) -> Option<String> {
+    if let Some(stem) = netname.strip_suffix("_P") {
+        Some(format!("{stem}_N"))
+    } else {
+        netname.strip_suffix("_N").map(|stem| format!("{stem}_P"))
+    }
 }
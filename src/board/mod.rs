@@ -4,5 +4,6 @@
 
 mod board;
 pub mod mesadata;
+pub mod shapes;
 
 pub use board::*;
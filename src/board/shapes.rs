@@ -0,0 +1,200 @@
+//! Adds the shapes read from design files to a [`Board`], such as those of the pads of a
+//! component, which are given relative to their pin and to where the component is placed.
+
+use geo::{point, Point, Rotate};
+
+use crate::{
+    board::{mesadata::AccessMesadata, Board},
+    drawing::{dot::FixedDotWeight, rules::ObjectKind, seg::FixedSegWeight},
+    layout::poly::SolidPolyWeight,
+    math::{Circle, PointWithRotation},
+};
+
+/// Places a point given relative to a pin, itself given relative to where its component is
+/// placed.
+pub fn pos(place: PointWithRotation, pin: PointWithRotation, x: f64, y: f64) -> Point {
+    let pos = (point! {x: x, y: y} + pin.pos).rotate_around_point(pin.rot, pin.pos);
+    (pos + place.pos).rotate_around_point(place.rot, place.pos)
+}
+
+/// Adds a circle centered on the pin as a fixed dot.
+pub fn add_circle(
+    board: &mut Board<impl AccessMesadata>,
+    place: PointWithRotation,
+    pin: PointWithRotation,
+    r: f64,
+    layer: usize,
+    maybe_net: Option<usize>,
+    kind: ObjectKind,
+    maybe_pin: Option<String>,
+) {
+    board.add_fixed_dot_infringably(
+        FixedDotWeight {
+            circle: Circle {
+                pos: pos(place, pin, 0.0, 0.0),
+                r,
+            },
+            layer,
+            maybe_net,
+            kind,
+        },
+        maybe_pin,
+    );
+}
+
+/// Adds a rectangle spanning between two opposite corners as a polygon.
+pub fn add_rect(
+    board: &mut Board<impl AccessMesadata>,
+    place: PointWithRotation,
+    pin: PointWithRotation,
+    (x1, y1): (f64, f64),
+    (x2, y2): (f64, f64),
+    weight: SolidPolyWeight,
+    kind: ObjectKind,
+    maybe_pin: Option<String>,
+) {
+    let SolidPolyWeight { layer, maybe_net } = weight;
+    let poly = board.add_poly(weight.into(), maybe_pin);
+
+    let corners = [(x1, y1), (x2, y1), (x2, y2), (x1, y2)].map(|(x, y)| {
+        board.add_poly_fixed_dot_infringably(
+            FixedDotWeight {
+                circle: Circle {
+                    pos: pos(place, pin, x, y),
+                    r: 0.5,
+                },
+                layer,
+                maybe_net,
+                kind,
+            },
+            poly,
+        )
+    });
+
+    for (i, corner) in corners.iter().enumerate() {
+        board.add_poly_fixed_seg_infringably(
+            *corner,
+            corners[(i + 1) % corners.len()],
+            FixedSegWeight {
+                width: 1.0,
+                layer,
+                maybe_net,
+                kind,
+            },
+            poly,
+        );
+    }
+}
+
+/// Adds a path as a chain of fixed segs of the given weight, joined by dots as wide as them.
+pub fn add_path(
+    board: &mut Board<impl AccessMesadata>,
+    place: PointWithRotation,
+    pin: PointWithRotation,
+    coords: &[Point],
+    weight: FixedSegWeight,
+    maybe_pin: Option<String>,
+) {
+    let FixedSegWeight {
+        width,
+        layer,
+        maybe_net,
+        kind,
+    } = weight;
+
+    // add the first coordinate in the path as a dot and save its index
+    let mut prev_pos = pos(place, pin, coords[0].x(), coords[0].y());
+    let mut prev_index = board.add_fixed_dot_infringably(
+        FixedDotWeight {
+            circle: Circle {
+                pos: prev_pos,
+                r: width / 2.0,
+            },
+            layer,
+            maybe_net,
+            kind,
+        },
+        maybe_pin.clone(),
+    );
+
+    // iterate through path coords starting from the second
+    for coord in coords.iter().skip(1) {
+        let pos = pos(place, pin, coord.x(), coord.y());
+
+        if pos == prev_pos {
+            continue;
+        }
+
+        let index = board.add_fixed_dot_infringably(
+            FixedDotWeight {
+                circle: Circle {
+                    pos,
+                    r: width / 2.0,
+                },
+                layer,
+                maybe_net,
+                kind,
+            },
+            maybe_pin.clone(),
+        );
+
+        // add a seg between the current and previous coords
+        board.add_fixed_seg_infringably(prev_index, index, weight, maybe_pin.clone());
+
+        prev_index = index;
+        prev_pos = pos;
+    }
+}
+
+/// Adds a polygon outlined by a chain of fixed segs of the given weight.
+pub fn add_polygon(
+    board: &mut Board<impl AccessMesadata>,
+    place: PointWithRotation,
+    pin: PointWithRotation,
+    coords: &[Point],
+    weight: FixedSegWeight,
+    maybe_pin: Option<String>,
+) {
+    let FixedSegWeight {
+        width,
+        layer,
+        maybe_net,
+        kind,
+    } = weight;
+    let poly = board.add_poly(SolidPolyWeight { layer, maybe_net }.into(), maybe_pin);
+
+    // add the first coordinate in the path as a dot and save its index
+    let mut prev_index = board.add_poly_fixed_dot_infringably(
+        FixedDotWeight {
+            circle: Circle {
+                pos: pos(place, pin, coords[0].x(), coords[0].y()),
+                r: width / 2.0,
+            },
+            layer,
+            maybe_net,
+            kind,
+        },
+        poly,
+    );
+
+    // iterate through path coords starting from the second
+    for coord in coords.iter().skip(1) {
+        let index = board.add_poly_fixed_dot_infringably(
+            FixedDotWeight {
+                circle: Circle {
+                    pos: pos(place, pin, coord.x(), coord.y()),
+                    r: width / 2.0,
+                },
+                layer,
+                maybe_net,
+                kind,
+            },
+            poly,
+        );
+
+        // add a seg between the current and previous coords
+        board.add_poly_fixed_seg_infringably(prev_index, index, weight, poly);
+
+        prev_index = index;
+    }
+}
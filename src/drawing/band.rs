@@ -2,17 +2,24 @@
 #![allow(clippy::derived_hash_with_manual_eq)]
 
 use enum_dispatch::enum_dispatch;
+use geo::Line;
 use petgraph::stable_graph::NodeIndex;
 
 use crate::{
-    geometry::shape::MeasureLength,
+    geometry::{
+        shape::{AccessShape, MeasureLength},
+        GetWidth,
+    },
     graph::{GetPetgraphIndex, MakeRef},
 };
 
 use super::{
+    bend::LooseBendIndex,
+    dot::{DotIndex, FixedDotIndex},
+    gear::GearIndex,
     graph::MakePrimitive,
     loose::{GetPrevNextLoose, LooseIndex},
-    primitive::MakePrimitiveShape,
+    primitive::{GetCore, GetInnerOuter, GetJoints, MakePrimitiveShape},
     rules::AccessRules,
     seg::{LoneLooseSegIndex, SeqLooseSegIndex},
     Drawing,
@@ -41,7 +48,7 @@ impl PartialEq for BandUid {
 impl Eq for BandUid {}
 
 #[enum_dispatch(GetPetgraphIndex)]
-#[derive(Debug, Hash, Clone, Copy)]
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
 pub enum BandTermsegIndex {
     Straight(LoneLooseSegIndex),
     Bended(SeqLooseSegIndex),
@@ -69,10 +76,129 @@ pub struct BandRef<'a, CW: Copy, R: AccessRules> {
     drawing: &'a Drawing<CW, R>,
 }
 
+/// The path of a band: the fixed dots it joins and what it wraps around in between.
+#[derive(Debug, Clone)]
+pub struct BandPath {
+    pub source: FixedDotIndex,
+    pub target: FixedDotIndex,
+    /// The bends of the band, from its source to its target.
+    pub bends: Vec<LooseBendIndex>,
+    /// The gears the bends of the band wrap around, each with whether it does so clockwise
+    /// when going from the source to the target.
+    pub wraps: Vec<(GearIndex, bool)>,
+    /// The straight segs of the band, one more than there are bends, in the direction of the
+    /// band from its source to its target.
+    pub legs: Vec<Line>,
+    pub width: f64,
+}
+
+impl BandPath {
+    /// The same path, but going from its target to its source.
+    pub fn reversed(&self) -> Self {
+        Self {
+            source: self.target,
+            target: self.source,
+            bends: self.bends.iter().rev().copied().collect(),
+            wraps: self
+                .wraps
+                .iter()
+                .rev()
+                .map(|(gear, cw)| (*gear, !cw))
+                .collect(),
+            legs: self
+                .legs
+                .iter()
+                .rev()
+                .map(|leg| Line::new(leg.end, leg.start))
+                .collect(),
+            width: self.width,
+        }
+    }
+}
+
 impl<'a, CW: Copy, R: AccessRules> BandRef<'a, CW, R> {
     pub fn new(first_seg: BandTermsegIndex, drawing: &'a Drawing<CW, R>) -> BandRef<'a, CW, R> {
         Self { first_seg, drawing }
     }
+
+    /// Walks the band from the terminating seg it's referred to by to its other end.
+    pub fn path(&self) -> BandPath {
+        let drawing = self.drawing;
+
+        let first_seg = match self.first_seg {
+            BandTermsegIndex::Straight(seg) => {
+                let (source, target) = drawing.primitive(seg).joints();
+
+                return BandPath {
+                    source,
+                    target,
+                    bends: vec![],
+                    wraps: vec![],
+                    legs: vec![Line::new(
+                        source.primitive(drawing).shape().center(),
+                        target.primitive(drawing).shape().center(),
+                    )],
+                    width: drawing.primitive(seg).width(),
+                };
+            }
+            BandTermsegIndex::Bended(seg) => seg,
+        };
+
+        let DotIndex::Fixed(source) = drawing.primitive(first_seg).joints().0 else {
+            unreachable!();
+        };
+        let mut target = source;
+        let mut points = vec![source.primitive(drawing).shape().center()];
+        let mut bends = vec![];
+        let mut wraps = vec![];
+        let mut maybe_bend = None;
+
+        let mut maybe_loose = Some(LooseIndex::SeqSeg(first_seg));
+        let mut prev = None;
+
+        while let Some(loose) = maybe_loose {
+            match loose {
+                LooseIndex::Dot(dot) => {
+                    points.push(dot.primitive(drawing).shape().center());
+
+                    // The dot a band leaves a bend through tells which way it wraps around.
+                    if let Some(bend) = maybe_bend.take() {
+                        let bend_primitive = drawing.primitive(bend);
+                        let gear = bend_primitive
+                            .inner()
+                            .map_or(GearIndex::FixedDot(bend_primitive.core()), Into::into);
+                        bends.push(bend);
+                        wraps.push((gear, dot != bend_primitive.joints().0));
+                    }
+                }
+                LooseIndex::SeqSeg(seg) => {
+                    if let DotIndex::Fixed(dot) = drawing.primitive(seg).joints().0 {
+                        target = dot;
+                    }
+                }
+                LooseIndex::Bend(bend) => maybe_bend = Some(bend),
+                LooseIndex::LoneSeg(..) => unreachable!(),
+            }
+
+            let prev_prev = prev;
+            prev = maybe_loose;
+            maybe_loose = drawing.loose(loose).next_loose(prev_prev);
+        }
+
+        points.push(target.primitive(drawing).shape().center());
+
+        BandPath {
+            source,
+            target,
+            bends,
+            wraps,
+            legs: points
+                .chunks(2)
+                .map(|chunk| Line::new(chunk[0], chunk[1]))
+                .collect(),
+            width: drawing.primitive(first_seg).width(),
+        }
+    }
 }
 
 impl<'a, CW: Copy, R: AccessRules> MeasureLength for BandRef<'a, CW, R> {
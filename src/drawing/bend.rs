@@ -4,7 +4,7 @@ use crate::{
     drawing::{
         graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex, PrimitiveWeight, Retag},
         primitive::{GenericPrimitive, Primitive},
-        rules::AccessRules,
+        rules::{AccessRules, GetObjectKind, ObjectKind},
         Drawing,
     },
     geometry::{AccessBendWeight, GetOffset, GetWidth, SetOffset},
@@ -77,6 +77,7 @@ pub struct FixedBendWeight {
     pub offset: f64,
     pub layer: usize,
     pub maybe_net: Option<usize>,
+    pub kind: ObjectKind,
 }
 
 impl_fixed_weight!(FixedBendWeight, FixedBend, FixedBendIndex);
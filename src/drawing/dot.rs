@@ -7,7 +7,7 @@ use crate::{
     drawing::{
         graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex, PrimitiveWeight, Retag},
         primitive::{GenericPrimitive, Primitive},
-        rules::AccessRules,
+        rules::{AccessRules, GetObjectKind, ObjectKind},
         Drawing,
     },
     geometry::{AccessDotWeight, GetPos, GetWidth, SetPos},
@@ -78,6 +78,7 @@ pub struct FixedDotWeight {
     pub circle: Circle,
     pub layer: usize,
     pub maybe_net: Option<usize>,
+    pub kind: ObjectKind,
 }
 
 impl_fixed_weight!(FixedDotWeight, FixedDot, FixedDotIndex);
@@ -1,20 +1,24 @@
+use std::collections::HashSet;
+
 use contracts_try::{debug_ensures, debug_invariant};
 use derive_getters::Getters;
 use enum_dispatch::enum_dispatch;
-use geo::Point;
+use geo::{Intersects, Point, Polygon};
 
 use rstar::{RTree, AABB};
 use thiserror::Error;
 
 use crate::geometry::{
     compound::ManageCompounds,
+    edit::{GeometryEdit, Recording},
     primitive::{AccessPrimitiveShape, PrimitiveShape},
+    shape::AccessShape,
     with_rtree::{BboxedIndex, GeometryWithRtree},
     AccessBendWeight, AccessDotWeight, AccessSegWeight, GenericNode, Geometry, GeometryLabel,
     GetOffset, GetPos, GetWidth,
 };
 use crate::graph::{GenericIndex, GetPetgraphIndex};
-use crate::math::NoTangents;
+use crate::math::{self, Circle, NoTangents};
 use crate::{
     drawing::{
         band::BandTermsegIndex,
@@ -28,13 +32,14 @@ use crate::{
         loose::{GetPrevNextLoose, Loose, LooseIndex},
         primitive::{
             GenericPrimitive, GetCore, GetInnerOuter, GetJoints, GetLimbs, GetOtherJoint,
-            MakePrimitiveShape,
+            GetWeight, MakePrimitiveShape,
         },
-        rules::{AccessRules, GetConditions},
+        rules::{AccessRules, GetConditions, GetObjectKind, ObjectKind},
         seg::{
             FixedSegIndex, FixedSegWeight, LoneLooseSegIndex, LoneLooseSegWeight, SegIndex,
             SegWeight, SeqLooseSegIndex, SeqLooseSegWeight,
         },
+        shove::Shove,
     },
     graph::MakeRef,
 };
@@ -79,6 +84,8 @@ pub struct Drawing<CW: Copy, R: AccessRules> {
         BendIndex,
     >,
     rules: R,
+    /// The outline of the board, outside of which nothing may be inserted.
+    boundary: Option<Polygon>,
 }
 
 #[debug_invariant(self.test_if_looses_dont_infringe_each_other())]
@@ -87,9 +94,16 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
         Self {
             geometry_with_rtree: GeometryWithRtree::new(layer_count),
             rules,
+            boundary: None,
         }
     }
 
+    /// Sets the outline of the board, whose primitives are to be inserted with
+    /// [`ObjectKind::Boundary`] on each layer it applies to.
+    pub fn set_boundary(&mut self, boundary: Polygon) {
+        self.boundary = Some(boundary);
+    }
+
     pub fn remove_band(&mut self, band: BandTermsegIndex) -> Result<(), DrawingException> {
         match band {
             BandTermsegIndex::Straight(seg) => {
@@ -155,6 +169,16 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
         Ok(())
     }
 
+    /// Removes only the terminating seg of a band, leaving the rest of the band in place so that
+    /// it can be finished again elsewhere.
+    #[debug_ensures(self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count() - 1))]
+    pub fn remove_termseg(&mut self, termseg: BandTermsegIndex) {
+        match termseg {
+            BandTermsegIndex::Straight(seg) => self.geometry_with_rtree.remove_seg(seg.into()),
+            BandTermsegIndex::Bended(seg) => self.geometry_with_rtree.remove_seg(seg.into()),
+        }
+    }
+
     #[debug_ensures(ret.is_ok() -> self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count() + 1))]
     #[debug_ensures(ret.is_err() -> self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count()))]
     #[debug_ensures(self.geometry_with_rtree.graph().edge_count() == old(self.geometry_with_rtree.graph().edge_count()))]
@@ -168,6 +192,14 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
         self.geometry_with_rtree.remove_dot(dot.into());
     }
 
+    /// Removes a fixed dot that is a member of compounds, taking it out of them.
+    ///
+    /// The dot mustn't be joined to anything.
+    #[debug_ensures(self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count() - 1))]
+    pub fn remove_compound_fixed_dot(&mut self, dot: FixedDotIndex) {
+        let _ = self.geometry_with_rtree.remove_dot(dot.into());
+    }
+
     #[debug_ensures(self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count() + 1))]
     #[debug_ensures(self.geometry_with_rtree.graph().edge_count() == old(self.geometry_with_rtree.graph().edge_count()))]
     pub fn add_fixed_dot_infringably(&mut self, weight: FixedDotWeight) -> FixedDotIndex {
@@ -639,6 +671,69 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
         Cane::from_dot(dot, self)
     }
 
+    /// Wraps a seq loose seg of another band around the outermost bend of the gear, or around
+    /// the gear itself if nothing is wrapped around it, so that a cane can then be inserted
+    /// under it. The joints of the seg are moved along their bends to meet the new bend.
+    ///
+    /// The seg is wrapped around the side of the gear it passes on. If the shoved band would
+    /// infringe on anything, it's left as it was.
+    #[debug_ensures(ret.is_ok() -> self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count() + 4))]
+    #[debug_ensures(ret.is_err() -> self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count()))]
+    pub fn shove_seg(
+        &mut self,
+        seg: SeqLooseSegIndex,
+        around: GearIndex,
+    ) -> Result<Shove, DrawingException> {
+        let shove = self.add_shove_infringably(seg, around)?;
+
+        let (from, to) = shove.joints;
+        let mut shoved: Vec<PrimitiveIndex> = vec![
+            shove.seg.into(),
+            shove.rest_seg.into(),
+            shove.dots.0.into(),
+            shove.dots.1.into(),
+            shove.bend.into(),
+        ];
+
+        for joint in [from, to.into()] {
+            if let DotIndex::Loose(..) = joint {
+                shoved.push(joint.into());
+                shoved.extend(joint.primitive(self).limbs());
+            }
+        }
+
+        if let Some(infringement) = shoved
+            .iter()
+            .find_map(|node| self.detect_infringement_except(*node, Some(&[])))
+        {
+            self.remove_shove_infringably(&shove);
+            return Err(infringement.into());
+        }
+
+        // Segs must not cross.
+        if let Some(collision) = [shove.seg, shove.rest_seg]
+            .iter()
+            .find_map(|seg| self.detect_collision((*seg).into()))
+        {
+            self.remove_shove_infringably(&shove);
+            return Err(collision.into());
+        }
+
+        Ok(shove)
+    }
+
+    /// Straightens a shoved seg back, restoring the band it belongs to as it was before it was
+    /// shoved. Shoves must be undone in the reverse order they were made in.
+    #[debug_ensures(self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count() - 4))]
+    pub fn unshove(&mut self, shove: &Shove) {
+        let maybe_outer = self.primitive(shove.bend).outer();
+        self.remove_shove_infringably(shove);
+
+        if let Some(outer) = maybe_outer {
+            self.update_this_and_outward_bows(outer).unwrap(); // Must never fail.
+        }
+    }
+
     #[debug_ensures(self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count()))]
     #[debug_ensures(self.geometry_with_rtree.graph().edge_count() == old(self.geometry_with_rtree.graph().edge_count()))]
     pub fn move_dot(&mut self, dot: DotIndex, to: Point) -> Result<(), Infringement> {
@@ -648,6 +743,53 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
         }
     }
 
+    /// Moves the fixed dots by the given offset all together, so that they can't infringe on
+    /// each other midway, as is needed to move a whole component.
+    ///
+    /// The dots and their limbs keep their relative positions, so only infringements on other
+    /// primitives are looked for. If one is found, all the dots are moved back.
+    #[debug_ensures(self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count()))]
+    #[debug_ensures(self.geometry_with_rtree.graph().edge_count() == old(self.geometry_with_rtree.graph().edge_count()))]
+    pub fn move_dots_by(
+        &mut self,
+        dots: &[FixedDotIndex],
+        offset: Point,
+    ) -> Result<(), Infringement> {
+        let old_positions: Vec<Point> = dots
+            .iter()
+            .map(|dot| {
+                self.geometry_with_rtree
+                    .geometry()
+                    .dot_weight((*dot).into())
+                    .pos()
+            })
+            .collect();
+
+        for (dot, old_pos) in dots.iter().zip(old_positions.iter()) {
+            self.geometry_with_rtree
+                .move_dot((*dot).into(), *old_pos + offset);
+        }
+
+        let moved: Vec<PrimitiveIndex> = dots
+            .iter()
+            .flat_map(|dot| std::iter::once((*dot).into()).chain(self.primitive(*dot).limbs()))
+            .collect();
+
+        if let Some(infringement) = moved
+            .iter()
+            .find_map(|node| self.detect_infringement_except(*node, Some(&moved)))
+        {
+            // Restore original state.
+            for (dot, old_pos) in dots.iter().zip(old_positions.iter()) {
+                self.geometry_with_rtree.move_dot((*dot).into(), *old_pos);
+            }
+
+            return Err(infringement);
+        }
+
+        Ok(())
+    }
+
     #[debug_ensures(self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count()))]
     #[debug_ensures(self.geometry_with_rtree.graph().edge_count() == old(self.geometry_with_rtree.graph().edge_count()))]
     fn move_dot_with_infringables(
@@ -719,6 +861,30 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
     }
 }
 
+#[debug_invariant(self.test_if_looses_dont_infringe_each_other())]
+impl<CW: Copy + PartialEq, R: AccessRules> Drawing<CW, R> {
+    /// Starts recording the changes made to the drawing, so that the edit they make up can be
+    /// found with [`Drawing::edit_since`].
+    pub fn start_recording(&mut self) -> Recording {
+        self.geometry_with_rtree.start_recording()
+    }
+
+    /// Finds the edit made to the drawing since the recording was started.
+    pub fn edit_since(&self, recording: &Recording) -> GeometryEdit<PrimitiveWeight, CW> {
+        self.geometry_with_rtree.edit_since(recording)
+    }
+
+    /// Stops the recording, returning the edit made to the drawing since it was started.
+    pub fn stop_recording(&mut self, recording: Recording) -> GeometryEdit<PrimitiveWeight, CW> {
+        self.geometry_with_rtree.stop_recording(recording)
+    }
+
+    /// Applies an edit, or the reverse of one, to a drawing in the state it starts from.
+    pub fn apply_edit(&mut self, edit: &GeometryEdit<PrimitiveWeight, CW>) {
+        self.geometry_with_rtree.apply_edit(edit);
+    }
+}
+
 impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
     #[debug_ensures(self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count() + 1))]
     #[debug_ensures(self.geometry_with_rtree.graph().edge_count() == old(self.geometry_with_rtree.graph().edge_count()))]
@@ -746,6 +912,152 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
         self.geometry_with_rtree.add_seg(from, to, weight)
     }
 
+    #[debug_ensures(ret.is_ok() -> self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count() + 4))]
+    #[debug_ensures(ret.is_err() -> self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count()))]
+    fn add_shove_infringably(
+        &mut self,
+        seg: SeqLooseSegIndex,
+        around: GearIndex,
+    ) -> Result<Shove, DrawingException> {
+        let mut around = around;
+
+        while let Some(outer) = around.ref_(self).next_gear() {
+            around = outer.into();
+        }
+
+        let weight = self.primitive(seg).weight();
+
+        // It makes no sense to wrap something around one of its connectables.
+        if let Some(net) = weight.maybe_net {
+            if around.primitive(self).maybe_net() == Some(net) {
+                return Err(AlreadyConnected(net, around.into()).into());
+            }
+        }
+
+        let (from, to) = self.primitive(seg).joints();
+        let joint_positions = (
+            self.geometry_with_rtree.geometry().dot_weight(from).pos(),
+            self.geometry_with_rtree
+                .geometry()
+                .dot_weight(to.into())
+                .pos(),
+        );
+        let core = match around {
+            GearIndex::FixedDot(dot) => dot.into(),
+            GearIndex::FixedBend(bend) => self.geometry_with_rtree.geometry().core(bend.into()),
+            GearIndex::LooseBend(bend) => self.geometry_with_rtree.geometry().core(bend.into()),
+        };
+        let core_pos = self.geometry_with_rtree.geometry().dot_weight(core).pos();
+
+        // The seg is wrapped so that the core stays on the side it was on.
+        let cw = math::seq_cross_product(joint_positions.0, joint_positions.1, core_pos) > 0.0;
+
+        let guide = Guide::new(self);
+        let from_head = guide.head(from);
+        let to_head = guide.head(to.into());
+        let (from_tangent, to_tangent, offset) = match around {
+            GearIndex::FixedDot(dot) => (
+                guide.head_around_dot_segment(&from_head, dot.into(), cw, weight.width)?,
+                guide.head_around_dot_segment(&to_head, dot.into(), !cw, weight.width)?,
+                guide.head_around_dot_offset(&from_head, dot.into(), weight.width),
+            ),
+            GearIndex::FixedBend(bend) => (
+                guide.head_around_bend_segment(&from_head, bend.into(), cw, weight.width)?,
+                guide.head_around_bend_segment(&to_head, bend.into(), !cw, weight.width)?,
+                guide.head_around_bend_offset(&from_head, bend.into(), weight.width),
+            ),
+            GearIndex::LooseBend(bend) => (
+                guide.head_around_bend_segment(&from_head, bend.into(), cw, weight.width)?,
+                guide.head_around_bend_segment(&to_head, bend.into(), !cw, weight.width)?,
+                guide.head_around_bend_offset(&from_head, bend.into(), weight.width),
+            ),
+        };
+
+        let dot_weight = |pos| LooseDotWeight {
+            circle: Circle {
+                pos,
+                r: weight.width / 2.0,
+            },
+            layer: weight.layer,
+            maybe_net: weight.maybe_net,
+        };
+        let dot1 = self.add_dot_infringably(dot_weight(from_tangent.end_point()));
+        let dot2 = self.add_dot_infringably(dot_weight(to_tangent.end_point()));
+
+        self.geometry_with_rtree
+            .rejoin_seg(seg.into(), to.into(), dot1.into());
+        let rest_seg = self.add_seg_infringably(dot2.into(), to.into(), weight);
+
+        let (bend_from, bend_to) = if cw { (dot2, dot1) } else { (dot1, dot2) };
+        let bend = self.geometry_with_rtree.add_bend(
+            bend_from.into(),
+            bend_to.into(),
+            core,
+            LooseBendWeight {
+                width: weight.width,
+                offset,
+                layer: weight.layer,
+                maybe_net: weight.maybe_net,
+            },
+        );
+
+        match around {
+            GearIndex::FixedDot(..) => (),
+            GearIndex::FixedBend(inner) => self
+                .geometry_with_rtree
+                .reattach_bend(bend.into(), Some(inner.into())),
+            GearIndex::LooseBend(inner) => self
+                .geometry_with_rtree
+                .reattach_bend(bend.into(), Some(inner.into())),
+        }
+
+        if let DotIndex::Loose(..) = from {
+            self.geometry_with_rtree
+                .move_dot(from, from_tangent.start_point());
+        }
+
+        self.geometry_with_rtree
+            .move_dot(to.into(), to_tangent.start_point());
+
+        Ok(Shove {
+            seg,
+            bend,
+            dots: (dot1, dot2),
+            rest_seg,
+            joints: (from, to),
+            joint_positions,
+        })
+    }
+
+    #[debug_ensures(self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count() - 4))]
+    fn remove_shove_infringably(&mut self, shove: &Shove) {
+        // Removing a loose bend affects its outer bends.
+        if let Some(outer) = self.primitive(shove.bend).outer() {
+            self.geometry_with_rtree.reattach_bend(
+                outer.into(),
+                self.primitive(shove.bend).inner().map(Into::into),
+            );
+        }
+
+        let (from, to) = shove.joints;
+
+        self.geometry_with_rtree.remove_bend(shove.bend.into());
+        self.geometry_with_rtree.remove_seg(shove.rest_seg.into());
+        self.geometry_with_rtree
+            .rejoin_seg(shove.seg.into(), shove.dots.0.into(), to.into());
+
+        let _ = self.geometry_with_rtree.remove_dot(shove.dots.1.into());
+        let _ = self.geometry_with_rtree.remove_dot(shove.dots.0.into());
+
+        if let DotIndex::Loose(..) = from {
+            self.geometry_with_rtree
+                .move_dot(from, shove.joint_positions.0);
+        }
+
+        self.geometry_with_rtree
+            .move_dot(to.into(), shove.joint_positions.1);
+    }
+
     #[debug_ensures(ret.is_ok() -> self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count()))]
     #[debug_ensures(ret.is_ok() -> self.geometry_with_rtree.graph().edge_count() == old(self.geometry_with_rtree.graph().edge_count()))]
     #[debug_ensures(ret.is_err() -> self.geometry_with_rtree.graph().node_count() == old(self.geometry_with_rtree.graph().node_count() - 1))]
@@ -772,6 +1084,10 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
         node: PrimitiveIndex,
         maybe_except: Option<&[PrimitiveIndex]>,
     ) -> Option<Infringement> {
+        if let Some(infringement) = self.detect_outside_boundary(node) {
+            return Some(infringement);
+        }
+
         self.find_infringement(
             node,
             self.locate_possible_infringers(node)
@@ -788,6 +1104,24 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
         )
     }
 
+    /// A primitive lying entirely outside the board infringes on its boundary, as it crosses
+    /// none of the boundary's primitives and so wouldn't be caught otherwise.
+    fn detect_outside_boundary(&self, node: PrimitiveIndex) -> Option<Infringement> {
+        let boundary = self.boundary.as_ref()?;
+        let shape = node.primitive(self).shape();
+
+        if boundary.intersects(&shape.center()) {
+            return None;
+        }
+
+        let boundary_node = self
+            .layer_primitive_nodes(node.primitive(self).layer())
+            .find(|boundary_node| {
+                boundary_node.primitive(self).object_kind() == ObjectKind::Boundary
+            })?;
+        Some(Infringement(shape, boundary_node))
+    }
+
     fn locate_possible_infringers(
         &self,
         node: PrimitiveIndex,
@@ -812,15 +1146,22 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
         node: PrimitiveIndex,
         it: impl Iterator<Item = PrimitiveIndex>,
     ) -> Option<Infringement> {
-        let mut inflated_shape = node.primitive(self).shape(); // Unused temporary value just for initialization.
+        self.infringements_among(node, it).next()
+    }
+
+    fn infringements_among<'a>(
+        &'a self,
+        node: PrimitiveIndex,
+        it: impl Iterator<Item = PrimitiveIndex> + 'a,
+    ) -> impl Iterator<Item = Infringement> + 'a {
         let conditions = node.primitive(self).conditions();
 
-        it.filter(|primitive_node| !self.are_connectable(node, *primitive_node))
-            .find_map(|primitive_node| {
+        it.filter(move |primitive_node| !self.are_connectable(node, *primitive_node))
+            .filter_map(move |primitive_node| {
                 let infringee_conditions = primitive_node.primitive(self).conditions();
 
                 let epsilon = 1.0;
-                inflated_shape = node.primitive(self).shape().inflate(
+                let inflated_shape = node.primitive(self).shape().inflate(
                     (self.rules.clearance(&conditions, &infringee_conditions) - epsilon)
                         .clamp(0.0, f64::INFINITY),
                 );
@@ -831,6 +1172,39 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
             })
     }
 
+    /// Finds all pairs of primitives that infringe on each other, including the ones that were
+    /// added infringably, each pair once.
+    ///
+    /// Unlike the checks made when inserting primitives, this sweeps the whole drawing, so it's
+    /// slow and meant only for checking a finished board against its rules.
+    pub fn find_all_infringements(&self) -> Vec<(PrimitiveIndex, Infringement)> {
+        let mut pairs = HashSet::new();
+        let mut infringements = vec![];
+
+        for node in self.primitive_nodes() {
+            let it = self
+                .locate_possible_infringers(node)
+                .filter_map(|n| {
+                    if let GenericNode::Primitive(primitive_node) = n {
+                        Some(primitive_node)
+                    } else {
+                        None
+                    }
+                })
+                .filter(|primitive_node| *primitive_node != node);
+
+            for infringement in self.infringements_among(node, it) {
+                let (a, b) = (node.petgraph_index(), infringement.1.petgraph_index());
+
+                if pairs.insert((a.min(b), a.max(b))) {
+                    infringements.push((node, infringement));
+                }
+            }
+        }
+
+        infringements
+    }
+
     pub fn primitive_nodes(&self) -> impl Iterator<Item = PrimitiveIndex> + '_ {
         self.geometry_with_rtree
             .rtree()
@@ -861,13 +1235,15 @@ impl<CW: Copy, R: AccessRules> Drawing<CW, R> {
     }
 
     fn are_connectable(&self, node1: PrimitiveIndex, node2: PrimitiveIndex) -> bool {
-        if let (Some(node1_net_id), Some(node2_net_id)) = (
-            node1.primitive(self).maybe_net(),
-            node2.primitive(self).maybe_net(),
-        ) {
-            node1_net_id == node2_net_id
-        } else {
-            true
+        let primitive1 = node1.primitive(self);
+        let primitive2 = node2.primitive(self);
+
+        match (primitive1.maybe_net(), primitive2.maybe_net()) {
+            (Some(node1_net_id), Some(node2_net_id)) => node1_net_id == node2_net_id,
+            // The board boundary and keepouts are obstacles to every net.
+            (Some(_), None) => !primitive2.object_kind().keeps_out(primitive1.object_kind()),
+            (None, Some(_)) => !primitive1.object_kind().keeps_out(primitive2.object_kind()),
+            (None, None) => true,
         }
     }
 
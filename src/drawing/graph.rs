@@ -74,12 +74,24 @@ macro_rules! impl_weight {
 macro_rules! impl_fixed_weight {
     ($weight_struct:ident, $weight_variant:ident, $index_struct:ident) => {
         impl_weight!($weight_struct, $weight_variant, $index_struct);
+
+        impl GetObjectKind for $weight_struct {
+            fn object_kind(&self) -> ObjectKind {
+                self.kind
+            }
+        }
     };
 }
 
 macro_rules! impl_loose_weight {
     ($weight_struct:ident, $weight_variant:ident, $index_struct:ident) => {
         impl_weight!($weight_struct, $weight_variant, $index_struct);
+
+        impl GetObjectKind for $weight_struct {
+            fn object_kind(&self) -> ObjectKind {
+                ObjectKind::Wire
+            }
+        }
     };
 }
 
@@ -48,6 +48,20 @@ impl From<LooseIndex> for PrimitiveIndex {
     }
 }
 
+impl TryFrom<PrimitiveIndex> for LooseIndex {
+    type Error = (); // TODO.
+
+    fn try_from(index: PrimitiveIndex) -> Result<LooseIndex, ()> {
+        match index {
+            PrimitiveIndex::LooseDot(dot) => Ok(LooseIndex::Dot(dot)),
+            PrimitiveIndex::LoneLooseSeg(seg) => Ok(LooseIndex::LoneSeg(seg)),
+            PrimitiveIndex::SeqLooseSeg(seg) => Ok(LooseIndex::SeqSeg(seg)),
+            PrimitiveIndex::LooseBend(bend) => Ok(LooseIndex::Bend(bend)),
+            _ => Err(()),
+        }
+    }
+}
+
 #[enum_dispatch(GetPrevNextLoose, GetDrawing, GetPetgraphIndex)]
 pub enum Loose<'a, CW: Copy, R: AccessRules> {
     Dot(LooseDot<'a, CW, R>),
@@ -13,5 +13,6 @@ pub mod loose;
 pub mod primitive;
 pub mod rules;
 pub mod seg;
+pub mod shove;
 
 pub use drawing::*;
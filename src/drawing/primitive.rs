@@ -6,7 +6,7 @@ use crate::{
         bend::{BendIndex, FixedBendWeight, LooseBendIndex, LooseBendWeight},
         dot::{DotIndex, DotWeight, FixedDotIndex, FixedDotWeight, LooseDotIndex, LooseDotWeight},
         graph::{GetLayer, GetMaybeNet, PrimitiveIndex, PrimitiveWeight, Retag},
-        rules::{AccessRules, Conditions, GetConditions},
+        rules::{AccessRules, Conditions, GetConditions, GetObjectKind, ObjectKind},
         seg::{FixedSegWeight, LoneLooseSegWeight, SegIndex, SeqLooseSegIndex, SeqLooseSegWeight},
         Drawing,
     },
@@ -133,6 +133,12 @@ macro_rules! impl_primitive {
                 self.weight().maybe_net()
             }
         }
+
+        impl<'a, CW: Copy, R: AccessRules> GetObjectKind for $primitive_struct<'a, CW, R> {
+            fn object_kind(&self) -> ObjectKind {
+                self.weight().object_kind()
+            }
+        }
     };
 }
 
@@ -151,6 +157,7 @@ macro_rules! impl_loose_primitive {
 #[enum_dispatch(
     GetLayer,
     GetMaybeNet,
+    GetObjectKind,
     GetWidth,
     GetDrawing,
     MakePrimitiveShape,
@@ -228,13 +235,16 @@ where
 
 impl<'a, W, CW: Copy, R: AccessRules> GetConditions for GenericPrimitive<'a, W, CW, R>
 where
-    GenericPrimitive<'a, W, CW, R>: GetMaybeNet,
+    GenericPrimitive<'a, W, CW, R>: GetMaybeNet + GetObjectKind + GetLayer + MakePrimitiveShape,
 {
     fn conditions(&self) -> Conditions {
+        let layer = self.layer();
+
         Conditions {
             maybe_net: self.maybe_net(),
-            maybe_region: Some("A".to_string()),
-            maybe_layer: Some("F.Cu".to_string()),
+            kind: self.object_kind(),
+            maybe_region: self.drawing.rules().maybe_region(layer, &self.shape()),
+            maybe_layer: Some(layer),
         }
     }
 }
@@ -1,20 +1,68 @@
+use crate::{drawing::primitive::Primitive, geometry::primitive::PrimitiveShape};
 use enum_dispatch::enum_dispatch;
 
-use crate::drawing::primitive::Primitive;
-
 #[enum_dispatch]
 pub trait GetConditions {
     fn conditions(&self) -> Conditions;
 }
 
+#[enum_dispatch]
+pub trait GetObjectKind {
+    fn object_kind(&self) -> ObjectKind;
+}
+
+/// The kinds of objects that clearance rules tell apart.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectKind {
+    /// A routed band or a fixed trace.
+    #[default]
+    Wire,
+    Via,
+    /// A pad of a through-hole pin.
+    Pin,
+    /// A pad of a surface-mount pin.
+    Smd,
+    /// A keepout.
+    Area,
+    /// A keepout of wires alone, into which vias may still be placed.
+    WireArea,
+    /// The board boundary.
+    Boundary,
+    /// A core a meander is wrapped around, which holds its bends in place but isn't copper.
+    MeanderCore,
+}
+
+impl ObjectKind {
+    /// Whether objects of this kind are obstacles to objects of the given kind of every net
+    /// even though they have none, as the board boundary and keepouts are.
+    pub fn keeps_out(self, kind: ObjectKind) -> bool {
+        match self {
+            Self::Area | Self::Boundary => true,
+            Self::WireArea => kind == Self::Wire,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Conditions {
     pub maybe_net: Option<usize>,
-    pub maybe_region: Option<String>,
-    pub maybe_layer: Option<String>,
+    pub kind: ObjectKind,
+    /// The index of the rule region the object lies in, if any, as the rules number regions.
+    pub maybe_region: Option<usize>,
+    pub maybe_layer: Option<usize>,
 }
 
 pub trait AccessRules {
     fn clearance(&self, conditions1: &Conditions, conditions2: &Conditions) -> f64;
     fn largest_clearance(&self, net: Option<usize>) -> f64;
+    /// The width wires of the given conditions are to have, if the rules set one.
+    fn maybe_width(&self, conditions: &Conditions) -> Option<f64>;
+    /// The diameter of the vias placed for the given net, if the rules set one.
+    fn maybe_via_diameter(&self, net: usize) -> Option<f64>;
+    /// The first and the last layer spanned by the vias placed for the given net, if the rules
+    /// set them.
+    fn maybe_via_layers(&self, net: usize) -> Option<(usize, usize)>;
+    /// Finds the rule region that the given shape on the given layer lies in, if any.
+    fn maybe_region(&self, layer: usize, shape: &PrimitiveShape) -> Option<usize>;
 }
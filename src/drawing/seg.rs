@@ -4,7 +4,7 @@ use crate::{
     drawing::{
         graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex, PrimitiveWeight, Retag},
         primitive::{GenericPrimitive, Primitive},
-        rules::AccessRules,
+        rules::{AccessRules, GetObjectKind, ObjectKind},
         Drawing,
     },
     geometry::{AccessSegWeight, GetWidth},
@@ -82,6 +82,7 @@ pub struct FixedSegWeight {
     pub width: f64,
     pub layer: usize,
     pub maybe_net: Option<usize>,
+    pub kind: ObjectKind,
 }
 
 impl_fixed_weight!(FixedSegWeight, FixedSeg, FixedSegIndex);
@@ -0,0 +1,28 @@
+use geo::Point;
+
+use crate::drawing::{
+    bend::LooseBendIndex,
+    dot::{DotIndex, LooseDotIndex},
+    seg::SeqLooseSegIndex,
+};
+
+/// A seq loose seg of a band that was wrapped around a gear to make way for a cane, together
+/// with what is needed to straighten it back.
+///
+/// The seg keeps its index and its first joint, so the terminating segs of the shoved band stay
+/// valid, while its other joint is moved over to the bend it was wrapped with.
+#[derive(Debug, Clone, Copy)]
+pub struct Shove {
+    /// The seg that was wrapped, now ending in the first dot of the bend.
+    pub seg: SeqLooseSegIndex,
+    /// The bend the seg was wrapped around the gear with.
+    pub bend: LooseBendIndex,
+    /// The dots of the bend, the first joined to `seg` and the second to `rest_seg`.
+    pub dots: (LooseDotIndex, LooseDotIndex),
+    /// The seg that leads from the bend on to where `seg` ended before.
+    pub rest_seg: SeqLooseSegIndex,
+    /// The joints of the seg from before it was wrapped.
+    pub joints: (DotIndex, LooseDotIndex),
+    /// The positions of the joints from before they were moved to meet the bend.
+    pub joint_positions: (Point, Point),
+}
@@ -0,0 +1,203 @@
+//! Module for the edits made to a geometry, recorded as they are made, which can be applied or
+//! reverted to move the geometry between the states before and after them
+
+use std::collections::BTreeMap;
+
+use petgraph::stable_graph::{NodeIndex, StableDiGraph};
+
+use crate::geometry::{GenericNode, GeometryLabel};
+
+/// The weight of a node or an edge before and after an edit, `None` where it didn't exist.
+type Change<W> = (Option<W>, Option<W>);
+
+/// Identifies a recording of the edits made to a geometry, started with `start_recording` and
+/// stopped with `stop_recording`.
+///
+/// What the recording has recorded is kept by the geometry itself, which records each change
+/// into all of its recordings.
+#[derive(Debug)]
+pub struct Recording(pub(super) usize);
+
+/// The nodes and edges touched since a recording was started, each with the weight it had before
+/// it was first touched.
+#[derive(Debug, Clone)]
+pub(super) struct GeometryRecord<PW, CW> {
+    nodes: BTreeMap<NodeIndex<usize>, Option<GenericNode<PW, CW>>>,
+    edges: BTreeMap<(NodeIndex<usize>, NodeIndex<usize>), Option<GeometryLabel>>,
+}
+
+impl<PW: Copy, CW: Copy> GeometryRecord<PW, CW> {
+    pub fn new() -> Self {
+        Self {
+            nodes: BTreeMap::new(),
+            edges: BTreeMap::new(),
+        }
+    }
+
+    /// Records the weight a node has before it's touched, unless it was touched already.
+    pub fn record_node(
+        &mut self,
+        node: NodeIndex<usize>,
+        weight: impl FnOnce() -> Option<GenericNode<PW, CW>>,
+    ) {
+        self.nodes.entry(node).or_insert_with(weight);
+    }
+
+    /// Records the label an edge has before it's touched, unless it was touched already.
+    pub fn record_edge(
+        &mut self,
+        endpoints: (NodeIndex<usize>, NodeIndex<usize>),
+        label: impl FnOnce() -> Option<GeometryLabel>,
+    ) {
+        self.edges.entry(endpoints).or_insert_with(label);
+    }
+}
+
+impl<PW: Copy + PartialEq, CW: Copy + PartialEq> GeometryRecord<PW, CW> {
+    /// Finds the edit made since recording started, given the graph as it is now.
+    pub fn edit(
+        &self,
+        graph: &StableDiGraph<GenericNode<PW, CW>, GeometryLabel, usize>,
+    ) -> GeometryEdit<PW, CW> {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|(node, before)| (*node, (*before, graph.node_weight(*node).copied())))
+            .filter(|(_, (before, after))| before != after)
+            .collect();
+        let edges = self
+            .edges
+            .iter()
+            .map(|((source, target), before)| {
+                let after = graph
+                    .find_edge(*source, *target)
+                    .map(|edge| *graph.edge_weight(edge).unwrap());
+                ((*source, *target), (*before, after))
+            })
+            .filter(|(_, (before, after))| before != after)
+            .collect();
+
+        GeometryEdit { nodes, edges }
+    }
+}
+
+/// The nodes and edges that differ between two states of a geometry's graph.
+///
+/// Nodes keep their indices, so anything referring to them stays valid once the edit is applied
+/// again.
+#[derive(Debug, Clone)]
+pub struct GeometryEdit<PW, CW> {
+    nodes: BTreeMap<NodeIndex<usize>, Change<GenericNode<PW, CW>>>,
+    edges: BTreeMap<(NodeIndex<usize>, NodeIndex<usize>), Change<GeometryLabel>>,
+}
+
+impl<PW: Copy + PartialEq, CW: Copy + PartialEq> GeometryEdit<PW, CW> {
+    /// The edit that undoes this one.
+    pub fn reverse(&self) -> Self {
+        Self {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|(node, (before, after))| (*node, (*after, *before)))
+                .collect(),
+            edges: self
+                .edges
+                .iter()
+                .map(|(endpoints, (before, after))| (*endpoints, (*after, *before)))
+                .collect(),
+        }
+    }
+
+    /// Whether the edit leaves the geometry as it was.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.edges.is_empty()
+    }
+
+    /// The nodes the edit adds, removes or reweighs.
+    pub fn nodes(&self) -> impl Iterator<Item = NodeIndex<usize>> + '_ {
+        self.nodes.keys().copied()
+    }
+
+    /// The endpoints of the edges the edit adds, removes or relabels.
+    pub fn edges(&self) -> impl Iterator<Item = (NodeIndex<usize>, NodeIndex<usize>)> + '_ {
+        self.edges.keys().copied()
+    }
+
+    /// The nodes the edit removes.
+    pub fn removed_nodes(&self) -> impl Iterator<Item = NodeIndex<usize>> + '_ {
+        self.nodes
+            .iter()
+            .filter(|(_, (_, after))| after.is_none())
+            .map(|(node, _)| *node)
+    }
+
+    /// Applies the edit to a graph that is in the state the edit starts from.
+    pub fn apply_to_graph(
+        &self,
+        graph: &mut StableDiGraph<GenericNode<PW, CW>, GeometryLabel, usize>,
+    ) {
+        for ((source, target), (before, after)) in self.edges.iter() {
+            if before.is_some() && after != before {
+                if let Some(edge) = graph.find_edge(*source, *target) {
+                    graph.remove_edge(edge);
+                }
+            }
+        }
+
+        for (node, (_, after)) in self.nodes.iter() {
+            if after.is_none() {
+                graph.remove_node(*node);
+            }
+        }
+
+        for (node, (before, after)) in self.nodes.iter() {
+            match (before, after) {
+                (None, Some(weight)) => Self::add_node_at_index(graph, *node, *weight),
+                (Some(..), Some(weight)) => *graph.node_weight_mut(*node).unwrap() = *weight,
+                _ => (),
+            }
+        }
+
+        for ((source, target), (before, after)) in self.edges.iter() {
+            if let Some(label) = after {
+                if after != before {
+                    graph.update_edge(*source, *target, *label);
+                }
+            }
+        }
+    }
+
+    /// Adds a node to the graph at the given vacant index.
+    ///
+    /// `StableDiGraph` can only reuse the indices of removed nodes in turn, so nodes are added
+    /// until the index comes up, and the other ones are removed again in reverse order so that
+    /// their indices are freed as they were. A vacant index always comes up, either from the
+    /// removed nodes or past the last node.
+    fn add_node_at_index(
+        graph: &mut StableDiGraph<GenericNode<PW, CW>, GeometryLabel, usize>,
+        index: NodeIndex<usize>,
+        weight: GenericNode<PW, CW>,
+    ) {
+        assert!(
+            graph.node_weight(index).is_none(),
+            "edit adds node at index {} that is already taken",
+            index.index()
+        );
+
+        let mut placeholders = vec![];
+
+        loop {
+            let node = graph.add_node(weight);
+
+            if node == index {
+                break;
+            }
+
+            placeholders.push(node);
+        }
+
+        for placeholder in placeholders.into_iter().rev() {
+            graph.remove_node(placeholder);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use std::{collections::BTreeMap, marker::PhantomData};
 
 use derive_getters::Getters;
 use enum_dispatch::enum_dispatch;
@@ -6,7 +6,7 @@ use geo::Point;
 use petgraph::{
     stable_graph::{NodeIndex, StableDiGraph},
     visit::EdgeRef,
-    Direction::Incoming,
+    Direction::{Incoming, Outgoing},
 };
 
 use crate::{
@@ -20,6 +20,7 @@ use crate::{
     },
     geometry::{
         compound::ManageCompounds,
+        edit::{GeometryEdit, GeometryRecord, Recording},
         primitive::{BendShape, DotShape, PrimitiveShape, SegShape},
     },
     graph::{GenericIndex, GetPetgraphIndex},
@@ -82,6 +83,11 @@ pub struct Geometry<
     BI: GetPetgraphIndex + Into<PI> + Copy,
 > {
     graph: StableDiGraph<GenericNode<PW, CW>, GeometryLabel, usize>,
+    /// The recordings started and not stopped yet, into each of which every change is recorded.
+    #[getter(skip)]
+    records: BTreeMap<usize, GeometryRecord<PW, CW>>,
+    #[getter(skip)]
+    next_recording: usize,
     weight_marker: PhantomData<PW>,
     dot_weight_marker: PhantomData<DW>,
     seg_weight_marker: PhantomData<SW>,
@@ -108,6 +114,8 @@ impl<
     pub fn new() -> Self {
         Self {
             graph: StableDiGraph::default(),
+            records: BTreeMap::new(),
+            next_recording: 0,
             weight_marker: PhantomData,
             dot_weight_marker: PhantomData,
             seg_weight_marker: PhantomData,
@@ -121,7 +129,7 @@ impl<
     }
 
     pub fn add_dot<W: AccessDotWeight<PW>>(&mut self, weight: W) -> GenericIndex<W> {
-        GenericIndex::<W>::new(self.graph.add_node(GenericNode::Primitive(weight.into())))
+        GenericIndex::<W>::new(self.add_node(GenericNode::Primitive(weight.into())))
     }
 
     pub fn add_seg<W: AccessSegWeight<PW>>(
@@ -130,15 +138,14 @@ impl<
         to: DI,
         weight: W,
     ) -> GenericIndex<W> {
-        let seg =
-            GenericIndex::<W>::new(self.graph.add_node(GenericNode::Primitive(weight.into())));
+        let seg = GenericIndex::<W>::new(self.add_node(GenericNode::Primitive(weight.into())));
 
-        self.graph.update_edge(
+        self.update_edge(
             from.petgraph_index(),
             seg.petgraph_index(),
             GeometryLabel::Joined,
         );
-        self.graph.update_edge(
+        self.update_edge(
             seg.petgraph_index(),
             to.petgraph_index(),
             GeometryLabel::Joined,
@@ -154,20 +161,19 @@ impl<
         core: DI,
         weight: W,
     ) -> GenericIndex<W> {
-        let bend =
-            GenericIndex::<W>::new(self.graph.add_node(GenericNode::Primitive(weight.into())));
+        let bend = GenericIndex::<W>::new(self.add_node(GenericNode::Primitive(weight.into())));
 
-        self.graph.update_edge(
+        self.update_edge(
             from.petgraph_index(),
             bend.petgraph_index(),
             GeometryLabel::Joined,
         );
-        self.graph.update_edge(
+        self.update_edge(
             bend.petgraph_index(),
             to.petgraph_index(),
             GeometryLabel::Joined,
         );
-        self.graph.update_edge(
+        self.update_edge(
             bend.petgraph_index(),
             core.petgraph_index(),
             GeometryLabel::Core,
@@ -177,58 +183,69 @@ impl<
     }
 
     pub fn remove_primitive(&mut self, primitive: PI) {
-        self.graph.remove_node(primitive.petgraph_index());
+        self.remove_node(primitive.petgraph_index());
     }
 
     pub fn move_dot(&mut self, dot: DI, to: Point) {
         let mut weight = self.dot_weight(dot);
         weight.set_pos(to);
-        *self.graph.node_weight_mut(dot.petgraph_index()).unwrap() =
-            GenericNode::Primitive(weight.into());
+        self.set_node_weight(dot.petgraph_index(), GenericNode::Primitive(weight.into()));
     }
 
     pub fn shift_bend(&mut self, bend: BI, offset: f64) {
         let mut weight = self.bend_weight(bend);
         weight.set_offset(offset);
-        *self.graph.node_weight_mut(bend.petgraph_index()).unwrap() =
-            GenericNode::Primitive(weight.into());
+        self.set_node_weight(bend.petgraph_index(), GenericNode::Primitive(weight.into()));
     }
 
     pub fn flip_bend(&mut self, bend: BI) {
         let (from, to) = self.bend_joints(bend);
         let from_edge_weight = self
-            .graph
-            .remove_edge(
-                self.graph
-                    .find_edge(from.petgraph_index(), bend.petgraph_index())
-                    .unwrap(),
-            )
+            .remove_edge(from.petgraph_index(), bend.petgraph_index())
             .unwrap();
         let to_edge_weight = self
-            .graph
-            .remove_edge(
-                self.graph
-                    .find_edge(bend.petgraph_index(), to.petgraph_index())
-                    .unwrap(),
-            )
+            .remove_edge(bend.petgraph_index(), to.petgraph_index())
             .unwrap();
-        self.graph
-            .update_edge(from.petgraph_index(), bend.petgraph_index(), to_edge_weight);
-        self.graph
-            .update_edge(bend.petgraph_index(), to.petgraph_index(), from_edge_weight);
+        self.update_edge(from.petgraph_index(), bend.petgraph_index(), to_edge_weight);
+        self.update_edge(bend.petgraph_index(), to.petgraph_index(), from_edge_weight);
+    }
+
+    /// Moves the end of the seg that is joined to `old_joint` over to `new_joint`, keeping the
+    /// direction of the joint's edge.
+    pub fn rejoin_seg(&mut self, seg: SI, old_joint: DI, new_joint: DI) {
+        if self
+            .remove_edge(seg.petgraph_index(), old_joint.petgraph_index())
+            .is_some()
+        {
+            self.update_edge(
+                seg.petgraph_index(),
+                new_joint.petgraph_index(),
+                GeometryLabel::Joined,
+            );
+        } else if self
+            .remove_edge(old_joint.petgraph_index(), seg.petgraph_index())
+            .is_some()
+        {
+            self.update_edge(
+                new_joint.petgraph_index(),
+                seg.petgraph_index(),
+                GeometryLabel::Joined,
+            );
+        }
     }
 
     pub fn reattach_bend(&mut self, bend: BI, maybe_new_inner: Option<BI>) {
-        if let Some(old_inner_edge) = self
+        if let Some(old_inner) = self
             .graph
             .edges_directed(bend.petgraph_index(), Incoming)
             .find(|edge| *edge.weight() == GeometryLabel::Outer)
+            .map(|edge| edge.source())
         {
-            self.graph.remove_edge(old_inner_edge.id());
+            self.remove_edge(old_inner, bend.petgraph_index());
         }
 
         if let Some(new_inner) = maybe_new_inner {
-            self.graph.update_edge(
+            self.update_edge(
                 new_inner.petgraph_index(),
                 bend.petgraph_index(),
                 GeometryLabel::Outer,
@@ -236,6 +253,129 @@ impl<
         }
     }
 
+    /// Starts recording the changes made to the geometry, to later find the edit they make up
+    /// with [`Geometry::edit_since`].
+    pub fn start_recording(&mut self) -> Recording {
+        let id = self.next_recording;
+        self.next_recording += 1;
+        self.records.insert(id, GeometryRecord::new());
+        Recording(id)
+    }
+
+    /// Finds the edit made to the geometry since the recording was started.
+    pub fn edit_since(&self, recording: &Recording) -> GeometryEdit<PW, CW>
+    where
+        PW: PartialEq,
+        CW: PartialEq,
+    {
+        self.records[&recording.0].edit(&self.graph)
+    }
+
+    /// Stops the recording, returning the edit made to the geometry since it was started.
+    pub fn stop_recording(&mut self, recording: Recording) -> GeometryEdit<PW, CW>
+    where
+        PW: PartialEq,
+        CW: PartialEq,
+    {
+        self.records.remove(&recording.0).unwrap().edit(&self.graph)
+    }
+
+    pub fn apply_edit(&mut self, edit: &GeometryEdit<PW, CW>)
+    where
+        PW: PartialEq,
+        CW: PartialEq,
+    {
+        for node in edit.removed_nodes() {
+            self.record_node_with_edges(node);
+        }
+
+        for node in edit.nodes() {
+            self.record_node(node);
+        }
+
+        for (source, target) in edit.edges() {
+            self.record_edge(source, target);
+        }
+
+        edit.apply_to_graph(&mut self.graph);
+    }
+
+    fn record_node(&mut self, node: NodeIndex<usize>) {
+        for record in self.records.values_mut() {
+            record.record_node(node, || self.graph.node_weight(node).copied());
+        }
+    }
+
+    fn record_edge(&mut self, source: NodeIndex<usize>, target: NodeIndex<usize>) {
+        for record in self.records.values_mut() {
+            record.record_edge((source, target), || {
+                self.graph
+                    .find_edge(source, target)
+                    .map(|edge| *self.graph.edge_weight(edge).unwrap())
+            });
+        }
+    }
+
+    /// Records a node along with all of its edges, which go away with it when it's removed.
+    fn record_node_with_edges(&mut self, node: NodeIndex<usize>) {
+        if self.records.is_empty() {
+            return;
+        }
+
+        let edges: Vec<_> = self
+            .graph
+            .edges_directed(node, Incoming)
+            .chain(self.graph.edges_directed(node, Outgoing))
+            .map(|edge| (edge.source(), edge.target()))
+            .collect();
+
+        for (source, target) in edges {
+            self.record_edge(source, target);
+        }
+
+        self.record_node(node);
+    }
+
+    fn add_node(&mut self, weight: GenericNode<PW, CW>) -> NodeIndex<usize> {
+        let node = self.graph.add_node(weight);
+
+        for record in self.records.values_mut() {
+            record.record_node(node, || None);
+        }
+
+        node
+    }
+
+    fn remove_node(&mut self, node: NodeIndex<usize>) {
+        self.record_node_with_edges(node);
+        self.graph.remove_node(node);
+    }
+
+    fn set_node_weight(&mut self, node: NodeIndex<usize>, weight: GenericNode<PW, CW>) {
+        self.record_node(node);
+        *self.graph.node_weight_mut(node).unwrap() = weight;
+    }
+
+    fn update_edge(
+        &mut self,
+        source: NodeIndex<usize>,
+        target: NodeIndex<usize>,
+        label: GeometryLabel,
+    ) {
+        self.record_edge(source, target);
+        self.graph.update_edge(source, target, label);
+    }
+
+    fn remove_edge(
+        &mut self,
+        source: NodeIndex<usize>,
+        target: NodeIndex<usize>,
+    ) -> Option<GeometryLabel> {
+        let edge = self.graph.find_edge(source, target)?;
+        self.record_edge(source, target);
+        self.graph.remove_edge(edge)
+    }
+
     pub fn dot_shape(&self, dot: DI) -> PrimitiveShape {
         let weight = self.dot_weight(dot);
         PrimitiveShape::Dot(DotShape {
@@ -494,15 +634,15 @@ impl<
     > ManageCompounds<CW, GenericIndex<CW>> for Geometry<PW, DW, SW, BW, CW, PI, DI, SI, BI>
 {
     fn add_compound(&mut self, weight: CW) -> GenericIndex<CW> {
-        GenericIndex::<CW>::new(self.graph.add_node(GenericNode::Compound(weight)))
+        GenericIndex::<CW>::new(self.add_node(GenericNode::Compound(weight)))
     }
 
     fn remove_compound(&mut self, compound: GenericIndex<CW>) {
-        self.graph.remove_node(compound.petgraph_index());
+        self.remove_node(compound.petgraph_index());
     }
 
     fn add_to_compound<W>(&mut self, primitive: GenericIndex<W>, compound: GenericIndex<CW>) {
-        self.graph.update_edge(
+        self.update_edge(
             primitive.petgraph_index(),
             compound.petgraph_index(),
             GeometryLabel::Compound,
@@ -1,6 +1,7 @@
 #[macro_use]
 mod geometry;
 pub mod compound;
+pub mod edit;
 pub mod poly;
 pub mod primitive;
 pub mod shape;
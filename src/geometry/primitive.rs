@@ -9,6 +9,9 @@ use crate::{
     math::{self, Circle},
 };
 
+/// The most segments [`BendShape::polyline`] splits an arc into.
+pub const MAX_POLYLINE_SEGMENT_COUNT: usize = 1000;
+
 #[enum_dispatch]
 pub trait AccessPrimitiveShape: AccessShape {
     fn priority(&self) -> usize;
@@ -139,7 +142,7 @@ pub struct SegShape {
 }
 
 impl SegShape {
-    fn polygon(&self) -> Polygon {
+    pub fn polygon(&self) -> Polygon {
         let tangent_vector = self.to - self.from;
         let tangent_vector_norm = tangent_vector.euclidean_distance(&point! {x: 0.0, y: 0.0});
         let unit_tangent_vector = tangent_vector / tangent_vector_norm;
@@ -296,6 +299,37 @@ impl BendShape {
             angle
         }
     }
+
+    /// Approximates the centerline arc of the bend with a chain of straight segments.
+    ///
+    /// The number of segments is chosen so that no segment deviates from the arc by more than
+    /// `max_deviation` (the chord error), which keeps small bends coarse and large bends smooth.
+    /// The arc is never split into more than [`MAX_POLYLINE_SEGMENT_COUNT`] segments though,
+    /// however small the deviation.
+    pub fn polyline(&self, max_deviation: f64) -> Vec<Point> {
+        let circle = self.circle();
+        let angle_from = self.start_angle();
+        let spanned_angle = self.spanned_angle();
+
+        // The sagitta of a chord spanning `angle` is `r * (1 - cos(angle / 2))`. A deviation
+        // that isn't positive can't be met by any number of chords, so the arc is then
+        // subdivided as finely as allowed.
+        let segment_count = if max_deviation > 0.0 {
+            let max_angle_step = 2.0 * (1.0 - max_deviation / circle.r).clamp(-1.0, 1.0).acos();
+            spanned_angle / max_angle_step
+        } else {
+            f64::INFINITY
+        };
+        let segment_count = (segment_count.ceil() as usize).clamp(1, MAX_POLYLINE_SEGMENT_COUNT);
+        let angle_step = spanned_angle / segment_count as f64;
+
+        (0..=segment_count)
+            .map(|i| {
+                let angle = angle_from + i as f64 * angle_step;
+                circle.pos + (circle.r * angle.cos(), circle.r * angle.sin()).into()
+            })
+            .collect()
+    }
 }
 
 impl MeasureLength for BendShape {
@@ -396,3 +430,51 @@ impl RTreeObject for PrimitiveShape {
         AccessPrimitiveShape::bbox(self, 0.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bend_polyline_stays_within_max_deviation() {
+        let bend = BendShape {
+            from: point! {x: 1000.0, y: 0.0},
+            to: point! {x: 0.0, y: 1000.0},
+            inner_circle: Circle {
+                pos: point! {x: 0.0, y: 0.0},
+                r: 950.0,
+            },
+            width: 100.0,
+        };
+
+        for max_deviation in [0.1, 1.0, 10.0, 100.0] {
+            let polyline = bend.polyline(max_deviation);
+
+            for chord in polyline.windows(2) {
+                let midpoint = (chord[0] + chord[1]) / 2.0;
+                let deviation = bend.radius() - midpoint.euclidean_distance(&bend.circle().pos);
+                assert!(deviation <= max_deviation + 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bend_polyline_segment_count_is_capped() {
+        let bend = BendShape {
+            from: point! {x: 1000.0, y: 0.0},
+            to: point! {x: -1000.0, y: 0.0},
+            inner_circle: Circle {
+                pos: point! {x: 0.0, y: 0.0},
+                r: 950.0,
+            },
+            width: 100.0,
+        };
+
+        for max_deviation in [0.0, -1.0, f64::NAN, 1e-12] {
+            assert_eq!(
+                bend.polyline(max_deviation).len(),
+                MAX_POLYLINE_SEGMENT_COUNT + 1
+            );
+        }
+    }
+}
@@ -1,15 +1,20 @@
-use std::marker::PhantomData;
+use std::{collections::BTreeSet, marker::PhantomData};
 
 use contracts_try::debug_invariant;
 use derive_getters::Getters;
 use geo::Point;
-use petgraph::stable_graph::StableDiGraph;
+use petgraph::{
+    stable_graph::{NodeIndex, StableDiGraph},
+    visit::EdgeRef,
+    Direction::{Incoming, Outgoing},
+};
 use rstar::{primitives::GeomWithData, Envelope, RTree, RTreeObject, AABB};
 
 use crate::{
     drawing::graph::{GetLayer, Retag},
     geometry::{
         compound::ManageCompounds,
+        edit::{GeometryEdit, Recording},
         primitive::{AccessPrimitiveShape, PrimitiveShape},
         AccessBendWeight, AccessDotWeight, AccessSegWeight, GenericNode, Geometry, GeometryLabel,
         GetWidth,
@@ -166,8 +171,23 @@ impl<
             return Err(());
         }
 
+        // The bboxes of the compounds the dot is a member of span the dot too.
+        let compounds: Vec<GenericIndex<CW>> = self
+            .geometry
+            .compounds(GenericIndex::<DW>::new(dot.petgraph_index()))
+            .collect();
+
+        for compound in compounds.iter() {
+            self.rtree.remove(&self.make_compound_bbox(*compound));
+        }
+
         self.rtree.remove(&self.make_dot_bbox(dot));
         self.geometry.remove_primitive(dot.into());
+
+        for compound in compounds {
+            self.rtree.insert(self.make_compound_bbox(compound));
+        }
+
         Ok(())
     }
 
@@ -187,6 +207,16 @@ impl<
     }
 
     pub fn move_dot(&mut self, dot: DI, to: Point) {
+        // The bboxes of the compounds the dot is a member of span the dot too.
+        let compounds: Vec<GenericIndex<CW>> = self
+            .geometry
+            .compounds(GenericIndex::<DW>::new(dot.petgraph_index()))
+            .collect();
+
+        for compound in compounds.iter() {
+            self.rtree.remove(&self.make_compound_bbox(*compound));
+        }
+
         for seg in self.geometry.joined_segs(dot) {
             self.rtree.remove(&self.make_seg_bbox(seg));
         }
@@ -206,6 +236,10 @@ impl<
         for seg in self.geometry.joined_segs(dot) {
             self.rtree.insert(self.make_seg_bbox(seg));
         }
+
+        for compound in compounds {
+            self.rtree.insert(self.make_compound_bbox(compound));
+        }
     }
 
     pub fn shift_bend(&mut self, bend: BI, offset: f64) {
@@ -233,6 +267,12 @@ impl<
         self.geometry.flip_bend(bend);
     }
 
+    pub fn rejoin_seg(&mut self, seg: SI, old_joint: DI, new_joint: DI) {
+        self.rtree.remove(&self.make_seg_bbox(seg));
+        self.geometry.rejoin_seg(seg, old_joint, new_joint);
+        self.rtree.insert(self.make_seg_bbox(seg));
+    }
+
     pub fn reattach_bend(&mut self, bend: BI, maybe_new_inner: Option<BI>) {
         let mut rail = bend;
 
@@ -252,6 +292,96 @@ impl<
             rail = outer;
         }
     }
+
+    pub fn start_recording(&mut self) -> Recording {
+        self.geometry.start_recording()
+    }
+
+    pub fn edit_since(&self, recording: &Recording) -> GeometryEdit<PW, CW>
+    where
+        PW: PartialEq,
+        CW: PartialEq,
+    {
+        self.geometry.edit_since(recording)
+    }
+
+    pub fn stop_recording(&mut self, recording: Recording) -> GeometryEdit<PW, CW>
+    where
+        PW: PartialEq,
+        CW: PartialEq,
+    {
+        self.geometry.stop_recording(recording)
+    }
+
+    pub fn apply_edit(&mut self, edit: &GeometryEdit<PW, CW>)
+    where
+        PW: PartialEq,
+        CW: PartialEq,
+    {
+        let touched: Vec<NodeIndex<usize>> = edit
+            .nodes()
+            .chain(edit.edges().flat_map(|(source, target)| [source, target]))
+            .collect();
+
+        // Only the bboxes of the touched nodes and of the nodes shaped by them change. These are
+        // taken out before the edit, then put back for the nodes that are left after it, along
+        // with the bboxes of the nodes the edit adds.
+        let reshaped = self.reshaped_nodes(touched.iter().copied());
+
+        for node in reshaped.iter() {
+            self.rtree.remove(&self.make_node_bbox(*node));
+        }
+
+        self.geometry.apply_edit(edit);
+
+        for node in self.reshaped_nodes(touched.into_iter().chain(reshaped)) {
+            self.rtree.insert(self.make_node_bbox(node));
+        }
+    }
+
+    /// Finds the existing nodes among the given ones, along with all the nodes whose shapes
+    /// depend on them: the segs and bends joined to a dot, the bends wrapped around a dot or a
+    /// bend and the compounds spanning a primitive.
+    fn reshaped_nodes(
+        &self,
+        nodes: impl IntoIterator<Item = NodeIndex<usize>>,
+    ) -> BTreeSet<NodeIndex<usize>> {
+        let graph = self.geometry.graph();
+        let mut reshaped = BTreeSet::new();
+        let mut stack: Vec<NodeIndex<usize>> = nodes
+            .into_iter()
+            .filter(|node| graph.node_weight(*node).is_some())
+            .collect();
+
+        while let Some(node) = stack.pop() {
+            if !reshaped.insert(node) {
+                continue;
+            }
+
+            let GenericNode::Primitive(weight) = graph.node_weight(node).unwrap() else {
+                continue;
+            };
+            let is_dot = <PI as TryInto<DI>>::try_into(weight.retag(node)).is_ok();
+
+            for edge in graph.edges_directed(node, Outgoing) {
+                match edge.weight() {
+                    GeometryLabel::Joined if is_dot => stack.push(edge.target()),
+                    GeometryLabel::Outer | GeometryLabel::Compound => stack.push(edge.target()),
+                    _ => (),
+                }
+            }
+
+            for edge in graph.edges_directed(node, Incoming) {
+                match edge.weight() {
+                    GeometryLabel::Joined if is_dot => stack.push(edge.source()),
+                    GeometryLabel::Core => stack.push(edge.source()),
+                    _ => (),
+                }
+            }
+        }
+
+        reshaped
+    }
 }
 
 impl<
@@ -278,6 +408,16 @@ impl<
         }
     }
 
+    fn make_node_bbox(
+        &self,
+        node: NodeIndex<usize>,
+    ) -> BboxedIndex<GenericNode<PI, GenericIndex<CW>>> {
+        match self.geometry.graph().node_weight(node).unwrap() {
+            GenericNode::Primitive(weight) => self.make_bbox(weight.retag(node)),
+            GenericNode::Compound(..) => self.make_compound_bbox(GenericIndex::new(node)),
+        }
+    }
+
     fn make_dot_bbox(&self, dot: DI) -> BboxedIndex<GenericNode<PI, GenericIndex<CW>>> {
         BboxedIndex::new(
             Bbox::new(
@@ -20,6 +20,8 @@ use crate::{
 
 pub struct InteractiveInput {
     pub pointer_pos: Point,
+    /// Whether the primary pointer button was clicked since the last update.
+    pub is_pointer_clicked: bool,
     pub dt: f32,
 }
 
@@ -50,7 +52,10 @@ impl<M: AccessMesadata> Step<ActivityContext<'_, M>, String> for ActivityStepper
     ) -> Result<ControlFlow<String>, ActivityError> {
         match self {
             ActivityStepper::Interaction(interaction) => Ok(interaction.step(context)?),
-            ActivityStepper::Execution(execution) => Ok(execution.step(context.invoker)?),
+            ActivityStepper::Execution(execution) => Ok(match execution.step(context.invoker)? {
+                ControlFlow::Continue(()) => ControlFlow::Continue(()),
+                ControlFlow::Break(outcome) => ControlFlow::Break(outcome.to_string()),
+            }),
         }
     }
 }
@@ -119,6 +124,17 @@ impl ActivityStepperWithStatus {
         }
     }
 
+    pub fn new_interaction(interaction: InteractionStepper) -> ActivityStepperWithStatus {
+        Self {
+            activity: ActivityStepper::Interaction(interaction),
+            maybe_status: None,
+        }
+    }
+
+    pub fn activity(&self) -> &ActivityStepper {
+        &self.activity
+    }
+
     pub fn maybe_status(&self) -> Option<ControlFlow<String>> {
         self.maybe_status.clone()
     }
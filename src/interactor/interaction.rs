@@ -11,7 +11,10 @@ use crate::{
     stepper::{Abort, Step},
 };
 
-use super::activity::ActivityContext;
+use super::{
+    activity::ActivityContext, move_component::MoveComponentInteractionStepper,
+    route_track::RouteTrackInteractionStepper,
+};
 
 #[derive(Error, Debug, Clone)]
 pub enum InteractionError {
@@ -20,10 +23,8 @@ pub enum InteractionError {
 }
 
 pub enum InteractionStepper {
-    // No interactions yet. This is only an empty skeleton for now.
-    // Examples of interactions:
-    // - interactively routing a track
-    // - interactively moving a footprint.
+    RouteTrack(Box<RouteTrackInteractionStepper>),
+    MoveComponent(Box<MoveComponentInteractionStepper>),
 }
 
 impl<'a, M: AccessMesadata> Step<ActivityContext<'a, M>, String> for InteractionStepper {
@@ -33,36 +34,54 @@ impl<'a, M: AccessMesadata> Step<ActivityContext<'a, M>, String> for Interaction
         &mut self,
         context: &mut ActivityContext<M>,
     ) -> Result<ControlFlow<String>, InteractionError> {
-        Ok(ControlFlow::Break(String::from("")))
+        match self {
+            InteractionStepper::RouteTrack(route_track) => route_track.step(context),
+            InteractionStepper::MoveComponent(move_component) => move_component.step(context),
+        }
     }
 }
 
 impl<'a, M: AccessMesadata> Abort<ActivityContext<'a, M>> for InteractionStepper {
     fn abort(&mut self, context: &mut ActivityContext<M>) {
-        todo!();
+        match self {
+            InteractionStepper::RouteTrack(route_track) => route_track.abort(context),
+            InteractionStepper::MoveComponent(move_component) => move_component.abort(context),
+        }
     }
 }
 
 impl GetMaybeNavmesh for InteractionStepper {
     fn maybe_navmesh(&self) -> Option<&Navmesh> {
-        todo!()
+        match self {
+            InteractionStepper::RouteTrack(route_track) => route_track.maybe_navmesh(),
+            InteractionStepper::MoveComponent(move_component) => move_component.maybe_navmesh(),
+        }
     }
 }
 
 impl GetMaybeNavcord for InteractionStepper {
     fn maybe_navcord(&self) -> Option<&NavcordStepper> {
-        todo!()
+        match self {
+            InteractionStepper::RouteTrack(route_track) => route_track.maybe_navcord(),
+            InteractionStepper::MoveComponent(move_component) => move_component.maybe_navcord(),
+        }
     }
 }
 
 impl GetGhosts for InteractionStepper {
     fn ghosts(&self) -> &[PrimitiveShape] {
-        todo!()
+        match self {
+            InteractionStepper::RouteTrack(route_track) => route_track.ghosts(),
+            InteractionStepper::MoveComponent(move_component) => move_component.ghosts(),
+        }
     }
 }
 
 impl GetObstacles for InteractionStepper {
     fn obstacles(&self) -> &[PrimitiveIndex] {
-        todo!()
+        match self {
+            InteractionStepper::RouteTrack(route_track) => route_track.obstacles(),
+            InteractionStepper::MoveComponent(move_component) => move_component.obstacles(),
+        }
     }
 }
@@ -4,14 +4,21 @@ use spade::InsertionError;
 
 use crate::{
     autorouter::{
-        execution::Command,
+        execution::{Command, ExecutionOutcome},
         history::History,
         invoker::{Invoker, InvokerError},
-        Autorouter,
+        selection::PinSelection,
+        Autorouter, AutorouterOptions,
     },
     board::{mesadata::AccessMesadata, Board},
-    interactor::activity::{
-        ActivityContext, ActivityError, ActivityStepperWithStatus, InteractiveInput,
+    interactor::{
+        activity::{
+            ActivityContext, ActivityError, ActivityStepper, ActivityStepperWithStatus,
+            InteractiveInput,
+        },
+        interaction::{InteractionError, InteractionStepper},
+        move_component::MoveComponentInteractionStepper,
+        route_track::RouteTrackInteractionStepper,
     },
     stepper::{Abort, Step},
 };
@@ -29,7 +36,7 @@ impl<M: AccessMesadata> Interactor<M> {
         })
     }
 
-    pub fn execute(&mut self, command: Command) -> Result<(), InvokerError> {
+    pub fn execute(&mut self, command: Command) -> Result<ExecutionOutcome, InvokerError> {
         self.invoker.execute(command)
     }
 
@@ -40,6 +47,51 @@ impl<M: AccessMesadata> Interactor<M> {
         Ok(())
     }
 
+    /// Starts routing a track from the selected pin to wherever the pointer goes. The track is
+    /// laid down on click.
+    pub fn route_track(
+        &mut self,
+        selection: PinSelection,
+        options: AutorouterOptions,
+    ) -> Result<(), InteractionError> {
+        self.activity = Some(ActivityStepperWithStatus::new_interaction(
+            InteractionStepper::RouteTrack(Box::new(RouteTrackInteractionStepper::new(
+                &mut self.invoker,
+                selection,
+                options,
+            )?)),
+        ));
+        Ok(())
+    }
+
+    /// Picks up the component of the selected pin to move it along with the pointer. The
+    /// component is dropped on click.
+    pub fn move_component(
+        &mut self,
+        selection: PinSelection,
+        options: AutorouterOptions,
+    ) -> Result<(), InteractionError> {
+        let component = selection
+            .selectors()
+            .find_map(|selector| {
+                self.invoker
+                    .autorouter()
+                    .board()
+                    .pinname_component(&selector.pin)
+            })
+            .ok_or(InteractionError::NothingToInteract)?
+            .clone();
+
+        self.activity = Some(ActivityStepperWithStatus::new_interaction(
+            InteractionStepper::MoveComponent(Box::new(MoveComponentInteractionStepper::new(
+                &mut self.invoker,
+                component,
+                options,
+            )?)),
+        ));
+        Ok(())
+    }
+
     pub fn undo(&mut self) -> Result<(), InvokerError> {
         self.invoker.undo()
     }
@@ -53,6 +105,7 @@ impl<M: AccessMesadata> Interactor<M> {
             activity.abort(&mut ActivityContext::<M> {
                 interactive_input: &InteractiveInput {
                     pointer_pos: [0.0, 0.0].into(),
+                    is_pointer_clicked: false,
                     dt: 0.0,
                 },
                 invoker: &mut self.invoker,
@@ -88,6 +141,14 @@ impl<M: AccessMesadata> Interactor<M> {
         &self.invoker
     }
 
+    /// Whether an interaction is under way, taking the pointer input for itself.
+    pub fn is_interacting(&self) -> bool {
+        self.activity.as_ref().is_some_and(|activity| {
+            matches!(activity.activity(), ActivityStepper::Interaction(..))
+                && !matches!(activity.maybe_status(), Some(ControlFlow::Break(..)))
+        })
+    }
+
     pub fn maybe_activity(&self) -> &Option<ActivityStepperWithStatus> {
         &self.activity
     }
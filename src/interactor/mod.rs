@@ -1,5 +1,7 @@
 pub mod activity;
 pub mod interaction;
 mod interactor;
+pub mod move_component;
+pub mod route_track;
 
 pub use interactor::*;
@@ -0,0 +1,181 @@
+//! Provides interactive moving of a component, which is dragged along with the pointer, with
+//! the bands joined to it routed anew each time the pointer moves, and dropped on click.
+
+use std::ops::ControlFlow;
+
+use geo::Point;
+
+use crate::{
+    autorouter::{
+        edit::AutorouterRecording,
+        execution::Command,
+        invoker::{GetGhosts, GetMaybeNavcord, GetMaybeNavmesh, GetObstacles, Invoker},
+        AutorouterOptions,
+    },
+    board::mesadata::AccessMesadata,
+    drawing::graph::PrimitiveIndex,
+    geometry::primitive::PrimitiveShape,
+    router::{navcord::NavcordStepper, navmesh::Navmesh},
+    stepper::{Abort, Step},
+};
+
+use super::{activity::ActivityContext, interaction::InteractionError};
+
+pub struct MoveComponentInteractionStepper {
+    component: String,
+    options: AutorouterOptions,
+    /// The recording of the edits made since the component was picked up, by which the layout
+    /// and the ratsnest are reverted before each move and when the move is aborted. It's
+    /// stopped once the interaction ends.
+    maybe_recording: Option<AutorouterRecording>,
+    /// The position of the pointer when the component was picked up.
+    maybe_grab_pos: Option<Point>,
+    /// The position of the pointer the component was last moved along with.
+    maybe_pointer_pos: Option<Point>,
+    /// The offset the component was last moved by, if it could be moved there.
+    maybe_offset: Option<Point>,
+    /// The message the interaction ended with, once it has been committed or aborted.
+    maybe_end_message: Option<String>,
+}
+
+impl MoveComponentInteractionStepper {
+    /// Picks up the component of the given name.
+    pub fn new(
+        invoker: &mut Invoker<impl AccessMesadata>,
+        component: String,
+        options: AutorouterOptions,
+    ) -> Result<Self, InteractionError> {
+        let autorouter = invoker.autorouter_mut();
+
+        if autorouter
+            .board()
+            .component_nodes(&component)
+            .next()
+            .is_none()
+        {
+            return Err(InteractionError::NothingToInteract);
+        }
+
+        Ok(Self {
+            component,
+            options,
+            maybe_recording: Some(autorouter.start_recording()),
+            maybe_grab_pos: None,
+            maybe_pointer_pos: None,
+            maybe_offset: None,
+            maybe_end_message: None,
+        })
+    }
+
+    /// Puts the component back where it was picked up and moves it by the given offset anew.
+    /// If it can't be moved there, the layout is left as it was before the component was picked
+    /// up.
+    fn move_by(&mut self, invoker: &mut Invoker<impl AccessMesadata>, offset: Point) {
+        let Some(ref recording) = self.maybe_recording else {
+            return;
+        };
+        let autorouter = invoker.autorouter_mut();
+        autorouter.revert_to(recording);
+
+        self.maybe_offset = None;
+
+        let result = autorouter
+            .move_component(&self.component, offset, self.options)
+            .and_then(|mut move_component| move_component.finish(autorouter));
+
+        match result {
+            Ok(..) => self.maybe_offset = Some(offset),
+            Err(..) => autorouter.revert_to(recording),
+        }
+    }
+
+    /// Drops the component where it was moved last, recording it in the history as a command
+    /// that moves it by the offset it was moved by.
+    fn commit(&mut self, invoker: &mut Invoker<impl AccessMesadata>, offset: Point) {
+        let Some(recording) = self.maybe_recording.take() else {
+            return;
+        };
+        let edit = invoker.autorouter_mut().stop_recording(recording);
+        invoker.record(
+            Command::MoveComponent(self.component.clone(), offset, self.options),
+            edit,
+        );
+
+        self.maybe_end_message = Some(String::from("finished moving component"));
+    }
+}
+
+impl<'a, M: AccessMesadata> Step<ActivityContext<'a, M>, String>
+    for MoveComponentInteractionStepper
+{
+    type Error = InteractionError;
+
+    fn step(
+        &mut self,
+        context: &mut ActivityContext<M>,
+    ) -> Result<ControlFlow<String>, InteractionError> {
+        if let Some(ref message) = self.maybe_end_message {
+            return Ok(ControlFlow::Break(message.clone()));
+        }
+
+        let pointer_pos = context.interactive_input.pointer_pos;
+        let grab_pos = *self.maybe_grab_pos.get_or_insert(pointer_pos);
+
+        // A click drops the component where it was moved last, which is where the user saw it.
+        // Clicks where it couldn't be moved are ignored.
+        if context.interactive_input.is_pointer_clicked {
+            if let Some(offset) = self.maybe_offset {
+                self.commit(context.invoker, offset);
+                return Ok(ControlFlow::Break(self.maybe_end_message.clone().unwrap()));
+            }
+        }
+
+        if self.maybe_pointer_pos != Some(pointer_pos) {
+            self.maybe_pointer_pos = Some(pointer_pos);
+            self.move_by(context.invoker, pointer_pos - grab_pos);
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+impl<'a, M: AccessMesadata> Abort<ActivityContext<'a, M>> for MoveComponentInteractionStepper {
+    fn abort(&mut self, context: &mut ActivityContext<M>) {
+        if self.maybe_end_message.is_some() {
+            return;
+        }
+
+        if let Some(recording) = self.maybe_recording.take() {
+            let autorouter = context.invoker.autorouter_mut();
+            autorouter.revert_to(&recording);
+            autorouter.stop_recording(recording);
+        }
+
+        self.maybe_offset = None;
+        self.maybe_end_message = Some(String::from("aborted"));
+    }
+}
+
+impl GetMaybeNavmesh for MoveComponentInteractionStepper {
+    fn maybe_navmesh(&self) -> Option<&Navmesh> {
+        None
+    }
+}
+
+impl GetMaybeNavcord for MoveComponentInteractionStepper {
+    fn maybe_navcord(&self) -> Option<&NavcordStepper> {
+        None
+    }
+}
+
+impl GetGhosts for MoveComponentInteractionStepper {
+    fn ghosts(&self) -> &[PrimitiveShape] {
+        &[]
+    }
+}
+
+impl GetObstacles for MoveComponentInteractionStepper {
+    fn obstacles(&self) -> &[PrimitiveIndex] {
+        &[]
+    }
+}
@@ -0,0 +1,185 @@
+//! Provides interactive routing of a single track, which is routed from a pin to wherever the
+//! pointer is, anew each time the pointer moves, and laid down for good on click.
+
+use std::ops::ControlFlow;
+
+use geo::Point;
+
+use crate::{
+    autorouter::{
+        edit::AutorouterRecording,
+        execution::Command,
+        invoker::{GetGhosts, GetMaybeNavcord, GetMaybeNavmesh, GetObstacles, Invoker},
+        pointroute::PointrouteExecutionStepper,
+        selection::PinSelection,
+        AutorouterOptions,
+    },
+    board::mesadata::AccessMesadata,
+    drawing::{band::BandTermsegIndex, graph::PrimitiveIndex},
+    geometry::primitive::PrimitiveShape,
+    router::{navcord::NavcordStepper, navmesh::Navmesh},
+    stepper::{Abort, Step},
+};
+
+use super::{activity::ActivityContext, interaction::InteractionError};
+
+pub struct RouteTrackInteractionStepper {
+    selection: PinSelection,
+    options: AutorouterOptions,
+    /// The recording of the edits made since the track was started, by which the layout is
+    /// reverted before each reroute and when the track is aborted. It's stopped once the
+    /// interaction ends.
+    maybe_recording: Option<AutorouterRecording>,
+    /// The position of the pointer the track was last routed to.
+    maybe_pointer_pos: Option<Point>,
+    /// The last route of the track, kept to show its navmesh and navcord.
+    maybe_pointroute: Option<PointrouteExecutionStepper>,
+    /// The band of the track, if it could be routed to where the pointer last was.
+    maybe_band: Option<BandTermsegIndex>,
+    /// The message the interaction ended with, once it has been committed or aborted.
+    maybe_end_message: Option<String>,
+}
+
+impl RouteTrackInteractionStepper {
+    /// Starts routing a track from the selected pin.
+    pub fn new(
+        invoker: &mut Invoker<impl AccessMesadata>,
+        selection: PinSelection,
+        options: AutorouterOptions,
+    ) -> Result<Self, InteractionError> {
+        if selection.selectors().next().is_none() {
+            return Err(InteractionError::NothingToInteract);
+        }
+
+        Ok(Self {
+            selection,
+            options,
+            maybe_recording: Some(invoker.autorouter_mut().start_recording()),
+            maybe_pointer_pos: None,
+            maybe_pointroute: None,
+            maybe_band: None,
+            maybe_end_message: None,
+        })
+    }
+
+    /// Removes the track routed before and routes it anew to the given point. If the track
+    /// can't be routed there, the layout is left as it was before the track was started.
+    fn reroute(&mut self, invoker: &mut Invoker<impl AccessMesadata>, point: Point) {
+        let Some(ref recording) = self.maybe_recording else {
+            return;
+        };
+        let autorouter = invoker.autorouter_mut();
+        autorouter.revert_to(recording);
+
+        self.maybe_pointer_pos = Some(point);
+        self.maybe_band = None;
+        self.maybe_pointroute = autorouter
+            .pointroute(&self.selection, point, self.options)
+            .ok();
+
+        let Some(ref mut pointroute) = self.maybe_pointroute else {
+            autorouter.revert_to(recording);
+            return;
+        };
+
+        match pointroute.finish(autorouter) {
+            Ok(band) => self.maybe_band = Some(band),
+            Err(..) => autorouter.revert_to(recording),
+        }
+    }
+
+    /// Lays down the track routed last, recording it in the history as a command that routes
+    /// it to the point it was routed to.
+    fn commit(&mut self, invoker: &mut Invoker<impl AccessMesadata>, point: Point) {
+        let Some(recording) = self.maybe_recording.take() else {
+            return;
+        };
+        let edit = invoker.autorouter_mut().stop_recording(recording);
+        invoker.record(
+            Command::Pointroute(self.selection.clone(), point, self.options),
+            edit,
+        );
+
+        self.maybe_end_message = Some(String::from("finished routing track"));
+    }
+}
+
+impl<'a, M: AccessMesadata> Step<ActivityContext<'a, M>, String> for RouteTrackInteractionStepper {
+    type Error = InteractionError;
+
+    fn step(
+        &mut self,
+        context: &mut ActivityContext<M>,
+    ) -> Result<ControlFlow<String>, InteractionError> {
+        if let Some(ref message) = self.maybe_end_message {
+            return Ok(ControlFlow::Break(message.clone()));
+        }
+
+        let pointer_pos = context.interactive_input.pointer_pos;
+
+        // A click commits the track as it was routed to where the pointer was last, which is
+        // where the user saw it. Clicks where it couldn't be routed are ignored.
+        if context.interactive_input.is_pointer_clicked {
+            if let (Some(point), Some(..)) = (self.maybe_pointer_pos, self.maybe_band) {
+                self.commit(context.invoker, point);
+                return Ok(ControlFlow::Break(self.maybe_end_message.clone().unwrap()));
+            }
+        }
+
+        if self.maybe_pointer_pos != Some(pointer_pos) {
+            self.reroute(context.invoker, pointer_pos);
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+impl<'a, M: AccessMesadata> Abort<ActivityContext<'a, M>> for RouteTrackInteractionStepper {
+    fn abort(&mut self, context: &mut ActivityContext<M>) {
+        if self.maybe_end_message.is_some() {
+            return;
+        }
+
+        if let Some(recording) = self.maybe_recording.take() {
+            let autorouter = context.invoker.autorouter_mut();
+            autorouter.revert_to(&recording);
+            autorouter.stop_recording(recording);
+        }
+
+        self.maybe_pointroute = None;
+        self.maybe_band = None;
+        self.maybe_end_message = Some(String::from("aborted"));
+    }
+}
+
+impl GetMaybeNavmesh for RouteTrackInteractionStepper {
+    fn maybe_navmesh(&self) -> Option<&Navmesh> {
+        self.maybe_pointroute
+            .as_ref()
+            .and_then(|pointroute| pointroute.maybe_navmesh())
+    }
+}
+
+impl GetMaybeNavcord for RouteTrackInteractionStepper {
+    fn maybe_navcord(&self) -> Option<&NavcordStepper> {
+        self.maybe_pointroute
+            .as_ref()
+            .and_then(|pointroute| pointroute.maybe_navcord())
+    }
+}
+
+impl GetGhosts for RouteTrackInteractionStepper {
+    fn ghosts(&self) -> &[PrimitiveShape] {
+        self.maybe_pointroute
+            .as_ref()
+            .map_or(&[], |pointroute| pointroute.ghosts())
+    }
+}
+
+impl GetObstacles for RouteTrackInteractionStepper {
+    fn obstacles(&self) -> &[PrimitiveIndex] {
+        self.maybe_pointroute
+            .as_ref()
+            .map_or(&[], |pointroute| pointroute.obstacles())
+    }
+}
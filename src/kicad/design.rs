@@ -0,0 +1,550 @@
+//! Module for managing KiCad PCB designs, including loading the `.kicad_pcb`
+//! board file, creating the [`Board`] object from it, as well as writing the
+//! routed board back into the same file format
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+};
+
+use geo::{point, EuclideanDistance, LineString, Point, Polygon};
+use thiserror::Error;
+
+use crate::{
+    board::{mesadata::AccessMesadata, shapes, Board},
+    drawing::{
+        dot::FixedDotWeight,
+        graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex},
+        primitive::{GetWeight, MakePrimitiveShape},
+        rules::ObjectKind,
+        seg::FixedSegWeight,
+        Drawing,
+    },
+    geometry::{
+        primitive::{BendShape, PrimitiveShape},
+        GetWidth,
+    },
+    kicad::{
+        mesadata::{KicadMesadata, MM_TO_BOARD_UNITS},
+        read::{self, List},
+        structure::{self, KicadPcb, KicadProject, Pad},
+        write::{ItemWriter, ZoneSettings},
+    },
+    layout::{poly::SolidPolyWeight, pour::PadConnection, Layout},
+    math::{Circle, PointWithRotation},
+    specctra::read::ListTokenizer,
+};
+
+pub use read::ParseErrorContext;
+
+/// Maximum deviation, in board units, of the segments imported in place of arcs.
+const ARC_MAX_DEVIATION: f64 = 1.0;
+
+/// Errors raised by [`KicadDesign::load`] and [`KicadDesign::load_project`]
+#[derive(Error, Debug)]
+pub enum LoadingError {
+    /// I/O file reading error from [`std::io::Error`]
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// Errors in the syntax or structure of the board file
+    #[error(transparent)]
+    Parse(#[from] read::ParseErrorContext),
+    /// Errors in the syntax of the project file
+    #[error(transparent)]
+    Project(#[from] serde_json::Error),
+}
+
+/// This struct is responsible for managing a KiCad PCB design: parsing the `.kicad_pcb` board
+/// file along with, optionally, the `.kicad_pro` project file holding its net classes, and
+/// writing the routed board back as a `.kicad_pcb` file.
+///
+/// KiCad's millimeters are converted to micrometers and the Y axis is flipped to point up, so
+/// that boards loaded from KiCad files have the same geometry as boards loaded from the Specctra
+/// designs KiCad exports.
+#[derive(Debug)]
+pub struct KicadDesign {
+    source: String,
+    pcb: KicadPcb,
+    maybe_project: Option<KicadProject>,
+}
+
+impl KicadDesign {
+    /// Loads a [`KicadDesign`] structure instance from a buffered reader of a board file.
+    ///
+    /// The original text of the file is kept, so that writing the design back preserves
+    /// everything Topola doesn't understand.
+    pub fn load(mut reader: impl io::BufRead) -> Result<KicadDesign, LoadingError> {
+        let mut source = String::new();
+        reader.read_to_string(&mut source)?;
+
+        let list = List::read(&mut ListTokenizer::new_with_escaped_quotes(
+            source.as_bytes(),
+            '"',
+        ))?;
+        let pcb = KicadPcb::from_list(&list)?;
+
+        Ok(Self {
+            source,
+            pcb,
+            maybe_project: None,
+        })
+    }
+
+    /// Loads the net classes of the design from a reader of its `.kicad_pro` project file.
+    ///
+    /// Since KiCad 6 the net classes are no longer stored in the board file, so without the
+    /// project every net is routed with the rules of KiCad's default net class.
+    pub fn load_project(&mut self, reader: impl Read) -> Result<(), LoadingError> {
+        let project: serde_json::Value = serde_json::from_reader(reader)?;
+        self.maybe_project = Some(KicadProject::from_json(&project));
+        Ok(())
+    }
+
+    /// Writes the board into the KiCad board file format.
+    ///
+    /// The written file is the loaded board file with the bands routed by Topola appended as
+    /// `segment` and `arc` items, the vias placed by Topola appended as `via` items, and the
+    /// pours appended as `zone` items along with their fills.
+    pub fn write_kicad_pcb(
+        &self,
+        board: &Board<KicadMesadata>,
+        mut writer: impl io::Write,
+    ) -> Result<(), io::Error> {
+        let mesadata = board.mesadata();
+        let drawing = board.layout().drawing();
+
+        let end = self.source.rfind(')').unwrap_or(self.source.len());
+        let (head, tail) = self.source.split_at(end);
+        writer.write_all(head.as_bytes())?;
+
+        if !head.ends_with('\n') {
+            writeln!(writer)?;
+        }
+
+        // KiCad 8 indents with tabs, older versions with pairs of spaces.
+        let indent = if head.contains("\n\t(") { "\t" } else { "  " };
+        let mut item_writer = ItemWriter::new(&mut writer, indent.to_string());
+
+        for index in drawing.primitive_nodes() {
+            if !matches!(
+                index,
+                PrimitiveIndex::LoneLooseSeg(..)
+                    | PrimitiveIndex::SeqLooseSeg(..)
+                    | PrimitiveIndex::LooseBend(..)
+            ) {
+                continue;
+            }
+
+            let primitive = index.primitive(drawing);
+
+            let Some(net) = primitive.maybe_net() else {
+                continue;
+            };
+            let layername = mesadata.layer_layername(primitive.layer()).unwrap();
+            let width = primitive.width() / MM_TO_BOARD_UNITS;
+
+            match primitive.shape() {
+                PrimitiveShape::Seg(seg) => {
+                    item_writer.write_segment(
+                        Self::kicad_coords(seg.from),
+                        Self::kicad_coords(seg.to),
+                        width,
+                        layername,
+                        net,
+                    )?;
+                }
+                PrimitiveShape::Bend(bend) => {
+                    let circle = bend.circle();
+                    let angle_from = bend.start_angle();
+                    let spanned_angle = bend.spanned_angle();
+                    let point_at = |angle: f64| {
+                        Self::kicad_coords(
+                            circle.pos
+                                + point! {x: circle.r * angle.cos(), y: circle.r * angle.sin()},
+                        )
+                    };
+
+                    item_writer.write_arc(
+                        point_at(angle_from),
+                        point_at(angle_from + spanned_angle / 2.0),
+                        point_at(angle_from + spanned_angle),
+                        width,
+                        layername,
+                        net,
+                    )?;
+                }
+                PrimitiveShape::Dot(..) => (),
+            }
+        }
+
+        for via in board.layout().via_nodes() {
+            let weight = board.layout().via(via).weight();
+
+            let Some(net) = weight.maybe_net else {
+                continue;
+            };
+
+            let diameter = 2.0 * weight.circle.r;
+            let drill = mesadata.get_rule(net).via_drill.min(diameter / 2.0);
+
+            item_writer.write_via(
+                Self::kicad_coords(weight.circle.pos),
+                diameter / MM_TO_BOARD_UNITS,
+                drill / MM_TO_BOARD_UNITS,
+                (
+                    mesadata.layer_layername(weight.from_layer).unwrap(),
+                    mesadata.layer_layername(weight.to_layer).unwrap(),
+                ),
+                net,
+            )?;
+        }
+
+        for pour in board.layout().pours() {
+            let Some(net) = pour.maybe_net() else {
+                continue;
+            };
+            let to_kicad_points = |points: Vec<Point>| -> Vec<(f64, f64)> {
+                points.into_iter().map(Self::kicad_coords).collect()
+            };
+
+            item_writer.write_zone(
+                &to_kicad_points(pour.outline.exterior().points().collect()),
+                &pour
+                    .fill
+                    .iter()
+                    .map(|polygon| to_kicad_points(Self::fractured(polygon)))
+                    .collect::<Vec<_>>(),
+                &ZoneSettings {
+                    clearance: mesadata.get_rule(net).clearance / MM_TO_BOARD_UNITS,
+                    maybe_thermal_relief: match pour.options.pad_connection {
+                        PadConnection::ThermalRelief => Some((
+                            pour.options.thermal_gap / MM_TO_BOARD_UNITS,
+                            pour.options.thermal_spoke_width / MM_TO_BOARD_UNITS,
+                        )),
+                        PadConnection::Solid => None,
+                    },
+                    remove_islands: pour.options.remove_islands,
+                },
+                mesadata.layer_layername(pour.layer()).unwrap(),
+                net,
+                mesadata.net_netname(net).unwrap(),
+            )?;
+        }
+
+        writer.write_all(tail.as_bytes())
+    }
+
+    /// Joins the holes of a polygon to its exterior with bridges of no width, making a single
+    /// ring out of it, as KiCad stores the fills of zones as polygons without holes.
+    fn fractured(polygon: &Polygon) -> Vec<Point> {
+        let open_ring = |ring: &LineString| -> Vec<Point> {
+            let mut points: Vec<Point> = ring.points().collect();
+            points.pop();
+            points
+        };
+
+        let mut ring = open_ring(polygon.exterior());
+        let mut holes: Vec<Vec<Point>> = polygon.interiors().iter().map(open_ring).collect();
+        // Holes are joined from left to right, so that the bridges seldom cross each other.
+        let min_x = |hole: &Vec<Point>| {
+            hole.iter()
+                .map(|point| point.x())
+                .fold(f64::INFINITY, f64::min)
+        };
+        holes.sort_by(|hole1, hole2| min_x(hole1).total_cmp(&min_x(hole2)));
+
+        for hole in holes {
+            let Some((i, j)) = (0..ring.len())
+                .flat_map(|i| (0..hole.len()).map(move |j| (i, j)))
+                .min_by(|(i1, j1), (i2, j2)| {
+                    ring[*i1]
+                        .euclidean_distance(&hole[*j1])
+                        .total_cmp(&ring[*i2].euclidean_distance(&hole[*j2]))
+                })
+            else {
+                continue;
+            };
+
+            let mut fractured = ring[..=i].to_vec();
+            fractured.extend_from_slice(&hole[j..]);
+            fractured.extend_from_slice(&hole[..=j]);
+            fractured.extend_from_slice(&ring[i..]);
+            ring = fractured;
+        }
+
+        ring
+    }
+
+    /// Generates a [`Board<KicadMesadata>`] from the current design.
+    ///
+    /// Pads of footprints become fixed dots and polygons named after the reference designator
+    /// of their footprint and their number, like in the Specctra designs exported by KiCad, and
+    /// make up a component named after the reference designator. Tracks and vias already
+    /// present in the file are added as fixed primitives.
+    pub fn make_board(&self) -> Board<KicadMesadata> {
+        let mesadata = KicadMesadata::from_pcb(&self.pcb, self.maybe_project.as_ref());
+        let mut board = Board::new(Layout::new(Drawing::new(mesadata, self.pcb.layers.len())));
+
+        for footprint in &self.pcb.footprints {
+            let place = PointWithRotation {
+                pos: Self::board_point(footprint.at.pos),
+                rot: footprint.at.rot,
+            };
+            let mut pinname_count = HashMap::<&str, usize>::new();
+
+            for pad in &footprint.pads {
+                // Non-plated holes have no copper to route to.
+                if pad.kind == "np_thru_hole" {
+                    continue;
+                }
+
+                // Pads store their position relative to the footprint, already mirrored for
+                // footprints on the back, but their rotation is absolute.
+                let pin = PointWithRotation {
+                    pos: Self::board_point(pad.at.pos),
+                    rot: pad.at.rot - footprint.at.rot,
+                };
+
+                // Pads sharing a number are told apart with an `@` suffix, the same way
+                // KiCad does it when exporting Specctra designs.
+                let maybe_pinname = if pad.number.is_empty() {
+                    None
+                } else {
+                    let count = pinname_count.entry(&pad.number).or_default();
+                    let pinname = if *count == 0 {
+                        format!("{}-{}", footprint.reference, pad.number)
+                    } else {
+                        format!("{}-{}@{}", footprint.reference, pad.number, count)
+                    };
+                    *count += 1;
+                    board.add_pin_to_component(pinname.clone(), footprint.reference.clone());
+                    Some(pinname)
+                };
+
+                for layer in Self::pad_layers(&board, pad) {
+                    Self::add_pad(&mut board, place, pin, pad, layer, maybe_pinname.clone());
+                }
+            }
+        }
+
+        for via in &self.pcb.vias {
+            let (Some(from_layer), Some(to_layer)) = (
+                board.mesadata().layername_layer(&via.layers.0),
+                board.mesadata().layername_layer(&via.layers.1),
+            ) else {
+                continue;
+            };
+
+            for layer in from_layer.min(to_layer)..=from_layer.max(to_layer) {
+                board.add_fixed_dot_infringably(
+                    FixedDotWeight {
+                        circle: Circle {
+                            pos: Self::board_point(via.at),
+                            r: via.size * MM_TO_BOARD_UNITS / 2.0,
+                        },
+                        layer,
+                        maybe_net: via.maybe_net,
+                        kind: ObjectKind::Via,
+                    },
+                    None,
+                );
+            }
+        }
+
+        for segment in &self.pcb.segments {
+            let Some(layer) = board.mesadata().layername_layer(&segment.layer) else {
+                continue;
+            };
+
+            shapes::add_path(
+                &mut board,
+                PointWithRotation::default(),
+                PointWithRotation::default(),
+                &[
+                    Self::board_point(segment.start),
+                    Self::board_point(segment.end),
+                ],
+                FixedSegWeight {
+                    width: segment.width * MM_TO_BOARD_UNITS,
+                    layer,
+                    maybe_net: segment.maybe_net,
+                    kind: ObjectKind::Wire,
+                },
+                None,
+            );
+        }
+
+        for arc in &self.pcb.arcs {
+            let Some(layer) = board.mesadata().layername_layer(&arc.layer) else {
+                continue;
+            };
+
+            shapes::add_path(
+                &mut board,
+                PointWithRotation::default(),
+                PointWithRotation::default(),
+                &Self::arc_coords(arc),
+                FixedSegWeight {
+                    width: arc.width * MM_TO_BOARD_UNITS,
+                    layer,
+                    maybe_net: arc.maybe_net,
+                    kind: ObjectKind::Wire,
+                },
+                None,
+            );
+        }
+
+        board
+    }
+
+    /// Resolves the copper layers of a pad, expanding KiCad's `*.Cu` and `F&B.Cu` wildcards.
+    fn pad_layers(board: &Board<KicadMesadata>, pad: &Pad) -> Vec<usize> {
+        let last_layer = board.layout().drawing().layer_count() - 1;
+
+        let mut layers: Vec<usize> = pad
+            .layers
+            .iter()
+            .flat_map(|layername| match layername.as_str() {
+                "*.Cu" => (0..=last_layer).collect(),
+                "F&B.Cu" => vec![0, last_layer],
+                layername => board
+                    .mesadata()
+                    .layername_layer(layername)
+                    .into_iter()
+                    .collect(),
+            })
+            .collect();
+        layers.sort_unstable();
+        layers.dedup();
+        layers
+    }
+
+    fn add_pad(
+        board: &mut Board<KicadMesadata>,
+        place: PointWithRotation,
+        pin: PointWithRotation,
+        pad: &Pad,
+        layer: usize,
+        maybe_pin: Option<String>,
+    ) {
+        let width = pad.size.x * MM_TO_BOARD_UNITS;
+        let height = pad.size.y * MM_TO_BOARD_UNITS;
+        let kind = if pad.kind == "smd" {
+            ObjectKind::Smd
+        } else {
+            ObjectKind::Pin
+        };
+
+        match pad.shape.as_str() {
+            "circle" => {
+                board.add_fixed_dot_infringably(
+                    FixedDotWeight {
+                        circle: Circle {
+                            pos: shapes::pos(place, pin, 0.0, 0.0),
+                            r: width / 2.0,
+                        },
+                        layer,
+                        maybe_net: pad.maybe_net,
+                        kind,
+                    },
+                    maybe_pin,
+                );
+            }
+            // An oval is a path between the centers of its two rounded ends.
+            "oval" => {
+                let half_length = (width - height).abs() / 2.0;
+                let (dx, dy) = if width > height {
+                    (half_length, 0.0)
+                } else {
+                    (0.0, half_length)
+                };
+
+                shapes::add_path(
+                    board,
+                    place,
+                    pin,
+                    &[point! {x: -dx, y: -dy}, point! {x: dx, y: dy}],
+                    FixedSegWeight {
+                        width: width.min(height),
+                        layer,
+                        maybe_net: pad.maybe_net,
+                        kind,
+                    },
+                    maybe_pin,
+                );
+            }
+            // Rounded rectangles, trapezoids and custom shapes are approximated by their
+            // bounding rectangle.
+            _ => shapes::add_rect(
+                board,
+                place,
+                pin,
+                (-width / 2.0, -height / 2.0),
+                (width / 2.0, height / 2.0),
+                SolidPolyWeight {
+                    layer,
+                    maybe_net: pad.maybe_net,
+                },
+                kind,
+                maybe_pin,
+            ),
+        }
+    }
+
+    /// Approximates an arc of the file with a chain of points in board coordinates.
+    fn arc_coords(arc: &structure::Arc) -> Vec<Point> {
+        let start = Self::board_point(arc.start);
+        let mid = Self::board_point(arc.mid);
+        let end = Self::board_point(arc.end);
+
+        // Find the center of the circle passing through the three points.
+        let (a, b, c) = (start, mid, end);
+        let d = 2.0 * (a.x() * (b.y() - c.y()) + b.x() * (c.y() - a.y()) + c.x() * (a.y() - b.y()));
+
+        if d.abs() < f64::EPSILON {
+            // The points are collinear, so the arc is a straight segment.
+            return vec![start, end];
+        }
+
+        let norm = |p: Point| p.x() * p.x() + p.y() * p.y();
+        let center = point! {
+            x: (norm(a) * (b.y() - c.y()) + norm(b) * (c.y() - a.y()) + norm(c) * (a.y() - b.y()))
+                / d,
+            y: (norm(a) * (c.x() - b.x()) + norm(b) * (a.x() - c.x()) + norm(c) * (b.x() - a.x()))
+                / d,
+        };
+        let width = arc.width * MM_TO_BOARD_UNITS;
+        let radius = (start - center).x().hypot((start - center).y());
+
+        // Bends always go counterclockwise, so reverse clockwise arcs.
+        let counterclockwise = (b - a).x() * (c - b).y() - (b - a).y() * (c - b).x() > 0.0;
+        let (from, to) = if counterclockwise {
+            (start, end)
+        } else {
+            (end, start)
+        };
+
+        BendShape {
+            from,
+            to,
+            inner_circle: Circle {
+                pos: center,
+                r: radius - width / 2.0,
+            },
+            width,
+        }
+        .polyline(ARC_MAX_DEVIATION)
+    }
+
+    /// Converts a point of the file into board coordinates.
+    fn board_point(point: structure::Point) -> Point {
+        point! {x: point.x * MM_TO_BOARD_UNITS, y: -point.y * MM_TO_BOARD_UNITS}
+    }
+
+    /// Converts a point in board coordinates into coordinates of the file.
+    fn kicad_coords(point: Point) -> (f64, f64) {
+        (
+            point.x() / MM_TO_BOARD_UNITS,
+            -point.y() / MM_TO_BOARD_UNITS,
+        )
+    }
+}
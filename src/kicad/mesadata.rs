@@ -0,0 +1,352 @@
+//! Module for handling KiCad's mesadata - net class rules, as well as layers,
+//! nets and footprints
+
+use std::collections::HashMap;
+
+use bimap::BiHashMap;
+
+use crate::{
+    board::mesadata::{complementary_netname, AccessMesadata, NetPairs},
+    drawing::rules::{AccessRules, Conditions},
+    geometry::primitive::PrimitiveShape,
+    kicad::structure::{KicadPcb, KicadProject, NetClass},
+};
+
+/// Scale between the millimeters of KiCad files and the micrometers of Topola's boards.
+pub(super) const MM_TO_BOARD_UNITS: f64 = 1000.0;
+
+#[derive(Debug, Clone)]
+/// [`KicadRule`] holds the design rules of a KiCad net class, converted to board units.
+pub struct KicadRule {
+    /// Width of the tracks routed for nets of the class.
+    pub track_width: f64,
+    /// Minimum clearance between the copper of nets of the class and copper of other nets.
+    pub clearance: f64,
+    /// Diameter of the vias placed for nets of the class.
+    pub via_diameter: f64,
+    /// Diameter of the holes drilled for these vias.
+    pub via_drill: f64,
+    /// Gap between the nets of the differential pairs of the class, if the class sets one.
+    pub maybe_diff_pair_gap: Option<f64>,
+}
+
+impl KicadRule {
+    /// The rule of KiCad's own default net class, used when the design doesn't define one.
+    fn kicad_default() -> Self {
+        Self {
+            track_width: 0.25 * MM_TO_BOARD_UNITS,
+            clearance: 0.2 * MM_TO_BOARD_UNITS,
+            via_diameter: 0.8 * MM_TO_BOARD_UNITS,
+            via_drill: 0.4 * MM_TO_BOARD_UNITS,
+            maybe_diff_pair_gap: None,
+        }
+    }
+
+    fn from_net_class(class: &NetClass, fallback: &KicadRule) -> Self {
+        Self {
+            track_width: class
+                .track_width
+                .map_or(fallback.track_width, |width| width * MM_TO_BOARD_UNITS),
+            clearance: class.clearance.map_or(fallback.clearance, |clearance| {
+                clearance * MM_TO_BOARD_UNITS
+            }),
+            via_diameter: class
+                .via_diameter
+                .map_or(fallback.via_diameter, |diameter| {
+                    diameter * MM_TO_BOARD_UNITS
+                }),
+            via_drill: class
+                .via_drill
+                .map_or(fallback.via_drill, |drill| drill * MM_TO_BOARD_UNITS),
+            maybe_diff_pair_gap: class
+                .diff_pair_gap
+                .map(|gap| gap * MM_TO_BOARD_UNITS)
+                .or(fallback.maybe_diff_pair_gap),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// [`KicadMesadata`] holds the net class rules, layers, nets and footprints of a KiCad board,
+/// providing Topola with the names and design rules it needs to route it.
+pub struct KicadMesadata {
+    /// The rule of the default net class, applied to nets not assigned to any other class.
+    default_rule: KicadRule,
+
+    // net class name -> rule
+    /// A map from net class names to their rules.
+    class_rules: HashMap<String, KicadRule>,
+
+    // layername <-> layer for Layout
+    /// A bidirectional map between the indices of copper layers in the layout and their names.
+    pub layer_layername: BiHashMap<usize, String>,
+
+    // netname <-> net for Layout
+    /// A bidirectional map between net indices and net names. The indices are the net numbers
+    /// KiCad uses in the board file, so they're written back unchanged.
+    pub net_netname: BiHashMap<usize, String>,
+
+    // net -> netclass
+    /// A map that associates net indices with the names of their net classes.
+    net_netclass: HashMap<usize, String>,
+
+    // net class name -> priority
+    /// A map from net class names to the priorities the project gives them.
+    class_priorities: HashMap<String, i64>,
+
+    // footprint reference -> pinnames
+    /// A map from the reference designators of footprints to the names of their pins.
+    footprint_pinnames: HashMap<String, Vec<String>>,
+
+    /// The differential pairs declared through [`AccessMesadata::pair_nets`]. KiCad itself
+    /// only pairs nets by their names.
+    net_pairs: NetPairs,
+}
+
+impl KicadMesadata {
+    /// Creates a [`KicadMesadata`] instance from a board and, optionally, its project.
+    ///
+    /// Net classes defined in the project take precedence over the ones defined in the board
+    /// file, which only older versions of KiCad write.
+    pub fn from_pcb(pcb: &KicadPcb, maybe_project: Option<&KicadProject>) -> Self {
+        let layer_layername = BiHashMap::from_iter(
+            pcb.layers
+                .iter()
+                .enumerate()
+                .map(|(index, layer)| (index, layer.name.clone())),
+        );
+
+        let net_netname =
+            BiHashMap::from_iter(pcb.nets.iter().map(|net| (net.id, net.name.clone())));
+
+        let net_classes: Vec<&NetClass> = maybe_project
+            .map(|project| project.net_classes.iter().collect())
+            .filter(|net_classes: &Vec<&NetClass>| !net_classes.is_empty())
+            .unwrap_or_else(|| pcb.net_classes.iter().collect());
+
+        let default_rule = net_classes
+            .iter()
+            .find(|class| class.name == "Default")
+            .map_or_else(KicadRule::kicad_default, |class| {
+                KicadRule::from_net_class(class, &KicadRule::kicad_default())
+            });
+
+        let class_rules = HashMap::from_iter(net_classes.iter().map(|class| {
+            (
+                class.name.clone(),
+                KicadRule::from_net_class(class, &default_rule),
+            )
+        }));
+
+        let class_priorities = HashMap::from_iter(
+            net_classes
+                .iter()
+                .filter_map(|class| Some((class.name.clone(), class.priority?))),
+        );
+
+        let mut net_netclass = HashMap::new();
+
+        for class in &net_classes {
+            for netname in &class.nets {
+                if let Some(net) = net_netname.get_by_right(netname) {
+                    net_netclass.insert(*net, class.name.clone());
+                }
+            }
+        }
+
+        if let Some(project) = maybe_project {
+            for (net, netname) in net_netname.iter() {
+                if net_netclass.contains_key(net) {
+                    continue;
+                }
+
+                if let Some((_, netclass)) = project
+                    .netclass_patterns
+                    .iter()
+                    .find(|(pattern, _)| wildcard_match(pattern, netname))
+                {
+                    net_netclass.insert(*net, netclass.clone());
+                }
+            }
+        }
+
+        let mut footprint_pinnames: HashMap<String, Vec<String>> = HashMap::new();
+
+        for footprint in &pcb.footprints {
+            let pinnames = footprint_pinnames
+                .entry(footprint.reference.clone())
+                .or_default();
+
+            for pad in footprint.pads.iter().filter(|pad| !pad.number.is_empty()) {
+                let pinname = format!("{}-{}", footprint.reference, pad.number);
+
+                if !pinnames.contains(&pinname) {
+                    pinnames.push(pinname);
+                }
+            }
+        }
+
+        Self {
+            default_rule,
+            class_rules,
+            layer_layername,
+            net_netname,
+            net_netclass,
+            class_priorities,
+            footprint_pinnames,
+            net_pairs: NetPairs::default(),
+        }
+    }
+
+    /// Retrieves the rule of the net class the given net belongs to, or the default rule if
+    /// the net isn't assigned to any known class.
+    pub fn get_rule(&self, net: usize) -> &KicadRule {
+        self.net_netclass
+            .get(&net)
+            .and_then(|netclass| self.class_rules.get(netclass))
+            .unwrap_or(&self.default_rule)
+    }
+
+    /// Retrieves the names of the pins of the footprint with the given reference designator.
+    pub fn footprint_pinnames(&self, reference: &str) -> Option<&[String]> {
+        self.footprint_pinnames
+            .get(reference)
+            .map(|pinnames| pinnames.as_slice())
+    }
+}
+
+impl AccessRules for KicadMesadata {
+    fn clearance(&self, conditions1: &Conditions, conditions2: &Conditions) -> f64 {
+        match (conditions1.maybe_net, conditions2.maybe_net) {
+            // The nets of a differential pair are kept at the gap declared for the pair or, if
+            // none is, at the one their net class sets.
+            (Some(net1), Some(net2)) if self.net_pair(net1) == Some(net2) => self
+                .net_pairs
+                .gap(net1, net2)
+                .or(self.get_rule(net1).maybe_diff_pair_gap)
+                .unwrap_or_else(|| {
+                    self.get_rule(net1)
+                        .clearance
+                        .max(self.get_rule(net2).clearance)
+                }),
+            (Some(net1), Some(net2)) => self
+                .get_rule(net1)
+                .clearance
+                .max(self.get_rule(net2).clearance),
+            // KiCad keeps netless copper apart from a net by the clearance of the net's class.
+            (Some(net), None) | (None, Some(net)) => self.get_rule(net).clearance,
+            (None, None) => 0.0,
+        }
+    }
+
+    fn largest_clearance(&self, _maybe_net: Option<usize>) -> f64 {
+        self.class_rules
+            .values()
+            .chain([&self.default_rule])
+            .flat_map(|rule| [Some(rule.clearance), rule.maybe_diff_pair_gap])
+            .flatten()
+            .fold(self.net_pairs.largest_gap(), f64::max)
+    }
+
+    fn maybe_width(&self, conditions: &Conditions) -> Option<f64> {
+        Some(
+            conditions
+                .maybe_net
+                .map_or(&self.default_rule, |net| self.get_rule(net))
+                .track_width,
+        )
+    }
+
+    fn maybe_via_diameter(&self, net: usize) -> Option<f64> {
+        Some(self.get_rule(net).via_diameter)
+    }
+
+    // Net classes of KiCad only set through vias, which span all layers.
+    fn maybe_via_layers(&self, _net: usize) -> Option<(usize, usize)> {
+        None
+    }
+
+    // Rule areas of KiCad boards aren't read, so there are no rule regions.
+    fn maybe_region(&self, _layer: usize, _shape: &PrimitiveShape) -> Option<usize> {
+        None
+    }
+}
+
+impl AccessMesadata for KicadMesadata {
+    fn bename_layer(&mut self, layer: usize, layername: String) {
+        self.layer_layername.insert(layer, layername);
+    }
+
+    fn layer_layername(&self, layer: usize) -> Option<&str> {
+        self.layer_layername.get_by_left(&layer).map(|s| s.as_str())
+    }
+
+    fn layername_layer(&self, layername: &str) -> Option<usize> {
+        self.layer_layername.get_by_right(layername).copied()
+    }
+
+    fn bename_net(&mut self, net: usize, netname: String) {
+        self.net_netname.insert(net, netname);
+    }
+
+    fn net_netname(&self, net: usize) -> Option<&str> {
+        self.net_netname.get_by_left(&net).map(|s| s.as_str())
+    }
+
+    fn netname_net(&self, netname: &str) -> Option<usize> {
+        self.net_netname.get_by_right(netname).copied()
+    }
+
+    // KiCad gives higher priority to lower values, so they're negated.
+    fn net_priority(&self, net: usize) -> i64 {
+        let netclass = self
+            .net_netclass
+            .get(&net)
+            .map_or("Default", |netclass| netclass.as_str());
+
+        self.class_priorities
+            .get(netclass)
+            .map_or(0, |priority| -priority)
+    }
+
+    fn pair_nets(&mut self, net1: usize, net2: usize, maybe_gap: Option<f64>) {
+        self.net_pairs.insert(net1, net2, maybe_gap);
+    }
+
+    fn net_pair(&self, net: usize) -> Option<usize> {
+        self.net_pairs
+            .pair(net)
+            .or_else(|| self.netname_net(&complementary_netname(self.net_netname(net)?)?))
+    }
+}
+
+/// Matches a net name against a KiCad net class pattern, where `*` matches any sequence of
+/// characters and `?` matches any single character.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // `matches[j]` tells whether the pattern read so far matches the first `j` characters.
+    let mut matches = vec![false; name.len() + 1];
+    matches[0] = true;
+
+    for &pattern_chr in &pattern {
+        let mut next = vec![false; name.len() + 1];
+
+        if pattern_chr == '*' {
+            next[0] = matches[0];
+
+            for j in 1..=name.len() {
+                next[j] = matches[j] || next[j - 1];
+            }
+        } else {
+            for j in 1..=name.len() {
+                next[j] = matches[j - 1] && (pattern_chr == '?' || pattern_chr == name[j - 1]);
+            }
+        }
+
+        matches = next;
+    }
+
+    matches[name.len()]
+}
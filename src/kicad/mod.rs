@@ -0,0 +1,8 @@
+//! Module containing the informations about handling the KiCad board
+//! file format, and parsing it into Topola's objects
+
+pub mod design;
+pub mod mesadata;
+mod read;
+mod structure;
+mod write;
@@ -0,0 +1,128 @@
+//! Reads KiCad's board files into a tree of S-expression lists, splitting them into tokens
+//! with the tokenizer of Specctra files, whose syntax they share.
+
+use std::io::BufRead;
+
+use crate::specctra::{common::ListToken, read::ListTokenizer};
+
+pub use crate::specctra::read::{ParseError, ParseErrorContext};
+
+/// A single item of an S-expression list: either a leaf or a nested list.
+#[derive(Debug, Clone)]
+pub enum ListItem {
+    Leaf(String),
+    List(List),
+}
+
+/// An S-expression list along with the position at which it starts.
+///
+/// KiCad files always begin every list with a keyword, which is kept separately as the name of
+/// the list, so `(at 1 2 90)` is a list named `at` with the leaves `1`, `2` and `90`.
+#[derive(Debug, Clone)]
+pub struct List {
+    pub name: String,
+    pub items: Vec<ListItem>,
+    context: (usize, usize),
+}
+
+impl List {
+    pub fn leaves(&self) -> impl Iterator<Item = &str> {
+        self.items.iter().filter_map(|item| match item {
+            ListItem::Leaf(leaf) => Some(leaf.as_str()),
+            ListItem::List(..) => None,
+        })
+    }
+
+    pub fn lists(&self) -> impl Iterator<Item = &List> {
+        self.items.iter().filter_map(|item| match item {
+            ListItem::Leaf(..) => None,
+            ListItem::List(list) => Some(list),
+        })
+    }
+
+    pub fn find(&self, name: &str) -> Option<&List> {
+        self.lists().find(|list| list.name == name)
+    }
+
+    pub fn find_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a List> {
+        self.lists().filter(move |list| list.name == name)
+    }
+
+    pub fn leaf(&self, index: usize) -> Option<&str> {
+        self.leaves().nth(index)
+    }
+
+    pub fn expect(&self, name: &'static str) -> Result<&List, ParseErrorContext> {
+        self.find(name)
+            .ok_or(ParseError::ExpectedStartOfList(name).add_context(self.context))
+    }
+
+    pub fn expect_leaf(&self, index: usize) -> Result<&str, ParseErrorContext> {
+        self.leaf(index)
+            .ok_or(ParseError::Expected("string").add_context(self.context))
+    }
+
+    pub fn expect_f64(&self, index: usize) -> Result<f64, ParseErrorContext> {
+        self.expect_leaf(index)?
+            .parse()
+            .map_err(|_| ParseError::Expected("f64").add_context(self.context))
+    }
+
+    pub fn expect_usize(&self, index: usize) -> Result<usize, ParseErrorContext> {
+        self.expect_leaf(index)?
+            .parse()
+            .map_err(|_| ParseError::Expected("usize").add_context(self.context))
+    }
+
+    /// Reads an optional number, failing only if a leaf is present but isn't a number.
+    pub fn maybe_f64(&self, index: usize) -> Result<Option<f64>, ParseErrorContext> {
+        self.leaf(index)
+            .map(|leaf| {
+                leaf.parse()
+                    .map_err(|_| ParseError::Expected("f64").add_context(self.context))
+            })
+            .transpose()
+    }
+
+    /// Reads the first leaf of the list named `name`, as in `(width 0.25)`.
+    pub fn expect_named_f64(&self, name: &'static str) -> Result<f64, ParseErrorContext> {
+        self.expect(name)?.expect_f64(0)
+    }
+
+    pub fn expect_named_leaf(&self, name: &'static str) -> Result<&str, ParseErrorContext> {
+        self.expect(name)?.expect_leaf(0)
+    }
+
+    pub fn context(&self) -> (usize, usize) {
+        self.context
+    }
+}
+
+impl List {
+    /// Reads the list starting at the next token, including all the lists nested in it.
+    pub fn read<R: BufRead>(tokenizer: &mut ListTokenizer<R>) -> Result<Self, ParseErrorContext> {
+        let input = tokenizer.consume_token()?;
+        let context = input.context();
+        let name = input.expect_any_start()?;
+        let mut items = vec![];
+
+        loop {
+            let input = tokenizer.consume_token()?;
+
+            match input.token {
+                ListToken::Start { .. } => {
+                    tokenizer.return_token(input);
+                    items.push(ListItem::List(Self::read(tokenizer)?));
+                }
+                ListToken::Leaf { value } => items.push(ListItem::Leaf(value)),
+                ListToken::End => break,
+            }
+        }
+
+        Ok(Self {
+            name,
+            items,
+            context,
+        })
+    }
+}
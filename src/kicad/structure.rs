@@ -0,0 +1,408 @@
+//! Typed representation of the parts of a `.kicad_pcb` file that Topola cares about.
+//!
+//! All coordinates and sizes are kept in the units and orientation of the file, that is in
+//! millimeters with the Y axis pointing down.
+
+use std::collections::HashMap;
+
+use super::read::{List, ParseError, ParseErrorContext};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    fn from_list(list: &List) -> Result<Self, ParseErrorContext> {
+        Ok(Self {
+            x: list.expect_f64(0)?,
+            y: list.expect_f64(1)?,
+        })
+    }
+}
+
+/// Position along with an optional rotation in degrees, as in `(at 1 2 90)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct At {
+    pub pos: Point,
+    pub rot: f64,
+}
+
+impl At {
+    fn from_list(list: &List) -> Result<Self, ParseErrorContext> {
+        Ok(Self {
+            pos: Point::from_list(list)?,
+            rot: list.maybe_f64(2)?.unwrap_or(0.0),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub ordinal: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Net {
+    pub id: usize,
+    pub name: String,
+}
+
+/// A net class, either defined in the board file (KiCad 5 and older) or in the project file.
+///
+/// Absent values fall back to the values of the default net class.
+#[derive(Debug, Clone, Default)]
+pub struct NetClass {
+    pub name: String,
+    pub clearance: Option<f64>,
+    pub track_width: Option<f64>,
+    pub via_diameter: Option<f64>,
+    pub via_drill: Option<f64>,
+    /// The gap between the nets of the differential pairs of the class.
+    pub diff_pair_gap: Option<f64>,
+    /// The priority KiCad 9 and newer give the class in the project, lower values coming
+    /// first.
+    pub priority: Option<i64>,
+    pub nets: Vec<String>,
+}
+
+impl NetClass {
+    fn from_list(list: &List) -> Result<Self, ParseErrorContext> {
+        let maybe_named_f64 = |name| list.find(name).map(|list| list.expect_f64(0)).transpose();
+
+        Ok(Self {
+            name: list.expect_leaf(0)?.to_string(),
+            clearance: maybe_named_f64("clearance")?,
+            track_width: maybe_named_f64("trace_width")?,
+            via_diameter: maybe_named_f64("via_dia")?,
+            via_drill: maybe_named_f64("via_drill")?,
+            diff_pair_gap: maybe_named_f64("diff_pair_gap")?,
+            priority: None,
+            nets: list
+                .find_all("add_net")
+                .map(|add_net| add_net.expect_leaf(0).map(str::to_string))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Footprint {
+    pub name: String,
+    pub reference: String,
+    pub layer: String,
+    pub at: At,
+    pub pads: Vec<Pad>,
+}
+
+impl Footprint {
+    fn from_list(list: &List, net_ids: &HashMap<String, usize>) -> Result<Self, ParseErrorContext> {
+        // KiCad 8 stores the reference designator as a property, older versions as a text.
+        let reference = list
+            .find_all("property")
+            .find(|property| property.leaf(0) == Some("Reference"))
+            .map(|property| property.expect_leaf(1))
+            .or_else(|| {
+                list.find_all("fp_text")
+                    .find(|text| text.leaf(0) == Some("reference"))
+                    .map(|text| text.expect_leaf(1))
+            })
+            .transpose()?
+            .unwrap_or_default()
+            .to_string();
+
+        Ok(Self {
+            name: list.expect_leaf(0)?.to_string(),
+            reference,
+            layer: list.expect_named_leaf("layer")?.to_string(),
+            at: At::from_list(list.expect("at")?)?,
+            pads: list
+                .find_all("pad")
+                .map(|pad| Pad::from_list(pad, net_ids))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Pad {
+    pub number: String,
+    pub kind: String,
+    pub shape: String,
+    /// Position relative to the footprint, with the rotation of the pad on the board.
+    ///
+    /// KiCad stores the position of pads of footprints on the back of the board already
+    /// mirrored, so it's used the same way for both sides.
+    pub at: At,
+    pub size: Point,
+    pub layers: Vec<String>,
+    pub maybe_net: Option<usize>,
+}
+
+impl Pad {
+    fn from_list(list: &List, net_ids: &HashMap<String, usize>) -> Result<Self, ParseErrorContext> {
+        Ok(Self {
+            number: list.expect_leaf(0)?.to_string(),
+            kind: list.expect_leaf(1)?.to_string(),
+            shape: list.expect_leaf(2)?.to_string(),
+            at: At::from_list(list.expect("at")?)?,
+            size: Point::from_list(list.expect("size")?)?,
+            layers: list
+                .find("layers")
+                .map(|layers| layers.leaves().map(str::to_string).collect())
+                .unwrap_or_default(),
+            maybe_net: maybe_net(list, net_ids)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+    pub width: f64,
+    pub layer: String,
+    pub maybe_net: Option<usize>,
+}
+
+impl Segment {
+    fn from_list(list: &List, net_ids: &HashMap<String, usize>) -> Result<Self, ParseErrorContext> {
+        Ok(Self {
+            start: Point::from_list(list.expect("start")?)?,
+            end: Point::from_list(list.expect("end")?)?,
+            width: list.expect_named_f64("width")?,
+            layer: list.expect_named_leaf("layer")?.to_string(),
+            maybe_net: maybe_net(list, net_ids)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Arc {
+    pub start: Point,
+    pub mid: Point,
+    pub end: Point,
+    pub width: f64,
+    pub layer: String,
+    pub maybe_net: Option<usize>,
+}
+
+impl Arc {
+    fn from_list(list: &List, net_ids: &HashMap<String, usize>) -> Result<Self, ParseErrorContext> {
+        Ok(Self {
+            start: Point::from_list(list.expect("start")?)?,
+            mid: Point::from_list(list.expect("mid")?)?,
+            end: Point::from_list(list.expect("end")?)?,
+            width: list.expect_named_f64("width")?,
+            layer: list.expect_named_leaf("layer")?.to_string(),
+            maybe_net: maybe_net(list, net_ids)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Via {
+    pub at: Point,
+    pub size: f64,
+    pub drill: f64,
+    pub layers: (String, String),
+    pub maybe_net: Option<usize>,
+}
+
+impl Via {
+    fn from_list(list: &List, net_ids: &HashMap<String, usize>) -> Result<Self, ParseErrorContext> {
+        let layers = list.expect("layers")?;
+
+        Ok(Self {
+            at: Point::from_list(list.expect("at")?)?,
+            size: list.expect_named_f64("size")?,
+            drill: list.expect_named_f64("drill")?,
+            layers: (
+                layers.expect_leaf(0)?.to_string(),
+                layers.expect_leaf(1)?.to_string(),
+            ),
+            maybe_net: maybe_net(list, net_ids)?,
+        })
+    }
+}
+
+/// The contents of a `.kicad_pcb` file.
+#[derive(Debug, Clone)]
+pub struct KicadPcb {
+    /// Copper layers, ordered from the front to the back of the board.
+    pub layers: Vec<Layer>,
+    pub nets: Vec<Net>,
+    pub net_classes: Vec<NetClass>,
+    pub footprints: Vec<Footprint>,
+    pub segments: Vec<Segment>,
+    pub arcs: Vec<Arc>,
+    pub vias: Vec<Via>,
+}
+
+impl KicadPcb {
+    pub fn from_list(list: &List) -> Result<Self, ParseErrorContext> {
+        if list.name != "kicad_pcb" {
+            return Err(ParseError::ExpectedStartOfList("kicad_pcb").add_context(list.context()));
+        }
+
+        let mut layers = list
+            .expect("layers")?
+            .lists()
+            .filter(|layer| layer.leaf(0).is_some_and(|name| name.ends_with(".Cu")))
+            .map(|layer| {
+                Ok(Layer {
+                    ordinal: layer.name.parse().map_err(|_| {
+                        ParseError::Expected("layer ordinal").add_context(layer.context())
+                    })?,
+                    name: layer.expect_leaf(0)?.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // Ordinals of copper layers aren't in stackup order in every version of the format, so
+        // sort by the names instead: F.Cu, In1.Cu, In2.Cu, ..., B.Cu.
+        layers.sort_by_key(|layer| Self::layer_stackup_position(&layer.name));
+
+        // Net 0 is the "no net" net, it's treated as the absence of a net.
+        let nets = list
+            .find_all("net")
+            .map(|net| {
+                Ok(Net {
+                    id: net.expect_usize(0)?,
+                    name: net.expect_leaf(1)?.to_string(),
+                })
+            })
+            .filter(|net| !matches!(net, Ok(Net { id: 0, .. })))
+            .collect::<Result<Vec<_>, ParseErrorContext>>()?;
+        let net_ids = nets
+            .iter()
+            .map(|net| (net.name.clone(), net.id))
+            .collect::<HashMap<_, _>>();
+
+        Ok(Self {
+            layers,
+            nets,
+            net_classes: list
+                .find_all("net_class")
+                .map(NetClass::from_list)
+                .collect::<Result<_, _>>()?,
+            footprints: list
+                .find_all("footprint")
+                .chain(list.find_all("module"))
+                .map(|footprint| Footprint::from_list(footprint, &net_ids))
+                .collect::<Result<_, _>>()?,
+            segments: list
+                .find_all("segment")
+                .map(|segment| Segment::from_list(segment, &net_ids))
+                .collect::<Result<_, _>>()?,
+            arcs: list
+                .find_all("arc")
+                .map(|arc| Arc::from_list(arc, &net_ids))
+                .collect::<Result<_, _>>()?,
+            vias: list
+                .find_all("via")
+                .map(|via| Via::from_list(via, &net_ids))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn layer_stackup_position(layername: &str) -> usize {
+        match layername {
+            "F.Cu" => 0,
+            "B.Cu" => usize::MAX,
+            _ => layername
+                .strip_prefix("In")
+                .and_then(|name| name.strip_suffix(".Cu"))
+                .and_then(|number| number.parse().ok())
+                .unwrap_or(usize::MAX - 1),
+        }
+    }
+}
+
+/// Reads the net of an item, given either as `(net 1 "GND")`, `(net 1)` or `(net "GND")`.
+fn maybe_net(
+    list: &List,
+    net_ids: &HashMap<String, usize>,
+) -> Result<Option<usize>, ParseErrorContext> {
+    let Some(net) = list.find("net") else {
+        return Ok(None);
+    };
+    let leaf = net.expect_leaf(0)?;
+
+    Ok(match leaf.parse::<usize>() {
+        Ok(0) => None,
+        Ok(id) => Some(id),
+        Err(..) => net_ids.get(leaf).copied(),
+    })
+}
+
+/// Net classes and their assignments to nets, read from a `.kicad_pro` project file, which is
+/// where KiCad 6 and newer keep them.
+#[derive(Debug, Clone, Default)]
+pub struct KicadProject {
+    pub net_classes: Vec<NetClass>,
+    /// Wildcard patterns of net names, each with the net class assigned to matching nets.
+    pub netclass_patterns: Vec<(String, String)>,
+}
+
+impl KicadProject {
+    pub fn from_json(project: &serde_json::Value) -> Self {
+        let net_settings = &project["net_settings"];
+
+        let net_classes = net_settings["classes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|class| {
+                Some(NetClass {
+                    name: class["name"].as_str()?.to_string(),
+                    clearance: class["clearance"].as_f64(),
+                    track_width: class["track_width"].as_f64(),
+                    via_diameter: class["via_diameter"].as_f64(),
+                    via_drill: class["via_drill"].as_f64(),
+                    diff_pair_gap: class["diff_pair_gap"].as_f64(),
+                    priority: class["priority"].as_i64(),
+                    // KiCad 6 lists the nets of each class, newer versions use assignments.
+                    nets: class["nets"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|net| Some(net.as_str()?.to_string()))
+                        .collect(),
+                })
+            })
+            .collect();
+
+        let mut netclass_patterns: Vec<(String, String)> = net_settings["netclass_patterns"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|pattern| {
+                Some((
+                    pattern["pattern"].as_str()?.to_string(),
+                    pattern["netclass"].as_str()?.to_string(),
+                ))
+            })
+            .collect();
+
+        // Explicit assignments behave like patterns matching a single net, but take precedence.
+        if let Some(assignments) = net_settings["netclass_assignments"].as_object() {
+            for (netname, netclass) in assignments {
+                let netclass = match netclass {
+                    serde_json::Value::Array(netclasses) => netclasses.first(),
+                    netclass => Some(netclass),
+                };
+
+                if let Some(netclass) = netclass.and_then(|netclass| netclass.as_str()) {
+                    netclass_patterns.insert(0, (netname.clone(), netclass.to_string()));
+                }
+            }
+        }
+
+        Self {
+            net_classes,
+            netclass_patterns,
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::io;
+
+/// Writes board items as single-line lists, indented like the top-level items of the board file
+/// they're appended to.
+pub struct ItemWriter<W: io::Write> {
+    writable: W,
+    indent: String,
+}
+
+impl<W: io::Write> ItemWriter<W> {
+    pub fn new(writable: W, indent: String) -> Self {
+        Self { writable, indent }
+    }
+
+    pub fn write_segment(
+        &mut self,
+        start: (f64, f64),
+        end: (f64, f64),
+        width: f64,
+        layername: &str,
+        net: usize,
+    ) -> Result<(), io::Error> {
+        writeln!(
+            self.writable,
+            "{}(segment (start {}) (end {}) (width {}) (layer {}) (net {}))",
+            self.indent,
+            coords(start),
+            coords(end),
+            number(width),
+            quote(layername),
+            net
+        )
+    }
+
+    pub fn write_arc(
+        &mut self,
+        start: (f64, f64),
+        mid: (f64, f64),
+        end: (f64, f64),
+        width: f64,
+        layername: &str,
+        net: usize,
+    ) -> Result<(), io::Error> {
+        writeln!(
+            self.writable,
+            "{}(arc (start {}) (mid {}) (end {}) (width {}) (layer {}) (net {}))",
+            self.indent,
+            coords(start),
+            coords(mid),
+            coords(end),
+            number(width),
+            quote(layername),
+            net
+        )
+    }
+
+    pub fn write_via(
+        &mut self,
+        at: (f64, f64),
+        size: f64,
+        drill: f64,
+        layernames: (&str, &str),
+        net: usize,
+    ) -> Result<(), io::Error> {
+        writeln!(
+            self.writable,
+            "{}(via (at {}) (size {}) (drill {}) (layers {} {}) (net {}))",
+            self.indent,
+            coords(at),
+            number(size),
+            number(drill),
+            quote(layernames.0),
+            quote(layernames.1),
+            net
+        )
+    }
+
+    /// Writes a zone along with its fill, in the zone syntax of KiCad 6 and newer.
+    ///
+    /// The hatching and the minimum thickness, which Topola has no use for, are KiCad's
+    /// defaults. The fill is written with `filled_areas_thickness` off, as it's already the
+    /// whole area of copper, which KiCad would otherwise grow by half the minimum thickness.
+    pub fn write_zone(
+        &mut self,
+        outline: &[(f64, f64)],
+        filled_polygons: &[Vec<(f64, f64)>],
+        settings: &ZoneSettings,
+        layername: &str,
+        net: usize,
+        netname: &str,
+    ) -> Result<(), io::Error> {
+        let (connect_pads, thermal_relief) = match settings.maybe_thermal_relief {
+            Some((gap, spoke_width)) => (
+                "",
+                format!(
+                    " (thermal_gap {}) (thermal_bridge_width {})",
+                    number(gap),
+                    number(spoke_width)
+                ),
+            ),
+            None => (" yes", String::new()),
+        };
+        // KiCad removes all islands by default and keeps them all with mode 1.
+        let island_removal_mode = if settings.remove_islands { 0 } else { 1 };
+
+        write!(
+            self.writable,
+            "{}(zone (net {}) (net_name {}) (layer {}) (hatch edge 0.5) \
+             (connect_pads{} (clearance {})) (min_thickness 0.25) (filled_areas_thickness no) \
+             (fill yes{} (island_removal_mode {})) (polygon {})",
+            self.indent,
+            net,
+            quote(netname),
+            quote(layername),
+            connect_pads,
+            number(settings.clearance),
+            thermal_relief,
+            island_removal_mode,
+            pts(outline)
+        )?;
+
+        for filled_polygon in filled_polygons {
+            write!(
+                self.writable,
+                " (filled_polygon (layer {}) {})",
+                quote(layername),
+                pts(filled_polygon)
+            )?;
+        }
+
+        writeln!(self.writable, ")")
+    }
+}
+
+/// How a zone is connected to the pads of its net and filled, in millimeters.
+#[derive(Debug, Clone, Copy)]
+pub struct ZoneSettings {
+    pub clearance: f64,
+    /// The gap and the spoke width of the thermal reliefs around pads, or none if pads are
+    /// connected to the fill directly.
+    pub maybe_thermal_relief: Option<(f64, f64)>,
+    pub remove_islands: bool,
+}
+
+fn pts(points: &[(f64, f64)]) -> String {
+    let xys: Vec<String> = points
+        .iter()
+        .map(|point| format!("(xy {})", coords(*point)))
+        .collect();
+    format!("(pts {})", xys.join(" "))
+}
+
+fn coords((x, y): (f64, f64)) -> String {
+    format!("{} {}", number(x), number(y))
+}
+
+/// Formats a number the way KiCad does: with at most six decimal places and no trailing zeros.
+fn number(value: f64) -> String {
+    let string = format!("{:.6}", value);
+    let string = string.trim_end_matches('0').trim_end_matches('.');
+
+    if string == "-0" {
+        "0".to_string()
+    } else {
+        string.to_string()
+    }
+}
+
+/// Quotes a string, escaping the characters KiCad escapes.
+fn quote(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+
+    for chr in string.chars() {
+        match chr {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            chr => quoted.push(chr),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        kicad::read::{List, ListItem},
+        specctra::read::ListTokenizer,
+    };
+
+    #[test]
+    fn test_zone_is_read_back() {
+        let mut written = vec![];
+        ItemWriter::new(&mut written, String::new())
+            .write_zone(
+                &[(0.0, 0.0), (10.0, 0.0), (10.0, 5.0)],
+                &[vec![(1.0, 1.0), (9.0, 1.0), (9.0, 4.0)]],
+                &ZoneSettings {
+                    clearance: 0.2,
+                    maybe_thermal_relief: Some((0.5, 0.3)),
+                    remove_islands: false,
+                },
+                "B.Cu",
+                3,
+                "Net-(\"D1\"-A)",
+            )
+            .unwrap();
+
+        let zone = List::read(&mut ListTokenizer::new_with_escaped_quotes(
+            written.as_slice(),
+            '"',
+        ))
+        .unwrap();
+        assert_eq!(zone.name, "zone");
+        assert_eq!(zone.expect("net").unwrap().expect_usize(0).unwrap(), 3);
+        assert_eq!(
+            zone.expect_named_leaf("net_name").unwrap(),
+            "Net-(\"D1\"-A)"
+        );
+        assert_eq!(zone.expect_named_leaf("layer").unwrap(), "B.Cu");
+
+        let connect_pads = zone.expect("connect_pads").unwrap();
+        assert_eq!(connect_pads.leaf(0), None);
+        assert_eq!(connect_pads.expect_named_f64("clearance").unwrap(), 0.2);
+        assert_eq!(
+            zone.expect_named_leaf("filled_areas_thickness").unwrap(),
+            "no"
+        );
+
+        let fill = zone.expect("fill").unwrap();
+        assert_eq!(fill.leaf(0), Some("yes"));
+        assert_eq!(fill.expect_named_f64("thermal_gap").unwrap(), 0.5);
+        assert_eq!(fill.expect_named_f64("thermal_bridge_width").unwrap(), 0.3);
+        assert_eq!(fill.expect_named_leaf("island_removal_mode").unwrap(), "1");
+
+        let point_count = |list: &List| list.expect("pts").unwrap().find_all("xy").count();
+        assert_eq!(point_count(zone.expect("polygon").unwrap()), 3);
+
+        let filled_polygon = zone.expect("filled_polygon").unwrap();
+        assert_eq!(filled_polygon.expect_named_leaf("layer").unwrap(), "B.Cu");
+        assert_eq!(point_count(filled_polygon), 3);
+        assert!(zone
+            .items
+            .iter()
+            .all(|item| matches!(item, ListItem::List(..))));
+    }
+}
@@ -0,0 +1,50 @@
+//! Module for handling via keepouts, areas into which no via may be placed
+//! while bands may still be routed through them
+
+use geo::{Contains, EuclideanDistance, LineString, Polygon};
+
+use crate::{layout::via::ViaWeight, math::Circle};
+
+/// The area covered by a [`ViaKeepout`].
+#[derive(Debug, Clone)]
+pub enum ViaKeepoutShape {
+    Circle(Circle),
+    Polygon(Polygon),
+    /// A polyline thickened by the given width.
+    Path(LineString, f64),
+}
+
+impl ViaKeepoutShape {
+    fn intersects_circle(&self, circle: &Circle) -> bool {
+        match self {
+            Self::Circle(keepout_circle) => {
+                keepout_circle.pos.euclidean_distance(&circle.pos) < keepout_circle.r + circle.r
+            }
+            Self::Polygon(polygon) => {
+                polygon.contains(&circle.pos) || circle.pos.euclidean_distance(polygon) < circle.r
+            }
+            Self::Path(line_string, width) => {
+                circle.pos.euclidean_distance(line_string) < width / 2.0 + circle.r
+            }
+        }
+    }
+}
+
+/// A keepout that only keeps vias out of an area on a layer.
+///
+/// Unlike keepouts of all kinds of copper, which are inserted into the drawing as netless fixed
+/// primitives, via keepouts are not obstacles to bands, so [`crate::layout::Layout`] holds them
+/// aside and only checks them when placing vias.
+#[derive(Debug, Clone)]
+pub struct ViaKeepout {
+    pub layer: usize,
+    pub shape: ViaKeepoutShape,
+}
+
+impl ViaKeepout {
+    /// Whether a via with the given weight would reach into the keepout.
+    pub fn intersects_via(&self, weight: &ViaWeight) -> bool {
+        (weight.from_layer..=weight.to_layer).contains(&self.layer)
+            && self.shape.intersects_circle(&weight.circle)
+    }
+}
@@ -1,8 +1,9 @@
 use contracts_try::debug_ensures;
 use derive_getters::Getters;
 use enum_dispatch::enum_dispatch;
-use geo::Point;
+use geo::{Point, Polygon};
 use rstar::AABB;
+use thiserror::Error;
 
 use crate::{
     drawing::{
@@ -11,44 +12,104 @@ use crate::{
         cane::Cane,
         dot::{DotIndex, FixedDotIndex, FixedDotWeight, LooseDotIndex, LooseDotWeight},
         gear::GearIndex,
-        graph::{GetMaybeNet, PrimitiveIndex},
-        rules::AccessRules,
+        graph::{GetMaybeNet, PrimitiveIndex, PrimitiveWeight},
+        primitive::GetLimbs,
+        rules::{AccessRules, ObjectKind},
         seg::{
             FixedSegIndex, FixedSegWeight, LoneLooseSegIndex, LoneLooseSegWeight, SeqLooseSegIndex,
             SeqLooseSegWeight,
         },
+        shove::Shove,
         Drawing, DrawingException, Infringement,
     },
-    geometry::{compound::ManageCompounds, GenericNode},
-    graph::{GenericIndex, GetPetgraphIndex},
+    geometry::{
+        compound::ManageCompounds,
+        edit::{GeometryEdit, Recording},
+        GenericNode,
+    },
+    graph::{GenericIndex, GetPetgraphIndex, MakeRef},
     layout::{
-        poly::{Poly, PolyWeight},
+        keepout::ViaKeepout,
+        meander::MeanderWeight,
+        poly::{GetMaybeApex, Poly, PolyWeight},
+        pour::Pour,
         via::{Via, ViaWeight},
     },
 };
 
 /// Represents a weight for various compounds
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[enum_dispatch(GetMaybeNet)]
 pub enum CompoundWeight {
     /// Represents the weight of a polygon compound, includes its basic [`Layout`] information
     Poly(PolyWeight),
     /// Represents Via weight properties, containing its [`Layout`] properties
     Via(ViaWeight),
+    /// Represents the cores of a meander, which are removed along with the band around them
+    Meander(MeanderWeight),
 }
 
 /// The alias to differ node types
 pub type NodeIndex = GenericNode<PrimitiveIndex, GenericIndex<CompoundWeight>>;
 
+/// The edit made to a [`Layout`] by a change, which can be reverted to undo it
+pub type LayoutEdit = GeometryEdit<PrimitiveWeight, CompoundWeight>;
+
+/// Errors raised when placing vias into the [`Layout`]
+#[derive(Error, Debug, Clone, Copy)]
+pub enum LayoutException {
+    #[error(transparent)]
+    Infringement(#[from] Infringement),
+    #[error(transparent)]
+    ViaKeepoutInfringement(#[from] ViaKeepoutInfringement),
+}
+
+/// The via with the given weight reaches into the via keepout with the given index.
+#[derive(Error, Debug, Clone, Copy)]
+#[error("{0:?} infringes on via keepout {1}")]
+pub struct ViaKeepoutInfringement(pub ViaWeight, pub usize);
+
 #[derive(Debug, Getters)]
 /// Structure for managing the Layout design
 pub struct Layout<R: AccessRules> {
     drawing: Drawing<CompoundWeight, R>,
+    via_keepouts: Vec<ViaKeepout>,
+    pours: Vec<Pour>,
 }
 
 impl<R: AccessRules> Layout<R> {
     pub fn new(drawing: Drawing<CompoundWeight, R>) -> Self {
-        Self { drawing }
+        Self {
+            drawing,
+            via_keepouts: vec![],
+            pours: vec![],
+        }
+    }
+
+    /// Insert a [`ViaKeepout`] into the [`Layout`]
+    pub fn add_via_keepout(&mut self, keepout: ViaKeepout) {
+        self.via_keepouts.push(keepout);
+    }
+
+    /// Set the outline of the board, outside of which nothing may be inserted
+    pub fn set_boundary(&mut self, boundary: Polygon) {
+        self.drawing.set_boundary(boundary);
+    }
+
+    /// Insert a [`Pour`] into the [`Layout`], returning its index. The pour is left unfilled
+    /// until the pours are filled.
+    pub fn add_pour(&mut self, pour: Pour) -> usize {
+        self.pours.push(pour);
+        self.pours.len() - 1
+    }
+
+    /// Fills all pours anew around what's on their layers now.
+    pub fn fill_pours(&mut self) {
+        let fills: Vec<_> = self.pours.iter().map(|pour| pour.make_fill(self)).collect();
+
+        for (pour, fill) in self.pours.iter_mut().zip(fills) {
+            pour.fill = fill;
+        }
     }
 
     /// Insert [`Cane`] object into the [`Layout`]
@@ -70,10 +131,35 @@ impl<R: AccessRules> Layout<R> {
         self.drawing.remove_cane(cane, face)
     }
 
+    /// Wrap a [`SeqLooseSegIndex`] of a band around a gear to make way for a [`Cane`]
+    pub fn shove_seg(
+        &mut self,
+        seg: SeqLooseSegIndex,
+        around: GearIndex,
+    ) -> Result<Shove, DrawingException> {
+        self.drawing.shove_seg(seg, around)
+    }
+
+    /// Straighten a [`Shove`]d seg back
+    pub fn unshove(&mut self, shove: &Shove) {
+        self.drawing.unshove(shove)
+    }
+
     #[debug_ensures(ret.is_ok() -> self.drawing.node_count() == old(self.drawing.node_count()) + weight.to_layer - weight.from_layer + 2)]
     #[debug_ensures(ret.is_err() -> self.drawing.node_count() == old(self.drawing.node_count()))]
     /// Insert [`Via`] into the [`Layout`]
-    pub fn add_via(&mut self, weight: ViaWeight) -> Result<GenericIndex<ViaWeight>, Infringement> {
+    pub fn add_via(
+        &mut self,
+        weight: ViaWeight,
+    ) -> Result<GenericIndex<ViaWeight>, LayoutException> {
+        if let Some(keepout) = self
+            .via_keepouts
+            .iter()
+            .position(|keepout| keepout.intersects_via(&weight))
+        {
+            return Err(ViaKeepoutInfringement(weight, keepout).into());
+        }
+
         let compound = self.drawing.add_compound(weight.into());
         let mut dots = vec![];
 
@@ -82,6 +168,7 @@ impl<R: AccessRules> Layout<R> {
                 circle: weight.circle,
                 layer,
                 maybe_net: weight.maybe_net,
+                kind: ObjectKind::Via,
             }) {
                 Ok(dot) => {
                     self.drawing.add_to_compound(dot, compound);
@@ -96,7 +183,7 @@ impl<R: AccessRules> Layout<R> {
                         self.drawing.remove_fixed_dot(*dot);
                     }
 
-                    return Err(err);
+                    return Err(err.into());
                 }
             }
         }
@@ -104,6 +191,64 @@ impl<R: AccessRules> Layout<R> {
         Ok(GenericIndex::<ViaWeight>::new(compound.petgraph_index()))
     }
 
+    #[debug_ensures(self.drawing.node_count() == old(self.drawing.node_count()) + weight.to_layer - weight.from_layer + 2)]
+    /// Insert [`Via`] into the [`Layout`] without checking for infringements or via keepouts,
+    /// as is done for the vias already present in design files
+    pub fn add_via_infringably(&mut self, weight: ViaWeight) -> GenericIndex<ViaWeight> {
+        let compound = self.drawing.add_compound(weight.into());
+
+        for layer in weight.from_layer..=weight.to_layer {
+            let dot = self.drawing.add_fixed_dot_infringably(FixedDotWeight {
+                circle: weight.circle,
+                layer,
+                maybe_net: weight.maybe_net,
+                kind: ObjectKind::Via,
+            });
+            self.drawing.add_to_compound(dot, compound);
+        }
+
+        GenericIndex::<ViaWeight>::new(compound.petgraph_index())
+    }
+
+    /// Remove a [`Via`] and all its dots from the [`Layout`]
+    ///
+    /// Bands joined to the dots of the via must be removed first.
+    pub fn remove_via(&mut self, via: GenericIndex<ViaWeight>) {
+        let dots: Vec<PrimitiveIndex> = self
+            .drawing
+            .geometry()
+            .compound_members(via.into())
+            .collect();
+
+        self.drawing.remove_compound(via.into());
+
+        for dot in dots {
+            if let PrimitiveIndex::FixedDot(dot) = dot {
+                self.drawing.remove_fixed_dot(dot);
+            }
+        }
+    }
+
+    /// Start recording the changes made to the [`Layout`], to later find the edit they make up
+    pub fn start_recording(&mut self) -> Recording {
+        self.drawing.start_recording()
+    }
+
+    /// Find the edit made to the [`Layout`] since the given recording was started
+    pub fn edit_since(&self, recording: &Recording) -> LayoutEdit {
+        self.drawing.edit_since(recording)
+    }
+
+    /// Stop the recording, returning the edit made to the [`Layout`] since it was started
+    pub fn stop_recording(&mut self, recording: Recording) -> LayoutEdit {
+        self.drawing.stop_recording(recording)
+    }
+
+    /// Apply an edit, or the reverse of one, to bring the [`Layout`] to the state it leads to
+    pub fn apply_edit(&mut self, edit: &LayoutEdit) {
+        self.drawing.apply_edit(edit);
+    }
+
 
     pub fn add_fixed_dot(&mut self, weight: FixedDotWeight) -> Result<FixedDotIndex, Infringement> {
         self.drawing.add_fixed_dot(weight)
@@ -137,6 +282,17 @@ impl<R: AccessRules> Layout<R> {
         dot
     }
 
+    /// Remove the apex of the polygon if it has one and no band ends at it anymore
+    pub fn remove_unjoined_poly_apex(&mut self, poly: GenericIndex<PolyWeight>) {
+        let Some(apex) = self.poly(poly).maybe_apex() else {
+            return;
+        };
+
+        if self.drawing.primitive(apex).segs().is_empty() {
+            self.drawing.remove_compound_fixed_dot(apex);
+        }
+    }
+
     pub fn add_fixed_seg(
         &mut self,
         from: FixedDotIndex,
@@ -205,6 +361,14 @@ impl<R: AccessRules> Layout<R> {
         self.drawing.move_dot(dot, to)
     }
 
+    pub fn move_dots_by(
+        &mut self,
+        dots: &[FixedDotIndex],
+        offset: Point,
+    ) -> Result<(), Infringement> {
+        self.drawing.move_dots_by(dots, offset)
+    }
+
     pub fn add_poly(&mut self, weight: PolyWeight) -> GenericIndex<PolyWeight> {
         GenericIndex::<PolyWeight>::new(
             self.drawing
@@ -213,10 +377,103 @@ impl<R: AccessRules> Layout<R> {
         )
     }
 
+    /// Remove a band, along with the meanders laid into it once nothing else wraps around
+    /// their cores
     pub fn remove_band(&mut self, band: BandTermsegIndex) -> Result<(), DrawingException> {
+        let meanders = self.band_meanders(band);
+        self.drawing.remove_band(band)?;
+
+        for meander in meanders {
+            let is_unwrapped = self
+                .meander_cores(meander)
+                .iter()
+                .all(|core| self.drawing.primitive(*core).limbs().is_empty());
+
+            if is_unwrapped {
+                self.remove_meander(meander);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove a band but keep the cores of the meanders laid into it, so that it can be drawn
+    /// around them anew
+    pub fn remove_band_keeping_meanders(
+        &mut self,
+        band: BandTermsegIndex,
+    ) -> Result<(), DrawingException> {
         self.drawing.remove_band(band)
     }
 
+    /// Add a meander to which the cores of the meander are to be added
+    pub fn add_meander(&mut self, weight: MeanderWeight) -> GenericIndex<MeanderWeight> {
+        GenericIndex::new(
+            self.drawing
+                .add_compound(CompoundWeight::Meander(weight))
+                .petgraph_index(),
+        )
+    }
+
+    /// Insert a core of a meander
+    pub fn add_meander_core(
+        &mut self,
+        meander: GenericIndex<MeanderWeight>,
+        weight: FixedDotWeight,
+    ) -> Result<FixedDotIndex, Infringement> {
+        let core = self.drawing.add_fixed_dot(weight)?;
+        self.drawing.add_to_compound(core, meander.into());
+        Ok(core)
+    }
+
+    /// Find the meanders the band winds around the cores of
+    pub fn band_meanders(&self, band: BandTermsegIndex) -> Vec<GenericIndex<MeanderWeight>> {
+        let mut meanders = vec![];
+
+        for gear in band.ref_(&self.drawing).path().wraps {
+            let GearIndex::FixedDot(dot) = gear.0 else {
+                continue;
+            };
+
+            for compound in self.drawing.compounds(dot) {
+                if let CompoundWeight::Meander(..) = self.drawing.compound_weight(compound) {
+                    let meander = GenericIndex::new(compound.petgraph_index());
+
+                    if !meanders.contains(&meander) {
+                        meanders.push(meander);
+                    }
+                }
+            }
+        }
+
+        meanders
+    }
+
+    fn meander_cores(&self, meander: GenericIndex<MeanderWeight>) -> Vec<FixedDotIndex> {
+        self.drawing
+            .geometry()
+            .compound_members(meander.into())
+            .filter_map(|member| match member {
+                PrimitiveIndex::FixedDot(dot) => Some(dot),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Remove a meander and all its cores, which nothing may wrap around anymore
+    fn remove_meander(&mut self, meander: GenericIndex<MeanderWeight>) {
+        let cores = self.meander_cores(meander);
+        self.drawing.remove_compound(meander.into());
+
+        for core in cores {
+            self.drawing.remove_fixed_dot(core);
+        }
+    }
+
+    pub fn remove_termseg(&mut self, termseg: BandTermsegIndex) {
+        self.drawing.remove_termseg(termseg)
+    }
+
     pub fn polys<W: 'static>(
         &self,
         node: GenericIndex<W>,
@@ -257,6 +514,18 @@ impl<R: AccessRules> Layout<R> {
             })
     }
 
+    pub fn via_nodes(&self) -> impl Iterator<Item = GenericIndex<ViaWeight>> + '_ {
+        self.drawing.rtree().iter().filter_map(|wrapper| {
+            if let NodeIndex::Compound(compound) = wrapper.data {
+                if let CompoundWeight::Via(..) = self.drawing.compound_weight(compound) {
+                    return Some(GenericIndex::<ViaWeight>::new(compound.petgraph_index()));
+                }
+            }
+
+            None
+        })
+    }
+
     pub fn poly_members(
         &self,
         poly: GenericIndex<PolyWeight>,
@@ -0,0 +1,35 @@
+//! Module for the meanders laid into bands to lengthen them, whose cores are kept together in a
+//! compound so that they're removed along with the band wrapped around them
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    drawing::graph::{GetLayer, GetMaybeNet},
+    graph::{GenericIndex, GetPetgraphIndex},
+    layout::CompoundWeight,
+};
+
+/// The weight of the compound of the cores of a meander, the fixed dots of no size and no net
+/// the meandering band winds around.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MeanderWeight {
+    pub layer: usize,
+}
+
+impl From<GenericIndex<MeanderWeight>> for GenericIndex<CompoundWeight> {
+    fn from(meander: GenericIndex<MeanderWeight>) -> Self {
+        GenericIndex::<CompoundWeight>::new(meander.petgraph_index())
+    }
+}
+
+impl GetLayer for MeanderWeight {
+    fn layer(&self) -> usize {
+        self.layer
+    }
+}
+
+impl GetMaybeNet for MeanderWeight {
+    fn maybe_net(&self) -> Option<usize> {
+        None
+    }
+}
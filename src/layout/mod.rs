@@ -1,7 +1,10 @@
 //! Layout module for handling board geometry.
 
+pub mod keepout;
 mod layout;
+pub mod meander;
 pub mod poly;
+pub mod pour;
 pub mod via;
 
 pub use layout::*;
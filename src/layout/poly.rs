@@ -7,9 +7,9 @@ use geo::{LineString, Point, Polygon};
 use crate::{
     drawing::{
         dot::FixedDotIndex,
-        graph::{GetLayer, GetMaybeNet, PrimitiveIndex},
+        graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex},
         primitive::GetLimbs,
-        rules::AccessRules,
+        rules::{AccessRules, GetObjectKind, ObjectKind},
         seg::SegIndex,
     },
     geometry::{compound::ManageCompounds, poly::PolyShape, GetPos},
@@ -71,6 +71,22 @@ impl<'a, R: AccessRules> GetMaybeNet for Poly<'a, R> {
     }
 }
 
+impl<'a, R: AccessRules> GetObjectKind for Poly<'a, R> {
+    /// A polygon is of the kind of the primitives that outline it, which is that of a pad.
+    fn object_kind(&self) -> ObjectKind {
+        self.layout
+            .drawing()
+            .geometry()
+            .compound_members(self.index.into())
+            .next()
+            .map_or(ObjectKind::Pin, |primitive_node| {
+                primitive_node
+                    .primitive(self.layout.drawing())
+                    .object_kind()
+            })
+    }
+}
+
 impl<'a, R: AccessRules> MakePolyShape for Poly<'a, R> {
     fn shape(&self) -> PolyShape {
         PolyShape {
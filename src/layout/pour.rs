@@ -0,0 +1,461 @@
+//! Module for handling copper pours, areas of a layer that are filled with the copper of a net
+//! everywhere except where it would come too close to the copper of other nets
+
+use std::f64::consts::{PI, TAU};
+
+use geo::{
+    point, BooleanOps, BoundingRect, Centroid, Coord, EuclideanDistance, Intersects, LineString,
+    MapCoords, MultiPolygon, Point, Polygon,
+};
+use rstar::AABB;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    drawing::{
+        graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex},
+        primitive::MakePrimitiveShape,
+        rules::{AccessRules, Conditions, GetConditions, GetObjectKind, ObjectKind},
+    },
+    geometry::{
+        compound::ManageCompounds,
+        primitive::{AccessPrimitiveShape, PrimitiveShape},
+        shape::AccessShape,
+        GenericNode,
+    },
+    graph::{GenericIndex, GetPetgraphIndex},
+    layout::{
+        poly::{MakePolyShape, PolyWeight, PourPolyWeight},
+        CompoundWeight, Layout,
+    },
+    math::Circle,
+};
+
+/// Maximum deviation, in board units, of the polygons that stand in for the arcs and circles of
+/// the shapes a pour is filled around. The polygons are drawn outside of the arcs, so that the
+/// fill keeps its clearance anyway.
+const ARC_MAX_DEVIATION: f64 = 5.0;
+
+/// Grid, in board units, to which the coordinates of polygons are rounded before and after each
+/// boolean operation, as the operations are prone to fail on points that lie apart only by
+/// rounding errors.
+const SNAP_GRID: f64 = 0.001;
+
+/// How far inside of its clearance the fill of a pour may lie without infringing it, as the
+/// polygons the fill is cut around stand in for arcs from outside of them and are snapped to
+/// [`SNAP_GRID`].
+const FILL_CLEARANCE_TOLERANCE: f64 = 2.0 * ARC_MAX_DEVIATION;
+
+/// How the pads of the net of a pour are connected to its fill.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PadConnection {
+    /// The fill is kept a gap away from the pad and reaches it only through spokes, so that
+    /// the pour doesn't draw away the heat of the soldering iron.
+    #[default]
+    ThermalRelief,
+    /// The fill runs into the pad.
+    Solid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PourOptions {
+    /// How the pads of the net of the pour are connected to its fill.
+    #[serde(default)]
+    pub pad_connection: PadConnection,
+    /// The width of the gap between a pad and the fill around it, spanned by the spokes of
+    /// the thermal relief.
+    pub thermal_gap: f64,
+    pub thermal_spoke_width: f64,
+    pub thermal_spoke_count: usize,
+    /// The angle of the first spoke, in degrees counterclockwise from the X axis. The other
+    /// spokes follow it at equal angles.
+    #[serde(default)]
+    pub thermal_spoke_angle: f64,
+    /// Whether to leave out the parts of the fill that don't touch any copper of their net.
+    pub remove_islands: bool,
+}
+
+impl Default for PourOptions {
+    fn default() -> Self {
+        Self {
+            pad_connection: PadConnection::ThermalRelief,
+            thermal_gap: 500.0,
+            thermal_spoke_width: 500.0,
+            thermal_spoke_count: 4,
+            thermal_spoke_angle: 0.0,
+            remove_islands: true,
+        }
+    }
+}
+
+/// A primitive of another net that the fill of a pour comes closer to than their clearance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FillInfringement {
+    pub primitive: PrimitiveIndex,
+    /// The center of where the fill lies within the clearance.
+    pub pos: Point,
+    pub clearance: f64,
+}
+
+/// A copper pour: an outline on a layer that is filled with the copper of a net.
+///
+/// Like via keepouts, pours are not obstacles to bands, so [`Layout`] holds them aside from the
+/// drawing. Their fill is made anew around whatever else is on the layer each time they're
+/// filled, rather than kept up to date as the layout changes.
+#[derive(Debug, Clone)]
+pub struct Pour {
+    pub weight: PourPolyWeight,
+    pub outline: Polygon,
+    pub options: PourOptions,
+    /// The copper the outline was filled with the last time the pour was filled.
+    pub fill: MultiPolygon,
+}
+
+impl Pour {
+    pub fn new(weight: PourPolyWeight, outline: Polygon, options: PourOptions) -> Self {
+        Self {
+            weight,
+            outline,
+            options,
+            fill: MultiPolygon::new(vec![]),
+        }
+    }
+
+    /// Fills the outline, leaving out the copper of other nets together with their clearances
+    /// and, if the pads of the net are connected with thermal reliefs, the gaps around them.
+    pub fn make_fill(&self, layout: &Layout<impl AccessRules>) -> MultiPolygon {
+        let drawing = layout.drawing();
+        let layer = self.weight.layer;
+        let maybe_net = self.weight.maybe_net;
+        let thermal = self.options.pad_connection == PadConnection::ThermalRelief;
+
+        let Some(rect) = self.outline.bounding_rect() else {
+            return MultiPolygon::new(vec![]);
+        };
+        let conditions = Conditions {
+            maybe_net,
+            kind: ObjectKind::Wire,
+            maybe_region: None,
+            maybe_layer: Some(layer),
+        };
+        let margin = drawing
+            .rules()
+            .largest_clearance(maybe_net)
+            .max(self.options.thermal_gap);
+        let envelope = AABB::from_corners(
+            [rect.min().x - margin, rect.min().y - margin, layer as f64],
+            [rect.max().x + margin, rect.max().y + margin, layer as f64],
+        );
+        let is_of_net =
+            |node_maybe_net: Option<usize>| maybe_net.is_some() && node_maybe_net == maybe_net;
+
+        let mut cutouts: Vec<MultiPolygon> = vec![];
+        let mut reliefs: Vec<MultiPolygon> = vec![];
+        // The copper of the net, which the parts of the fill that aren't islands touch.
+        let mut connections: Vec<Polygon> = vec![];
+
+        for wrapper in drawing.rtree().locate_in_envelope_intersecting(&envelope) {
+            match wrapper.data {
+                GenericNode::Primitive(node) => {
+                    let primitive = node.primitive(drawing);
+                    let shape = primitive.shape();
+
+                    if primitive.object_kind() == ObjectKind::MeanderCore {
+                        continue;
+                    }
+
+                    if !is_of_net(primitive.maybe_net()) {
+                        let clearance = drawing
+                            .rules()
+                            .clearance(&conditions, &primitive.conditions());
+                        cutouts.push(shape_polygon(&shape, clearance).into());
+                        continue;
+                    }
+
+                    connections.push(shape_polygon(&shape, 0.0));
+
+                    // The pads outlined by polygons get their thermal reliefs as a whole below.
+                    if let PrimitiveIndex::FixedDot(dot) = node {
+                        if thermal
+                            && matches!(primitive.object_kind(), ObjectKind::Pin | ObjectKind::Smd)
+                            && layout.polys(dot).next().is_none()
+                        {
+                            reliefs.push(self.thermal_relief(
+                                shape_polygon(&shape, self.options.thermal_gap).into(),
+                                shape.center(),
+                            ));
+                        }
+                    }
+                }
+                GenericNode::Compound(compound) => {
+                    let CompoundWeight::Poly(..) = drawing.compound_weight(compound) else {
+                        continue;
+                    };
+                    let poly = GenericIndex::<PolyWeight>::new(compound.petgraph_index());
+                    let polygon = layout.poly(poly).shape().polygon;
+
+                    // The primitives outlining the polygon are cut out or connected to above,
+                    // so only what they enclose is left to be.
+                    if !is_of_net(layout.poly(poly).maybe_net()) {
+                        cutouts.push(polygon.into());
+                        continue;
+                    }
+
+                    connections.push(polygon.clone());
+
+                    if thermal
+                        && matches!(
+                            layout.poly(poly).object_kind(),
+                            ObjectKind::Pin | ObjectKind::Smd
+                        )
+                    {
+                        let Some(center) = polygon.centroid() else {
+                            continue;
+                        };
+                        let pad = union_all(
+                            layout
+                                .poly_members(poly)
+                                .map(|member| {
+                                    shape_polygon(
+                                        &member.primitive(drawing).shape(),
+                                        self.options.thermal_gap,
+                                    )
+                                    .into()
+                                })
+                                .chain(std::iter::once(polygon.into()))
+                                .collect(),
+                        );
+                        reliefs.push(self.thermal_relief(pad, center));
+                    }
+                }
+            }
+        }
+
+        let mut fill = difference(
+            &difference(
+                &MultiPolygon::new(vec![self.outline.clone()]),
+                &union_all(cutouts),
+            ),
+            &union_all(reliefs),
+        );
+
+        if self.options.remove_islands {
+            fill.0.retain(|polygon| {
+                connections
+                    .iter()
+                    .any(|connection| polygon.intersects(connection))
+            });
+        }
+
+        fill
+    }
+
+    /// Finds the primitives of other nets that the fill comes closer to than their clearance.
+    /// A fill made around the layout as it is now infringes none.
+    pub fn find_fill_infringements(
+        &self,
+        layout: &Layout<impl AccessRules>,
+    ) -> Vec<FillInfringement> {
+        let drawing = layout.drawing();
+        let layer = self.weight.layer;
+        let maybe_net = self.weight.maybe_net;
+
+        let Some(rect) = self.fill.bounding_rect() else {
+            return vec![];
+        };
+        let conditions = Conditions {
+            maybe_net,
+            kind: ObjectKind::Wire,
+            maybe_region: None,
+            maybe_layer: Some(layer),
+        };
+        let margin = drawing.rules().largest_clearance(maybe_net);
+        let envelope = AABB::from_corners(
+            [rect.min().x - margin, rect.min().y - margin, layer as f64],
+            [rect.max().x + margin, rect.max().y + margin, layer as f64],
+        );
+
+        drawing
+            .rtree()
+            .locate_in_envelope_intersecting(&envelope)
+            .filter_map(|wrapper| {
+                let GenericNode::Primitive(node) = wrapper.data else {
+                    return None;
+                };
+                let primitive = node.primitive(drawing);
+
+                if primitive.object_kind() == ObjectKind::MeanderCore
+                    || maybe_net.is_some() && primitive.maybe_net() == maybe_net
+                {
+                    return None;
+                }
+
+                let clearance = drawing
+                    .rules()
+                    .clearance(&conditions, &primitive.conditions());
+                let keepout = MultiPolygon::new(vec![shape_polygon(
+                    &primitive.shape(),
+                    (clearance - FILL_CLEARANCE_TOLERANCE).max(0.0),
+                )]);
+                let overlap = intersection(&self.fill, &keepout);
+
+                Some(FillInfringement {
+                    primitive: node,
+                    pos: overlap.centroid()?,
+                    clearance,
+                })
+            })
+            .collect()
+    }
+
+    /// The area to leave out of the fill around a pad connected to it with a thermal relief:
+    /// the pad grown by the thermal gap, less the spokes running from its center outward.
+    fn thermal_relief(&self, pad: MultiPolygon, center: Point) -> MultiPolygon {
+        let Some(rect) = pad.bounding_rect() else {
+            return pad;
+        };
+        let reach = [
+            rect.min(),
+            rect.max(),
+            (rect.min().x, rect.max().y).into(),
+            (rect.max().x, rect.min().y).into(),
+        ]
+        .into_iter()
+        .map(|corner: Coord| center.euclidean_distance(&Point::from(corner)))
+        .fold(0.0, f64::max)
+            + self.options.thermal_gap;
+        let halfwidth = self.options.thermal_spoke_width / 2.0;
+
+        let spokes: MultiPolygon = (0..self.options.thermal_spoke_count)
+            .map(|i| {
+                let angle = self.options.thermal_spoke_angle.to_radians()
+                    + i as f64 * TAU / self.options.thermal_spoke_count as f64;
+                let direction = point! {x: angle.cos(), y: angle.sin()};
+                let normal = point! {x: -direction.y(), y: direction.x()};
+                let end = center + direction * reach;
+
+                Polygon::new(
+                    LineString::from(vec![
+                        center - normal * halfwidth,
+                        end - normal * halfwidth,
+                        end + normal * halfwidth,
+                        center + normal * halfwidth,
+                    ]),
+                    vec![],
+                )
+            })
+            .collect::<Vec<Polygon>>()
+            .into();
+
+        difference(&pad, &spokes)
+    }
+}
+
+impl GetLayer for Pour {
+    fn layer(&self) -> usize {
+        self.weight.layer
+    }
+}
+
+impl GetMaybeNet for Pour {
+    fn maybe_net(&self) -> Option<usize> {
+        self.weight.maybe_net
+    }
+}
+
+/// Unites polygons pairwise, so that each union is of polygons of about the same size.
+fn union_all(mut polygons: Vec<MultiPolygon>) -> MultiPolygon {
+    while polygons.len() > 1 {
+        polygons = polygons
+            .chunks(2)
+            .map(|pair| match pair {
+                [polygon1, polygon2] => union(polygon1, polygon2),
+                [polygon] => polygon.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+
+    polygons.pop().unwrap_or_else(|| MultiPolygon::new(vec![]))
+}
+
+fn union(polygon1: &MultiPolygon, polygon2: &MultiPolygon) -> MultiPolygon {
+    snapped(&snapped(polygon1).union(&snapped(polygon2)))
+}
+
+fn difference(polygon1: &MultiPolygon, polygon2: &MultiPolygon) -> MultiPolygon {
+    snapped(&snapped(polygon1).difference(&snapped(polygon2)))
+}
+
+fn intersection(polygon1: &MultiPolygon, polygon2: &MultiPolygon) -> MultiPolygon {
+    snapped(&snapped(polygon1).intersection(&snapped(polygon2)))
+}
+
+fn snapped(polygon: &MultiPolygon) -> MultiPolygon {
+    polygon.map_coords(|coord| Coord {
+        x: (coord.x / SNAP_GRID).round() * SNAP_GRID,
+        y: (coord.y / SNAP_GRID).round() * SNAP_GRID,
+    })
+}
+
+/// A polygon covering the shape grown by the margin.
+fn shape_polygon(shape: &PrimitiveShape, margin: f64) -> Polygon {
+    match shape.inflate(margin) {
+        PrimitiveShape::Dot(dot) => circle_polygon(dot.circle),
+        PrimitiveShape::Seg(seg) => {
+            if seg.from == seg.to {
+                circle_polygon(Circle {
+                    pos: seg.from,
+                    r: seg.width / 2.0,
+                })
+            } else {
+                seg.polygon()
+            }
+        }
+        PrimitiveShape::Bend(bend) => {
+            let outer_circle = bend.outer_circle();
+            let inner_circle = Circle {
+                pos: bend.inner_circle.pos,
+                r: bend.inner_circle.r.max(0.0),
+            };
+            let angle_from = bend.start_angle();
+            let spanned_angle = bend.spanned_angle();
+            let mut points = arc_points(outer_circle, angle_from, spanned_angle, true);
+            points.extend(
+                arc_points(inner_circle, angle_from, spanned_angle, false)
+                    .into_iter()
+                    .rev(),
+            );
+
+            Polygon::new(LineString::from(points), vec![])
+        }
+    }
+}
+
+fn circle_polygon(circle: Circle) -> Polygon {
+    let mut points = arc_points(circle, 0.0, TAU, true);
+    points.pop();
+    Polygon::new(LineString::from(points), vec![])
+}
+
+/// Approximates an arc with a chain of points that deviates from it by at most
+/// [`ARC_MAX_DEVIATION`]. The chain is drawn either through points on the arc, running inside
+/// of it, or around it, touching it halfway between each two points.
+fn arc_points(circle: Circle, angle_from: f64, spanned_angle: f64, around: bool) -> Vec<Point> {
+    // The sagitta of a chord spanning `angle` is `r * (1 - cos(angle / 2))`.
+    let max_angle_step = (2.0 * (1.0 - ARC_MAX_DEVIATION / circle.r).clamp(-1.0, 1.0).acos())
+        .clamp(f64::EPSILON, PI / 4.0);
+    let step_count = ((spanned_angle / max_angle_step).ceil() as usize).max(1);
+    let angle_step = spanned_angle / step_count as f64;
+    let r = if around {
+        circle.r / (angle_step / 2.0).cos()
+    } else {
+        circle.r
+    };
+
+    (0..=step_count)
+        .map(|i| {
+            let angle = angle_from + i as f64 * angle_step;
+            circle.pos + point! {x: r * angle.cos(), y: r * angle.sin()}
+        })
+        .collect()
+}
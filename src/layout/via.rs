@@ -3,7 +3,12 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    drawing::{graph::GetMaybeNet, primitive::MakePrimitiveShape, rules::AccessRules},
+    drawing::{
+        dot::FixedDotIndex,
+        graph::{GetLayer, GetMaybeNet, PrimitiveIndex},
+        primitive::{GetWeight, MakePrimitiveShape},
+        rules::AccessRules,
+    },
     geometry::{
         compound::ManageCompounds,
         primitive::{DotShape, PrimitiveShape},
@@ -25,28 +30,52 @@ impl<'a, R: AccessRules> Via<'a, R> {
     }
 }
 
-impl<'a, R: AccessRules> GetMaybeNet for Via<'a, R> {
-    fn maybe_net(&self) -> Option<usize> {
+impl<'a, R: AccessRules> Via<'a, R> {
+    /// Finds the dot of the via on the given layer, if the via spans it.
+    pub fn layer_dot(&self, layer: usize) -> Option<FixedDotIndex> {
         self.layout
             .drawing()
-            .compound_weight(self.index.into())
-            .maybe_net()
+            .geometry()
+            .compound_members(self.index.into())
+            .find_map(|member| match member {
+                PrimitiveIndex::FixedDot(dot)
+                    if self.layout.drawing().primitive(dot).layer() == layer =>
+                {
+                    Some(dot)
+                }
+                _ => None,
+            })
     }
 }
 
-impl<'a, R: AccessRules> MakePrimitiveShape for Via<'a, R> {
-    fn shape(&self) -> PrimitiveShape {
+impl<'a, R: AccessRules> GetWeight<ViaWeight> for Via<'a, R> {
+    fn weight(&self) -> ViaWeight {
         if let CompoundWeight::Via(weight) =
             self.layout.drawing().compound_weight(self.index.into())
         {
-            weight.shape()
+            weight
         } else {
             unreachable!();
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+impl<'a, R: AccessRules> GetMaybeNet for Via<'a, R> {
+    fn maybe_net(&self) -> Option<usize> {
+        self.layout
+            .drawing()
+            .compound_weight(self.index.into())
+            .maybe_net()
+    }
+}
+
+impl<'a, R: AccessRules> MakePrimitiveShape for Via<'a, R> {
+    fn shape(&self) -> PrimitiveShape {
+        self.weight().shape()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct ViaWeight {
     pub from_layer: usize,
     pub to_layer: usize,
@@ -13,6 +13,7 @@ pub mod autorouter;
 pub mod board;
 pub mod geometry;
 pub mod interactor;
+pub mod kicad;
 pub mod layout;
 pub mod math;
 pub mod router;
@@ -18,6 +18,8 @@ use std::cmp::Ordering;
 
 use crate::stepper::Step;
 
+use super::navcorder::NavcorderException;
+
 #[derive(Copy, Clone, Debug)]
 pub struct MinScored<K, T>(pub K, pub T);
 
@@ -106,7 +108,12 @@ where
     for<'a> &'a G: IntoEdges<NodeId = G::NodeId, EdgeId = G::EdgeId> + MakeEdgeRef,
     K: Measure + Copy,
 {
-    fn is_goal(&mut self, graph: &G, node: G::NodeId, tracker: &PathTracker<G>) -> Option<R>;
+    fn is_goal(
+        &mut self,
+        graph: &G,
+        node: G::NodeId,
+        tracker: &PathTracker<G>,
+    ) -> Result<Option<R>, AstarError>;
     fn place_probe<'a>(
         &mut self,
         graph: &'a G,
@@ -150,6 +157,8 @@ pub enum AstarContinueStatus {
 pub enum AstarError {
     #[error("A* search found no path")]
     NotFound,
+    #[error("could not redraw band along found path: {0}")]
+    CouldNotReworkPath(#[from] NavcorderException),
 }
 
 impl<G, K> Astar<G, K>
@@ -206,6 +215,10 @@ where
                 let edge = (&self.graph).edge_ref(edge_id);
 
                 if let Some(edge_cost) = strategy.place_probe(&self.graph, edge) {
+                    // The probe has to be removed before the next one is placed even if it
+                    // doesn't lead anywhere new.
+                    self.is_probing = true;
+
                     let next = edge.target();
                     let next_score = node_score + edge_cost;
 
@@ -228,7 +241,6 @@ where
                         next_score + strategy.estimate_cost(&self.graph, next);
                     self.visit_next.push(MinScored(next_estimate_score, next));
 
-                    self.is_probing = true;
                     return Ok(ControlFlow::Continue(AstarContinueStatus::Probing));
                 }
 
@@ -242,7 +254,7 @@ where
             return Err(AstarError::NotFound);
         };
 
-        if let Some(result) = strategy.is_goal(&self.graph, node, &self.path_tracker) {
+        if let Some(result) = strategy.is_goal(&self.graph, node, &self.path_tracker)? {
             let path = self.path_tracker.reconstruct_path_to(node);
             let cost = self.scores[&node];
             return Ok(ControlFlow::Break((cost, path, result)));
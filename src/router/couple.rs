@@ -0,0 +1,90 @@
+//! Draws the band of one net of a differential pair alongside the band already routed for the
+//! other net, so that the two run in parallel, kept apart by the clearance between the nets of
+//! the pair, which is their gap.
+//!
+//! The band being drawn follows the path of the band it's coupled with: wherever that band
+//! turns, the new one wraps around the outside of its bend or, if it runs on the inner side of
+//! the turn, around whatever the bend itself wraps around, thus slipping under the bend.
+
+use crate::{
+    drawing::{
+        band::{BandTermsegIndex, BandUid},
+        bend::BendIndex,
+        dot::FixedDotIndex,
+        gear::GearIndex,
+        graph::MakePrimitive,
+        head::{BareHead, Head},
+        primitive::MakePrimitiveShape,
+        rules::AccessRules,
+    },
+    geometry::shape::AccessShape,
+    graph::MakeRef,
+    layout::Layout,
+};
+
+use super::draw::{Draw, DrawException};
+
+pub struct Coupler<'a, R: AccessRules> {
+    layout: &'a mut Layout<R>,
+}
+
+impl<'a, R: AccessRules> Coupler<'a, R> {
+    pub fn new(layout: &'a mut Layout<R>) -> Self {
+        Self { layout }
+    }
+
+    /// Draws a band of the given width from the source to the target along the band it's
+    /// coupled with, whose end next to the source is given.
+    ///
+    /// On failure, the layout is left part way through and has to be reverted by the caller.
+    pub fn couple(
+        &mut self,
+        leader: BandTermsegIndex,
+        leader_source: FixedDotIndex,
+        source: FixedDotIndex,
+        target: FixedDotIndex,
+        width: f64,
+    ) -> Result<BandUid, DrawException> {
+        let drawing = self.layout.drawing();
+        let mut path = leader.ref_(drawing).path();
+
+        if path.source != leader_source {
+            path = path.reversed();
+        }
+
+        // Which side of the leader the coupled band runs on is told by where its source lies
+        // relative to the first seg of the leader.
+        let first_leg = path.legs[0];
+        let offset = source.primitive(drawing).shape().center() - first_leg.start_point();
+        let is_on_left = first_leg.dx() * offset.y() - first_leg.dy() * offset.x() > 0.0;
+
+        let mut draw = Draw::new(self.layout);
+        let mut head: Head = BareHead { face: source }.into();
+        let mut maybe_first_seg = None;
+
+        for (bend, (gear, cw)) in path.bends.iter().zip(path.wraps.iter().copied()) {
+            // The gear of a clockwise bend lies on the left of the leader, so the coupled band
+            // runs outside of the bend if it's on the right, and inside of it otherwise.
+            let cane_head = if cw != is_on_left {
+                draw.cane_around_bend(head, BendIndex::Loose(*bend), cw, width)?
+            } else {
+                match gear {
+                    GearIndex::FixedDot(dot) => draw.cane_around_dot(head, dot, cw, width)?,
+                    GearIndex::FixedBend(bend) => {
+                        draw.cane_around_bend(head, BendIndex::Fixed(bend), cw, width)?
+                    }
+                    GearIndex::LooseBend(bend) => {
+                        draw.cane_around_bend(head, BendIndex::Loose(bend), cw, width)?
+                    }
+                }
+            };
+
+            maybe_first_seg.get_or_insert(BandTermsegIndex::Bended(cane_head.cane.seg));
+            head = cane_head.into();
+        }
+
+        let termseg = draw.finish_in_dot(head, target, width)?;
+
+        Ok(BandUid::new(maybe_first_seg.unwrap_or(termseg), termseg))
+    }
+}
@@ -8,12 +8,13 @@ use crate::{
         bend::{BendIndex, LooseBendWeight},
         dot::{DotIndex, FixedDotIndex, LooseDotIndex, LooseDotWeight},
         gear::GearIndex,
-        graph::{GetLayer, GetMaybeNet, MakePrimitive},
+        graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex},
         guide::Guide,
         head::{CaneHead, GetFace, Head},
         primitive::GetOtherJoint,
         rules::AccessRules,
-        seg::{LoneLooseSegWeight, SeqLooseSegWeight},
+        seg::{LoneLooseSegIndex, LoneLooseSegWeight, SeqLooseSegWeight},
+        shove::Shove,
         DrawingException, Infringement,
     },
     layout::Layout,
@@ -29,15 +30,27 @@ pub enum DrawException {
     CannotFinishIn(FixedDotIndex, #[source] DrawingException),
     #[error("cannot wrap around {0:?}")]
     CannotWrapAround(GearIndex, #[source] DrawingException),
+    #[error("cannot shove {1:?} to wrap around {0:?}")]
+    CannotShoveLoneSeg(GearIndex, LoneLooseSegIndex),
 }
 
 pub struct Draw<'a, R: AccessRules> {
     layout: &'a mut Layout<R>,
+    shove_depth: usize,
 }
 
 impl<'a, R: AccessRules> Draw<'a, R> {
     pub fn new(layout: &'a mut Layout<R>) -> Self {
-        Self { layout }
+        Self::with_shove_depth(layout, 0)
+    }
+
+    /// Makes a drawer whose canes shove up to `shove_depth` loose bands of other nets out of
+    /// their way, each further outward than the one before.
+    pub fn with_shove_depth(layout: &'a mut Layout<R>, shove_depth: usize) -> Self {
+        Self {
+            layout,
+            shove_depth,
+        }
     }
 
     pub fn start(&mut self, from: LooseDotIndex) -> Head {
@@ -101,6 +114,30 @@ impl<'a, R: AccessRules> Draw<'a, R> {
         cw: bool,
         width: f64,
     ) -> Result<CaneHead, DrawException> {
+        let shove_depth = std::mem::replace(&mut self.shove_depth, 0);
+        let result = self.shove_cane_around_dot(head, around, cw, width);
+        self.shove_depth = shove_depth;
+        result.map(|(head, _)| head)
+    }
+
+    /// Draws a cane around a dot like [`Draw::cane_around_dot`], but if the cane would infringe
+    /// on the loose band of another net, that band is shoved out of the way by wrapping it around
+    /// the dot over the cane, as many times as the shove depth allows.
+    ///
+    /// Bands made of a lone seg can't be shoved, as wrapping them would change their
+    /// terminating seg, so one in the way makes the cane fail with
+    /// [`DrawException::CannotShoveLoneSeg`].
+    ///
+    /// Returns the shoves made, which must be undone with [`Draw::unshove`] after the cane is.
+    #[debug_ensures(ret.is_ok() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count()) + 4 + 4 * ret.as_ref().map_or(0, |(_, shoves)| shoves.len()))]
+    #[debug_ensures(ret.is_err() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count()))]
+    pub fn shove_cane_around_dot(
+        &mut self,
+        head: Head,
+        around: FixedDotIndex,
+        cw: bool,
+        width: f64,
+    ) -> Result<(CaneHead, Vec<Shove>), DrawException> {
         let tangent = self
             .guide()
             .head_around_dot_segment(&head, around.into(), cw, width)?;
@@ -116,7 +153,7 @@ impl<'a, R: AccessRules> Draw<'a, R> {
             width,
             offset,
         )
-        .map_err(|err| DrawException::CannotWrapAround(around.into(), err))
+        .map_err(|err| self.wrap_around_exception(around.into(), err))
     }
 
     #[debug_ensures(ret.is_ok() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count() + 4))]
@@ -128,6 +165,23 @@ impl<'a, R: AccessRules> Draw<'a, R> {
         cw: bool,
         width: f64,
     ) -> Result<CaneHead, DrawException> {
+        let shove_depth = std::mem::replace(&mut self.shove_depth, 0);
+        let result = self.shove_cane_around_bend(head, around, cw, width);
+        self.shove_depth = shove_depth;
+        result.map(|(head, _)| head)
+    }
+
+    /// Draws a cane around a bend like [`Draw::cane_around_bend`], shoving the loose bands of
+    /// other nets in its way as [`Draw::shove_cane_around_dot`] does.
+    #[debug_ensures(ret.is_ok() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count()) + 4 + 4 * ret.as_ref().map_or(0, |(_, shoves)| shoves.len()))]
+    #[debug_ensures(ret.is_err() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count()))]
+    pub fn shove_cane_around_bend(
+        &mut self,
+        head: Head,
+        around: BendIndex,
+        cw: bool,
+        width: f64,
+    ) -> Result<(CaneHead, Vec<Shove>), DrawException> {
         let tangent = self
             .guide()
             .head_around_bend_segment(&head, around, cw, width)?;
@@ -142,10 +196,10 @@ impl<'a, R: AccessRules> Draw<'a, R> {
             width,
             offset,
         )
-        .map_err(|err| DrawException::CannotWrapAround(around.into(), err))
+        .map_err(|err| self.wrap_around_exception(around.into(), err))
     }
 
-    #[debug_ensures(ret.is_ok() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count() + 4))]
+    #[debug_ensures(ret.is_ok() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count()) + 4 + 4 * ret.as_ref().map_or(0, |(_, shoves)| shoves.len()))]
     #[debug_ensures(ret.is_err() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count()))]
     fn cane_around(
         &mut self,
@@ -156,11 +210,23 @@ impl<'a, R: AccessRules> Draw<'a, R> {
         cw: bool,
         width: f64,
         offset: f64,
-    ) -> Result<CaneHead, DrawingException> {
+    ) -> Result<(CaneHead, Vec<Shove>), DrawingException> {
         let head = self.extend_head(head, from)?;
         self.cane(head, around, to, cw, width, offset)
     }
 
+    /// Tells a band that can't be shoved from any other obstacle, if shoving was allowed.
+    fn wrap_around_exception(&self, around: GearIndex, err: DrawingException) -> DrawException {
+        match err {
+            DrawingException::Infringement(Infringement(.., PrimitiveIndex::LoneLooseSeg(seg)))
+                if self.shove_depth > 0 =>
+            {
+                DrawException::CannotShoveLoneSeg(around, seg)
+            }
+            _ => DrawException::CannotWrapAround(around, err),
+        }
+    }
+
     #[debug_ensures(self.layout.drawing().node_count() == old(self.layout.drawing().node_count()))]
     fn extend_head(&mut self, head: Head, to: Point) -> Result<Head, Infringement> {
         if let Head::Cane(head) = head {
@@ -171,7 +237,7 @@ impl<'a, R: AccessRules> Draw<'a, R> {
         }
     }
 
-    #[debug_ensures(ret.is_ok() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count() + 4))]
+    #[debug_ensures(ret.is_ok() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count()) + 4 + 4 * ret.as_ref().map_or(0, |(_, shoves)| shoves.len()))]
     #[debug_ensures(ret.is_err() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count()))]
     fn cane(
         &mut self,
@@ -181,41 +247,94 @@ impl<'a, R: AccessRules> Draw<'a, R> {
         cw: bool,
         width: f64,
         offset: f64,
-    ) -> Result<CaneHead, DrawingException> {
+    ) -> Result<(CaneHead, Vec<Shove>), DrawingException> {
         let layer = head.face().primitive(self.layout.drawing()).layer();
         let maybe_net = head.face().primitive(self.layout.drawing()).maybe_net();
-        let cane = self.layout.insert_cane(
-            head.face(),
-            around,
-            LooseDotWeight {
-                circle: Circle {
-                    pos: to,
-                    r: width / 2.0,
+        let mut shoves = vec![];
+        let mut maybe_first_err = None;
+
+        loop {
+            let result = self.layout.insert_cane(
+                head.face(),
+                around,
+                LooseDotWeight {
+                    circle: Circle {
+                        pos: to,
+                        r: width / 2.0,
+                    },
+                    layer,
+                    maybe_net,
                 },
-                layer,
-                maybe_net,
-            },
-            SeqLooseSegWeight {
-                width,
-                layer,
-                maybe_net,
-            },
-            LooseBendWeight {
-                width,
-                offset,
-                layer,
-                maybe_net,
-            },
-            cw,
-        )?;
-        Ok(CaneHead {
-            face: self
-                .layout
-                .drawing()
-                .primitive(cane.bend)
-                .other_joint(cane.dot),
-            cane,
-        })
+                SeqLooseSegWeight {
+                    width,
+                    layer,
+                    maybe_net,
+                },
+                LooseBendWeight {
+                    width,
+                    offset,
+                    layer,
+                    maybe_net,
+                },
+                cw,
+            );
+
+            match result {
+                Ok(cane) => {
+                    let head = CaneHead {
+                        face: self
+                            .layout
+                            .drawing()
+                            .primitive(cane.bend)
+                            .other_joint(cane.dot),
+                        cane,
+                    };
+                    return Ok((head, shoves));
+                }
+                Err(err) => {
+                    // The band in the way is wrapped around the gear, so that the cane can be
+                    // inserted under it, pushing it outward.
+                    if shoves.len() < self.shove_depth {
+                        if let Some(shove) = self.shove(err, around) {
+                            maybe_first_err.get_or_insert(err);
+                            shoves.push(shove);
+                            continue;
+                        }
+                    }
+
+                    self.unshove(&shoves);
+                    // What got in the way after shoving is gone now, so the original obstacle
+                    // is reported.
+                    return Err(maybe_first_err.unwrap_or(err));
+                }
+            }
+        }
+    }
+
+    /// Shoves the band that was infringed on, if it can be shoved.
+    ///
+    /// Only seq loose segs are shoved, as a band made of a lone seg would have to change its
+    /// terminating seg to be wrapped around anything.
+    fn shove(&mut self, err: DrawingException, around: GearIndex) -> Option<Shove> {
+        let DrawingException::Infringement(Infringement(.., infringee)) = err else {
+            return None;
+        };
+
+        let seg = match infringee {
+            PrimitiveIndex::SeqLooseSeg(seg) => seg,
+            PrimitiveIndex::LooseDot(dot) => self.layout.drawing().primitive(dot).seg()?,
+            _ => return None,
+        };
+
+        self.layout.shove_seg(seg, around).ok()
+    }
+
+    /// Straightens the shoved bands back, in the reverse order they were shoved in.
+    #[debug_ensures(self.layout.drawing().node_count() == old(self.layout.drawing().node_count()) - 4 * shoves.len())]
+    pub fn unshove(&mut self, shoves: &[Shove]) {
+        for shove in shoves.iter().rev() {
+            self.layout.unshove(shove);
+        }
     }
 
     #[debug_ensures(ret.is_some() -> self.layout.drawing().node_count() == old(self.layout.drawing().node_count() - 4))]
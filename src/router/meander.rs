@@ -0,0 +1,178 @@
+//! Draws meanders into bands to make them longer, as is needed to match the lengths of the
+//! bands of a bus.
+//!
+//! A meander is a serpentine laid along the longest straight seg of a band, redrawn around
+//! cores placed alternately on either side of the seg, which stay in the layout along with it.
+
+use geo::{point, EuclideanLength, Point};
+use thiserror::Error;
+
+use crate::{
+    drawing::{
+        band::{BandPath, BandTermsegIndex, BandUid},
+        bend::BendIndex,
+        dot::{FixedDotIndex, FixedDotWeight},
+        gear::GearIndex,
+        graph::{GetLayer, GetMaybeNet},
+        head::{BareHead, Head},
+        rules::{AccessRules, ObjectKind},
+        DrawingException, Infringement,
+    },
+    graph::MakeRef,
+    layout::{meander::MeanderWeight, Layout},
+    math::Circle,
+};
+
+use super::draw::{Draw, DrawException};
+
+#[derive(Error, Debug, Clone, Copy)]
+pub enum MeanderException {
+    #[error(transparent)]
+    CannotRemoveBand(#[from] DrawingException),
+    #[error(transparent)]
+    CannotPlaceCore(#[from] Infringement),
+    #[error(transparent)]
+    CannotDraw(#[from] DrawException),
+    #[error("no room for a meander of {0} cores")]
+    NoRoom(usize),
+}
+
+/// The shape of a meander.
+#[derive(Debug, Clone, Copy)]
+pub struct Meander {
+    /// The number of cores the band winds around, which is one more than the number of times
+    /// it crosses the seg the meander is laid along.
+    pub core_count: usize,
+    /// The distance of the cores from the seg. The band is left straight at the negated radius
+    /// of its bends around the cores, and runs further out the larger the amplitude is.
+    pub amplitude: f64,
+}
+
+pub struct Meanderer<'a, R: AccessRules> {
+    layout: &'a mut Layout<R>,
+}
+
+impl<'a, R: AccessRules> Meanderer<'a, R> {
+    pub fn new(layout: &'a mut Layout<R>) -> Self {
+        Self { layout }
+    }
+
+    /// The fixed dots the band joins, from its source to its target.
+    pub fn band_endpoints(&self, band: BandTermsegIndex) -> (FixedDotIndex, FixedDotIndex) {
+        let path = self.band_path(band);
+        (path.source, path.target)
+    }
+
+    /// The radius of the bends of the band around the cores of its meander, which is also half
+    /// the distance between successive cores along the seg.
+    pub fn bend_radius(&self, band: BandTermsegIndex) -> f64 {
+        let path = self.band_path(band);
+        let maybe_net = self.layout.drawing().primitive(path.source).maybe_net();
+
+        path.width / 2.0 + self.layout.rules().largest_clearance(maybe_net)
+    }
+
+    /// The most cores a meander in the band can have, as many as fit along its longest seg.
+    pub fn max_core_count(&self, band: BandTermsegIndex) -> usize {
+        let path = self.band_path(band);
+        let pitch = 2.0 * self.bend_radius(band);
+        let leg_length = path.legs[Self::longest_leg(&path)].euclidean_length();
+
+        ((leg_length / pitch).floor() as usize).saturating_sub(1)
+    }
+
+    /// Removes the band and draws it anew along the same path, but with the given meander laid
+    /// along its longest straight seg.
+    ///
+    /// On failure, the layout is left part way through and has to be reverted by the caller.
+    pub fn redraw_with_meander(
+        &mut self,
+        band: BandTermsegIndex,
+        meander: Meander,
+    ) -> Result<BandUid, MeanderException> {
+        if meander.core_count == 0 || meander.core_count > self.max_core_count(band) {
+            return Err(MeanderException::NoRoom(meander.core_count));
+        }
+
+        let path = self.band_path(band);
+        let radius = self.bend_radius(band);
+        let leg_index = Self::longest_leg(&path);
+        let leg = path.legs[leg_index];
+        let layer = self.layout.drawing().primitive(path.source).layer();
+
+        // The cores of meanders laid into the band before are wrapped around anew.
+        self.layout.remove_band_keeping_meanders(band)?;
+
+        let length = leg.euclidean_length();
+        let direction = (leg.end_point() - leg.start_point()) / length;
+        let left = point! {x: -direction.y(), y: direction.x()};
+        let midpoint = (leg.start_point() + leg.end_point()) / 2.0;
+        let compound = self.layout.add_meander(MeanderWeight { layer });
+        let mut cores = vec![];
+
+        for i in 0..meander.core_count {
+            let along = (i as f64 - (meander.core_count - 1) as f64 / 2.0) * 2.0 * radius;
+            // Cores on the left of the band are wrapped around counterclockwise and the ones on
+            // the right clockwise, so that the band runs beyond each of them.
+            let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+            let pos: Point = midpoint + direction * along + left * (side * meander.amplitude);
+
+            let core = self.layout.add_meander_core(
+                compound,
+                FixedDotWeight {
+                    circle: Circle { pos, r: 0.0 },
+                    layer,
+                    // Bands can't wrap around anything of their own net.
+                    maybe_net: None,
+                    kind: ObjectKind::MeanderCore,
+                },
+            )?;
+            cores.push((GearIndex::FixedDot(core), side < 0.0));
+        }
+
+        let mut wraps = path.wraps.clone();
+        wraps.splice(leg_index..leg_index, cores);
+
+        let mut draw = Draw::new(self.layout);
+        let mut head: Head = BareHead { face: path.source }.into();
+        let mut maybe_first_seg = None;
+
+        for (gear, cw) in wraps {
+            let cane_head = match gear {
+                GearIndex::FixedDot(dot) => draw.cane_around_dot(head, dot, cw, path.width)?,
+                GearIndex::FixedBend(bend) => {
+                    draw.cane_around_bend(head, BendIndex::Fixed(bend), cw, path.width)?
+                }
+                GearIndex::LooseBend(bend) => {
+                    draw.cane_around_bend(head, BendIndex::Loose(bend), cw, path.width)?
+                }
+            };
+
+            maybe_first_seg.get_or_insert(cane_head.cane.seg);
+            head = cane_head.into();
+        }
+
+        let termseg = draw.finish_in_dot(head, path.target, path.width)?;
+
+        // There's at least one core, so the band always starts with a seg wrapping around one.
+        Ok(BandUid::new(
+            BandTermsegIndex::Bended(maybe_first_seg.unwrap()),
+            termseg,
+        ))
+    }
+
+    fn longest_leg(path: &BandPath) -> usize {
+        path.legs
+            .iter()
+            .enumerate()
+            .max_by(|(_, leg1), (_, leg2)| {
+                leg1.euclidean_length().total_cmp(&leg2.euclidean_length())
+            })
+            .map(|(i, _)| i)
+            .unwrap()
+    }
+
+    fn band_path(&self, band: BandTermsegIndex) -> BandPath {
+        band.ref_(self.layout.drawing()).path()
+    }
+}
@@ -1,9 +1,12 @@
 pub mod astar;
+pub mod couple;
 pub mod draw;
+pub mod meander;
 pub mod navcord;
 pub mod navcorder;
 pub mod navmesh;
 pub mod route;
 pub mod router;
+pub mod trace;
 
 pub use router::*;
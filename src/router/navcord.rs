@@ -1,25 +1,55 @@
 use contracts_try::debug_ensures;
 use petgraph::data::DataMap;
 
-use crate::drawing::{
-    bend::LooseBendIndex,
-    dot::FixedDotIndex,
-    graph::PrimitiveIndex,
-    head::{BareHead, CaneHead, Head},
-    rules::AccessRules,
+use crate::{
+    drawing::{
+        band::BandTermsegIndex,
+        bend::LooseBendIndex,
+        dot::FixedDotIndex,
+        graph::{GetMaybeNet, MakePrimitive},
+        head::{BareHead, CaneHead, GetFace, Head},
+        rules::AccessRules,
+        shove::Shove,
+    },
+    graph::GenericIndex,
+    layout::via::ViaWeight,
+    math::Circle,
 };
 
 use super::{
     draw::Draw,
     navcorder::{Navcorder, NavcorderException},
-    navmesh::{BinavvertexNodeIndex, Navmesh, NavvertexIndex},
+    navmesh::{BinavvertexNodeIndex, Navmesh, NavmeshVias, NavvertexIndex, NavvertexNode, ViaSite},
 };
 
+/// What a single step of a navcord did, recorded so that the step can be undone.
+#[derive(Debug, Clone)]
+pub enum NavcordStep {
+    /// The navcord wrapped around a navvertex with a cane, shoving the given bands out of its
+    /// way.
+    Cane { shoves: Vec<Shove> },
+    /// The navcord placed a via and finished the band drawn so far in it.
+    EnterVia {
+        termseg: BandTermsegIndex,
+        via: GenericIndex<ViaWeight>,
+        prev_head: Head,
+    },
+    /// The navcord moved to another layer of the via it's in, to start a new band there.
+    SwitchLayer {
+        via: GenericIndex<ViaWeight>,
+        prev_head: Head,
+    },
+}
+
 #[derive(Debug)]
 pub struct NavcordStepper {
     pub path: Vec<NavvertexIndex>,
+    pub steps: Vec<NavcordStep>,
     pub head: Head,
     pub width: f64,
+    pub vias: NavmeshVias,
+    /// How many bands each cane of the navcord may shove out of its way.
+    pub shove_depth: usize,
 }
 
 impl NavcordStepper {
@@ -27,14 +57,34 @@ impl NavcordStepper {
         source: FixedDotIndex,
         source_navvertex: NavvertexIndex,
         width: f64,
+        vias: NavmeshVias,
+        shove_depth: usize,
     ) -> NavcordStepper {
         Self {
             path: vec![source_navvertex],
+            steps: vec![],
             head: BareHead { face: source }.into(),
             width,
+            vias,
+            shove_depth,
         }
     }
 
+    /// Whether the navcord has just entered a via, in which case it can only move on by going
+    /// to another layer of that via.
+    pub fn is_in_via(&self) -> bool {
+        matches!(self.steps.last(), Some(NavcordStep::EnterVia { .. }))
+    }
+
+    /// Iterates over the vias placed by the navcord together with the terminating segs of the
+    /// bands that were finished in them.
+    pub fn vias(&self) -> impl Iterator<Item = (BandTermsegIndex, GenericIndex<ViaWeight>)> + '_ {
+        self.steps.iter().filter_map(|step| match step {
+            NavcordStep::EnterVia { termseg, via, .. } => Some((*termseg, *via)),
+            _ => None,
+        })
+    }
+
     fn wrap(
         &mut self,
         navcorder: &mut Navcorder<impl AccessRules>,
@@ -42,19 +92,20 @@ impl NavcordStepper {
         head: Head,
         around: NavvertexIndex,
         width: f64,
-    ) -> Result<CaneHead, NavcorderException> {
+    ) -> Result<(CaneHead, Vec<Shove>), NavcorderException> {
         let cw = self
             .maybe_cw(navmesh, around)
             .ok_or(NavcorderException::CannotWrap)?;
 
         match self.binavvertex(navmesh, around) {
-            BinavvertexNodeIndex::FixedDot(dot) => {
+            Some(BinavvertexNodeIndex::FixedDot(dot)) => {
                 self.wrap_around_fixed_dot(navcorder, head, dot, cw, width)
             }
-            BinavvertexNodeIndex::FixedBend(_fixed_bend) => todo!(),
-            BinavvertexNodeIndex::LooseBend(loose_bend) => {
+            Some(BinavvertexNodeIndex::FixedBend(_fixed_bend)) => todo!(),
+            Some(BinavvertexNodeIndex::LooseBend(loose_bend)) => {
                 self.wrap_around_loose_bend(navcorder, head, loose_bend, cw, width)
             }
+            None => Err(NavcorderException::CannotWrap),
         }
     }
 
@@ -65,8 +116,9 @@ impl NavcordStepper {
         around: FixedDotIndex,
         cw: bool,
         width: f64,
-    ) -> Result<CaneHead, NavcorderException> {
-        Ok(Draw::new(navcorder.layout).cane_around_dot(head, around, cw, width)?)
+    ) -> Result<(CaneHead, Vec<Shove>), NavcorderException> {
+        Ok(Draw::with_shove_depth(navcorder.layout, self.shove_depth)
+            .shove_cane_around_dot(head, around, cw, width)?)
     }
 
     fn wrap_around_loose_bend(
@@ -76,16 +128,90 @@ impl NavcordStepper {
         around: LooseBendIndex,
         cw: bool,
         width: f64,
-    ) -> Result<CaneHead, NavcorderException> {
-        Ok(Draw::new(navcorder.layout).cane_around_bend(head, around.into(), cw, width)?)
+    ) -> Result<(CaneHead, Vec<Shove>), NavcorderException> {
+        Ok(
+            Draw::with_shove_depth(navcorder.layout, self.shove_depth).shove_cane_around_bend(
+                head,
+                around.into(),
+                cw,
+                width,
+            )?,
+        )
     }
 
-    fn binavvertex(&self, navmesh: &Navmesh, navvertex: NavvertexIndex) -> BinavvertexNodeIndex {
-        navmesh.node_weight(navvertex).unwrap().node
+    fn enter_via(
+        &mut self,
+        navcorder: &mut Navcorder<impl AccessRules>,
+        via_site: ViaSite,
+        width: f64,
+    ) -> Result<NavcordStep, NavcorderException> {
+        let maybe_net = self
+            .head
+            .face()
+            .primitive(navcorder.layout.drawing())
+            .maybe_net();
+        let via = navcorder.layout.add_via(ViaWeight {
+            from_layer: self.vias.layers.0,
+            to_layer: self.vias.layers.1,
+            circle: Circle {
+                pos: via_site.pos,
+                r: self.vias.radius,
+            },
+            maybe_net,
+        })?;
+        let dot = navcorder.layout.via(via).layer_dot(via_site.layer).unwrap();
+
+        match Draw::new(navcorder.layout).finish_in_dot(self.head, dot, width) {
+            Ok(termseg) => {
+                let prev_head = self.head;
+                self.head = BareHead { face: dot }.into();
+
+                Ok(NavcordStep::EnterVia {
+                    termseg,
+                    via,
+                    prev_head,
+                })
+            }
+            Err(err) => {
+                navcorder.layout.remove_via(via);
+                Err(err.into())
+            }
+        }
     }
 
-    fn primitive(&self, navmesh: &Navmesh, navvertex: NavvertexIndex) -> PrimitiveIndex {
-        self.binavvertex(navmesh, navvertex).into()
+    fn switch_layer(
+        &mut self,
+        navcorder: &mut Navcorder<impl AccessRules>,
+        via: GenericIndex<ViaWeight>,
+        via_site: ViaSite,
+    ) -> Result<NavcordStep, NavcorderException> {
+        let dot = navcorder
+            .layout
+            .via(via)
+            .layer_dot(via_site.layer)
+            .ok_or(NavcorderException::CannotWrap)?;
+        let prev_head = self.head;
+        self.head = BareHead { face: dot }.into();
+
+        Ok(NavcordStep::SwitchLayer { via, prev_head })
+    }
+
+    fn binavvertex(
+        &self,
+        navmesh: &Navmesh,
+        navvertex: NavvertexIndex,
+    ) -> Option<BinavvertexNodeIndex> {
+        match navmesh.node_weight(navvertex).unwrap().node {
+            NavvertexNode::Binavvertex(binavvertex) => Some(binavvertex),
+            NavvertexNode::ViaSite(..) => None,
+        }
+    }
+
+    fn via_site(&self, navmesh: &Navmesh, navvertex: NavvertexIndex) -> Option<ViaSite> {
+        match navmesh.node_weight(navvertex).unwrap().node {
+            NavvertexNode::Binavvertex(..) => None,
+            NavvertexNode::ViaSite(via_site) => Some(via_site),
+        }
     }
 
     fn maybe_cw(&self, navmesh: &Navmesh, navvertex: NavvertexIndex) -> Option<bool> {
@@ -101,23 +227,45 @@ pub struct NavcordStepContext<'a: 'b, 'b, R: AccessRules> {
 }
 
 impl NavcordStepper {
-    #[debug_ensures(ret.is_ok() -> matches!(self.head, Head::Cane(..)))]
     #[debug_ensures(ret.is_ok() -> self.path.len() == old(self.path.len() + 1))]
     #[debug_ensures(ret.is_err() -> self.path.len() == old(self.path.len()))]
+    #[debug_ensures(self.steps.len() + 1 == self.path.len())]
     pub fn step<'a, 'b, R: AccessRules>(
         &mut self,
         input: &mut NavcordStepContext<'a, 'b, R>,
     ) -> Result<(), NavcorderException> {
-        self.head = self
-            .wrap(
-                input.navcorder,
-                input.navmesh,
-                self.head,
-                input.to,
-                input.width,
-            )?
-            .into();
+        let from = *self.path.last().unwrap();
+        let maybe_from_via_site = self.via_site(input.navmesh, from);
+
+        let step = match (self.via_site(input.navmesh, input.to), self.steps.last()) {
+            // Moving between the via sites of the same via means changing layers.
+            (
+                Some(to_via_site),
+                Some(NavcordStep::EnterVia { via, .. } | NavcordStep::SwitchLayer { via, .. }),
+            ) if maybe_from_via_site.is_some_and(|from| from.site == to_via_site.site) => {
+                let via = *via;
+                self.switch_layer(input.navcorder, via, to_via_site)?
+            }
+            // A via is useless if the navcord leaves it on the layer it entered it on.
+            (_, Some(NavcordStep::EnterVia { .. })) => {
+                return Err(NavcorderException::CannotLeaveVia)
+            }
+            (Some(to_via_site), _) => self.enter_via(input.navcorder, to_via_site, input.width)?,
+            (None, _) => {
+                let (head, shoves) = self.wrap(
+                    input.navcorder,
+                    input.navmesh,
+                    self.head,
+                    input.to,
+                    input.width,
+                )?;
+                self.head = head.into();
+                NavcordStep::Cane { shoves }
+            }
+        };
+
         self.path.push(input.to);
+        self.steps.push(step);
 
         Ok(())
     }
@@ -127,10 +275,29 @@ impl NavcordStepper {
         &mut self,
         navcorder: &mut Navcorder<'a, R>,
     ) -> Result<(), NavcorderException> {
-        if let Head::Cane(head) = self.head {
-            self.head = Draw::new(navcorder.layout).undo_cane(head).unwrap();
-        } else {
-            panic!();
+        match self.steps.pop() {
+            Some(NavcordStep::Cane { shoves }) => {
+                if let Head::Cane(head) = self.head {
+                    let mut draw = Draw::new(navcorder.layout);
+                    self.head = draw.undo_cane(head).unwrap();
+                    draw.unshove(&shoves);
+                } else {
+                    panic!();
+                }
+            }
+            Some(NavcordStep::EnterVia {
+                termseg,
+                via,
+                prev_head,
+            }) => {
+                navcorder.layout.remove_termseg(termseg);
+                navcorder.layout.remove_via(via);
+                self.head = prev_head;
+            }
+            Some(NavcordStep::SwitchLayer { prev_head, .. }) => {
+                self.head = prev_head;
+            }
+            None => panic!(),
         }
 
         self.path.pop();
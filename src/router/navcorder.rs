@@ -3,7 +3,7 @@ use thiserror::Error;
 
 use crate::{
     drawing::{band::BandTermsegIndex, dot::FixedDotIndex, rules::AccessRules},
-    layout::Layout,
+    layout::{Layout, LayoutException},
 };
 
 use super::{
@@ -18,6 +18,10 @@ pub enum NavcorderException {
     CannotDraw(#[from] DrawException),
     #[error("cannot wrap")]
     CannotWrap,
+    #[error(transparent)]
+    CannotPlaceVia(#[from] LayoutException),
+    #[error("cannot leave a via on the layer it was entered on")]
+    CannotLeaveVia,
 }
 
 #[derive(Debug)]
@@ -30,13 +34,14 @@ impl<'a, R: AccessRules> Navcorder<'a, R> {
         Navcorder { layout }
     }
 
-    pub fn start(
-        &mut self,
-        source: FixedDotIndex,
-        source_navvertex: NavvertexIndex,
-        width: f64,
-    ) -> NavcordStepper {
-        NavcordStepper::new(source, source_navvertex, width)
+    pub fn start(&mut self, navmesh: &Navmesh, width: f64, shove_depth: usize) -> NavcordStepper {
+        NavcordStepper::new(
+            navmesh.origin(),
+            navmesh.origin_navvertex(),
+            width,
+            navmesh.vias(),
+            shove_depth,
+        )
     }
 
     pub fn finish(
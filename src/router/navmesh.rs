@@ -11,6 +11,7 @@ use petgraph::{
         IntoNodeIdentifiers, NodeIndexable,
     },
 };
+use rstar::AABB;
 use spade::{HasPosition, InsertionError, Point2};
 use thiserror::Error;
 
@@ -21,12 +22,17 @@ use crate::{
         gear::{GearIndex, GetNextGear},
         graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex},
         primitive::{MakePrimitiveShape, Primitive},
-        rules::AccessRules,
+        rules::{AccessRules, Conditions, GetConditions, GetObjectKind, ObjectKind},
         Drawing,
     },
-    geometry::shape::AccessShape,
+    geometry::{
+        primitive::{AccessPrimitiveShape, DotShape, PrimitiveShape},
+        shape::AccessShape,
+        GenericNode,
+    },
     graph::{GetPetgraphIndex, MakeRef},
     layout::Layout,
+    math::Circle,
     router::astar::MakeEdgeRef,
     triangulation::{GetTrianvertexNodeIndex, Triangulation},
 };
@@ -105,9 +111,48 @@ impl HasPosition for TrianvertexWeight {
     }
 }
 
+/// A candidate via position on one layer.
+///
+/// Each via site has a navvertex on every layer, linked to the vertices of the triangle of the
+/// layer's triangulation it lies in and to the navvertices of the same site on the other layers,
+/// so that a path may change layers through it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViaSite {
+    /// Number of the via site, shared by its navvertices on all layers.
+    pub site: usize,
+    pub pos: Point,
+    pub layer: usize,
+}
+
+/// The vias through which the paths in a navmesh change layers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NavmeshVias {
+    pub radius: f64,
+    /// The first and the last layer spanned by the vias.
+    pub layers: (usize, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NavvertexNode {
+    Binavvertex(BinavvertexNodeIndex),
+    ViaSite(ViaSite),
+}
+
+impl NavvertexNode {
+    pub fn pos(&self, drawing: &Drawing<impl Copy, impl AccessRules>) -> Point {
+        match self {
+            Self::Binavvertex(binavvertex) => PrimitiveIndex::from(*binavvertex)
+                .primitive(drawing)
+                .shape()
+                .center(),
+            Self::ViaSite(via_site) => via_site.pos,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NavvertexWeight {
-    pub node: BinavvertexNodeIndex,
+    pub node: NavvertexNode,
     pub maybe_cw: Option<bool>,
 }
 
@@ -124,6 +169,7 @@ pub struct Navmesh {
     origin_navvertex: NavvertexIndex,
     destination: FixedDotIndex,
     destination_navvertex: NavvertexIndex,
+    vias: NavmeshVias,
 }
 
 impl Navmesh {
@@ -131,47 +177,193 @@ impl Navmesh {
         layout: &Layout<impl AccessRules>,
         origin: FixedDotIndex,
         destination: FixedDotIndex,
+        width: f64,
         options: RouterOptions,
     ) -> Result<Self, NavmeshError> {
+        let origin_layer = layout.drawing().primitive(origin).layer();
+        let destination_layer = layout.drawing().primitive(destination).layer();
+        let maybe_net = layout.drawing().primitive(origin).maybe_net();
+
+        // Without a via diameter in the rules, vias are made twice as wide as the band, and
+        // without a via padstack they span all layers.
+        let vias = NavmeshVias {
+            radius: maybe_net
+                .and_then(|net| layout.rules().maybe_via_diameter(net))
+                .map_or(width, |diameter| diameter / 2.0),
+            layers: maybe_net
+                .and_then(|net| layout.rules().maybe_via_layers(net))
+                .unwrap_or((0, layout.drawing().layer_count() - 1)),
+        };
+
+        let mut layers = vec![origin_layer];
+
+        if destination_layer != origin_layer {
+            layers.push(destination_layer);
+        }
+
+        // Vias can only be used if they reach both the layer of the origin and the one of the
+        // destination.
+        let via_span = vias.layers.0..=vias.layers.1;
+        let places_vias = options.via_cost.is_some()
+            && vias.layers.0 < vias.layers.1
+            && via_span.contains(&origin_layer)
+            && via_span.contains(&destination_layer);
+
+        if places_vias {
+            layers = via_span.collect();
+        }
+
+        let triangulations = layers
+            .iter()
+            .map(|layer| {
+                Ok((
+                    *layer,
+                    Self::triangulate_layer(layout, *layer, origin, destination)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, NavmeshError>>()?;
+
+        // Vias may be put at the centers of the triangles between the obstacles on the layers
+        // of the origin and of the destination, but only where they keep their clearance from
+        // the obstacles on all layers they span, which rules out most centers in crowded areas.
+        let mut via_sites: Vec<Point> = vec![];
+
+        if places_vias {
+            for (layer, triangulation) in triangulations.iter() {
+                if *layer != origin_layer && *layer != destination_layer {
+                    continue;
+                }
+
+                for center in triangulation.triangle_centers() {
+                    if !via_sites.contains(&center)
+                        && Self::via_fits(layout, center, vias, maybe_net)
+                    {
+                        via_sites.push(center);
+                    }
+                }
+            }
+        }
+
+        Self::new_from_triangulations(
+            layout,
+            triangulations,
+            &via_sites,
+            vias,
+            origin,
+            destination,
+            options,
+        )
+    }
+
+    /// Whether a via of the given net put at the given position keeps its clearance from the
+    /// obstacles to it on the layers it spans.
+    fn via_fits(
+        layout: &Layout<impl AccessRules>,
+        pos: Point,
+        vias: NavmeshVias,
+        maybe_net: Option<usize>,
+    ) -> bool {
+        let via_shape = PrimitiveShape::Dot(DotShape {
+            circle: Circle {
+                pos,
+                r: vias.radius,
+            },
+        });
+        let envelope =
+            via_shape.envelope_3d(layout.rules().largest_clearance(maybe_net), vias.layers.0);
+        let envelope = AABB::from_corners(
+            envelope.lower(),
+            [
+                envelope.upper()[0],
+                envelope.upper()[1],
+                vias.layers.1 as f64,
+            ],
+        );
+
+        layout
+            .drawing()
+            .rtree()
+            .locate_in_envelope_intersecting(&envelope)
+            .all(|wrapper| {
+                let GenericNode::Primitive(node) = wrapper.data else {
+                    return true;
+                };
+                let primitive = node.primitive(layout.drawing());
+                let is_obstacle = match (maybe_net, primitive.maybe_net()) {
+                    (Some(net), Some(primitive_net)) => net != primitive_net,
+                    (_, None) => primitive.object_kind().keeps_out(ObjectKind::Via),
+                    (None, Some(..)) => true,
+                };
+
+                if !is_obstacle {
+                    return true;
+                }
+
+                let clearance = layout.rules().clearance(
+                    &Conditions {
+                        maybe_net,
+                        kind: ObjectKind::Via,
+                        maybe_region: layout.rules().maybe_region(primitive.layer(), &via_shape),
+                        maybe_layer: Some(primitive.layer()),
+                    },
+                    &primitive.conditions(),
+                );
+
+                !primitive.shape().intersects(&via_shape.inflate(clearance))
+            })
+    }
+
+    fn triangulate_layer(
+        layout: &Layout<impl AccessRules>,
+        layer: usize,
+        origin: FixedDotIndex,
+        destination: FixedDotIndex,
+    ) -> Result<Triangulation<TrianvertexNodeIndex, TrianvertexWeight, ()>, NavmeshError> {
         let mut triangulation: Triangulation<TrianvertexNodeIndex, TrianvertexWeight, ()> =
             Triangulation::new(layout.drawing().geometry().graph().node_bound());
 
-        let layer = layout.drawing().primitive(origin).layer();
         let maybe_net = layout.drawing().primitive(origin).maybe_net();
 
         for node in layout.drawing().layer_primitive_nodes(layer) {
             let primitive = node.primitive(layout.drawing());
 
-            if let Some(primitive_net) = primitive.maybe_net() {
-                if node == origin.into()
-                    || node == destination.into()
-                    || Some(primitive_net) != maybe_net
-                {
-                    match node {
-                        PrimitiveIndex::FixedDot(dot) => {
-                            triangulation.add_vertex(TrianvertexWeight {
-                                node: dot.into(),
-                                pos: primitive.shape().center(),
-                            })?;
-                        }
-                        PrimitiveIndex::FixedBend(bend) => {
-                            triangulation.add_vertex(TrianvertexWeight {
-                                node: bend.into(),
-                                pos: primitive.shape().center(),
-                            })?;
-                        }
-                        _ => (),
+            // The board boundary and keepouts are obstacles to every net.
+            if node == origin.into()
+                || node == destination.into()
+                || primitive
+                    .maybe_net()
+                    .is_some_and(|primitive_net| Some(primitive_net) != maybe_net)
+                || primitive.object_kind().keeps_out(ObjectKind::Wire)
+            {
+                match node {
+                    PrimitiveIndex::FixedDot(dot) => {
+                        triangulation.add_vertex(TrianvertexWeight {
+                            node: dot.into(),
+                            pos: primitive.shape().center(),
+                        })?;
+                    }
+                    PrimitiveIndex::FixedBend(bend) => {
+                        triangulation.add_vertex(TrianvertexWeight {
+                            node: bend.into(),
+                            pos: primitive.shape().center(),
+                        })?;
                     }
+                    _ => (),
                 }
             }
         }
 
-        Self::new_from_triangulation(layout, triangulation, origin, destination, options)
+        Ok(triangulation)
     }
 
-    fn new_from_triangulation(
+    fn new_from_triangulations(
         layout: &Layout<impl AccessRules>,
-        triangulation: Triangulation<TrianvertexNodeIndex, TrianvertexWeight, ()>,
+        triangulations: Vec<(
+            usize,
+            Triangulation<TrianvertexNodeIndex, TrianvertexWeight, ()>,
+        )>,
+        via_sites: &[Point],
+        vias: NavmeshVias,
         origin: FixedDotIndex,
         destination: FixedDotIndex,
         options: RouterOptions,
@@ -180,83 +372,117 @@ impl Navmesh {
         let mut origin_navvertex = None;
         let mut destination_navvertex = None;
 
-        // `HashMap` is obviously suboptimal here.
-        let mut map = HashMap::new();
+        // Navvertices of each via site, one per layer.
+        let mut via_site_navvertices: Vec<Vec<NodeIndex<usize>>> = vec![vec![]; via_sites.len()];
 
-        for trianvertex in triangulation.node_identifiers() {
-            if trianvertex == origin.into() {
-                let navvertex = graph.add_node(NavvertexWeight {
-                    node: trianvertex.into(),
-                    maybe_cw: None,
-                });
+        for (layer, triangulation) in triangulations.iter() {
+            // `HashMap` is obviously suboptimal here.
+            let mut map = HashMap::new();
 
-                origin_navvertex = Some(navvertex);
-                map.insert(trianvertex, vec![(navvertex, navvertex)]);
-            } else if trianvertex == destination.into() {
-                let navvertex = graph.add_node(NavvertexWeight {
-                    node: trianvertex.into(),
-                    maybe_cw: None,
-                });
+            for trianvertex in triangulation.node_identifiers() {
+                let binavvertex = BinavvertexNodeIndex::from(trianvertex);
+
+                if trianvertex == origin.into() {
+                    let navvertex = graph.add_node(NavvertexWeight {
+                        node: NavvertexNode::Binavvertex(binavvertex),
+                        maybe_cw: None,
+                    });
+
+                    origin_navvertex = Some(navvertex);
+                    map.insert(trianvertex, vec![(navvertex, navvertex)]);
+                } else if trianvertex == destination.into() {
+                    let navvertex = graph.add_node(NavvertexWeight {
+                        node: NavvertexNode::Binavvertex(binavvertex),
+                        maybe_cw: None,
+                    });
 
-                destination_navvertex = Some(navvertex);
-                map.insert(trianvertex, vec![(navvertex, navvertex)]);
-            } else {
-                map.insert(trianvertex, vec![]);
-
-                let mut gear =
-                    Into::<GearIndex>::into(Into::<BinavvertexNodeIndex>::into(trianvertex));
-
-                if options.squeeze_through_under_bands {
-                    Self::add_node_to_graph_and_map_as_binavvertex(
-                        &mut graph,
-                        &mut map,
-                        trianvertex,
-                        trianvertex.into(),
-                    );
-
-                    if options.wrap_around_bands {
-                        while let Some(bend) = gear.ref_(layout.drawing()).next_gear() {
-                            Self::add_node_to_graph_and_map_as_binavvertex(
-                                &mut graph,
-                                &mut map,
-                                trianvertex,
-                                bend.into(),
-                            );
+                    destination_navvertex = Some(navvertex);
+                    map.insert(trianvertex, vec![(navvertex, navvertex)]);
+                } else {
+                    map.insert(trianvertex, vec![]);
+
+                    let mut gear = Into::<GearIndex>::into(binavvertex);
+
+                    if options.squeeze_through_under_bands {
+                        Self::add_node_to_graph_and_map_as_binavvertex(
+                            &mut graph,
+                            &mut map,
+                            trianvertex,
+                            binavvertex,
+                        );
+
+                        if options.wrap_around_bands {
+                            while let Some(bend) = gear.ref_(layout.drawing()).next_gear() {
+                                Self::add_node_to_graph_and_map_as_binavvertex(
+                                    &mut graph,
+                                    &mut map,
+                                    trianvertex,
+                                    bend.into(),
+                                );
+                                gear = bend.into();
+                            }
+                        }
+                    } else if let Some(first_bend) = gear.ref_(layout.drawing()).next_gear() {
+                        let mut bend = first_bend;
+
+                        while let Some(next_bend) = gear.ref_(layout.drawing()).next_gear() {
+                            bend = next_bend;
                             gear = bend.into();
                         }
+
+                        Self::add_node_to_graph_and_map_as_binavvertex(
+                            &mut graph,
+                            &mut map,
+                            trianvertex,
+                            bend.into(),
+                        );
+                    } else {
+                        Self::add_node_to_graph_and_map_as_binavvertex(
+                            &mut graph,
+                            &mut map,
+                            trianvertex,
+                            binavvertex,
+                        );
                     }
-                } else if let Some(first_bend) = gear.ref_(layout.drawing()).next_gear() {
-                    let mut bend = first_bend;
+                };
+            }
 
-                    while let Some(next_bend) = gear.ref_(layout.drawing()).next_gear() {
-                        bend = next_bend;
-                        gear = bend.into();
+            for edge in triangulation.edge_references() {
+                for (from_navvertex1, from_navvertex2) in map[&edge.source()].iter() {
+                    for (to_navvertex1, to_navvertex2) in map[&edge.target()].iter() {
+                        graph.update_edge(*from_navvertex1, *to_navvertex1, ());
+                        graph.update_edge(*from_navvertex1, *to_navvertex2, ());
+                        graph.update_edge(*from_navvertex2, *to_navvertex1, ());
+                        graph.update_edge(*from_navvertex2, *to_navvertex2, ());
                     }
+                }
+            }
 
-                    Self::add_node_to_graph_and_map_as_binavvertex(
-                        &mut graph,
-                        &mut map,
-                        trianvertex,
-                        bend.into(),
-                    );
-                } else {
-                    Self::add_node_to_graph_and_map_as_binavvertex(
-                        &mut graph,
-                        &mut map,
-                        trianvertex,
-                        trianvertex.into(),
-                    );
+            for (site, pos) in via_sites.iter().enumerate() {
+                let navvertex = graph.add_node(NavvertexWeight {
+                    node: NavvertexNode::ViaSite(ViaSite {
+                        site,
+                        pos: *pos,
+                        layer: *layer,
+                    }),
+                    maybe_cw: None,
+                });
+                via_site_navvertices[site].push(navvertex);
+
+                for trianvertex in triangulation.locate_vertices(*pos) {
+                    for (navvertex1, navvertex2) in map[&trianvertex].iter() {
+                        graph.update_edge(navvertex, *navvertex1, ());
+                        graph.update_edge(navvertex, *navvertex2, ());
+                    }
                 }
-            };
+            }
         }
 
-        for edge in triangulation.edge_references() {
-            for (from_navvertex1, from_navvertex2) in map[&edge.source()].iter() {
-                for (to_navvertex1, to_navvertex2) in map[&edge.target()].iter() {
-                    graph.update_edge(*from_navvertex1, *to_navvertex1, ());
-                    graph.update_edge(*from_navvertex1, *to_navvertex2, ());
-                    graph.update_edge(*from_navvertex2, *to_navvertex1, ());
-                    graph.update_edge(*from_navvertex2, *to_navvertex2, ());
+        // Link every layer of a via site to every other.
+        for navvertices in via_site_navvertices.iter() {
+            for (i, from_navvertex) in navvertices.iter().enumerate() {
+                for to_navvertex in navvertices[i + 1..].iter() {
+                    graph.update_edge(*from_navvertex, *to_navvertex, ());
                 }
             }
         }
@@ -267,6 +493,7 @@ impl Navmesh {
             origin_navvertex: NavvertexIndex(origin_navvertex.unwrap()),
             destination,
             destination_navvertex: NavvertexIndex(destination_navvertex.unwrap()),
+            vias,
         })
     }
 
@@ -277,12 +504,12 @@ impl Navmesh {
         node: BinavvertexNodeIndex,
     ) {
         let navvertex1 = graph.add_node(NavvertexWeight {
-            node,
+            node: NavvertexNode::Binavvertex(node),
             maybe_cw: Some(false),
         });
 
         let navvertex2 = graph.add_node(NavvertexWeight {
-            node,
+            node: NavvertexNode::Binavvertex(node),
             maybe_cw: Some(true),
         });
 
@@ -310,6 +537,10 @@ impl Navmesh {
     pub fn destination_navvertex(&self) -> NavvertexIndex {
         self.destination_navvertex
     }
+
+    pub fn vias(&self) -> NavmeshVias {
+        self.vias
+    }
 }
 
 impl GraphBase for Navmesh {
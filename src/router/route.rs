@@ -18,6 +18,7 @@ use crate::{
 pub struct RouteStepper {
     astar: Astar<Navmesh, f64>,
     navcord: NavcordStepper,
+    via_cost: f64,
     ghosts: Vec<PrimitiveShape>,
     obstacles: Vec<PrimitiveIndex>,
 }
@@ -29,7 +30,7 @@ impl RouteStepper {
         to: FixedDotIndex,
         width: f64,
     ) -> Result<Self, NavmeshError> {
-        let navmesh = Navmesh::new(router.layout(), from, to, router.options().clone())?;
+        let navmesh = Navmesh::new(router.layout(), from, to, width, *router.options())?;
         Ok(Self::new_from_navmesh(router, navmesh, width))
     }
 
@@ -38,14 +39,15 @@ impl RouteStepper {
         navmesh: Navmesh,
         width: f64,
     ) -> Self {
-        let source = navmesh.origin();
         let source_navvertex = navmesh.origin_navvertex();
         let target = navmesh.destination();
+        let via_cost = router.options().via_cost.unwrap_or(0.0);
+        let shove_depth = router.options().shove_depth;
 
         let mut navcorder = Navcorder::new(router.layout_mut());
-        let mut navcord = navcorder.start(source, source_navvertex, width);
+        let mut navcord = navcorder.start(&navmesh, width, shove_depth);
 
-        let mut strategy = RouterAstarStrategy::new(navcorder, &mut navcord, target);
+        let mut strategy = RouterAstarStrategy::new(navcorder, &mut navcord, target, via_cost);
         let astar = Astar::new(navmesh, source_navvertex, &mut strategy);
         let ghosts = vec![];
         let obstacles = vec![];
@@ -53,6 +55,7 @@ impl RouteStepper {
         Self {
             astar,
             navcord,
+            via_cost,
             ghosts,
             obstacles,
         }
@@ -73,6 +76,13 @@ impl RouteStepper {
     pub fn obstacles(&self) -> &[PrimitiveIndex] {
         &self.obstacles
     }
+
+    /// Steps the navcord back to the origin, removing everything drawn so far by the route.
+    pub fn abandon(&mut self, router: &mut Router<impl AccessRules>) {
+        let mut navcorder = Navcorder::new(router.layout_mut());
+        let step_count = self.navcord.path.len() - 1;
+        navcorder.undo_path(&mut self.navcord, step_count);
+    }
 }
 
 impl<'a, R: AccessRules> Step<Router<'a, R>, BandTermsegIndex> for RouteStepper {
@@ -84,7 +94,8 @@ impl<'a, R: AccessRules> Step<Router<'a, R>, BandTermsegIndex> for RouteStepper
     ) -> Result<ControlFlow<BandTermsegIndex>, AstarError> {
         let navcorder = Navcorder::new(router.layout_mut());
         let target = self.astar.graph.destination();
-        let mut strategy = RouterAstarStrategy::new(navcorder, &mut self.navcord, target);
+        let mut strategy =
+            RouterAstarStrategy::new(navcorder, &mut self.navcord, target, self.via_cost);
 
         let result = match self.astar.step(&mut strategy)? {
             ControlFlow::Continue(..) => Ok(ControlFlow::Continue(())),
@@ -7,7 +7,7 @@ use crate::{
     drawing::{
         band::BandTermsegIndex,
         dot::{DotIndex, FixedDotIndex},
-        graph::{MakePrimitive, PrimitiveIndex},
+        graph::{GetLayer, MakePrimitive, PrimitiveIndex},
         head::GetFace,
         primitive::MakePrimitiveShape,
         rules::AccessRules,
@@ -22,11 +22,11 @@ use crate::{
 };
 
 use super::{
-    astar::{AstarStrategy, PathTracker},
+    astar::{AstarError, AstarStrategy, PathTracker},
     draw::DrawException,
-    navcord::{NavcordStepContext, NavcordStepper},
+    navcord::{NavcordStep, NavcordStepContext, NavcordStepper},
     navcorder::{Navcorder, NavcorderException},
-    navmesh::{Navmesh, NavmeshEdgeReference, NavmeshError, NavvertexIndex},
+    navmesh::{Navmesh, NavmeshEdgeReference, NavmeshError, NavvertexIndex, NavvertexNode},
     route::RouteStepper,
 };
 
@@ -35,6 +35,18 @@ pub struct RouterOptions {
     pub routed_band_width: f64,
     pub wrap_around_bands: bool,
     pub squeeze_through_under_bands: bool,
+    /// The cost of a via, in the units of band length, added to a route for every layer change.
+    /// If `None`, no vias are placed, so bands are routed only on the layer of their origin.
+    #[serde(default)]
+    pub via_cost: Option<f64>,
+    /// How many loose bands of other nets a cane may shove out of its way, wrapping each around
+    /// the gear it's drawn around. If `0`, bands in the way make the cane fail.
+    ///
+    /// Only bands that already bend somewhere can be shoved. A band that runs straight from pin
+    /// to pin, being a single lone seg, is never shoved, as wrapping it would turn it into a
+    /// different band. The cane then fails as blocked by that band.
+    #[serde(default)]
+    pub shove_depth: usize,
 }
 
 #[derive(Debug)]
@@ -42,6 +54,7 @@ pub struct RouterAstarStrategy<'a, R: AccessRules> {
     pub navcorder: Navcorder<'a, R>,
     pub navcord: &'a mut NavcordStepper,
     pub target: FixedDotIndex,
+    pub via_cost: f64,
     pub probe_ghosts: Vec<PrimitiveShape>,
     pub probe_obstacles: Vec<PrimitiveIndex>,
 }
@@ -51,11 +64,13 @@ impl<'a, R: AccessRules> RouterAstarStrategy<'a, R> {
         navcorder: Navcorder<'a, R>,
         navcord: &'a mut NavcordStepper,
         target: FixedDotIndex,
+        via_cost: f64,
     ) -> Self {
         Self {
             navcorder,
             navcord,
             target,
+            via_cost,
             probe_ghosts: vec![],
             probe_obstacles: vec![],
         }
@@ -88,25 +103,55 @@ impl<'a, R: AccessRules> AstarStrategy<Navmesh, f64, BandTermsegIndex>
         navmesh: &Navmesh,
         vertex: NavvertexIndex,
         tracker: &PathTracker<Navmesh>,
-    ) -> Option<BandTermsegIndex> {
+    ) -> Result<Option<BandTermsegIndex>, AstarError> {
         let new_path = tracker.reconstruct_path_to(vertex);
         let width = self.navcord.width;
 
         self.navcorder
-            .rework_path(navmesh, self.navcord, &new_path[..], width)
-            .unwrap();
+            .rework_path(navmesh, self.navcord, &new_path[..], width)?;
 
-        self.navcorder
+        // The band can only be finished on the layer of the target, and not right after entering
+        // a via, which would then be left on the layer it was entered on.
+        let head_layer = self
+            .navcord
+            .head
+            .face()
+            .primitive(self.navcorder.layout.drawing())
+            .layer();
+
+        if self.navcord.is_in_via()
+            || head_layer
+                != self
+                    .navcorder
+                    .layout
+                    .drawing()
+                    .primitive(self.target)
+                    .layer()
+        {
+            return Ok(None);
+        }
+
+        Ok(self
+            .navcorder
             .finish(navmesh, self.navcord, self.target, width)
-            .ok()
+            .ok())
     }
 
     fn place_probe(&mut self, navmesh: &Navmesh, edge: NavmeshEdgeReference) -> Option<f64> {
-        if edge.target().petgraph_index() == self.target.petgraph_index() {
+        if edge.target().petgraph_index() == self.target.petgraph_index()
+            || self.navcord.path.last() != Some(&edge.source())
+        {
             return None;
         }
 
         let prev_bihead_length = self.bihead_length();
+        let prev_head_pos = self
+            .navcord
+            .head
+            .face()
+            .primitive(self.navcorder.layout.drawing())
+            .shape()
+            .center();
 
         let width = self.navcord.width;
         let result = self.navcord.step(&mut NavcordStepContext {
@@ -116,16 +161,33 @@ impl<'a, R: AccessRules> AstarStrategy<Navmesh, f64, BandTermsegIndex>
             width,
         });
 
-        let probe_length = self.bihead_length() - prev_bihead_length;
-
         match result {
-            Ok(..) => Some(probe_length),
+            // Entering a via finishes the band drawn so far and changing layers starts a new
+            // one, so the length of the bihead can't be used to measure these steps.
+            Ok(..) => match self.navcord.steps.last() {
+                Some(NavcordStep::EnterVia { .. }) => {
+                    let NavvertexNode::ViaSite(via_site) =
+                        navmesh.node_weight(edge.target()).unwrap().node
+                    else {
+                        unreachable!();
+                    };
+
+                    Some(prev_head_pos.euclidean_distance(&via_site.pos))
+                }
+                Some(NavcordStep::SwitchLayer { .. }) => Some(self.via_cost),
+                _ => Some(self.bihead_length() - prev_bihead_length),
+            },
             Err(err) => {
                 if let NavcorderException::CannotDraw(draw_err) = err {
                     let layout_err = match draw_err {
                         DrawException::NoTangents(..) => return None,
                         DrawException::CannotFinishIn(.., layout_err) => layout_err,
                         DrawException::CannotWrapAround(.., layout_err) => layout_err,
+                        DrawException::CannotShoveLoneSeg(.., seg) => {
+                            self.probe_ghosts = vec![];
+                            self.probe_obstacles = vec![seg.into()];
+                            return None;
+                        }
                     };
 
                     let (ghost, obstacle) = match layout_err {
@@ -152,10 +214,11 @@ impl<'a, R: AccessRules> AstarStrategy<Navmesh, f64, BandTermsegIndex>
     }
 
     fn estimate_cost(&mut self, navmesh: &Navmesh, vertex: NavvertexIndex) -> f64 {
-        let start_point = PrimitiveIndex::from(navmesh.node_weight(vertex).unwrap().node)
-            .primitive(self.navcorder.layout.drawing())
-            .shape()
-            .center();
+        let start_point = navmesh
+            .node_weight(vertex)
+            .unwrap()
+            .node
+            .pos(self.navcorder.layout.drawing());
         let end_point = self
             .navcorder
             .layout
@@ -0,0 +1,178 @@
+//! Draws a band along a given polyline, as is done to bring the wires read from design files
+//! into the layout as loose bands.
+//!
+//! A band can only turn by wrapping around something, so the polyline only tells which gears
+//! the band wraps around and in which direction, while the band itself is drawn taut. At every
+//! corner of the polyline, the band wraps around the gears that lie between the corner and the
+//! straight line that would cut it off, going from one to the next along their convex hull. A
+//! corner with nothing under it is cut off.
+
+use geo::Point;
+
+use crate::{
+    drawing::{
+        band::{BandTermsegIndex, BandUid},
+        bend::BendIndex,
+        dot::FixedDotIndex,
+        gear::GearIndex,
+        graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex},
+        head::{BareHead, Head},
+        primitive::MakePrimitiveShape,
+        rules::AccessRules,
+    },
+    geometry::shape::AccessShape,
+    layout::Layout,
+    math::{cross_product, dot_product},
+};
+
+use super::draw::{Draw, DrawException};
+
+pub struct Tracer<'a, R: AccessRules> {
+    layout: &'a mut Layout<R>,
+}
+
+impl<'a, R: AccessRules> Tracer<'a, R> {
+    pub fn new(layout: &'a mut Layout<R>) -> Self {
+        Self { layout }
+    }
+
+    /// Draws a band of the given width from the source to the target, turning where the given
+    /// polyline turns. The polyline starts at the source and ends at the target.
+    ///
+    /// On failure, the layout is left part way through and has to be reverted by the caller.
+    pub fn trace(
+        &mut self,
+        source: FixedDotIndex,
+        target: FixedDotIndex,
+        polyline: &[Point],
+        width: f64,
+    ) -> Result<BandUid, DrawException> {
+        let wraps = self.wraps(source, polyline);
+
+        let mut draw = Draw::new(self.layout);
+        let mut head: Head = BareHead { face: source }.into();
+        let mut maybe_first_seg = None;
+
+        for (gear, cw) in wraps {
+            let cane_head = match gear {
+                GearIndex::FixedDot(dot) => draw.cane_around_dot(head, dot, cw, width)?,
+                GearIndex::FixedBend(bend) => {
+                    draw.cane_around_bend(head, BendIndex::Fixed(bend), cw, width)?
+                }
+                GearIndex::LooseBend(bend) => {
+                    draw.cane_around_bend(head, BendIndex::Loose(bend), cw, width)?
+                }
+            };
+
+            maybe_first_seg.get_or_insert(BandTermsegIndex::Bended(cane_head.cane.seg));
+            head = cane_head.into();
+        }
+
+        let termseg = draw.finish_in_dot(head, target, width)?;
+
+        Ok(BandUid::new(maybe_first_seg.unwrap_or(termseg), termseg))
+    }
+
+    /// The gears the band wraps around, in order, each with whether it's wrapped clockwise.
+    fn wraps(&self, source: FixedDotIndex, polyline: &[Point]) -> Vec<(GearIndex, bool)> {
+        let drawing = self.layout.drawing();
+        let maybe_net = drawing.primitive(source).maybe_net();
+
+        // Bands can't wrap around anything of their own net.
+        let gears: Vec<(GearIndex, Point)> = drawing
+            .layer_primitive_nodes(drawing.primitive(source).layer())
+            .filter(|node| maybe_net.is_none() || node.primitive(drawing).maybe_net() != maybe_net)
+            .filter_map(|node| {
+                let gear = match node {
+                    PrimitiveIndex::FixedDot(dot) => GearIndex::FixedDot(dot),
+                    PrimitiveIndex::FixedBend(bend) => GearIndex::FixedBend(bend),
+                    _ => return None,
+                };
+                Some((gear, node.primitive(drawing).shape().center()))
+            })
+            .collect();
+
+        let mut wraps: Vec<(GearIndex, bool)> = vec![];
+
+        for corner in polyline.windows(3) {
+            let (from, at, to) = (corner[0], corner[1], corner[2]);
+            let turn = cross_product(at - from, to - at);
+
+            if turn == 0.0 {
+                continue;
+            }
+
+            // Gears are wrapped clockwise when they lie on the left of the band.
+            let cw = turn > 0.0;
+            let under: Vec<(GearIndex, Point)> = gears
+                .iter()
+                .copied()
+                .filter(|(_, pos)| {
+                    cross_product(at - from, *pos - from) * turn > 0.0
+                        && cross_product(to - at, *pos - at) * turn > 0.0
+                        && cross_product(from - to, *pos - to) * turn > 0.0
+                })
+                .collect();
+
+            for gear in Self::hull_chain(from, to, at, under) {
+                if wraps.last() != Some(&(gear, cw)) {
+                    wraps.push((gear, cw));
+                }
+            }
+        }
+
+        wraps
+    }
+
+    /// Orders the gears lying under a corner along the taut path from one side of the corner
+    /// to the other, leaving out those the path doesn't touch.
+    fn hull_chain(
+        from: Point,
+        to: Point,
+        corner: Point,
+        gears: Vec<(GearIndex, Point)>,
+    ) -> Vec<GearIndex> {
+        let chord = to - from;
+        // The side of the chord the corner lies on, so that the gears under it are measured
+        // upward from the chord.
+        let side = cross_product(chord, corner - from).signum();
+
+        let mut points: Vec<(f64, f64, Option<GearIndex>)> = gears
+            .into_iter()
+            .map(|(gear, pos)| {
+                (
+                    dot_product(chord, pos - from),
+                    side * cross_product(chord, pos - from),
+                    Some(gear),
+                )
+            })
+            .collect();
+        points.push((dot_product(chord, chord), 0.0, None));
+
+        // Scanned around the start of the chord, from the gear seen the furthest toward the
+        // corner down to the end of the chord.
+        points.sort_by(|p1, p2| {
+            p2.1.atan2(p2.0)
+                .total_cmp(&p1.1.atan2(p1.0))
+                .then((p1.0 * p1.0 + p1.1 * p1.1).total_cmp(&(p2.0 * p2.0 + p2.1 * p2.1)))
+        });
+
+        let mut chain: Vec<(f64, f64, Option<GearIndex>)> = vec![(0.0, 0.0, None)];
+
+        for point in points {
+            while chain.len() >= 2 {
+                let (p1, p2) = (chain[chain.len() - 2], chain[chain.len() - 1]);
+
+                if (p2.0 - p1.0) * (point.1 - p1.1) - (p2.1 - p1.1) * (point.0 - p1.0) >= 0.0 {
+                    chain.pop();
+                } else {
+                    break;
+                }
+            }
+
+            chain.push(point);
+        }
+
+        chain.into_iter().filter_map(|(.., gear)| gear).collect()
+    }
+}
@@ -1,27 +1,37 @@
 //! Module for managing the various Specctra PCB design, including loading the
 //! Design DSN file, creating the [`Board`] object from the file, as well as
 //! exporting the session file
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use geo::{point, Point, Rotate};
+use geo::{point, EuclideanDistance, Intersects, LineString, Point, Polygon};
+use rstar::AABB;
 use thiserror::Error;
 
 use crate::{
-    board::{mesadata::AccessMesadata, Board},
+    board::{mesadata::AccessMesadata, shapes, Board},
     drawing::{
-        dot::FixedDotWeight,
-        graph::{GetLayer, GetMaybeNet, MakePrimitive},
-        primitive::MakePrimitiveShape,
+        dot::{FixedDotIndex, FixedDotWeight},
+        graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex},
+        primitive::{GetWeight, MakePrimitiveShape},
+        rules::ObjectKind,
         seg::FixedSegWeight,
         Drawing,
     },
-    geometry::{primitive::PrimitiveShape, GetWidth},
-    layout::{poly::SolidPolyWeight, Layout},
+    geometry::{compound::ManageCompounds, primitive::PrimitiveShape, GenericNode, GetWidth},
+    graph::{GenericIndex, GetPetgraphIndex},
+    layout::{
+        keepout::{ViaKeepout, ViaKeepoutShape},
+        poly::{MakePolyShape, PolyWeight, PourPolyWeight, SolidPolyWeight},
+        pour::{Pour, PourOptions},
+        via::ViaWeight,
+        CompoundWeight, Layout, NodeIndex,
+    },
     math::{Circle, PointWithRotation},
+    router::trace::Tracer,
     specctra::{
         mesadata::SpecctraMesadata,
         read::{self, ListTokenizer},
-        structure::{self, DsnFile, Layer, Pcb, Shape},
+        structure::{self, DsnFile, KeepoutKind, Layer, Pcb, Shape},
         write::ListWriter,
     },
 };
@@ -74,9 +84,13 @@ impl SpecctraDesign {
     /// This function generates a Specctra SES session file that represents the board's net routing and
     /// writes it to the provided output stream. The session data includes routed nets, wires,
     /// layers, and other essential information for routing management.
+    ///
+    /// Bends are exported as chains of straight segments, each deviating from the arc it
+    /// approximates by at most `max_arc_deviation`, expressed in board units.
     pub fn write_ses(
         &self,
         board: &Board<SpecctraMesadata>,
+        max_arc_deviation: f64,
         writer: impl std::io::Write,
     ) -> Result<(), std::io::Error> {
         let mesadata = board.mesadata();
@@ -104,23 +118,14 @@ impl SpecctraDesign {
                     PrimitiveShape::Bend(bend) => {
                         // Since general circle arcs don't seem to be supported
                         // we're downgrading each one to a chain of straight
-                        // line segments.
-                        // TODO: make this configurable? pick a smarter value?
-                        let segment_count: usize = 100;
-
-                        let circle = bend.circle();
-                        let angle_from = bend.start_angle();
-                        let angle_step = bend.spanned_angle() / segment_count as f64;
-
-                        let mut points = Vec::new();
-                        for i in 0..=segment_count {
-                            let x = circle.pos.x()
-                                + circle.r * (angle_from + i as f64 * angle_step).cos();
-                            let y = circle.pos.y()
-                                + circle.r * (angle_from + i as f64 * angle_step).sin();
-                            points.push(structure::Point { x, y });
-                        }
-                        points
+                        // line segments that stays within the given tolerance.
+                        bend.polyline(max_arc_deviation)
+                            .into_iter()
+                            .map(|point| structure::Point {
+                                x: point.x(),
+                                y: point.y(),
+                            })
+                            .collect()
                     }
 
                     // Intentionally skipped for now.
@@ -132,28 +137,96 @@ impl SpecctraDesign {
                 };
 
                 let wire = structure::WireOut {
-                    path: structure::Path {
+                    path: Some(structure::Path {
                         layer: mesadata
                             .layer_layername(primitive.layer())
                             .unwrap()
                             .to_owned(),
                         width: primitive.width(),
                         coords,
-                    },
+                    }),
+                    polygon: None,
+                    windows: Vec::new(),
                 };
 
-                if let Some(net) = net_outs.get_mut(&net) {
-                    net.wire.push(wire);
-                } else {
-                    net_outs.insert(
-                        net,
-                        structure::NetOut {
-                            name: mesadata.net_netname(net).unwrap().to_owned(),
-                            wire: vec![wire],
-                            via: Vec::new(),
-                        },
-                    );
-                }
+                net_outs
+                    .entry(net)
+                    .or_insert_with(|| structure::NetOut {
+                        name: mesadata.net_netname(net).unwrap().to_owned(),
+                        wire: Vec::new(),
+                        via: Vec::new(),
+                    })
+                    .wire
+                    .push(wire);
+            }
+        }
+
+        for via in board.layout().via_nodes() {
+            let weight = board.layout().via(via).weight();
+
+            let Some(net) = weight.maybe_net else {
+                continue;
+            };
+            let Some(padstack_name) = self.via_padstack_name(mesadata, &weight) else {
+                continue;
+            };
+
+            net_outs
+                .entry(net)
+                .or_insert_with(|| structure::NetOut {
+                    name: mesadata.net_netname(net).unwrap().to_owned(),
+                    wire: Vec::new(),
+                    via: Vec::new(),
+                })
+                .via
+                .push(structure::ViaOut {
+                    name: padstack_name.to_owned(),
+                    x: weight.circle.pos.x(),
+                    y: weight.circle.pos.y(),
+                });
+        }
+
+        // The fills of pours are written as wires shaped like polygons, with their holes as
+        // windows.
+        for pour in board.layout().pours() {
+            let Some(net) = pour.maybe_net() else {
+                continue;
+            };
+            let layername = mesadata.layer_layername(pour.layer()).unwrap();
+            let ses_polygon = |ring: &LineString| structure::Polygon {
+                layer: layername.to_owned(),
+                width: 0.0,
+                coords: ring
+                    .coords()
+                    .map(|coord| structure::Point {
+                        x: coord.x,
+                        y: coord.y,
+                    })
+                    .collect(),
+            };
+
+            for polygon in pour.fill.iter() {
+                let wire = structure::WireOut {
+                    path: None,
+                    polygon: Some(ses_polygon(polygon.exterior())),
+                    windows: polygon
+                        .interiors()
+                        .iter()
+                        .map(|interior| structure::Window {
+                            polygon: ses_polygon(interior),
+                        })
+                        .collect(),
+                };
+
+                net_outs
+                    .entry(net)
+                    .or_insert_with(|| structure::NetOut {
+                        name: mesadata.net_netname(net).unwrap().to_owned(),
+                        wire: Vec::new(),
+                        via: Vec::new(),
+                    })
+                    .wire
+                    .push(wire);
             }
         }
 
@@ -179,6 +252,52 @@ impl SpecctraDesign {
         ListWriter::new(writer).write_value(&ses)
     }
 
+    /// Finds the name of the via padstack that best matches the given via.
+    ///
+    /// A padstack matches if its shapes span exactly the layers of the via. The padstack
+    /// preferred by the net class of the via is tried first, then every padstack listed in the
+    /// structure, picking the one whose diameter is the closest to the via's. If none matches,
+    /// the first padstack listed in the structure is returned.
+    fn via_padstack_name(&self, mesadata: &SpecctraMesadata, weight: &ViaWeight) -> Option<&str> {
+        let spans_via_layers = |padstack: &structure::Padstack| {
+            padstack.layer_span(&mesadata.layer_layername)
+                == Some((weight.from_layer, weight.to_layer))
+        };
+
+        let diameter_error =
+            |padstack: &structure::Padstack| (padstack.diameter() - 2.0 * weight.circle.r).abs();
+
+        let class_via = weight.maybe_net.and_then(|net| {
+            let netname = mesadata.net_netname(net)?;
+            self.pcb
+                .network
+                .classes
+                .iter()
+                .find(|class| class.nets.iter().any(|name| name == netname))
+                .map(|class| class.circuit.use_via.as_str())
+        });
+
+        if let Some(padstack) = class_via
+            .and_then(|name| self.pcb.library.find_padstack_by_name(name))
+            .filter(|padstack| spans_via_layers(padstack))
+        {
+            return Some(&padstack.name);
+        }
+
+        self.pcb
+            .structure
+            .via
+            .names
+            .iter()
+            .filter_map(|name| self.pcb.library.find_padstack_by_name(name))
+            .filter(|padstack| spans_via_layers(padstack))
+            .min_by(|padstack1, padstack2| {
+                diameter_error(padstack1).total_cmp(&diameter_error(padstack2))
+            })
+            .map(|padstack| padstack.name.as_str())
+            .or_else(|| self.pcb.structure.via.names.first().map(String::as_str))
+    }
+
     /// Generates a [`Board<SpecctraMesadata>`] from the current PCB data.
     ///
     /// This function takes the internal `Pcb` structure and transforms it into a [`Board`] object,
@@ -230,77 +349,132 @@ impl SpecctraDesign {
                     .unwrap();
 
                 let place_side_is_front = place.side == "front";
-                let get_layer = |board: &Board<SpecctraMesadata>, name: &str| {
-                    Self::layer(board, &self.pcb.structure.layers, name, place_side_is_front)
+                let get_layers = |board: &Board<SpecctraMesadata>, name: &str| -> Vec<usize> {
+                    // Shapes on the `signal` layer, as of through-hole pads, span all layers.
+                    if board.mesadata().layername_layer(name).is_some() {
+                        vec![Self::layer(
+                            board,
+                            &self.pcb.structure.layers,
+                            name,
+                            place_side_is_front,
+                        )]
+                    } else {
+                        (0..self.pcb.structure.layers.len()).collect()
+                    }
                 };
 
                 for pin in &image.pins {
                     let pinname = format!("{}-{}", place.name, pin.id);
                     let net = pin_nets.get(&pinname).unwrap();
+                    board.add_pin_to_component(pinname.clone(), place.name.clone());
 
                     let padstack = self.pcb.library.find_padstack_by_name(&pin.name).unwrap();
+                    // Pads with copper on one layer only are surface-mounted, unlike ones given
+                    // on the `signal` layer, which stands for all layers even on boards with one.
+                    let pad_layers: HashSet<usize> = padstack
+                        .shapes
+                        .iter()
+                        .flat_map(|shape| get_layers(&board, shape.layer()))
+                        .collect();
+                    let kind = if pad_layers.len() == 1
+                        && padstack
+                            .shapes
+                            .iter()
+                            .all(|shape| board.mesadata().layername_layer(shape.layer()).is_some())
+                    {
+                        ObjectKind::Smd
+                    } else {
+                        ObjectKind::Pin
+                    };
 
                     for shape in padstack.shapes.iter() {
-                        match shape {
-                            Shape::Circle(circle) => {
-                                let layer = get_layer(&board, &circle.layer);
-                                Self::add_circle(
+                        // Images placed on the back side are mirrored.
+                        let mirrored;
+                        let shape = if place_side_is_front {
+                            shape
+                        } else {
+                            mirrored = shape.mirrored();
+                            &mirrored
+                        };
+
+                        for layer in get_layers(&board, shape.layer()) {
+                            match shape {
+                                Shape::Circle(circle) => shapes::add_circle(
                                     &mut board,
                                     place.point_with_rotation(),
-                                    pin.point_with_rotation(),
+                                    pin.point_with_rotation_on_side(place_side_is_front),
                                     circle.diameter / 2.0,
                                     layer,
-                                    *net,
+                                    Some(*net),
+                                    kind,
                                     Some(pinname.clone()),
-                                )
-                            }
-                            Shape::Rect(rect) => {
-                                let layer = get_layer(&board, &rect.layer);
-                                Self::add_rect(
+                                ),
+                                Shape::Rect(rect) => shapes::add_rect(
                                     &mut board,
                                     place.point_with_rotation(),
-                                    pin.point_with_rotation(),
-                                    rect.x1,
-                                    rect.y1,
-                                    rect.x2,
-                                    rect.y2,
-                                    layer,
-                                    *net,
+                                    pin.point_with_rotation_on_side(place_side_is_front),
+                                    (rect.x1, rect.y1),
+                                    (rect.x2, rect.y2),
+                                    SolidPolyWeight {
+                                        layer,
+                                        maybe_net: Some(*net),
+                                    },
+                                    kind,
                                     Some(pinname.clone()),
-                                )
-                            }
-                            Shape::Path(path) => {
-                                let layer = get_layer(&board, &path.layer);
-                                Self::add_path(
+                                ),
+                                Shape::Path(path) => shapes::add_path(
                                     &mut board,
                                     place.point_with_rotation(),
-                                    pin.point_with_rotation(),
-                                    &path.coords,
-                                    path.width,
-                                    layer,
-                                    *net,
+                                    pin.point_with_rotation_on_side(place_side_is_front),
+                                    &Self::coords(&path.coords),
+                                    FixedSegWeight {
+                                        width: path.width,
+                                        layer,
+                                        maybe_net: Some(*net),
+                                        kind,
+                                    },
                                     Some(pinname.clone()),
-                                )
-                            }
-                            Shape::Polygon(polygon) => {
-                                let layer = get_layer(&board, &polygon.layer);
-                                Self::add_polygon(
+                                ),
+                                Shape::Polygon(polygon) => shapes::add_polygon(
                                     &mut board,
                                     place.point_with_rotation(),
-                                    pin.point_with_rotation(),
-                                    &polygon.coords,
-                                    polygon.width,
-                                    layer,
-                                    *net,
+                                    pin.point_with_rotation_on_side(place_side_is_front),
+                                    &Self::coords(&polygon.coords),
+                                    FixedSegWeight {
+                                        width: polygon.width,
+                                        layer,
+                                        maybe_net: Some(*net),
+                                        kind,
+                                    },
                                     Some(pinname.clone()),
-                                )
-                            }
-                        };
+                                ),
+                            };
+                        }
                     }
                 }
+
+                for keepout in &image.keepouts {
+                    Self::add_keepout(
+                        &mut board,
+                        &self.pcb.structure.layers,
+                        place.point_with_rotation(),
+                        keepout,
+                        place_side_is_front,
+                    );
+                }
             }
         }
 
+        for keepout in &self.pcb.structure.keepouts {
+            Self::add_keepout(
+                &mut board,
+                &self.pcb.structure.layers,
+                PointWithRotation::default(),
+                keepout,
+                true,
+            );
+        }
+
         for via in &self.pcb.wiring.vias {
             let net = board
                 .layout()
@@ -310,71 +484,33 @@ impl SpecctraDesign {
                 .unwrap();
 
             let padstack = self.pcb.library.find_padstack_by_name(&via.name).unwrap();
-
-            let get_layer = |board: &Board<SpecctraMesadata>, name: &str| {
-                Self::layer(board, &self.pcb.structure.layers, name, true)
+            let Some((from_layer, to_layer)) =
+                padstack.layer_span(&board.layout().drawing().rules().layer_layername)
+            else {
+                continue;
             };
 
-            for shape in &padstack.shapes {
-                match shape {
-                    Shape::Circle(circle) => {
-                        let layer = get_layer(&board, &circle.layer);
-                        Self::add_circle(
-                            &mut board,
-                            PointWithRotation::default(),
-                            PointWithRotation::default(),
-                            circle.diameter / 2.0,
-                            layer,
-                            net,
-                            None,
-                        )
-                    }
-                    Shape::Rect(rect) => {
-                        let layer = get_layer(&board, &rect.layer);
-                        Self::add_rect(
-                            &mut board,
-                            PointWithRotation::default(),
-                            PointWithRotation::default(),
-                            rect.x1,
-                            rect.y1,
-                            rect.x2,
-                            rect.y2,
-                            layer,
-                            net,
-                            None,
-                        )
-                    }
-                    Shape::Path(path) => {
-                        let layer = get_layer(&board, &path.layer);
-                        Self::add_path(
-                            &mut board,
-                            PointWithRotation::default(),
-                            PointWithRotation::default(),
-                            &path.coords,
-                            path.width,
-                            layer,
-                            net,
-                            None,
-                        )
-                    }
-                    Shape::Polygon(polygon) => {
-                        let layer = get_layer(&board, &polygon.layer);
-                        Self::add_polygon(
-                            &mut board,
-                            PointWithRotation::default(),
-                            PointWithRotation::default(),
-                            &polygon.coords,
-                            polygon.width,
-                            layer,
-                            net,
-                            None,
-                        )
-                    }
-                };
-            }
+            board.layout_mut().add_via_infringably(ViaWeight {
+                from_layer,
+                to_layer,
+                circle: Circle {
+                    pos: point! {x: via.x, y: via.y},
+                    r: padstack.diameter() / 2.0,
+                },
+                maybe_net: Some(net),
+            });
         }
 
-        for wire in self.pcb.wiring.wires.iter() {
+        // Wires meant to be routed are imported as loose bands once everything they can wrap
+        // around is in place, while the ones meant to stay as they are are imported as fixed.
+        let (route_wires, fixed_wires): (Vec<&structure::Wire>, Vec<&structure::Wire>) = self
+            .pcb
+            .wiring
+            .wires
+            .iter()
+            .partition(|wire| matches!(wire.r#type.as_str(), "route" | "normal"));
+
+        for wire in fixed_wires {
             let layer = board
                 .layout()
                 .drawing()
@@ -388,21 +524,294 @@ impl SpecctraDesign {
                 .netname_net(&wire.net)
                 .unwrap();
 
-            Self::add_path(
+            shapes::add_path(
                 &mut board,
                 PointWithRotation::default(),
                 PointWithRotation::default(),
-                &wire.path.coords,
-                wire.path.width,
-                layer,
-                net,
+                &Self::coords(&wire.path.coords),
+                FixedSegWeight {
+                    width: wire.path.width,
+                    layer,
+                    maybe_net: Some(net),
+                    kind: ObjectKind::Wire,
+                },
                 None,
             );
         }
 
+        let (boundary_coords, boundary_width) = self.pcb.structure.boundary.outline();
+
+        Self::add_boundary(
+            &mut board,
+            &boundary_coords,
+            boundary_width,
+            self.pcb.structure.layers.len(),
+        );
+
+        Self::add_route_wires(&mut board, &route_wires);
+
+        for plane in &self.pcb.structure.planes {
+            let Some(layer) = board
+                .layout()
+                .drawing()
+                .rules()
+                .layername_layer(&plane.polygon.layer)
+            else {
+                continue;
+            };
+            let maybe_net = board.layout().drawing().rules().netname_net(&plane.net);
+
+            board.layout_mut().add_pour(Pour::new(
+                PourPolyWeight { layer, maybe_net },
+                Polygon::new(
+                    LineString::from(
+                        plane
+                            .polygon
+                            .coords
+                            .iter()
+                            .map(|coord| (coord.x, coord.y))
+                            .collect::<Vec<_>>(),
+                    ),
+                    vec![],
+                ),
+                PourOptions::default(),
+            ));
+        }
+
+        // Planes are poured around everything else.
+        board.layout_mut().fill_pours();
+
         board
     }
 
+    /// Imports wires as loose bands, so that they can be rerouted and rubberbanded like the
+    /// bands routed by topola.
+    ///
+    /// A band can only run between two pads or vias, so wires are split wherever they pass
+    /// through a pad or via of their net and chained wherever they meet end to end. Chains that
+    /// end or branch anywhere else, as well as those whose bands can't be drawn, are imported
+    /// as fixed wires instead.
+    fn add_route_wires(board: &mut Board<SpecctraMesadata>, wires: &[&structure::Wire]) {
+        let mut pieces: Vec<WirePiece> = vec![];
+        let mut wire_ends: HashSet<WirePointKey> = HashSet::new();
+        let mut wire_points: Vec<(usize, usize, f64, Vec<Point>)> = vec![];
+
+        for wire in wires {
+            let layer = board
+                .layout()
+                .drawing()
+                .rules()
+                .layername_layer(&wire.path.layer)
+                .unwrap();
+            let net = board
+                .layout()
+                .drawing()
+                .rules()
+                .netname_net(&wire.net)
+                .unwrap();
+
+            let mut points: Vec<Point> = vec![];
+
+            for coord in &wire.path.coords {
+                let pos = point! {x: coord.x, y: coord.y};
+
+                if points.last() != Some(&pos) {
+                    points.push(pos);
+                }
+            }
+
+            let (Some(first), Some(last)) = (points.first(), points.last()) else {
+                continue;
+            };
+
+            for pos in [first, last] {
+                wire_ends.insert((net, layer, pos.x().to_bits(), pos.y().to_bits()));
+            }
+
+            wire_points.push((net, layer, wire.path.width, points));
+        }
+
+        for (net, layer, width, mut points) in wire_points {
+            let mut piece = WirePiece {
+                layer,
+                net,
+                width,
+                points: points.drain(..1).collect(),
+            };
+
+            // Wires are also split where other wires branch off them.
+            for (i, pos) in points.iter().enumerate() {
+                piece.points.push(*pos);
+
+                if i + 1 < points.len()
+                    && (wire_ends.contains(&(net, layer, pos.x().to_bits(), pos.y().to_bits()))
+                        || Self::wire_end(board, layer, net, *pos).is_some())
+                {
+                    let next_points = vec![*pos];
+                    pieces.push(WirePiece {
+                        points: std::mem::replace(&mut piece.points, next_points),
+                        ..piece
+                    });
+                }
+            }
+
+            pieces.push(piece);
+        }
+
+        // The ends of the pieces meeting at each point, with whether it's the last point of the
+        // piece.
+        let mut point_ends: HashMap<WirePointKey, Vec<(usize, bool)>> = HashMap::new();
+
+        for (i, piece) in pieces.iter().enumerate() {
+            for (pos, is_last) in [(piece.first(), false), (piece.last(), true)] {
+                point_ends
+                    .entry(piece.point_key(pos))
+                    .or_default()
+                    .push((i, is_last));
+            }
+        }
+
+        let mut visited = vec![false; pieces.len()];
+
+        for start in 0..pieces.len() {
+            for start_is_last in [false, true] {
+                if visited[start] {
+                    break;
+                }
+
+                let start_piece = &pieces[start];
+                let start_pos = if start_is_last {
+                    start_piece.last()
+                } else {
+                    start_piece.first()
+                };
+                let Some(source) =
+                    Self::wire_end(board, start_piece.layer, start_piece.net, start_pos)
+                else {
+                    continue;
+                };
+
+                let mut chain = vec![];
+                let mut polyline = vec![start_pos];
+                let (mut current, mut reversed) = (start, start_is_last);
+
+                let maybe_target = loop {
+                    let piece = &pieces[current];
+                    visited[current] = true;
+                    chain.push(current);
+
+                    if reversed {
+                        polyline.extend(piece.points.iter().rev().skip(1));
+                    } else {
+                        polyline.extend(piece.points.iter().skip(1));
+                    }
+
+                    let end = *polyline.last().unwrap();
+
+                    if let Some(target) = Self::wire_end(board, piece.layer, piece.net, end) {
+                        break Some(target);
+                    }
+
+                    // Only where exactly two pieces meet can they be chained.
+                    let ends = &point_ends[&piece.point_key(end)];
+
+                    match ends.iter().find(|(i, _)| *i != current) {
+                        Some(&(next, next_is_last)) if ends.len() == 2 && !visited[next] => {
+                            current = next;
+                            reversed = next_is_last;
+                        }
+                        _ => break None,
+                    }
+                };
+
+                let piece = &pieces[start];
+
+                if let Some(target) = maybe_target.filter(|target| *target != source) {
+                    let recording = board.layout_mut().start_recording();
+                    let result = Tracer::new(board.layout_mut()).trace(
+                        source,
+                        target,
+                        &polyline,
+                        piece.width,
+                    );
+                    let edit = board.layout_mut().stop_recording(recording);
+
+                    match result {
+                        Ok(band) => {
+                            board.try_set_band_between_nodes(source, target, band);
+                            continue;
+                        }
+                        Err(..) => board.layout_mut().apply_edit(&edit.reverse()),
+                    }
+                }
+
+                for i in chain {
+                    pieces[i].add_fixed(board);
+                }
+            }
+        }
+
+        for (i, piece) in pieces.iter().enumerate() {
+            if !visited[i] {
+                piece.add_fixed(board);
+            }
+        }
+    }
+
+    /// Finds the pad or via of the given net on the given layer that a wire ending at the given
+    /// point ends in, giving the fixed dot a band can be joined to there.
+    fn wire_end(
+        board: &mut Board<SpecctraMesadata>,
+        layer: usize,
+        net: usize,
+        pos: Point,
+    ) -> Option<FixedDotIndex> {
+        // Only the nodes whose bounding boxes contain the point can be ended in there.
+        let nodes: Vec<NodeIndex> = board
+            .layout()
+            .drawing()
+            .rtree()
+            .locate_in_envelope_intersecting(&AABB::from_point([pos.x(), pos.y(), layer as f64]))
+            .map(|wrapper| wrapper.data)
+            .collect();
+
+        let maybe_poly = nodes.iter().find_map(|node| {
+            let GenericNode::Compound(compound) = *node else {
+                return None;
+            };
+            let CompoundWeight::Poly(..) = board.layout().drawing().compound_weight(compound)
+            else {
+                return None;
+            };
+            let poly = GenericIndex::<PolyWeight>::new(compound.petgraph_index());
+
+            (board.layout().poly(poly).maybe_net() == Some(net)
+                && board.layout().poly(poly).shape().polygon.intersects(&pos))
+            .then_some(poly)
+        });
+
+        if let Some(poly) = maybe_poly {
+            return Some(board.poly_apex(poly));
+        }
+
+        let drawing = board.layout().drawing();
+
+        nodes.into_iter().find_map(|node| {
+            let GenericNode::Primitive(PrimitiveIndex::FixedDot(dot)) = node else {
+                return None;
+            };
+            let primitive = drawing.primitive(dot);
+
+            // Fixed wires aren't ended in, so that which wires become bands doesn't
+            // depend on the order they're imported in.
+            (primitive.maybe_net() == Some(net)
+                && primitive.weight().kind != ObjectKind::Wire
+                && primitive.weight().circle.pos.euclidean_distance(&pos)
+                    <= primitive.weight().circle.r)
+                .then_some(dot)
+        })
+    }
+
     fn layer(
         board: &Board<SpecctraMesadata>,
         layers: &[Layer],
@@ -423,268 +832,266 @@ impl SpecctraDesign {
         }
     }
 
-    fn add_circle(
+    /// Adds a keepout on its layer or, if it's given for all signal layers, on every layer.
+    ///
+    /// Keepouts of vias alone are not obstacles to bands, so they're held aside by the layout,
+    /// whereas other keepouts are added as netless fixed primitives, of a kind that lets vias
+    /// into keepouts of wires alone.
+    fn add_keepout(
         board: &mut Board<SpecctraMesadata>,
+        layers: &[Layer],
         place: PointWithRotation,
-        pin: PointWithRotation,
-        r: f64,
-        layer: usize,
-        net: usize,
-        maybe_pin: Option<String>,
+        keepout: &structure::Keepout,
+        front: bool,
     ) {
-        let circle = Circle {
-            pos: Self::pos(place, pin, 0.0, 0.0),
-            r,
+        let layername = keepout.shape.layer();
+        let keepout_layers: Vec<usize> = if board.mesadata().layername_layer(layername).is_some() {
+            vec![Self::layer(board, layers, layername, front)]
+        } else {
+            // Specctra places keepouts of all layers on the `signal` layer.
+            (0..layers.len()).collect()
         };
 
-        board.add_fixed_dot_infringably(
-            FixedDotWeight {
-                circle,
-                layer,
-                maybe_net: Some(net),
-            },
-            maybe_pin.clone(),
-        );
+        // Images placed on the back side are mirrored.
+        let mirrored;
+        let shape = if front {
+            &keepout.shape
+        } else {
+            mirrored = keepout.shape.mirrored();
+            &mirrored
+        };
+        let kind = if keepout.kind == KeepoutKind::WireKeepout {
+            ObjectKind::WireArea
+        } else {
+            ObjectKind::Area
+        };
+
+        for layer in keepout_layers {
+            if keepout.kind == KeepoutKind::ViaKeepout {
+                board.layout_mut().add_via_keepout(ViaKeepout {
+                    layer,
+                    shape: Self::via_keepout_shape(place, shape),
+                });
+                continue;
+            }
+
+            match shape {
+                Shape::Circle(circle) => shapes::add_circle(
+                    board,
+                    place,
+                    Self::circle_offset(circle),
+                    circle.diameter / 2.0,
+                    layer,
+                    None,
+                    kind,
+                    None,
+                ),
+                Shape::Rect(rect) => shapes::add_rect(
+                    board,
+                    place,
+                    PointWithRotation::default(),
+                    (rect.x1, rect.y1),
+                    (rect.x2, rect.y2),
+                    SolidPolyWeight {
+                        layer,
+                        maybe_net: None,
+                    },
+                    kind,
+                    None,
+                ),
+                Shape::Path(path) => shapes::add_path(
+                    board,
+                    place,
+                    PointWithRotation::default(),
+                    &Self::coords(&path.coords),
+                    FixedSegWeight {
+                        width: path.width,
+                        layer,
+                        maybe_net: None,
+                        kind,
+                    },
+                    None,
+                ),
+                Shape::Polygon(polygon) => shapes::add_polygon(
+                    board,
+                    place,
+                    PointWithRotation::default(),
+                    &Self::coords(&polygon.coords),
+                    FixedSegWeight {
+                        width: polygon.width,
+                        layer,
+                        maybe_net: None,
+                        kind,
+                    },
+                    None,
+                ),
+            }
+        }
+    }
+
+    fn via_keepout_shape(place: PointWithRotation, shape: &Shape) -> ViaKeepoutShape {
+        let pos = |x: f64, y: f64| shapes::pos(place, PointWithRotation::default(), x, y);
+        let line_string = |coords: &[structure::Point]| -> LineString {
+            coords.iter().map(|coord| pos(coord.x, coord.y)).collect()
+        };
+
+        match shape {
+            Shape::Circle(circle) => ViaKeepoutShape::Circle(Circle {
+                pos: shapes::pos(place, Self::circle_offset(circle), 0.0, 0.0),
+                r: circle.diameter / 2.0,
+            }),
+            Shape::Rect(rect) => ViaKeepoutShape::Polygon(Polygon::new(
+                LineString::from(vec![
+                    pos(rect.x1, rect.y1),
+                    pos(rect.x2, rect.y1),
+                    pos(rect.x2, rect.y2),
+                    pos(rect.x1, rect.y2),
+                ]),
+                vec![],
+            )),
+            Shape::Path(path) => ViaKeepoutShape::Path(line_string(&path.coords), path.width),
+            Shape::Polygon(polygon) => {
+                ViaKeepoutShape::Polygon(Polygon::new(line_string(&polygon.coords), vec![]))
+            }
+        }
+    }
+
+    fn coords(coords: &[structure::Point]) -> Vec<Point> {
+        coords
+            .iter()
+            .map(|coord| point! {x: coord.x, y: coord.y})
+            .collect()
+    }
+
+    fn circle_offset(circle: &structure::Circle) -> PointWithRotation {
+        circle
+            .offset
+            .map_or(PointWithRotation::default(), |offset| PointWithRotation {
+                pos: (offset.x, offset.y).into(),
+                rot: 0.0,
+            })
     }
 
-    fn add_rect(
+    /// Adds the board outline on every layer as a netless obstacle that no band may cross, and
+    /// keeps anything from being inserted entirely outside of it.
+    fn add_boundary(
         board: &mut Board<SpecctraMesadata>,
-        place: PointWithRotation,
-        pin: PointWithRotation,
-        x1: f64,
-        y1: f64,
-        x2: f64,
-        y2: f64,
-        layer: usize,
-        net: usize,
-        maybe_pin: Option<String>,
+        coords: &[structure::Point],
+        width: f64,
+        layer_count: usize,
     ) {
-        let poly = board.add_poly(
-            SolidPolyWeight {
-                layer,
-                maybe_net: Some(net),
-            }
-            .into(),
-            maybe_pin.clone(),
-        );
+        for layer in 0..layer_count {
+            Self::add_boundary_on_layer(board, coords, width, layer);
+        }
 
-        // Corners.
-        let dot_1_1 = board.add_poly_fixed_dot_infringably(
-            FixedDotWeight {
-                circle: Circle {
-                    pos: Self::pos(place, pin, x1, y1),
-                    r: 0.5,
-                },
-                layer,
-                maybe_net: Some(net),
-            },
-            poly,
-        );
-        let dot_2_1 = board.add_poly_fixed_dot_infringably(
-            FixedDotWeight {
-                circle: Circle {
-                    pos: Self::pos(place, pin, x2, y1),
-                    r: 0.5,
-                },
-                layer,
-                maybe_net: Some(net),
-            },
-            poly,
-        );
-        let dot_2_2 = board.add_poly_fixed_dot_infringably(
-            FixedDotWeight {
-                circle: Circle {
-                    pos: Self::pos(place, pin, x2, y2),
-                    r: 0.5,
-                },
-                layer,
-                maybe_net: Some(net),
-            },
-            poly,
-        );
-        let dot_1_2 = board.add_poly_fixed_dot_infringably(
-            FixedDotWeight {
-                circle: Circle {
-                    pos: Self::pos(place, pin, x1, y2),
-                    r: 0.5,
-                },
-                layer,
-                maybe_net: Some(net),
-            },
-            poly,
-        );
-        // Sides.
-        board.add_poly_fixed_seg_infringably(
-            dot_1_1,
-            dot_2_1,
-            FixedSegWeight {
-                width: 1.0,
-                layer,
-                maybe_net: Some(net),
-            },
-            poly,
-        );
-        board.add_poly_fixed_seg_infringably(
-            dot_2_1,
-            dot_2_2,
-            FixedSegWeight {
-                width: 1.0,
-                layer,
-                maybe_net: Some(net),
-            },
-            poly,
-        );
-        board.add_poly_fixed_seg_infringably(
-            dot_2_2,
-            dot_1_2,
-            FixedSegWeight {
-                width: 1.0,
-                layer,
-                maybe_net: Some(net),
-            },
-            poly,
-        );
-        board.add_poly_fixed_seg_infringably(
-            dot_1_2,
-            dot_1_1,
-            FixedSegWeight {
-                width: 1.0,
-                layer,
-                maybe_net: Some(net),
-            },
-            poly,
-        );
+        // A lone line encloses nothing.
+        if coords.len() > 3 {
+            board.layout_mut().set_boundary(Polygon::new(
+                coords.iter().map(|coord| (coord.x, coord.y)).collect(),
+                vec![],
+            ));
+        }
     }
 
-    fn add_path(
+    fn add_boundary_on_layer(
         board: &mut Board<SpecctraMesadata>,
-        place: PointWithRotation,
-        pin: PointWithRotation,
         coords: &[structure::Point],
         width: f64,
         layer: usize,
-        net: usize,
-        maybe_pin: Option<String>,
     ) {
-        // add the first coordinate in the wire path as a dot and save its index
-        let mut prev_pos = Self::pos(place, pin, coords[0].x, coords[0].y);
-        let mut prev_index = board.add_fixed_dot_infringably(
-            FixedDotWeight {
-                circle: Circle {
-                    pos: prev_pos,
-                    r: width / 2.0,
-                },
-                layer,
-                maybe_net: Some(net),
-            },
-            maybe_pin.clone(),
-        );
+        // The outline is usually drawn with zero width, which would make for degenerate shapes,
+        // so we give it at least the width of the sides of polygonal pads.
+        let width = width.max(1.0);
+        let mut dots: Vec<(Point, FixedDotIndex)> = vec![];
 
-        // iterate through path coords starting from the second
-        for coord in coords.iter().skip(1) {
-            let pos = Self::pos(place, pin, coord.x, coord.y);
+        // The outline is closed, so its last vertex is the first one.
+        for coord in coords.iter().take(coords.len().saturating_sub(1)) {
+            let pos = point! {x: coord.x, y: coord.y};
 
-            if pos == prev_pos {
+            if dots.last().is_some_and(|(prev_pos, _)| *prev_pos == pos) {
                 continue;
             }
 
-            let index = board.add_fixed_dot_infringably(
+            let dot = board.add_fixed_dot_infringably(
                 FixedDotWeight {
                     circle: Circle {
                         pos,
                         r: width / 2.0,
                     },
                     layer,
-                    maybe_net: Some(net),
+                    maybe_net: None,
+                    kind: ObjectKind::Boundary,
                 },
-                maybe_pin.clone(),
+                None,
             );
+            dots.push((pos, dot));
+        }
 
-            // add a seg between the current and previous coords
-            let _ = board.add_fixed_seg_infringably(
-                prev_index,
-                index,
+        // Join the last vertex back to the first one, unless the outline is a lone line.
+        let seg_count = match dots.len() {
+            0 | 1 => 0,
+            2 => 1,
+            len => len,
+        };
+
+        for i in 0..seg_count {
+            let (_, from) = dots[i];
+            let (_, to) = dots[(i + 1) % dots.len()];
+
+            board.add_fixed_seg_infringably(
+                from,
+                to,
                 FixedSegWeight {
                     width,
                     layer,
-                    maybe_net: Some(net),
+                    maybe_net: None,
+                    kind: ObjectKind::Boundary,
                 },
-                maybe_pin.clone(),
+                None,
             );
-
-            prev_index = index;
-            prev_pos = pos;
         }
     }
+}
 
-    fn add_polygon(
-        board: &mut Board<SpecctraMesadata>,
-        place: PointWithRotation,
-        pin: PointWithRotation,
-        coords: &[structure::Point],
-        width: f64,
-        layer: usize,
-        net: usize,
-        maybe_pin: Option<String>,
-    ) {
-        let poly = board.add_poly(
-            SolidPolyWeight {
-                layer,
-                maybe_net: Some(net),
-            }
-            .into(),
-            maybe_pin.clone(),
-        );
+/// The net and layer of a point of a wire, with the bits of its coordinates, by which the ends
+/// of wires meeting at the same point are found.
+type WirePointKey = (usize, usize, u64, u64);
 
-        // add the first coordinate in the wire path as a dot and save its index
-        let mut prev_index = board.add_poly_fixed_dot_infringably(
-            FixedDotWeight {
-                circle: Circle {
-                    pos: Self::pos(place, pin, coords[0].x, coords[0].y),
-                    r: width / 2.0,
-                },
-                layer,
-                maybe_net: Some(net),
-            },
-            // TODO: This manual retagging shouldn't be necessary, `.into()` should suffice.
-            //GenericIndex::new(poly.petgraph_index()).into(),
-            poly,
-        );
+/// A piece of a wire to be imported as a loose band, running between two points where it meets
+/// other pieces or ends in pads or vias.
+struct WirePiece {
+    layer: usize,
+    net: usize,
+    width: f64,
+    points: Vec<Point>,
+}
 
-        // iterate through path coords starting from the second
-        for coord in coords.iter().skip(1) {
-            let index = board.add_poly_fixed_dot_infringably(
-                FixedDotWeight {
-                    circle: Circle {
-                        pos: Self::pos(place, pin, coord.x, coord.y),
-                        r: width / 2.0,
-                    },
-                    layer,
-                    maybe_net: Some(net),
-                },
-                // TODO: This manual retagging shouldn't be necessary, `.into()` should suffice.
-                poly,
-            );
+impl WirePiece {
+    fn first(&self) -> Point {
+        self.points[0]
+    }
 
-            // add a seg between the current and previous coords
-            let _ = board.add_poly_fixed_seg_infringably(
-                prev_index,
-                index,
-                FixedSegWeight {
-                    width,
-                    layer,
-                    maybe_net: Some(net),
-                },
-                // TODO: This manual retagging shouldn't be necessary, `.into()` should suffice.
-                poly,
-            );
+    fn last(&self) -> Point {
+        self.points[self.points.len() - 1]
+    }
 
-            prev_index = index;
-        }
+    fn point_key(&self, pos: Point) -> WirePointKey {
+        (self.net, self.layer, pos.x().to_bits(), pos.y().to_bits())
     }
 
-    fn pos(place: PointWithRotation, pin: PointWithRotation, x: f64, y: f64) -> Point {
-        let pos = (point! {x: x, y: y} + pin.pos).rotate_around_point(pin.rot, pin.pos);
-        (pos + place.pos).rotate_around_point(place.rot, place.pos)
+    fn add_fixed(&self, board: &mut Board<SpecctraMesadata>) {
+        shapes::add_path(
+            board,
+            PointWithRotation::default(),
+            PointWithRotation::default(),
+            &self.points,
+            FixedSegWeight {
+                width: self.width,
+                layer: self.layer,
+                maybe_net: Some(self.net),
+                kind: ObjectKind::Wire,
+            },
+            None,
+        );
     }
 }
@@ -4,38 +4,233 @@
 use std::collections::HashMap;
 
 use bimap::BiHashMap;
+use geo::{BoundingRect, EuclideanDistance, Intersects, LineString, Polygon};
+use rstar::{primitives::GeomWithData, RTree, AABB};
 
 use crate::{
-    board::mesadata::AccessMesadata,
-    drawing::rules::{AccessRules, Conditions},
-    specctra::structure::Pcb,
+    board::mesadata::{complementary_netname, AccessMesadata, NetPairs},
+    drawing::rules::{AccessRules, Conditions, ObjectKind},
+    geometry::{
+        primitive::{AccessPrimitiveShape, PrimitiveShape},
+        with_rtree::Bbox,
+    },
+    specctra::structure::{Clearance, Padstack, Pcb, Region, StructureRule},
 };
 
 #[derive(Debug)]
 /// [`SpecctraRule`] represents the basic routing constraints used by an auto-router, such as
 /// the Topola auto-router, in a PCB design process. This struct defines two key design
-/// rules: the width of the trace and the minimum clearance between electrical features.
+/// rules: the width of the trace and the minimum clearances between electrical features.
 pub struct SpecctraRule {
     /// Specifies the width of the trace (or conductor) in millimeters.
     /// This value ensures that the traces meet electrical
     /// and mechanical requirements, such as current-carrying capacity or signal integrity.
     pub width: f64,
-    /// Defines the minimum clearance (spacing) between traces, pads,
+    /// Defines the minimum clearances (spacing) between traces, pads,
     /// or other conductive features on the PCB. Adequate clearance is important for
     /// preventing electrical shorts or interference between signals, and is often
     /// dictated by manufacturing constraints or voltage considerations.
-    pub clearance: f64,
+    ///
+    /// The clearances are keyed by the pair of object kinds they apply to, in both orders,
+    /// with `None` standing for Specctra's `default` type, which matches objects of any kind.
+    /// The clearance given without a type is keyed by a pair of `None`s.
+    pub clearances: HashMap<(Option<ObjectKind>, Option<ObjectKind>), f64>,
 }
 
 impl SpecctraRule {
-    fn from_dsn(rule: &super::structure::Rule) -> Self {
+    fn from_dsn(width: f64, clearances: &[Clearance]) -> Self {
+        let mut clearance_map = HashMap::new();
+
+        for clearance in clearances {
+            let Some((kind1, kind2)) = Self::parse_clearance_type(clearance.r#type.as_deref())
+            else {
+                continue;
+            };
+
+            clearance_map.insert((kind1, kind2), clearance.value as f64);
+            clearance_map.insert((kind2, kind1), clearance.value as f64);
+        }
+
         Self {
-            width: rule.width as f64,
-            clearance: rule.clearances[0].value as f64, // picks the generic clearance only for now
+            width,
+            clearances: clearance_map,
+        }
+    }
+
+    /// Collapses a sequence of rules, as some tools write one per clearance, into a single one.
+    fn from_structure_rules<'a>(rules: impl IntoIterator<Item = &'a StructureRule>) -> Self {
+        let mut width = 0.0;
+        let mut clearances = Vec::new();
+
+        for rule in rules {
+            if let Some(rule_width) = rule.width {
+                width = rule_width;
+            }
+            clearances.extend_from_slice(&rule.clearances);
+        }
+
+        Self::from_dsn(width as f64, &clearances)
+    }
+
+    /// Parses a Specctra clearance type such as `smd_smd` or `default_via` into the pair of
+    /// object kinds it applies to. Returns `None` for types that don't name a pair of kinds
+    /// known to Topola, e.g. layer-specific ones, so that they're ignored.
+    fn parse_clearance_type(
+        maybe_type: Option<&str>,
+    ) -> Option<(Option<ObjectKind>, Option<ObjectKind>)> {
+        let Some(r#type) = maybe_type else {
+            return Some((None, None));
+        };
+
+        let parse_kind = |name: &str| match name {
+            "default" => Some(None),
+            "wire" => Some(Some(ObjectKind::Wire)),
+            "via" => Some(Some(ObjectKind::Via)),
+            "pin" => Some(Some(ObjectKind::Pin)),
+            "smd" => Some(Some(ObjectKind::Smd)),
+            "area" => Some(Some(ObjectKind::Area)),
+            _ => None,
+        };
+
+        let mut names = r#type.split('_');
+        let kind1 = parse_kind(names.next()?)?;
+        let kind2 = parse_kind(names.next()?)?;
+
+        if names.next().is_some() {
+            return None;
+        }
+
+        Some((kind1, kind2))
+    }
+
+    /// Finds the clearance this rule sets between objects of the given kinds, if any.
+    ///
+    /// A clearance given for the exact pair of kinds takes precedence over the ones given for
+    /// either kind against the `default` type, which in turn take precedence over the clearance
+    /// given without a type.
+    pub fn clearance(&self, kind1: ObjectKind, kind2: ObjectKind) -> Option<f64> {
+        // Clearances of the `area` type apply to keepouts of wires alone as well.
+        let [kind1, kind2] = [kind1, kind2].map(|kind| match kind {
+            ObjectKind::WireArea => ObjectKind::Area,
+            kind => kind,
+        });
+
+        self.clearances
+            .get(&(Some(kind1), Some(kind2)))
+            .copied()
+            .or_else(|| {
+                [(Some(kind1), None), (None, Some(kind2))]
+                    .iter()
+                    .filter_map(|kinds| self.clearances.get(kinds).copied())
+                    .reduce(f64::max)
+            })
+            .or_else(|| self.clearances.get(&(None, None)).copied())
+    }
+
+    fn largest_clearance(&self) -> f64 {
+        self.clearances.values().copied().fold(0.0, f64::max)
+    }
+}
+
+#[derive(Debug)]
+/// [`SpecctraRegion`] is an area of the board with a rule of its own, read from a Specctra
+/// `region` statement.
+pub struct SpecctraRegion {
+    /// The identifier of the region in the design.
+    pub name: String,
+    /// The layer the region lies on, or `None` if it spans all layers.
+    pub maybe_layer: Option<usize>,
+    /// The outline of the region.
+    pub polygon: Polygon,
+    /// The rule applied to the objects within the region.
+    pub rule: SpecctraRule,
+}
+
+impl SpecctraRegion {
+    fn from_dsn(region: &Region, layer_layername: &BiHashMap<usize, String>) -> Option<Self> {
+        let (layername, exterior): (&str, LineString) = if let Some(rect) = &region.rect {
+            (
+                &rect.layer,
+                LineString::from(vec![
+                    (rect.x1, rect.y1),
+                    (rect.x2, rect.y1),
+                    (rect.x2, rect.y2),
+                    (rect.x1, rect.y2),
+                ]),
+            )
+        } else {
+            let polygon = region.polygon.as_ref()?;
+            (
+                &polygon.layer,
+                polygon
+                    .coords
+                    .iter()
+                    .map(|coord| (coord.x, coord.y))
+                    .collect(),
+            )
+        };
+
+        Some(Self {
+            name: region.id.clone(),
+            // Regions given on the `signal` layer, or any other unknown one, span all layers.
+            maybe_layer: layer_layername.get_by_right(layername).copied(),
+            polygon: Polygon::new(exterior, vec![]),
+            rule: SpecctraRule::from_structure_rules(&region.rules),
+        })
+    }
+
+    /// Returns the box bounding the region on the layers it lies on, as it's stored in the
+    /// R-tree of regions.
+    fn bbox(&self, layer_count: usize) -> Bbox {
+        let rect = self.polygon.bounding_rect().unwrap();
+        let (min_layer, max_layer) = match self.maybe_layer {
+            Some(layer) => (layer, layer),
+            None => (0, layer_count.saturating_sub(1)),
+        };
+
+        Bbox::new(AABB::from_corners(
+            [rect.min().x, rect.min().y, min_layer as f64],
+            [rect.max().x, rect.max().y, max_layer as f64],
+        ))
+    }
+
+    /// Whether the given shape overlaps the region, not only with its center.
+    fn intersects(&self, shape: &PrimitiveShape) -> bool {
+        match shape {
+            PrimitiveShape::Dot(dot) => {
+                dot.circle.pos.euclidean_distance(&self.polygon) < dot.circle.r
+            }
+            PrimitiveShape::Seg(seg) => seg.polygon().intersects(&self.polygon),
+            PrimitiveShape::Bend(bend) => {
+                LineString::from(bend.polyline(bend.width / 2.0)).euclidean_distance(&self.polygon)
+                    < bend.width / 2.0
+            }
         }
     }
 }
 
+#[derive(Debug)]
+/// [`SpecctraVia`] is a via padstack, as named by the `via` statement of the structure or by
+/// the `use_via` circuit descriptors of net classes.
+struct SpecctraVia {
+    /// The diameter of the circle enclosing the largest shape of the padstack.
+    diameter: f64,
+    /// The first and the last layer the shapes of the padstack lie on.
+    layers: (usize, usize),
+}
+
+impl SpecctraVia {
+    fn from_padstack(
+        padstack: &Padstack,
+        layer_layername: &BiHashMap<usize, String>,
+    ) -> Option<Self> {
+        Some(Self {
+            diameter: padstack.diameter(),
+            layers: padstack.layer_span(layer_layername)?,
+        })
+    }
+}
+
 #[derive(Debug)]
 /// [`SpecctraMesadata`] holds the metadata required by the Specctra auto-router to
 /// understand and enforce design rules across various net classes and layers in a PCB layout.
@@ -50,6 +245,41 @@ pub struct SpecctraMesadata {
     /// These rules are applied to all nets belonging to the respective net clas
     class_rules: HashMap<String, SpecctraRule>,
 
+    // (net class name, net class name) -> rule
+    /// A map from pairs of net class names, in both orders, to the rules of the `class_class`
+    /// statements given for them. These set the clearances between nets of the two classes.
+    class_class_rules: HashMap<(String, String), SpecctraRule>,
+
+    // layer -> rule
+    /// A map from layer indices to the rules given in the descriptors of these layers, which
+    /// override the net class rules on their layer.
+    layer_rules: HashMap<usize, SpecctraRule>,
+
+    /// Areas of the board whose rules override all other rules within them.
+    regions: Vec<SpecctraRegion>,
+
+    /// An R-tree of the boxes bounding the regions, keyed by their indices in `regions`, so
+    /// that finding the region of an object doesn't take a scan through all of them.
+    region_rtree: RTree<GeomWithData<Bbox, usize>>,
+
+    /// The clearance between the board boundary and any copper set by a structure clearance of
+    /// type `boundary`, if there's one.
+    maybe_boundary_clearance: Option<f64>,
+
+    // net class name -> via
+    /// A map from net class names to the via padstacks their `use_via` circuit descriptors
+    /// name.
+    class_vias: HashMap<String, SpecctraVia>,
+
+    /// The first via padstack listed in the structure, used for nets whose class doesn't name
+    /// one.
+    maybe_structure_via: Option<SpecctraVia>,
+
+    // net class name -> priority
+    /// A map from net class names to the priorities their circuit descriptors give them.
+    /// Specctra routes classes of higher priority first.
+    class_priorities: HashMap<String, u32>,
+
     // layername <-> layer for Layout
     /// A bidirectional map between layer indices and layer names, allowing translation
     /// between index-based layers in the layout and user-defined layer names.
@@ -64,6 +294,10 @@ pub struct SpecctraMesadata {
     /// A map that associates network indices with their respective net class names.
     /// This is used to apply net class-specific routing rules to each net.
     net_netclass: HashMap<usize, String>,
+
+    /// The differential pairs declared by the `pair` descriptors of the network or through
+    /// [`AccessMesadata::pair_nets`].
+    net_pairs: NetPairs,
 }
 
 impl SpecctraMesadata {
@@ -101,30 +335,114 @@ impl SpecctraMesadata {
                         net_netclass.insert(*net, class.name.clone());
                     }
                 })
-                .map(|class| (class.name.clone(), SpecctraRule::from_dsn(&class.rule))),
+                .map(|class| {
+                    (
+                        class.name.clone(),
+                        SpecctraRule::from_dsn(class.rule.width as f64, &class.rule.clearances),
+                    )
+                }),
         );
 
-        let mut structure_rule = super::structure::Rule {
-            width: 0.0,
-            clearances: Vec::new(),
-        };
-        // workaround for differing syntax
-        // collapse multiple rule entries into a single one
-        for rule in &pcb.structure.rules {
-            if rule.width.is_some() {
-                structure_rule.width = rule.width.unwrap()
+        let mut class_class_rules = HashMap::new();
+        for class_class in &pcb.network.class_classes {
+            let [class1, class2] = class_class.classes.names.as_slice() else {
+                continue;
+            };
+
+            class_class_rules.insert(
+                (class1.clone(), class2.clone()),
+                SpecctraRule::from_structure_rules(&class_class.rules),
+            );
+            class_class_rules.insert(
+                (class2.clone(), class1.clone()),
+                SpecctraRule::from_structure_rules(&class_class.rules),
+            );
+        }
+
+        let layer_rules = HashMap::from_iter(pcb.structure.layers.iter().enumerate().filter_map(
+            |(layer, layer_desc)| {
+                let rule = layer_desc.rule.as_ref()?;
+                Some((layer, SpecctraRule::from_structure_rules([rule])))
+            },
+        ));
+
+        let regions: Vec<SpecctraRegion> = pcb
+            .structure
+            .regions
+            .iter()
+            .filter_map(|region| SpecctraRegion::from_dsn(region, &layer_layername))
+            .collect();
+        let region_rtree = RTree::bulk_load(
+            regions
+                .iter()
+                .enumerate()
+                .map(|(index, region)| GeomWithData::new(region.bbox(layer_layername.len()), index))
+                .collect(),
+        );
+
+        let maybe_boundary_clearance = pcb
+            .structure
+            .rules
+            .iter()
+            .flat_map(|rule| &rule.clearances)
+            .rfind(|clearance| clearance.r#type.as_deref() == Some("boundary"))
+            .map(|clearance| clearance.value as f64);
+
+        let class_vias = HashMap::from_iter(pcb.network.classes.iter().filter_map(|class| {
+            let padstack = pcb.library.find_padstack_by_name(&class.circuit.use_via)?;
+            Some((
+                class.name.clone(),
+                SpecctraVia::from_padstack(padstack, &layer_layername)?,
+            ))
+        }));
+
+        let class_priorities = HashMap::from_iter(
+            pcb.network
+                .classes
+                .iter()
+                .filter_map(|class| Some((class.name.clone(), class.circuit.priority?))),
+        );
+
+        let mut net_pairs = NetPairs::default();
+
+        for pair in &pcb.network.pairs {
+            let [netname1, netname2] = pair.nets.names.as_slice() else {
+                continue;
+            };
+
+            if let (Some(net1), Some(net2)) = (
+                net_netname.get_by_right(netname1),
+                net_netname.get_by_right(netname2),
+            ) {
+                net_pairs.insert(*net1, *net2, pair.gap.map(|gap| gap as f64));
             }
-            structure_rule
-                .clearances
-                .extend_from_slice(&rule.clearances);
         }
 
+        let maybe_structure_via = pcb
+            .structure
+            .via
+            .names
+            .first()
+            .and_then(|name| pcb.library.find_padstack_by_name(name))
+            .and_then(|padstack| SpecctraVia::from_padstack(padstack, &layer_layername));
+
         Self {
-            structure_rule: SpecctraRule::from_dsn(&structure_rule),
+            // workaround for differing syntax
+            // collapse multiple rule entries into a single one
+            structure_rule: SpecctraRule::from_structure_rules(&pcb.structure.rules),
             class_rules,
+            class_class_rules,
+            layer_rules,
+            regions,
+            region_rtree,
+            maybe_boundary_clearance,
+            class_vias,
+            maybe_structure_via,
+            class_priorities,
             layer_layername,
             net_netname,
             net_netclass,
+            net_pairs,
         }
     }
 
@@ -146,32 +464,159 @@ impl SpecctraMesadata {
     }
 }
 
+impl SpecctraMesadata {
+    /// Finds the via padstack of the given net: the one its net class names or, if it names
+    /// none, the first one listed in the structure.
+    fn via(&self, net: usize) -> Option<&SpecctraVia> {
+        self.net_netclass
+            .get(&net)
+            .and_then(|netclass| self.class_vias.get(netclass))
+            .or(self.maybe_structure_via.as_ref())
+    }
+
+    /// Finds the clearance that the rules of the given net set between objects of the given
+    /// kinds, falling back on the structure rule if the net class rule sets none.
+    fn net_clearance(&self, net: usize, kind1: ObjectKind, kind2: ObjectKind) -> f64 {
+        self.get_rule(net)
+            .clearance(kind1, kind2)
+            .or_else(|| self.structure_rule.clearance(kind1, kind2))
+            .unwrap_or(0.0)
+    }
+}
+
 impl AccessRules for SpecctraMesadata {
+    /// Finds the clearance between two objects.
+    ///
+    /// The gap of a differential pair takes precedence between the nets of the pair, then the
+    /// rules of the regions the objects lie in, then the rules of their layer, then the
+    /// `class_class` rule of their net classes, and finally the rules of their nets.
     fn clearance(&self, conditions1: &Conditions, conditions2: &Conditions) -> f64 {
-        let (Some(net1), Some(net2)) = (conditions1.maybe_net, conditions2.maybe_net) else {
+        let (kind1, kind2) = (conditions1.kind, conditions2.kind);
+
+        if conditions1.maybe_net.is_none() && conditions2.maybe_net.is_none() {
             return 0.0;
-        };
+        }
+
+        if let Some(gap) = conditions1
+            .maybe_net
+            .zip(conditions2.maybe_net)
+            .and_then(|(net1, net2)| self.net_pairs.gap(net1, net2))
+        {
+            return gap;
+        }
 
-        let clr1 = self.get_rule(net1).clearance;
-        let clr2 = self.get_rule(net2).clearance;
+        let region_clearance = [conditions1, conditions2]
+            .iter()
+            .filter_map(|conditions| conditions.maybe_region)
+            .filter_map(|region| self.regions[region].rule.clearance(kind1, kind2))
+            .reduce(f64::max);
 
-        if clr1 > clr2 {
-            clr1
-        } else {
-            clr2
+        if let Some(clearance) = region_clearance {
+            return clearance;
         }
-    }
 
-    fn largest_clearance(&self, _maybe_net: Option<usize>) -> f64 {
-        let mut largest: f64 = 0.0;
+        let layer_clearance = [conditions1, conditions2]
+            .iter()
+            .filter_map(|conditions| conditions.maybe_layer)
+            .filter_map(|layer| self.layer_rules.get(&layer)?.clearance(kind1, kind2))
+            .reduce(f64::max);
+
+        if let Some(clearance) = layer_clearance {
+            return clearance;
+        }
 
-        for rule in self.class_rules.values() {
-            if rule.clearance > largest {
-                largest = rule.clearance;
+        let (net1, net2) = match (conditions1.maybe_net, conditions2.maybe_net) {
+            (Some(net1), Some(net2)) => (net1, net2),
+            (Some(net), None) | (None, Some(net)) => {
+                // The board boundary belongs to no net class, so a structure clearance of type
+                // `boundary` keeps every net apart from it.
+                if let Some(clearance) = self
+                    .maybe_boundary_clearance
+                    .filter(|_| kind1 == ObjectKind::Boundary || kind2 == ObjectKind::Boundary)
+                {
+                    return clearance;
+                }
+
+                // Otherwise, netful primitives are kept apart from netless obstacles, such as
+                // the board boundary and keepouts, by the clearance their net's rules set
+                // against the kind of the obstacle.
+                return self.net_clearance(net, kind1, kind2);
             }
+            (None, None) => unreachable!(),
+        };
+
+        if let Some(clearance) = self
+            .net_netclass
+            .get(&net1)
+            .zip(self.net_netclass.get(&net2))
+            .and_then(|(class1, class2)| {
+                self.class_class_rules
+                    .get(&(class1.clone(), class2.clone()))
+            })
+            .and_then(|rule| rule.clearance(kind1, kind2))
+        {
+            return clearance;
         }
 
-        largest
+        self.net_clearance(net1, kind1, kind2)
+            .max(self.net_clearance(net2, kind1, kind2))
+    }
+
+    fn largest_clearance(&self, _maybe_net: Option<usize>) -> f64 {
+        self.class_rules
+            .values()
+            .chain(self.class_class_rules.values())
+            .chain(self.layer_rules.values())
+            .chain(self.regions.iter().map(|region| &region.rule))
+            .map(SpecctraRule::largest_clearance)
+            .fold(self.structure_rule.largest_clearance(), f64::max)
+            .max(self.maybe_boundary_clearance.unwrap_or(0.0))
+            .max(self.net_pairs.largest_gap())
+    }
+
+    /// Finds the width of wires, giving precedence to the rule of the region they lie in, then
+    /// to the rule of their layer, then to the rule of their net class, and finally to the
+    /// structure rule.
+    fn maybe_width(&self, conditions: &Conditions) -> Option<f64> {
+        let region_rule = conditions
+            .maybe_region
+            .map(|region| &self.regions[region].rule);
+        let layer_rule = conditions
+            .maybe_layer
+            .and_then(|layer| self.layer_rules.get(&layer));
+        let net_rule = conditions.maybe_net.map(|net| self.get_rule(net));
+
+        // A width of zero means that the rule doesn't set any.
+        [
+            region_rule,
+            layer_rule,
+            net_rule,
+            Some(&self.structure_rule),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|rule| rule.width)
+        .find(|width| *width > 0.0)
+    }
+
+    fn maybe_via_diameter(&self, net: usize) -> Option<f64> {
+        self.via(net)
+            .map(|via| via.diameter)
+            .filter(|diameter| *diameter > 0.0)
+    }
+
+    fn maybe_via_layers(&self, net: usize) -> Option<(usize, usize)> {
+        self.via(net).map(|via| via.layers)
+    }
+
+    /// Finds the region the given shape overlaps. Where regions overlap one another, the one
+    /// given first in the design takes precedence.
+    fn maybe_region(&self, layer: usize, shape: &PrimitiveShape) -> Option<usize> {
+        self.region_rtree
+            .locate_in_envelope_intersecting(&shape.envelope_3d(0.0, layer))
+            .map(|region| region.data)
+            .filter(|region| self.regions[*region].intersects(shape))
+            .min()
     }
 }
 
@@ -199,4 +644,21 @@ impl AccessMesadata for SpecctraMesadata {
     fn netname_net(&self, netname: &str) -> Option<usize> {
         self.net_netname.get_by_right(netname).copied()
     }
+
+    fn net_priority(&self, net: usize) -> i64 {
+        self.net_netclass
+            .get(&net)
+            .and_then(|netclass| self.class_priorities.get(netclass))
+            .map_or(0, |priority| *priority as i64)
+    }
+
+    fn pair_nets(&mut self, net1: usize, net2: usize, maybe_gap: Option<f64>) {
+        self.net_pairs.insert(net1, net2, maybe_gap);
+    }
+
+    fn net_pair(&self, net: usize) -> Option<usize> {
+        self.net_pairs
+            .pair(net)
+            .or_else(|| self.netname_net(&complementary_netname(self.net_netname(net)?)?))
+    }
 }
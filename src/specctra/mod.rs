@@ -1,9 +1,9 @@
 //! Module containing the informations about handling the Specctra
 //! based file format, and parsing it into Topola's objects
 
-mod common;
+pub(crate) mod common;
 pub mod design;
 pub mod mesadata;
-mod read;
+pub(crate) mod read;
 mod structure;
 mod write;
@@ -41,6 +41,10 @@ impl InputToken {
         Self { token, context }
     }
 
+    pub fn context(&self) -> (usize, usize) {
+        self.context
+    }
+
     pub fn expect_start(self, name: &'static str) -> Result<(), ParseErrorContext> {
         self.token
             .expect_start(name)
@@ -168,6 +172,7 @@ pub struct ListTokenizer<R: std::io::BufRead> {
     cached_token: Option<InputToken>,
     space_in_quoted: bool,
     quote_char: Option<char>,
+    backslash_escapes: bool,
     line: usize,
     column: usize,
 }
@@ -180,11 +185,24 @@ impl<R: std::io::BufRead> ListTokenizer<R> {
             cached_token: None,
             space_in_quoted: false,
             quote_char: None,
+            backslash_escapes: false,
             line: 1,
             column: 0,
         }
     }
 
+    /// Creates a tokenizer for files whose strings are always quoted with the given character
+    /// and may contain spaces and characters escaped with a backslash, like KiCad's files,
+    /// rather than declaring how strings are quoted in their header.
+    pub fn new_with_escaped_quotes(reader: R, quote_char: char) -> Self {
+        Self {
+            space_in_quoted: true,
+            quote_char: Some(quote_char),
+            backslash_escapes: true,
+            ..Self::new(reader)
+        }
+    }
+
     pub fn context(&self) -> (usize, usize) {
         (self.line, self.column)
     }
@@ -232,7 +250,7 @@ impl<R: std::io::BufRead> ListTokenizer<R> {
     fn skip_whitespace(&mut self) -> Result<(), ParseErrorContext> {
         loop {
             let chr = self.peek_char()?;
-            if chr == ' ' || chr == '\r' || chr == '\n' {
+            if chr == ' ' || chr == '\t' || chr == '\r' || chr == '\n' {
                 self.next_char().unwrap();
             } else {
                 return Ok(());
@@ -254,7 +272,7 @@ impl<R: std::io::BufRead> ListTokenizer<R> {
 
         loop {
             let chr = self.peek_char()?;
-            if chr == ' ' || chr == '(' || chr == ')' || chr == '\r' || chr == '\n' {
+            if chr == ' ' || chr == '\t' || chr == '(' || chr == ')' || chr == '\r' || chr == '\n' {
                 break;
             }
             string.push(self.next_char().unwrap());
@@ -283,6 +301,17 @@ impl<R: std::io::BufRead> ListTokenizer<R> {
                 self.next_char().unwrap();
                 break;
             }
+            if self.backslash_escapes && chr == '\\' {
+                self.next_char().unwrap();
+
+                match self.next_char()? {
+                    'n' => string.push('\n'),
+                    'r' => string.push('\r'),
+                    't' => string.push('\t'),
+                    chr => string.push(chr),
+                }
+                continue;
+            }
             string.push(self.next_char().unwrap());
         }
 
@@ -1,3 +1,5 @@
+use bimap::BiHashMap;
+
 use super::common::ListToken;
 use super::read::ReadDsn;
 use super::read::{ListTokenizer, ParseError, ParseErrorContext};
@@ -42,7 +44,17 @@ pub struct NetOut {
     #[vec("wire")]
     pub wire: Vec<WireOut>,
     #[vec("via")]
-    pub via: Vec<Via>,
+    pub via: Vec<ViaOut>,
+}
+
+#[derive(ReadDsn, WriteSes, Debug)]
+pub struct ViaOut {
+    #[anon]
+    pub name: String,
+    #[anon]
+    pub x: f64,
+    #[anon]
+    pub y: f64,
 }
 
 #[derive(ReadDsn, WriteSes, Debug)]
@@ -87,6 +99,10 @@ pub struct Structure {
     pub boundary: Boundary,
     #[vec("plane")]
     pub planes: Vec<Plane>,
+    #[vec("region")]
+    pub regions: Vec<Region>,
+    #[vec("keepout")]
+    pub keepouts: Vec<Keepout>,
     pub via: ViaNames,
     #[vec("grid")]
     pub grids: Vec<Grid>,
@@ -104,6 +120,8 @@ impl<R: std::io::BufRead> ReadDsn<R> for Structure {
             layers: tokenizer.read_named_array("layer")?,
             boundary: tokenizer.read_named("boundary")?,
             planes: tokenizer.read_named_array("plane")?,
+            regions: tokenizer.read_named_array("region")?,
+            keepouts: Keepout::read_dsn_array(tokenizer)?,
             via: tokenizer.read_named("via")?,
             grids: tokenizer.read_named_array("grid")?,
             rules: tokenizer.read_named_array("rule")?,
@@ -123,6 +141,7 @@ pub struct Layer {
     pub name: String,
     pub r#type: String,
     pub property: Option<Property>,
+    pub rule: Option<StructureRule>,
 }
 
 #[derive(ReadDsn, WriteSes, Debug)]
@@ -132,7 +151,53 @@ pub struct Property {
 
 #[derive(ReadDsn, WriteSes, Debug)]
 pub struct Boundary {
-    pub path: Path,
+    pub path: Option<Path>,
+    pub rect: Option<Rect>,
+    pub polygon: Option<Polygon>,
+}
+
+impl Boundary {
+    /// Returns the vertices of the board outline, closed so that the last
+    /// vertex equals the first one, together with the width of its line.
+    pub fn outline(&self) -> (Vec<Point>, f64) {
+        let (mut coords, width) = if let Some(path) = &self.path {
+            (path.coords.clone(), path.width)
+        } else if let Some(polygon) = &self.polygon {
+            (polygon.coords.clone(), polygon.width)
+        } else if let Some(rect) = &self.rect {
+            (
+                vec![
+                    Point {
+                        x: rect.x1,
+                        y: rect.y1,
+                    },
+                    Point {
+                        x: rect.x2,
+                        y: rect.y1,
+                    },
+                    Point {
+                        x: rect.x2,
+                        y: rect.y2,
+                    },
+                    Point {
+                        x: rect.x1,
+                        y: rect.y2,
+                    },
+                ],
+                0.0,
+            )
+        } else {
+            (vec![], 0.0)
+        };
+
+        if let (Some(first), Some(last)) = (coords.first(), coords.last()) {
+            if first != last {
+                coords.push(*first);
+            }
+        }
+
+        (coords, width)
+    }
 }
 
 #[derive(ReadDsn, WriteSes, Debug)]
@@ -148,6 +213,17 @@ pub struct ViaNames {
     pub names: Vec<String>,
 }
 
+/// An area of the board with design rules of its own.
+#[derive(ReadDsn, WriteSes, Debug)]
+pub struct Region {
+    #[anon]
+    pub id: String,
+    pub rect: Option<Rect>,
+    pub polygon: Option<Polygon>,
+    #[vec("rule")]
+    pub rules: Vec<StructureRule>,
+}
+
 #[derive(ReadDsn, WriteSes, Debug)]
 pub struct Grid {
     #[anon]
@@ -216,7 +292,7 @@ impl Library {
     }
 }
 
-#[derive(ReadDsn, WriteSes, Debug)]
+#[derive(WriteSes, Debug)]
 pub struct Image {
     #[anon]
     pub name: String,
@@ -228,6 +304,18 @@ pub struct Image {
     pub keepouts: Vec<Keepout>,
 }
 
+// custom impl to read the keepouts of all kinds
+impl<R: std::io::BufRead> ReadDsn<R> for Image {
+    fn read_dsn(tokenizer: &mut ListTokenizer<R>) -> Result<Self, ParseErrorContext> {
+        Ok(Self {
+            name: tokenizer.read_value()?,
+            outlines: tokenizer.read_named_array("outline")?,
+            pins: tokenizer.read_named_array("pin")?,
+            keepouts: Keepout::read_dsn_array(tokenizer)?,
+        })
+    }
+}
+
 #[derive(ReadDsn, WriteSes, Debug)]
 pub struct Outline {
     pub path: Path,
@@ -253,14 +341,81 @@ impl Pin {
             rot: self.rotate.unwrap_or(0.0),
         }
     }
+
+    /// Like [`Pin::point_with_rotation`], but mirrored about the Y axis when the image is placed
+    /// on the back side.
+    pub fn point_with_rotation_on_side(&self, front: bool) -> PointWithRotation {
+        if front {
+            self.point_with_rotation()
+        } else {
+            PointWithRotation {
+                pos: (-self.x, self.y).into(),
+                rot: -self.rotate.unwrap_or(0.0),
+            }
+        }
+    }
 }
 
-#[derive(ReadDsn, WriteSes, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeepoutKind {
+    /// Keeps out both wires and vias.
+    Keepout,
+    /// Keeps out vias only.
+    ViaKeepout,
+    /// Keeps out wires only.
+    WireKeepout,
+}
+
+#[derive(Debug)]
 pub struct Keepout {
-    #[anon]
     pub idk: String,
-    #[anon]
     pub shape: Shape,
+    pub kind: KeepoutKind,
+}
+
+impl Keepout {
+    // custom reader, as the kind of a keepout is given by the name of its list
+    // and keepouts of different kinds may come in any order
+    fn read_dsn_array<R: std::io::BufRead>(
+        tokenizer: &mut ListTokenizer<R>,
+    ) -> Result<Vec<Self>, ParseErrorContext> {
+        let mut array = Vec::new();
+
+        loop {
+            let input = tokenizer.consume_token()?;
+            let maybe_kind = match input.token {
+                ListToken::Start { ref name } => match name.as_str() {
+                    "keepout" => Some(KeepoutKind::Keepout),
+                    "via_keepout" => Some(KeepoutKind::ViaKeepout),
+                    "wire_keepout" => Some(KeepoutKind::WireKeepout),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            let Some(kind) = maybe_kind else {
+                tokenizer.return_token(input);
+                break;
+            };
+
+            array.push(Self {
+                idk: tokenizer.read_value()?,
+                shape: tokenizer.read_value()?,
+                kind,
+            });
+            tokenizer.consume_token()?.expect_end()?;
+        }
+
+        Ok(array)
+    }
+}
+
+// custom impl, the kind is written by the list containing the keepout
+impl<W: std::io::Write> WriteSes<W> for Keepout {
+    fn write_dsn(&self, writer: &mut ListWriter<W>) -> Result<(), std::io::Error> {
+        writer.write_value(&self.idk)?;
+        writer.write_value(&self.shape)
+    }
 }
 
 #[derive(ReadDsn, WriteSes, Debug)]
@@ -272,6 +427,40 @@ pub struct Padstack {
     pub attach: Option<bool>,
 }
 
+impl Padstack {
+    /// The diameter of the circle enclosing the largest shape of the padstack, used as the
+    /// diameter of the vias made with it.
+    pub fn diameter(&self) -> f64 {
+        self.shapes
+            .iter()
+            .map(|shape| match shape {
+                Shape::Circle(circle) => circle.diameter,
+                Shape::Rect(rect) => (rect.x2 - rect.x1).abs().max((rect.y2 - rect.y1).abs()),
+                Shape::Path(path) => path.width,
+                Shape::Polygon(polygon) => polygon.width,
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// Finds the range of layers spanned by the shapes of the padstack, from the first to the
+    /// last.
+    ///
+    /// Shapes on layers that aren't named in the given map, such as `signal`, are taken to span
+    /// all layers.
+    pub fn layer_span(&self, layer_layername: &BiHashMap<usize, String>) -> Option<(usize, usize)> {
+        let mut layers = self.shapes.iter().map(|shape| {
+            layer_layername
+                .get_by_right(shape.layer())
+                .map_or((0, layer_layername.len().saturating_sub(1)), |layer| {
+                    (*layer, *layer)
+                })
+        });
+        let first = layers.next()?;
+
+        Some(layers.fold(first, |(from, to), (lo, hi)| (from.min(lo), to.max(hi))))
+    }
+}
+
 // TODO: derive for enums if more than this single one is needed
 #[derive(Debug)]
 pub enum Shape {
@@ -297,6 +486,58 @@ impl<R: std::io::BufRead> ReadDsn<R> for Shape {
     }
 }
 
+impl Shape {
+    pub fn layer(&self) -> &str {
+        match self {
+            Self::Circle(circle) => &circle.layer,
+            Self::Rect(rect) => &rect.layer,
+            Self::Path(path) => &path.layer,
+            Self::Polygon(polygon) => &polygon.layer,
+        }
+    }
+
+    /// Mirrors the shape about the Y axis, as images placed on the back side are.
+    pub fn mirrored(&self) -> Self {
+        let mirror_coords = |coords: &[Point]| -> Vec<Point> {
+            coords
+                .iter()
+                .map(|coord| Point {
+                    x: -coord.x,
+                    y: coord.y,
+                })
+                .collect()
+        };
+
+        match self {
+            Self::Circle(circle) => Self::Circle(Circle {
+                layer: circle.layer.clone(),
+                diameter: circle.diameter,
+                offset: circle.offset.map(|offset| Point {
+                    x: -offset.x,
+                    y: offset.y,
+                }),
+            }),
+            Self::Rect(rect) => Self::Rect(Rect {
+                layer: rect.layer.clone(),
+                x1: -rect.x2,
+                y1: rect.y1,
+                x2: -rect.x1,
+                y2: rect.y2,
+            }),
+            Self::Path(path) => Self::Path(Path {
+                layer: path.layer.clone(),
+                width: path.width,
+                coords: mirror_coords(&path.coords),
+            }),
+            Self::Polygon(polygon) => Self::Polygon(Polygon {
+                layer: polygon.layer.clone(),
+                width: polygon.width,
+                coords: mirror_coords(&polygon.coords),
+            }),
+        }
+    }
+}
+
 impl<W: std::io::Write> WriteSes<W> for Shape {
     fn write_dsn(&self, writer: &mut ListWriter<W>) -> Result<(), std::io::Error> {
         match self {
@@ -324,6 +565,10 @@ pub struct Network {
     pub nets: Vec<NetPinAssignments>,
     #[vec("class")]
     pub classes: Vec<Class>,
+    #[vec("class_class")]
+    pub class_classes: Vec<ClassClass>,
+    #[vec("pair")]
+    pub pairs: Vec<Pair>,
 }
 
 #[derive(ReadDsn, WriteSes, Debug)]
@@ -350,9 +595,35 @@ pub struct Class {
     pub rule: Rule,
 }
 
+#[derive(ReadDsn, WriteSes, Debug)]
+pub struct ClassClass {
+    pub classes: ClassNames,
+    #[vec("rule")]
+    pub rules: Vec<StructureRule>,
+}
+
+#[derive(ReadDsn, WriteSes, Debug)]
+pub struct ClassNames {
+    #[anon_vec]
+    pub names: Vec<String>,
+}
+
+#[derive(ReadDsn, WriteSes, Debug)]
+pub struct Pair {
+    pub nets: PairNets,
+    pub gap: Option<f32>,
+}
+
+#[derive(ReadDsn, WriteSes, Debug)]
+pub struct PairNets {
+    #[anon_vec]
+    pub names: Vec<String>,
+}
+
 #[derive(ReadDsn, WriteSes, Debug)]
 pub struct Circuit {
     pub use_via: String,
+    pub priority: Option<u32>,
 }
 
 #[derive(ReadDsn, WriteSes, Debug)]
@@ -370,9 +641,19 @@ pub struct Wire {
     pub r#type: String,
 }
 
+/// A wire of a session, either a trace along a path or an area of copper, such as the fill of
+/// a pour, outlined by a polygon with windows cut into it.
 #[derive(ReadDsn, WriteSes, Debug)]
 pub struct WireOut {
-    pub path: Path,
+    pub path: Option<Path>,
+    pub polygon: Option<Polygon>,
+    #[vec("window")]
+    pub windows: Vec<Window>,
+}
+
+#[derive(ReadDsn, WriteSes, Debug)]
+pub struct Window {
+    pub polygon: Polygon,
 }
 
 ////////////////////////////////////////////
@@ -383,7 +664,7 @@ pub struct WireOut {
 // more conveniently represented as fields on the enclosing struct)
 // It exists to give a way to read arrays of coordinates
 // (and enforce that such an array actually contains a whole number of points)
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -481,9 +762,9 @@ pub struct Via {
     #[anon]
     pub name: String,
     #[anon]
-    pub x: i32,
+    pub x: f64,
     #[anon]
-    pub y: i32,
+    pub y: f64,
     pub net: String,
     pub r#type: String,
 }
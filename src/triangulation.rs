@@ -2,7 +2,10 @@ use std::{cmp::Ordering, marker::PhantomData};
 
 use geo::{point, EuclideanDistance, Point};
 use petgraph::visit;
-use spade::{handles::FixedVertexHandle, DelaunayTriangulation, HasPosition, InsertionError};
+use spade::{
+    handles::FixedVertexHandle, DelaunayTriangulation, HasPosition, InsertionError, Point2,
+    PositionInTriangulation,
+};
 
 use crate::graph::GetPetgraphIndex;
 
@@ -58,6 +61,37 @@ impl<
         )
     }
 
+    /// Returns the centers of the triangles of the triangulation.
+    pub fn triangle_centers(&self) -> impl Iterator<Item = Point> + '_ {
+        spade::Triangulation::inner_faces(&self.triangulation).map(|face| {
+            let center = face.center();
+            point! {x: center.x, y: center.y}
+        })
+    }
+
+    /// Returns the vertices of the triangle containing the given point, or of the edge or the
+    /// vertex it lies on. For a point outside of the triangulation, the vertices of the closest
+    /// edge of its convex hull are returned.
+    pub fn locate_vertices(&self, point: Point) -> Vec<I> {
+        match spade::Triangulation::locate(&self.triangulation, Point2::new(point.x(), point.y())) {
+            PositionInTriangulation::OnVertex(vertex) => vec![self.vertex(vertex)],
+            PositionInTriangulation::OnEdge(edge)
+            | PositionInTriangulation::OutsideOfConvexHull(edge) => {
+                spade::Triangulation::directed_edge(&self.triangulation, edge)
+                    .vertices()
+                    .map(|vertex| self.vertex(vertex.fix()))
+                    .to_vec()
+            }
+            PositionInTriangulation::OnFace(face) => {
+                spade::Triangulation::face(&self.triangulation, face)
+                    .vertices()
+                    .map(|vertex| self.vertex(vertex.fix()))
+                    .to_vec()
+            }
+            PositionInTriangulation::NoTriangulation => vec![],
+        }
+    }
+
     pub fn position(&self, vertex: I) -> Point {
         let position =
             spade::Triangulation::vertex(&self.triangulation, self.handle(vertex)).position();
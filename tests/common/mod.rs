@@ -3,14 +3,24 @@ use std::{fs::File, io::BufReader};
 use petgraph::{stable_graph::NodeIndex, unionfind::UnionFind, visit::NodeIndexable};
 use topola::{
     autorouter::{
+        autoroute::AutorouteReport,
+        diff_pair::DiffPairOptions,
+        execution::{Command, ExecutionOutcome},
         history::HistoryError,
         invoker::{Invoker, InvokerError},
-        Autorouter,
+        ratline_ordering::RatlineOrdering,
+        selection::PinSelection,
+        Autorouter, AutorouterOptions,
     },
     board::{mesadata::AccessMesadata, Board},
-    drawing::graph::{GetLayer, GetMaybeNet},
-    geometry::shape::MeasureLength,
-    graph::{GetPetgraphIndex, MakeRef},
+    drawing::{
+        dot::FixedDotIndex,
+        graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex},
+    },
+    geometry::{shape::MeasureLength, GenericNode},
+    graph::{GenericIndex, GetPetgraphIndex, MakeRef},
+    layout::poly::PolyWeight,
+    router::RouterOptions,
     specctra::{design::SpecctraDesign, mesadata::SpecctraMesadata},
 };
 
@@ -58,6 +68,40 @@ pub fn replay_and_assert(invoker: &mut Invoker<SpecctraMesadata>, filename: &str
     );
 }
 
+/// The options the tests autoroute with, save for those they test in particular.
+pub fn autorouter_options() -> AutorouterOptions {
+    AutorouterOptions {
+        ratline_ordering: RatlineOrdering::Unordered,
+        router_options: RouterOptions {
+            routed_band_width: 100.0,
+            wrap_around_bands: true,
+            squeeze_through_under_bands: true,
+            via_cost: None,
+            shove_depth: 0,
+        },
+        max_ripups: 0,
+        ripup_cost_escalation: 2.0,
+        skip_failed_ratlines: false,
+        diff_pair_options: DiffPairOptions::default(),
+    }
+}
+
+/// Autoroutes every pin of the given layer as a command, returning what was routed.
+pub fn autoroute_layer(
+    invoker: &mut Invoker<SpecctraMesadata>,
+    layer: usize,
+    options: AutorouterOptions,
+) -> AutorouteReport {
+    let selection = PinSelection::new_select_layer(invoker.autorouter().board(), layer);
+    let outcome = invoker
+        .execute(Command::Autoroute(selection, options))
+        .unwrap();
+    let ExecutionOutcome::Autoroute(report) = outcome else {
+        panic!("autoroute yielded {:?}", outcome);
+    };
+    report
+}
+
 pub fn assert_single_layer_groundless_autoroute(
     autorouter: &mut Autorouter<impl AccessMesadata>,
     layername: &str,
@@ -159,6 +203,40 @@ pub fn assert_band_length(
     );
 }
 
+/// Finds the fixed dot on the given layer a band can be drawn from or around at the pin of the
+/// given name, which is the apex of the pin's polygon if it has one.
+pub fn pin_dot(board: &mut Board<SpecctraMesadata>, pinname: &str, layer: usize) -> FixedDotIndex {
+    let component = pinname.split('-').next().unwrap();
+    // Through-hole pins have nodes on every layer, which come in no particular order.
+    let nodes: Vec<_> = board
+        .component_nodes(component)
+        .filter(|node| board.node_pinname(node).unwrap() == pinname)
+        .collect();
+
+    let dot = nodes.iter().find_map(|node| match node {
+        GenericNode::Primitive(PrimitiveIndex::FixedDot(dot))
+            if dot.primitive(board.layout().drawing()).layer() == layer =>
+        {
+            Some(*dot)
+        }
+        _ => None,
+    });
+
+    dot.unwrap_or_else(|| {
+        let poly = nodes
+            .iter()
+            .find_map(|node| match node {
+                GenericNode::Compound(compound) => {
+                    let poly = GenericIndex::<PolyWeight>::new(compound.petgraph_index());
+                    (board.layout().poly(poly).layer() == layer).then_some(poly)
+                }
+                _ => None,
+            })
+            .unwrap();
+        board.poly_apex(poly)
+    })
+}
+
 fn unionfind(autorouter: &mut Autorouter<impl AccessMesadata>) -> UnionFind<NodeIndex<usize>> {
     for ratline in autorouter.ratsnest().graph().edge_indices() {
         // Accessing endpoints may create new dots because apex construction is lazy, so we access
@@ -0,0 +1,265 @@
+use std::{collections::BTreeMap, fs::File, io::BufReader};
+
+use geo::{coord, Rect};
+use topola::{
+    autorouter::{invoker::Invoker, Autorouter},
+    board::{mesadata::AccessMesadata, Board},
+    drawing::{
+        graph::{GetLayer, GetMaybeNet, MakePrimitive, PrimitiveIndex},
+        primitive::MakePrimitiveShape,
+    },
+    geometry::{shape::AccessShape, GenericNode},
+    kicad::{design::KicadDesign, mesadata::KicadMesadata},
+    layout::{
+        poly::PourPolyWeight,
+        pour::{Pour, PourOptions},
+    },
+};
+
+mod common;
+
+fn load_kicad_design(filename: &str) -> KicadDesign {
+    let design_file = File::open(filename).unwrap();
+    let mut design = KicadDesign::load(BufReader::new(design_file)).unwrap();
+
+    let project_file = File::open(std::path::Path::new(filename).with_extension("kicad_pro"));
+    design.load_project(project_file.unwrap()).unwrap();
+
+    design
+}
+
+fn sorted_pinnames(board: &Board<impl AccessMesadata>) -> Vec<String> {
+    let mut pinnames: Vec<String> = board
+        .layout()
+        .drawing()
+        .rtree()
+        .iter()
+        .filter_map(|wrapper| board.node_pinname(&wrapper.data).cloned())
+        .collect();
+    pinnames.sort();
+    pinnames.dedup();
+    pinnames
+}
+
+/// Finds the center of the bounding box of the primitives of each pin on each of its layers,
+/// which is where the pad is there, as pads are symmetric about their center.
+fn pin_layer_positions(
+    board: &Board<impl AccessMesadata>,
+) -> BTreeMap<(String, usize), (f64, f64)> {
+    let mut bboxes = BTreeMap::<(String, usize), Rect>::new();
+
+    for primitive in board.layout().drawing().primitive_nodes() {
+        let Some(pinname) = board.node_pinname(&GenericNode::Primitive(primitive)) else {
+            continue;
+        };
+        let primitive = primitive.primitive(board.layout().drawing());
+        let center = primitive.shape().center();
+        bboxes
+            .entry((pinname.clone(), primitive.layer()))
+            .and_modify(|bbox| {
+                *bbox = Rect::new(
+                    coord! {x: bbox.min().x.min(center.x()), y: bbox.min().y.min(center.y())},
+                    coord! {x: bbox.max().x.max(center.x()), y: bbox.max().y.max(center.y())},
+                )
+            })
+            .or_insert(Rect::new(center, center));
+    }
+
+    bboxes
+        .into_iter()
+        .map(|(key, bbox)| (key, bbox.center().x_y()))
+        .collect()
+}
+
+fn netful_primitive_count(board: &Board<impl AccessMesadata>) -> usize {
+    board
+        .layout()
+        .drawing()
+        .primitive_nodes()
+        .filter(|primitive| {
+            primitive
+                .primitive(board.layout().drawing())
+                .maybe_net()
+                .is_some()
+        })
+        .count()
+}
+
+#[test]
+fn test_tht_de9_to_tht_de9_same_as_specctra() {
+    let kicad_board =
+        load_kicad_design("tests/single_layer/tht_de9_to_tht_de9/tht_de9_to_tht_de9.kicad_pcb")
+            .make_board();
+    let specctra_invoker = common::load_design_and_assert(
+        "tests/single_layer/tht_de9_to_tht_de9/tht_de9_to_tht_de9.dsn",
+    );
+    let specctra_board = specctra_invoker.autorouter().board();
+
+    // The Specctra board also has its boundary, which is netless.
+    assert_eq!(
+        netful_primitive_count(&kicad_board),
+        netful_primitive_count(specctra_board)
+    );
+    assert_eq!(
+        sorted_pinnames(&kicad_board),
+        sorted_pinnames(specctra_board)
+    );
+}
+
+#[test]
+fn test_prerouted_lm317_breakout_pads_same_as_specctra() {
+    let kicad_board = load_kicad_design(
+        "tests/multilayer/prerouted_lm317_breakout/prerouted_lm317_breakout.kicad_pcb",
+    )
+    .make_board();
+    let specctra_invoker = common::load_design_and_assert(
+        "tests/multilayer/prerouted_lm317_breakout/prerouted_lm317_breakout.dsn",
+    );
+    let specctra_board = specctra_invoker.autorouter().board();
+
+    let kicad_positions = pin_layer_positions(&kicad_board);
+    let specctra_positions = pin_layer_positions(specctra_board);
+
+    let near = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).abs() < 1.0 && (a.1 - b.1).abs() < 1.0;
+
+    // C1 is placed on the back side.
+    assert!(near(
+        kicad_positions[&("C1-1".to_string(), 1)],
+        (124500.0, -81025.0)
+    ));
+
+    assert_eq!(
+        kicad_positions.keys().collect::<Vec<_>>(),
+        specctra_positions.keys().collect::<Vec<_>>()
+    );
+
+    for (key, kicad_pos) in &kicad_positions {
+        let specctra_pos = specctra_positions[key];
+        assert!(
+            near(*kicad_pos, specctra_pos),
+            "{:?} is at {:?} in KiCad but at {:?} in Specctra",
+            key,
+            kicad_pos,
+            specctra_pos
+        );
+    }
+}
+
+#[test]
+fn test_prerouted_lm317_breakout_net_classes() {
+    let board = load_kicad_design(
+        "tests/multilayer/prerouted_lm317_breakout/prerouted_lm317_breakout.kicad_pcb",
+    )
+    .make_board();
+    let mesadata: &KicadMesadata = board.mesadata();
+
+    assert_eq!(mesadata.layer_layername(0), Some("F.Cu"));
+    assert_eq!(mesadata.layer_layername(1), Some("B.Cu"));
+
+    let input = mesadata.netname_net("/wejście").unwrap();
+    let ground = mesadata.netname_net("GND").unwrap();
+    assert_eq!(mesadata.get_rule(input).track_width, 1200.0);
+    assert_eq!(mesadata.get_rule(ground).track_width, 250.0);
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_write_kicad_pcb() {
+    let design = load_kicad_design(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.kicad_pcb",
+    );
+    let mut invoker = Invoker::new(Autorouter::new(design.make_board()).unwrap());
+
+    let file =
+        File::open("tests/single_layer/tht_diode_bridge_rectifier/autoroute_all.cmd").unwrap();
+    invoker.replay(serde_json::from_reader(file).unwrap());
+
+    let (mut autorouter, ..) = invoker.dissolve();
+    common::assert_single_layer_groundless_autoroute(&mut autorouter, "F.Cu");
+
+    let mut written = vec![];
+    design
+        .write_kicad_pcb(autorouter.board(), &mut written)
+        .unwrap();
+
+    // Every routed seg and bend is written as a track of its own.
+    let loose_count = autorouter
+        .board()
+        .layout()
+        .drawing()
+        .primitive_nodes()
+        .filter(|primitive| {
+            matches!(
+                primitive,
+                PrimitiveIndex::LoneLooseSeg(..)
+                    | PrimitiveIndex::SeqLooseSeg(..)
+                    | PrimitiveIndex::LooseBend(..)
+            )
+        })
+        .count();
+    let written = String::from_utf8(written).unwrap();
+    let track_count = written.matches("\t(segment ").count() + written.matches("\t(arc ").count();
+    assert!(loose_count > 0);
+    assert_eq!(track_count, loose_count);
+
+    // The tracks are read back as fixed primitives.
+    let reloaded_board = KicadDesign::load(BufReader::new(written.as_bytes()))
+        .unwrap()
+        .make_board();
+    assert!(
+        reloaded_board.layout().drawing().node_count()
+            > design.make_board().layout().drawing().node_count() + loose_count
+    );
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_write_kicad_pcb_pours() {
+    let design = load_kicad_design(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.kicad_pcb",
+    );
+    let mut board = design.make_board();
+    let net = board.mesadata().netname_net("Net-(D1-A)").unwrap();
+
+    // A pour spanning the whole board.
+    let (mut min, mut max) = ([f64::INFINITY; 2], [-f64::INFINITY; 2]);
+
+    for node in board.layout().drawing().primitive_nodes() {
+        let center = node.primitive(board.layout().drawing()).shape().center();
+        min = [min[0].min(center.x()), min[1].min(center.y())];
+        max = [max[0].max(center.x()), max[1].max(center.y())];
+    }
+
+    board.layout_mut().add_pour(Pour::new(
+        PourPolyWeight {
+            layer: 0,
+            maybe_net: Some(net),
+        },
+        Rect::new(
+            coord! {x: min[0] - 5000.0, y: min[1] - 5000.0},
+            coord! {x: max[0] + 5000.0, y: max[1] + 5000.0},
+        )
+        .to_polygon(),
+        PourOptions::default(),
+    ));
+    board.layout_mut().fill_pours();
+    assert!(!board.layout().pours()[0].fill.0.is_empty());
+
+    let mut written = vec![];
+    design.write_kicad_pcb(&board, &mut written).unwrap();
+    let written = String::from_utf8(written).unwrap();
+
+    // The pour is written as a zone with thermal reliefs, along with its fill.
+    assert!(written.contains(&format!(
+        "\t(zone (net {}) (net_name \"Net-(D1-A)\") (layer \"F.Cu\") ",
+        net
+    )));
+    assert!(written.contains(" (connect_pads (clearance "));
+    assert!(written.contains(
+        " (fill yes (thermal_gap 0.5) (thermal_bridge_width 0.5) (island_removal_mode 0))"
+    ));
+    assert!(written.contains("(filled_polygon (layer \"F.Cu\") (pts (xy "));
+
+    // Zones are ignored when reading the board back.
+    KicadDesign::load(BufReader::new(written.as_bytes()))
+        .unwrap()
+        .make_board();
+}
@@ -1,8 +1,27 @@
+use std::{fs::File, io::BufReader};
+
+use geo::{point, Contains, EuclideanDistance};
 use topola::{
-    autorouter::{execution::Command, invoker::InvokerError, AutorouterError},
+    autorouter::{
+        check_design_rules::DesignRuleViolation,
+        execution::{Command, ExecutionOutcome},
+        invoker::{Invoker, InvokerError},
+        ratsnest::RatvertexWeight,
+        selection::PinSelection,
+        Autorouter, AutorouterError, AutorouterOptions,
+    },
     board::mesadata::AccessMesadata,
+    drawing::{
+        dot::FixedDotWeight,
+        graph::{GetLayer, GetMaybeNet, PrimitiveIndex},
+        primitive::GetWeight,
+        rules::{AccessRules, Conditions, GetConditions, ObjectKind},
+        seg::FixedSegWeight,
+    },
     layout::via::ViaWeight,
     math::Circle,
+    specctra::{design::SpecctraDesign, mesadata::SpecctraMesadata},
+    triangulation::GetTrianvertexNodeIndex,
 };
 
 mod common;
@@ -30,6 +49,442 @@ fn test_unrouted_lm317_breakout() {
     ));
 }
 
+#[test]
+fn test_unrouted_lm317_breakout_via_on_boundary() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/multilayer/prerouted_lm317_breakout/unrouted_lm317_breakout.dsn",
+    );
+
+    let net = invoker
+        .autorouter()
+        .board()
+        .mesadata()
+        .netname_net("GND")
+        .unwrap();
+
+    // The via would stick out of the right edge of the board.
+    let result = invoker.execute(Command::PlaceVia(ViaWeight {
+        from_layer: 0,
+        to_layer: 1,
+        circle: Circle {
+            pos: [149000.0, -78000.0].into(),
+            r: 400.0,
+        },
+        maybe_net: Some(net),
+    }));
+    assert!(matches!(
+        result,
+        Err(InvokerError::Autorouter(AutorouterError::CouldNotPlaceVia(
+            ..
+        )))
+    ));
+}
+
+#[test]
+fn test_unrouted_lm317_breakout_via_outside_boundary() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/multilayer/prerouted_lm317_breakout/unrouted_lm317_breakout.dsn",
+    );
+
+    let net = invoker
+        .autorouter()
+        .board()
+        .mesadata()
+        .netname_net("GND")
+        .unwrap();
+
+    // The via would lie far right of the board, touching nothing.
+    let result = invoker.execute(Command::PlaceVia(ViaWeight {
+        from_layer: 0,
+        to_layer: 1,
+        circle: Circle {
+            pos: [180000.0, -78000.0].into(),
+            r: 400.0,
+        },
+        maybe_net: Some(net),
+    }));
+    assert!(matches!(
+        result,
+        Err(InvokerError::Autorouter(AutorouterError::CouldNotPlaceVia(
+            ..
+        )))
+    ));
+}
+
+#[test]
+fn test_unrouted_lm317_breakout_write_ses_vias() {
+    let design_file =
+        File::open("tests/multilayer/prerouted_lm317_breakout/unrouted_lm317_breakout.dsn")
+            .unwrap();
+    let design = SpecctraDesign::load(BufReader::new(design_file)).unwrap();
+    let mut invoker = Invoker::new(Autorouter::new(design.make_board()).unwrap());
+
+    let net = invoker
+        .autorouter()
+        .board()
+        .mesadata()
+        .netname_net("GND")
+        .unwrap();
+    invoker
+        .execute(Command::PlaceVia(ViaWeight {
+            from_layer: 0,
+            to_layer: 1,
+            circle: Circle {
+                pos: [115000.0, -75000.0].into(),
+                r: 400.0,
+            },
+            maybe_net: Some(net),
+        }))
+        .unwrap();
+
+    let mut ses = vec![];
+    design
+        .write_ses(invoker.autorouter().board(), 1.0, &mut ses)
+        .unwrap();
+    let ses = String::from_utf8(ses).unwrap();
+
+    assert!(ses.contains("(net GND"));
+    assert!(ses.contains("(via Via[0-1]_800:400_um 115000 -75000)"));
+}
+
+#[test]
+fn test_unrouted_lm317_breakout_undo_place_via() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/multilayer/prerouted_lm317_breakout/unrouted_lm317_breakout.dsn",
+    );
+    let prev_node_count = invoker.autorouter().board().layout().drawing().node_count();
+
+    let net = invoker
+        .autorouter()
+        .board()
+        .mesadata()
+        .netname_net("GND")
+        .unwrap();
+    invoker
+        .execute(Command::PlaceVia(ViaWeight {
+            from_layer: 0,
+            to_layer: 1,
+            circle: Circle {
+                pos: [115000.0, -75000.0].into(),
+                r: 400.0,
+            },
+            maybe_net: Some(net),
+        }))
+        .unwrap();
+    assert_eq!(invoker.autorouter().board().layout().via_nodes().count(), 1);
+
+    invoker.undo().unwrap();
+    assert_eq!(invoker.autorouter().board().layout().via_nodes().count(), 0);
+    assert_eq!(
+        invoker.autorouter().board().layout().drawing().node_count(),
+        prev_node_count
+    );
+
+    invoker.redo().unwrap();
+    assert_eq!(invoker.autorouter().board().layout().via_nodes().count(), 1);
+}
+
+#[test]
+fn test_unrouted_lm317_breakout_pours() {
+    let invoker = common::load_design_and_assert(
+        "tests/multilayer/prerouted_lm317_breakout/unrouted_lm317_breakout.dsn",
+    );
+    let board = invoker.autorouter().board();
+    let drawing = board.layout().drawing();
+    let ground = board.mesadata().netname_net("GND").unwrap();
+
+    // Both GND planes are poured.
+    let pours = board.layout().pours();
+    assert_eq!(pours.len(), 2);
+    assert!(pours.iter().all(|pour| pour.maybe_net() == Some(ground)));
+    let pour = pours.iter().find(|pour| pour.layer() == 0).unwrap();
+    assert!(!pour.fill.0.is_empty());
+
+    // The fill keeps its clearance from the pads of other nets.
+    let pour_conditions = Conditions {
+        maybe_net: Some(ground),
+        kind: ObjectKind::Wire,
+        maybe_region: None,
+        maybe_layer: Some(0),
+    };
+
+    for node in drawing.layer_primitive_nodes(0) {
+        let PrimitiveIndex::FixedDot(dot) = node else {
+            continue;
+        };
+
+        if drawing.primitive(dot).maybe_net() == Some(ground) {
+            continue;
+        }
+
+        let circle = drawing.primitive(dot).weight().circle;
+        let clearance = drawing
+            .rules()
+            .clearance(&pour_conditions, &drawing.primitive(dot).conditions());
+
+        for polygon in pour.fill.iter() {
+            assert!(circle.pos.euclidean_distance(polygon) >= circle.r + clearance - 0.01);
+        }
+    }
+
+    // The GND pad of C3 is connected through thermal relief spokes along the axes, with a gap
+    // between them.
+    let pad_pos = point! {x: 133500.0, y: -74000.0};
+    let gap_radius = 800.0 + pour.options.thermal_gap / 2.0;
+    assert!(pour
+        .fill
+        .contains(&(pad_pos + point! {x: gap_radius, y: 0.0})));
+    assert!(pour
+        .fill
+        .contains(&(pad_pos + point! {x: 0.0, y: -gap_radius})));
+    assert!(!pour
+        .fill
+        .contains(&(pad_pos + point! {x: gap_radius, y: gap_radius} / 2.0_f64.sqrt())));
+    assert!(pour
+        .fill
+        .contains(&(pad_pos + point! {x: 2000.0, y: 2000.0} / 2.0_f64.sqrt())));
+}
+
+#[test]
+fn test_unrouted_lm317_breakout_check_design_rules() {
+    let design_file =
+        File::open("tests/multilayer/prerouted_lm317_breakout/unrouted_lm317_breakout.dsn")
+            .unwrap();
+    let design = SpecctraDesign::load(BufReader::new(design_file)).unwrap();
+    let pour_clearance_violations = |violations: Vec<DesignRuleViolation>| {
+        violations
+            .into_iter()
+            .filter(|violation| matches!(violation, DesignRuleViolation::PourClearance { .. }))
+            .collect::<Vec<_>>()
+    };
+
+    let mut invoker = Invoker::new(Autorouter::new(design.make_board()).unwrap());
+    let ExecutionOutcome::CheckDesignRules(violations) =
+        invoker.execute(Command::CheckDesignRules).unwrap()
+    else {
+        panic!();
+    };
+    // Nothing is routed yet, so each ratline is reported once, between pins whose pads are
+    // connected across layers.
+    assert_eq!(
+        violations
+            .iter()
+            .filter(|violation| matches!(violation, DesignRuleViolation::UnconnectedRatline { .. }))
+            .count(),
+        invoker.autorouter().ratsnest().graph().edge_count()
+    );
+    // The pours were filled on loading, so their fills keep their clearances.
+    assert!(pour_clearance_violations(violations).is_empty());
+
+    // A dot of another net put into the GND fill near C3 without refilling the pours.
+    let mut board = design.make_board();
+    let net = board.mesadata().netname_net("/adj").unwrap();
+    let pos = point! {x: 133500.0, y: -74000.0} + point! {x: 2000.0, y: 2000.0} / 2.0_f64.sqrt();
+    let dot = board.add_fixed_dot_infringably(
+        FixedDotWeight {
+            circle: Circle { pos, r: 200.0 },
+            layer: 0,
+            maybe_net: Some(net),
+            kind: ObjectKind::Via,
+        },
+        None,
+    );
+
+    let mut invoker = Invoker::new(Autorouter::new(board).unwrap());
+    let ExecutionOutcome::CheckDesignRules(violations) =
+        invoker.execute(Command::CheckDesignRules).unwrap()
+    else {
+        panic!();
+    };
+    let violations = pour_clearance_violations(violations);
+    assert_eq!(violations.len(), 1);
+    let DesignRuleViolation::PourClearance {
+        primitive,
+        netnames,
+        layername,
+        pos: violation_pos,
+        ..
+    } = &violations[0]
+    else {
+        unreachable!();
+    };
+    assert_eq!(*primitive, PrimitiveIndex::FixedDot(dot));
+    assert_eq!(
+        *netnames,
+        (Some("GND".to_string()), Some("/adj".to_string()))
+    );
+    assert_eq!(layername.as_deref(), Some("F.Cu"));
+    assert!(violation_pos.euclidean_distance(&pos) < 1000.0);
+}
+
+#[test]
+fn test_unrouted_lm317_breakout_pours_refilled_after_place_via() {
+    let design_file =
+        File::open("tests/multilayer/prerouted_lm317_breakout/unrouted_lm317_breakout.dsn")
+            .unwrap();
+    let design = SpecctraDesign::load(BufReader::new(design_file)).unwrap();
+    let mut invoker = Invoker::new(Autorouter::new(design.make_board()).unwrap());
+    let via_pos = point! {x: 120000.0, y: -75000.0};
+    let fill_contains_via_pos = |invoker: &Invoker<_>| {
+        invoker
+            .autorouter()
+            .board()
+            .layout()
+            .pours()
+            .iter()
+            .all(|pour| pour.fill.contains(&via_pos))
+    };
+    assert!(fill_contains_via_pos(&invoker));
+
+    let net = invoker
+        .autorouter()
+        .board()
+        .mesadata()
+        .netname_net("/adj")
+        .unwrap();
+    invoker
+        .execute(Command::PlaceVia(ViaWeight {
+            from_layer: 0,
+            to_layer: 1,
+            circle: Circle {
+                pos: via_pos,
+                r: 400.0,
+            },
+            maybe_net: Some(net),
+        }))
+        .unwrap();
+    assert!(!fill_contains_via_pos(&invoker));
+
+    // The fills are written to the session as polygon wires with their holes as windows.
+    let mut ses = vec![];
+    design
+        .write_ses(invoker.autorouter().board(), 1.0, &mut ses)
+        .unwrap();
+    let ses = String::from_utf8(ses).unwrap();
+    assert!(ses.contains("(polygon F.Cu 0 "));
+    assert!(ses.contains("(polygon B.Cu 0 "));
+    assert!(ses.contains("(window"));
+
+    invoker.undo().unwrap();
+    assert!(fill_contains_via_pos(&invoker));
+}
+
+/// The test options, with vias made costly enough that the router prefers staying on a layer.
+fn costly_via_options() -> AutorouterOptions {
+    let mut options = common::autorouter_options();
+    options.router_options.via_cost = Some(1000.0);
+    options
+}
+
+const ACROSS_WALL_WIRE_KEEPOUT: &str =
+    "(wire_keepout \"\" (rect F.Cu 141000 -104000 141200 -96000))";
+
+/// Loads the board on which the resistor is walled off from the pin header, with the wall
+/// replaced by the given keepout, then autoroutes the GND band between them.
+fn autoroute_0603_breakout_across_wall(
+    keepout: &str,
+) -> (
+    Invoker<SpecctraMesadata>,
+    Result<ExecutionOutcome, InvokerError>,
+) {
+    let dsn = std::fs::read_to_string(
+        "tests/multilayer/0603_breakout_across_wall/0603_breakout_across_wall.dsn",
+    )
+    .unwrap()
+    .replacen(ACROSS_WALL_WIRE_KEEPOUT, keepout, 1);
+    let design = SpecctraDesign::load(BufReader::new(dsn.as_bytes())).unwrap();
+    let mut invoker = Invoker::new(Autorouter::new(design.make_board()).unwrap());
+    let selection: PinSelection = serde_json::from_str(
+        r#"{"selectors": [{"pin": "R1-2", "layer": "F.Cu"}, {"pin": "J1-2", "layer": "F.Cu"}]}"#,
+    )
+    .unwrap();
+
+    let result = invoker.execute(Command::Autoroute(selection, costly_via_options()));
+    (invoker, result)
+}
+
+#[test]
+fn test_0603_breakout_vias_across_keepout_wall() {
+    // The wall is on the front layer only, so that the GND band can only get across on the
+    // back layer.
+    let (mut invoker, result) = autoroute_0603_breakout_across_wall(ACROSS_WALL_WIRE_KEEPOUT);
+    result.unwrap();
+
+    let via_count = invoker.autorouter().board().layout().via_nodes().count();
+    assert!(via_count >= 2);
+
+    let prev_node_count = invoker.autorouter().board().layout().drawing().node_count();
+
+    // Undoing the autoroute removes the vias together with the bands.
+    invoker.undo().unwrap();
+    assert_eq!(invoker.autorouter().board().layout().via_nodes().count(), 0);
+
+    invoker.redo().unwrap();
+    assert_eq!(
+        invoker.autorouter().board().layout().drawing().node_count(),
+        prev_node_count
+    );
+}
+
+#[test]
+fn test_0603_breakout_keepout_wall_blocks_bands() {
+    // The wall is on every layer, so that the GND band can't get across at all.
+    let (_, result) = autoroute_0603_breakout_across_wall(
+        "(keepout \"\" (rect signal 141000 -104000 141200 -96000))",
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_0603_breakout_ratsnest_across_layers() {
+    // Flip the resistor to the back layer and leave the pin header with copper on the front
+    // layer only, so that each net has its pads on two different layers.
+    let dsn = std::fs::read_to_string("tests/single_layer/0603_breakout/0603_breakout.dsn")
+        .unwrap()
+        .replacen(
+            "(place R1 142100.000000 -99900.000000 front",
+            "(place R1 142100.000000 -99900.000000 back",
+            1,
+        )
+        .replacen("      (shape (path B.Cu 850  0 0  0 0))\n", "", 1)
+        .replacen("      (shape (rect B.Cu -425 -425 425 425))\n", "", 1);
+    let design = SpecctraDesign::load(BufReader::new(dsn.as_bytes())).unwrap();
+    let mut invoker = Invoker::new(Autorouter::new(design.make_board()).unwrap());
+
+    let ratsnest = invoker.autorouter().ratsnest();
+    assert_eq!(ratsnest.graph().edge_count(), 2);
+    assert!(ratsnest
+        .graph()
+        .edge_indices()
+        .all(|ratline| ratsnest.requires_layer_change(ratline)));
+
+    let selection: PinSelection = serde_json::from_str(
+        r#"{"selectors": [{"pin": "R1-2", "layer": "B.Cu"}, {"pin": "J1-2", "layer": "F.Cu"}]}"#,
+    )
+    .unwrap();
+
+    invoker
+        .execute(Command::Autoroute(selection, costly_via_options()))
+        .unwrap();
+
+    assert!(invoker.autorouter().board().layout().via_nodes().count() >= 1);
+}
+
+#[test]
+fn test_0603_breakout_ratsnest_joins_through_hole_pads_on_common_layer() {
+    let invoker =
+        common::load_design_and_assert("tests/single_layer/0603_breakout/0603_breakout.dsn");
+
+    // The through-hole pads of the pin header have copper on both layers, so the ratlines
+    // leading to them stay on the front layer of the resistor.
+    let ratsnest = invoker.autorouter().ratsnest();
+    assert_eq!(ratsnest.graph().edge_count(), 2);
+    assert!(ratsnest
+        .graph()
+        .edge_indices()
+        .all(|ratline| !ratsnest.requires_layer_change(ratline)));
+}
+
 #[test]
 fn test_signal_integrity_test() {
     let invoker = common::load_design_and_assert(
@@ -70,3 +525,84 @@ fn test_signal_integrity_test() {
         }
     }
 }
+
+#[test]
+fn test_signal_integrity_test_vias() {
+    let invoker = common::load_design_and_assert(
+        "tests/multilayer/signal_integrity_test/signal_integrity_test.dsn",
+    );
+    let layout = invoker.autorouter().board().layout();
+    let mesadata = invoker.autorouter().board().mesadata();
+
+    let vias: Vec<ViaWeight> = layout
+        .via_nodes()
+        .map(|via| layout.via(via).weight())
+        .collect();
+    assert_eq!(vias.len(), 6);
+
+    // The GND vias go through all four layers, while those of the input net stop at In2.Cu.
+    for via in &vias {
+        let netname = mesadata.net_netname(via.maybe_net.unwrap()).unwrap();
+        assert_eq!(via.from_layer, 0);
+        assert_eq!(via.to_layer, if netname == "GND" { 3 } else { 2 });
+        assert_eq!(via.circle.r, 1000.0);
+    }
+
+    assert!(vias
+        .iter()
+        .any(|via| via.circle.pos == [106680.0, -109220.0].into()));
+    assert!(vias
+        .iter()
+        .any(|via| via.circle.pos == [110744.0, -101600.0].into()));
+}
+
+#[test]
+fn test_unrouted_lm317_breakout_ratsnest_of_back_layer_wire() {
+    let design_file =
+        File::open("tests/multilayer/prerouted_lm317_breakout/unrouted_lm317_breakout.dsn")
+            .unwrap();
+    let design = SpecctraDesign::load(BufReader::new(design_file)).unwrap();
+    let mut board = design.make_board();
+    let unrouted_ratlines = ratline_pinnames(&Autorouter::new(design.make_board()).unwrap());
+    assert!(unrouted_ratlines.contains(&("C3-2".to_string(), "C2-2".to_string())));
+
+    // The ratline between the GND pins is drawn on the front layer, but a wire joins them on
+    // the back layer.
+    let from = common::pin_dot(&mut board, "C3-2", 1);
+    let to = common::pin_dot(&mut board, "C2-2", 1);
+    let maybe_net = board.layout().drawing().primitive(from).maybe_net();
+    board.add_fixed_seg_infringably(
+        from,
+        to,
+        FixedSegWeight {
+            width: 500.0,
+            layer: 1,
+            maybe_net,
+            kind: ObjectKind::Wire,
+        },
+        None,
+    );
+
+    let ratlines = ratline_pinnames(&Autorouter::new(board).unwrap());
+    assert_eq!(ratlines.len(), unrouted_ratlines.len() - 1);
+    assert!(!ratlines.contains(&("C3-2".to_string(), "C2-2".to_string())));
+}
+
+fn ratline_pinnames(autorouter: &Autorouter<SpecctraMesadata>) -> Vec<(String, String)> {
+    let graph = autorouter.ratsnest().graph();
+    let pinname = |ratvertex: &RatvertexWeight| {
+        autorouter
+            .board()
+            .node_pinname(&ratvertex.node_index().into())
+            .unwrap()
+            .clone()
+    };
+
+    graph
+        .edge_indices()
+        .map(|ratline| {
+            let (source, target) = graph.edge_endpoints(ratline).unwrap();
+            (pinname(&graph[source]), pinname(&graph[target]))
+        })
+        .collect()
+}
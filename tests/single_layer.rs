@@ -1,11 +1,45 @@
+use std::{io::BufReader, ops::ControlFlow};
+
+use geo::{coord, point, BoundingRect, Contains, MultiPoint, Point, Rect};
+
 use topola::{
     autorouter::{
-        execution::Command,
+        autoroute::AutorouteReport,
+        check_design_rules::DesignRuleViolation,
+        diff_pair::DiffPairOptions,
+        execution::{Command, ExecutionOutcome},
+        history::History,
         invoker::{Invoker, InvokerError},
-        AutorouterError,
+        ratline_ordering::RatlineOrdering,
+        selection::{BandSelection, PinSelection},
+        Autorouter, AutorouterError, AutorouterOptions,
+    },
+    board::mesadata::{complementary_netname, AccessMesadata},
+    drawing::{
+        band::BandTermsegIndex,
+        gear::GearIndex,
+        graph::{GetMaybeNet, MakePrimitive, PrimitiveIndex},
+        head::BareHead,
+        loose::LooseIndex,
+        primitive::{GetWeight, MakePrimitiveShape},
+        rules::{AccessRules, Conditions, GetObjectKind, ObjectKind},
+    },
+    geometry::{
+        primitive::{DotShape, PrimitiveShape},
+        shape::{AccessShape, MeasureLength},
+        GenericNode, GetWidth,
+    },
+    graph::{GetPetgraphIndex, MakeRef},
+    interactor::{activity::InteractiveInput, Interactor},
+    layout::{
+        poly::PourPolyWeight,
+        pour::{Pour, PourOptions},
+        via::ViaWeight,
+        LayoutException,
     },
-    layout::via::ViaWeight,
     math::Circle,
+    router::draw::{Draw, DrawException},
+    specctra::{design::SpecctraDesign, mesadata::SpecctraMesadata},
 };
 
 mod common;
@@ -59,6 +93,737 @@ fn test_tht_diode_bridge_rectifier() {
     ));
 }
 
+#[test]
+fn test_tht_diode_bridge_rectifier_measure_length() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.dsn",
+    );
+    common::replay_and_assert(
+        &mut invoker,
+        "tests/single_layer/tht_diode_bridge_rectifier/autoroute_all.cmd",
+    );
+
+    let selection: BandSelection =
+        serde_json::from_str(r#"{"selectors": [{"band": ["D4-2", "J2-2"]}]}"#).unwrap();
+    let outcome = invoker.execute(Command::MeasureLength(selection)).unwrap();
+    let ExecutionOutcome::MeasureLength(length) = outcome else {
+        panic!("measuring length yielded {:?}", outcome);
+    };
+    assert!((length - 15900.0).abs() < 15900.0 * 0.01);
+    assert_eq!(
+        outcome.to_string(),
+        format!("Total length of selected bands: {}", length)
+    );
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_tune_length() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.dsn",
+    );
+    common::replay_and_assert(
+        &mut invoker,
+        "tests/single_layer/tht_diode_bridge_rectifier/autoroute_all.cmd",
+    );
+
+    let selection: BandSelection =
+        serde_json::from_str(r#"{"selectors": [{"band": ["D4-2", "J2-2"]}]}"#).unwrap();
+    let measure_length = |invoker: &mut Invoker<SpecctraMesadata>| {
+        let outcome = invoker
+            .execute(Command::MeasureLength(selection.clone()))
+            .unwrap();
+        let ExecutionOutcome::MeasureLength(length) = outcome else {
+            panic!("measuring length yielded {:?}", outcome);
+        };
+        length
+    };
+
+    let initial_length = measure_length(&mut invoker);
+    assert!(initial_length < 17000.0 - 20.0);
+
+    let outcome = invoker
+        .execute(Command::TuneLength(selection.clone(), 17000.0, 20.0))
+        .unwrap();
+    let ExecutionOutcome::TuneLength(lengths) = outcome else {
+        panic!("tuning length yielded {:?}", outcome);
+    };
+    assert_eq!(lengths.len(), 1);
+    assert!((lengths[0].1 - 17000.0).abs() <= 20.0);
+    assert_eq!(measure_length(&mut invoker), lengths[0].1);
+
+    let core_count = |invoker: &Invoker<SpecctraMesadata>| {
+        let drawing = invoker.autorouter().board().layout().drawing();
+        drawing
+            .primitive_nodes()
+            .filter(|node| node.primitive(drawing).object_kind() == ObjectKind::MeanderCore)
+            .count()
+    };
+    assert!(core_count(&invoker) > 0);
+
+    // The cores aren't copper, so a fill of the net of the band isn't cut out around them,
+    // though the ones next to copper of other nets are within its clearance.
+    let board = invoker.autorouter().board();
+    let drawing = board.layout().drawing();
+    let band = board.band_between_pins("D4-2", "J2-2").unwrap();
+    let core_positions: Vec<Point> = drawing
+        .primitive_nodes()
+        .filter(|node| node.primitive(drawing).object_kind() == ObjectKind::MeanderCore)
+        .map(|node| node.primitive(drawing).shape().center())
+        .collect();
+    let bounds = MultiPoint::from(core_positions.clone())
+        .bounding_rect()
+        .unwrap();
+    let pour = Pour::new(
+        PourPolyWeight {
+            layer: 0,
+            maybe_net: PrimitiveIndex::from(LooseIndex::from(band.0))
+                .primitive(drawing)
+                .maybe_net(),
+        },
+        Rect::new(
+            bounds.min() - coord! {x: 5000.0, y: 5000.0},
+            bounds.max() + coord! {x: 5000.0, y: 5000.0},
+        )
+        .to_polygon(),
+        PourOptions::default(),
+    );
+    let fill = pour.make_fill(board.layout());
+    assert!(core_positions.iter().any(|pos| fill.contains(pos)));
+
+    // Undo the measurement and the tuning.
+    invoker.undo().unwrap();
+    invoker.undo().unwrap();
+    assert_eq!(measure_length(&mut invoker), initial_length);
+    assert_eq!(core_count(&invoker), 0);
+
+    // The cores of the meander are removed along with the band.
+    invoker
+        .execute(Command::TuneLength(selection.clone(), 17000.0, 20.0))
+        .unwrap();
+    assert!(core_count(&invoker) > 0);
+    invoker.execute(Command::RemoveBands(selection)).unwrap();
+    assert_eq!(core_count(&invoker), 0);
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_undo_redo_autoroute() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.dsn",
+    );
+    let unrouted_node_count = invoker.autorouter().board().layout().drawing().node_count();
+    common::replay_and_assert(
+        &mut invoker,
+        "tests/single_layer/tht_diode_bridge_rectifier/autoroute_all.cmd",
+    );
+    let routed_node_count = invoker.autorouter().board().layout().drawing().node_count();
+
+    let routed_ratlines = routed_ratline_count(&invoker);
+    let band_name_count = invoker.autorouter().board().band_bandnames().len();
+    assert!(routed_ratlines > 0);
+    assert_eq!(band_name_count, routed_ratlines);
+
+    // Undoing both autoroute commands of the file leaves the board unrouted, even though the
+    // pins they select overlap, and takes the bands away from the ratlines and the band names.
+    invoker.undo().unwrap();
+    invoker.undo().unwrap();
+    assert_eq!(
+        invoker.autorouter().board().layout().drawing().node_count(),
+        unrouted_node_count
+    );
+    assert_eq!(routed_ratline_count(&invoker), 0);
+    assert!(invoker.autorouter().board().band_bandnames().is_empty());
+
+    // Redoing brings every band back where it was, so the band names refer to them again.
+    invoker.redo().unwrap();
+    invoker.redo().unwrap();
+    assert_eq!(
+        invoker.autorouter().board().layout().drawing().node_count(),
+        routed_node_count
+    );
+    assert_eq!(routed_ratline_count(&invoker), routed_ratlines);
+    assert_eq!(
+        invoker.autorouter().board().band_bandnames().len(),
+        band_name_count
+    );
+    common::assert_band_length(invoker.autorouter().board(), "J2-2", "D4-2", 15900.0, 0.01);
+}
+
+#[test]
+fn test_4x_3rd_order_smd_lc_filters_undo_failed_redo() {
+    let design_file = std::fs::File::open(
+        "tests/single_layer/4x_3rd_order_smd_lc_filters/4x_3rd_order_smd_lc_filters.dsn",
+    )
+    .unwrap();
+    let design = SpecctraDesign::load(BufReader::new(design_file)).unwrap();
+    let autorouter = Autorouter::new(design.make_board()).unwrap();
+    let unrouted_node_count = autorouter.board().layout().drawing().node_count();
+
+    // Routing every pin of the layer, grounds included, gets stuck after some ratlines have
+    // already been routed.
+    let mut history = History::new();
+    history.set_undone([Command::Autoroute(
+        PinSelection::new_select_layer(autorouter.board(), 0),
+        common::autorouter_options(),
+    )]);
+    let mut invoker = Invoker::new_with_history(autorouter, history);
+
+    assert!(invoker.redo().is_err());
+    assert!(invoker.autorouter().board().layout().drawing().node_count() > unrouted_node_count);
+
+    // What the failed redo routed is undone like that of any other command.
+    invoker.undo().unwrap();
+    assert_eq!(
+        invoker.autorouter().board().layout().drawing().node_count(),
+        unrouted_node_count
+    );
+    assert_eq!(routed_ratline_count(&invoker), 0);
+}
+
+fn routed_ratline_count(invoker: &Invoker<SpecctraMesadata>) -> usize {
+    invoker
+        .autorouter()
+        .ratsnest()
+        .graph()
+        .edge_weights()
+        .filter(|ratline| ratline.band_termseg.is_some())
+        .count()
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_undo_remove_bands() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.dsn",
+    );
+    let report = common::autoroute_layer(&mut invoker, 0, common::autorouter_options());
+    assert_eq!(report.routed_ratlines().len(), 8);
+    assert!(report.failed_ratlines().is_empty());
+    let routed_node_count = invoker.autorouter().board().layout().drawing().node_count();
+    let band_length = invoker
+        .autorouter()
+        .board()
+        .band_between_pins("J2-2", "D4-2")
+        .unwrap()
+        .0
+        .ref_(invoker.autorouter().board().layout().drawing())
+        .length();
+
+    // Remove every band, including the ones wrapped around each other.
+    let selection: BandSelection = serde_json::from_str(
+        r#"{"selectors": [
+            {"band": ["D1-2", "J2-2"]}, {"band": ["D4-2", "J2-2"]},
+            {"band": ["D1-1", "D2-2"]}, {"band": ["D2-2", "J1-1"]},
+            {"band": ["D3-2", "D4-1"]}, {"band": ["D3-2", "J1-2"]},
+            {"band": ["D2-1", "D3-1"]}, {"band": ["D3-1", "J2-1"]}
+        ]}"#,
+    )
+    .unwrap();
+    invoker.execute(Command::RemoveBands(selection)).unwrap();
+    let removed_node_count = invoker.autorouter().board().layout().drawing().node_count();
+    assert!(removed_node_count < routed_node_count);
+
+    invoker.undo().unwrap();
+    assert_eq!(
+        invoker.autorouter().board().layout().drawing().node_count(),
+        routed_node_count
+    );
+    common::assert_band_length(
+        invoker.autorouter().board(),
+        "J2-2",
+        "D4-2",
+        band_length,
+        0.001,
+    );
+
+    invoker.redo().unwrap();
+    assert_eq!(
+        invoker.autorouter().board().layout().drawing().node_count(),
+        removed_node_count
+    );
+
+    // The autoroute can still be undone after the bands it routed were drawn again.
+    invoker.undo().unwrap();
+    invoker.undo().unwrap();
+    assert_eq!(
+        invoker.autorouter().board().layout().drawing().node_count(),
+        removed_node_count
+    );
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_check_design_rules() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.dsn",
+    );
+    let ratline_count = invoker.autorouter().ratsnest().graph().edge_count();
+
+    // Before routing, the only violations are the ratlines left to route.
+    let ExecutionOutcome::CheckDesignRules(violations) =
+        invoker.execute(Command::CheckDesignRules).unwrap()
+    else {
+        panic!();
+    };
+    assert_eq!(violations.len(), ratline_count);
+
+    for violation in violations {
+        let DesignRuleViolation::UnconnectedRatline {
+            netname,
+            layernames,
+            ..
+        } = violation
+        else {
+            panic!("unexpected violation {:?}", violation);
+        };
+        assert!(netname.is_some());
+        assert_eq!(
+            layernames,
+            (Some("F.Cu".to_string()), Some("F.Cu".to_string()))
+        );
+    }
+
+    common::autoroute_layer(&mut invoker, 0, common::autorouter_options());
+
+    let ExecutionOutcome::CheckDesignRules(violations) =
+        invoker.execute(Command::CheckDesignRules).unwrap()
+    else {
+        panic!();
+    };
+    assert!(violations.is_empty(), "{:?}", violations);
+}
+
+#[test]
+fn test_prerouted_tht_diode_bridge_rectifier() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/single_layer/tht_diode_bridge_rectifier/prerouted_tht_diode_bridge_rectifier.dsn",
+    );
+    let board = invoker.autorouter().board();
+    let drawing = board.layout().drawing();
+
+    // The wires to be routed become bands, the one led around J2-1 wrapping around two corners
+    // of its pad, while the protected wire stays fixed.
+    let nodes: Vec<PrimitiveIndex> = drawing.primitive_nodes().collect();
+    let count = |f: fn(&PrimitiveIndex) -> bool| nodes.iter().filter(|node| f(node)).count();
+    assert_eq!(
+        count(|node| matches!(node, PrimitiveIndex::LooseBend(..))),
+        2
+    );
+    assert_eq!(
+        count(|node| matches!(node, PrimitiveIndex::SeqLooseSeg(..))),
+        3
+    );
+    assert_eq!(
+        count(|node| matches!(node, PrimitiveIndex::LoneLooseSeg(..))),
+        1
+    );
+    assert_eq!(
+        nodes
+            .iter()
+            .filter(|node| matches!(node, PrimitiveIndex::FixedSeg(..))
+                && node.primitive(drawing).object_kind() == ObjectKind::Wire)
+            .count(),
+        1
+    );
+    assert!(board.band_between_pins("D2-1", "D3-1").is_some());
+
+    // The band is drawn taut, cutting the corners of the wire that have nothing under them.
+    common::assert_band_length(board, "D4-2", "J2-2", 16000.0, 0.01);
+
+    common::autoroute_layer(&mut invoker, 0, common::autorouter_options());
+
+    let ExecutionOutcome::CheckDesignRules(violations) =
+        invoker.execute(Command::CheckDesignRules).unwrap()
+    else {
+        panic!();
+    };
+    assert!(violations.is_empty(), "{:?}", violations);
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_via_keepout() {
+    let dsn = std::fs::read_to_string(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.dsn",
+    )
+    .unwrap()
+    .replacen(
+        "    (via ",
+        "    (via_keepout \"\" (circle signal 20000 113030 -77470))\n    (via ",
+        1,
+    );
+    let design = SpecctraDesign::load(BufReader::new(dsn.as_bytes())).unwrap();
+    let mut invoker = Invoker::new(Autorouter::new(design.make_board()).unwrap());
+
+    let result = invoker.execute(Command::PlaceVia(ViaWeight {
+        from_layer: 0,
+        to_layer: 1,
+        circle: Circle {
+            pos: [113030.0, -77470.0].into(),
+            r: 300.0,
+        },
+        maybe_net: Some(0),
+    }));
+    assert!(matches!(
+        result,
+        Err(InvokerError::Autorouter(AutorouterError::CouldNotPlaceVia(
+            LayoutException::ViaKeepoutInfringement(..)
+        )))
+    ));
+
+    // Via keepouts don't keep the bands out.
+    common::replay_and_assert(
+        &mut invoker,
+        "tests/single_layer/tht_diode_bridge_rectifier/autoroute_all.cmd",
+    );
+
+    let (mut autorouter, ..) = invoker.dissolve();
+
+    common::assert_single_layer_groundless_autoroute(&mut autorouter, "F.Cu");
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_via_in_wire_keepout() {
+    let dsn = std::fs::read_to_string(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.dsn",
+    )
+    .unwrap()
+    .replacen(
+        "    (via ",
+        "    (wire_keepout \"\" (circle signal 20000 113030 -77470))\n    (via ",
+        1,
+    );
+    let design = SpecctraDesign::load(BufReader::new(dsn.as_bytes())).unwrap();
+    let mut invoker = Invoker::new(Autorouter::new(design.make_board()).unwrap());
+
+    // Keepouts of wires alone don't keep the vias out.
+    invoker
+        .execute(Command::PlaceVia(ViaWeight {
+            from_layer: 0,
+            to_layer: 1,
+            circle: Circle {
+                pos: [113030.0, -77470.0].into(),
+                r: 300.0,
+            },
+            maybe_net: Some(0),
+        }))
+        .unwrap();
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_signal_layer_padstack() {
+    let dsn = std::fs::read_to_string(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.dsn",
+    )
+    .unwrap()
+    .replacen(
+        "      (shape (circle F.Cu 3000))\n      (shape (circle B.Cu 3000))\n",
+        "      (shape (circle signal 3000))\n",
+        1,
+    );
+    let design = SpecctraDesign::load(BufReader::new(dsn.as_bytes())).unwrap();
+    let board = design.make_board();
+
+    // A shape on the `signal` layer makes for a through-hole pad on every layer.
+    let round_pad_layers: Vec<usize> = board
+        .layout()
+        .drawing()
+        .primitive_nodes()
+        .filter_map(|node| match node {
+            PrimitiveIndex::FixedDot(dot) => Some(board.layout().drawing().primitive(dot).weight()),
+            _ => None,
+        })
+        .filter(|weight| weight.circle.r == 1500.0)
+        .map(|weight| {
+            assert_eq!(weight.kind, ObjectKind::Pin);
+            weight.layer
+        })
+        .collect();
+    assert!(round_pad_layers.contains(&0));
+    assert!(round_pad_layers.contains(&1));
+}
+
+#[test]
+fn test_0603_breakout_clearance_matrix() {
+    let dsn = std::fs::read_to_string("tests/single_layer/0603_breakout/0603_breakout.dsn")
+        .unwrap()
+        .replacen(
+            "        (clearance 200)\n      )\n    )\n  )",
+            "        (clearance 200)\n        (clearance 300 (type default_via))\n      )\n    )\n    \
+             (class_class (classes kicad_default kicad_default)\n      \
+             (rule (clearance 400 (type wire_smd)))\n    )\n  )",
+            1,
+        );
+    let design = SpecctraDesign::load(BufReader::new(dsn.as_bytes())).unwrap();
+    let board = design.make_board();
+    let mesadata = board.mesadata();
+
+    let gnd = mesadata.netname_net("GND").unwrap();
+    let pin_1 = mesadata.netname_net("Net-(J1-Pin_1)").unwrap();
+    let clearance = |maybe_net1, kind1, maybe_net2, kind2| {
+        mesadata.clearance(
+            &Conditions {
+                maybe_net: maybe_net1,
+                kind: kind1,
+                ..Default::default()
+            },
+            &Conditions {
+                maybe_net: maybe_net2,
+                kind: kind2,
+                ..Default::default()
+            },
+        )
+    };
+
+    // The class-to-class rule takes precedence for the kinds it sets a clearance for.
+    assert_eq!(
+        clearance(Some(gnd), ObjectKind::Wire, Some(pin_1), ObjectKind::Smd),
+        400.0
+    );
+    assert_eq!(
+        clearance(Some(gnd), ObjectKind::Smd, Some(pin_1), ObjectKind::Wire),
+        400.0
+    );
+    // Typed clearances of the class take precedence over its generic clearance.
+    assert_eq!(
+        clearance(Some(gnd), ObjectKind::Via, Some(pin_1), ObjectKind::Wire),
+        300.0
+    );
+    assert_eq!(
+        clearance(Some(gnd), ObjectKind::Wire, Some(pin_1), ObjectKind::Pin),
+        200.0
+    );
+    // The generic clearance of the class takes precedence over the structure's `smd_smd`.
+    assert_eq!(
+        clearance(Some(gnd), ObjectKind::Smd, Some(pin_1), ObjectKind::Smd),
+        200.0
+    );
+    assert_eq!(
+        clearance(None, ObjectKind::Area, Some(gnd), ObjectKind::Via),
+        300.0
+    );
+    assert_eq!(mesadata.largest_clearance(None), 400.0);
+}
+
+#[test]
+fn test_0603_breakout_boundary_clearance() {
+    let dsn = std::fs::read_to_string("tests/single_layer/0603_breakout/0603_breakout.dsn")
+        .unwrap()
+        .replacen(
+            "      (clearance 50 (type smd_smd))\n",
+            "      (clearance 50 (type smd_smd))\n      (clearance 700 (type boundary))\n",
+            1,
+        );
+    let design = SpecctraDesign::load(BufReader::new(dsn.as_bytes())).unwrap();
+    let board = design.make_board();
+    let mesadata = board.mesadata();
+
+    let gnd = mesadata.netname_net("GND").unwrap();
+    let clearance = |kind1, kind2| {
+        mesadata.clearance(
+            &Conditions {
+                maybe_net: Some(gnd),
+                kind: kind1,
+                ..Default::default()
+            },
+            &Conditions {
+                maybe_net: None,
+                kind: kind2,
+                ..Default::default()
+            },
+        )
+    };
+
+    assert_eq!(clearance(ObjectKind::Wire, ObjectKind::Boundary), 700.0);
+    assert_eq!(clearance(ObjectKind::Via, ObjectKind::Boundary), 700.0);
+    // Keepouts aren't the boundary.
+    assert_eq!(clearance(ObjectKind::Wire, ObjectKind::Area), 200.0);
+    assert_eq!(mesadata.largest_clearance(None), 700.0);
+}
+
+#[test]
+fn test_0603_breakout_layer_and_region_rules() {
+    let dsn = std::fs::read_to_string("tests/single_layer/0603_breakout/0603_breakout.dsn")
+        .unwrap()
+        .replacen(
+            "        (index 1)\n      )\n",
+            "        (index 1)\n      )\n      (rule (clearance 150))\n",
+            1,
+        )
+        .replacen(
+            "    (via ",
+            "    (region R (rect signal 138700 -101700 140000 -98100) (rule (clearance 120)))\n    \
+             (via ",
+            1,
+        );
+    let design = SpecctraDesign::load(BufReader::new(dsn.as_bytes())).unwrap();
+    let board = design.make_board();
+    let mesadata = board.mesadata();
+
+    let dot = |x: f64, r| {
+        PrimitiveShape::Dot(DotShape {
+            circle: Circle {
+                pos: [x, -100000.0].into(),
+                r,
+            },
+        })
+    };
+
+    assert_eq!(mesadata.maybe_region(0, &dot(139000.0, 100.0)), Some(0));
+    assert_eq!(mesadata.maybe_region(1, &dot(142000.0, 100.0)), None);
+    // Shapes whose center lies outside the region still lie in it if they overlap it.
+    assert_eq!(mesadata.maybe_region(1, &dot(140100.0, 200.0)), Some(0));
+    assert_eq!(mesadata.maybe_region(1, &dot(140300.0, 200.0)), None);
+
+    let gnd = mesadata.netname_net("GND").unwrap();
+    let pin_1 = mesadata.netname_net("Net-(J1-Pin_1)").unwrap();
+    let clearance = |maybe_layer, maybe_region| {
+        mesadata.clearance(
+            &Conditions {
+                maybe_net: Some(gnd),
+                maybe_region,
+                maybe_layer,
+                ..Default::default()
+            },
+            &Conditions {
+                maybe_net: Some(pin_1),
+                maybe_layer,
+                ..Default::default()
+            },
+        )
+    };
+
+    assert_eq!(clearance(Some(0), None), 200.0);
+    // Layer rules take precedence over the net class rules.
+    assert_eq!(clearance(Some(1), None), 150.0);
+    // Region rules take precedence over all other rules.
+    assert_eq!(clearance(Some(1), Some(0)), 120.0);
+
+    let mut invoker = Invoker::new(Autorouter::new(board).unwrap());
+    common::replay_and_assert(
+        &mut invoker,
+        "tests/single_layer/0603_breakout/autoroute_all.cmd",
+    );
+}
+
+#[test]
+fn test_0603_breakout_layer_and_region_widths() {
+    let dsn = std::fs::read_to_string("tests/single_layer/0603_breakout/0603_breakout.dsn")
+        .unwrap()
+        .replacen(
+            "        (index 0)\n      )\n",
+            "        (index 0)\n      )\n      (rule (width 150))\n",
+            1,
+        )
+        .replacen(
+            "    (via ",
+            "    (region R (rect signal 141500 -101700 143200 -98100) (rule (width 120)))\n    \
+             (via ",
+            1,
+        );
+    let design = SpecctraDesign::load(BufReader::new(dsn.as_bytes())).unwrap();
+    let board = design.make_board();
+    let mesadata = board.mesadata();
+    let gnd = mesadata.netname_net("GND").unwrap();
+    let width = |maybe_layer, maybe_region| {
+        mesadata.maybe_width(&Conditions {
+            maybe_net: Some(gnd),
+            maybe_region,
+            maybe_layer,
+            ..Default::default()
+        })
+    };
+
+    assert_eq!(width(None, None), Some(200.0));
+    // Layer rules take precedence over the net class rules.
+    assert_eq!(width(Some(0), None), Some(150.0));
+    assert_eq!(width(Some(1), None), Some(200.0));
+    // Region rules take precedence over all other rules.
+    assert_eq!(width(Some(0), Some(0)), Some(120.0));
+
+    let mut invoker = Invoker::new(Autorouter::new(board).unwrap());
+    common::replay_and_assert(
+        &mut invoker,
+        "tests/single_layer/0603_breakout/autoroute_all.cmd",
+    );
+
+    let (autorouter, ..) = invoker.dissolve();
+    let drawing = autorouter.board().layout().drawing();
+    let mut widths: Vec<f64> = drawing
+        .primitive_nodes()
+        .filter(|index| {
+            matches!(
+                index,
+                PrimitiveIndex::LoneLooseSeg(..) | PrimitiveIndex::SeqLooseSeg(..)
+            )
+        })
+        .map(|index| index.primitive(drawing).width())
+        .collect();
+    widths.sort_by(f64::total_cmp);
+    widths.dedup();
+
+    // One band starts on a pad of R1, within the region, and the other one outside of it.
+    assert_eq!(widths, vec![120.0, 150.0]);
+}
+
+#[test]
+fn test_0603_breakout_net_class_width() {
+    let dsn = std::fs::read_to_string("tests/single_layer/0603_breakout/0603_breakout.dsn")
+        .unwrap()
+        .replacen("        (width 200)", "        (width 150)", 1);
+    let design = SpecctraDesign::load(BufReader::new(dsn.as_bytes())).unwrap();
+    let mut invoker = Invoker::new(Autorouter::new(design.make_board()).unwrap());
+
+    // The width given in the options is overridden by the one of the net class.
+    common::replay_and_assert(
+        &mut invoker,
+        "tests/single_layer/0603_breakout/autoroute_all.cmd",
+    );
+
+    let (autorouter, ..) = invoker.dissolve();
+    let drawing = autorouter.board().layout().drawing();
+    let mut loose_seg_count = 0;
+
+    for index in drawing.primitive_nodes() {
+        if let PrimitiveIndex::LoneLooseSeg(..) | PrimitiveIndex::SeqLooseSeg(..) = index {
+            assert_eq!(index.primitive(drawing).width(), 150.0);
+            loose_seg_count += 1;
+        }
+    }
+
+    assert!(loose_seg_count > 0);
+}
+
+#[test]
+fn test_0603_breakout_net_class_width_pointroute() {
+    let dsn = std::fs::read_to_string("tests/single_layer/0603_breakout/0603_breakout.dsn")
+        .unwrap()
+        .replacen("        (width 200)", "        (width 150)", 1);
+    let design = SpecctraDesign::load(BufReader::new(dsn.as_bytes())).unwrap();
+    let mut invoker = Invoker::new(Autorouter::new(design.make_board()).unwrap());
+    let selection: PinSelection =
+        serde_json::from_str(r#"{"selectors": [{"pin": "J1-2", "layer": "F.Cu"}]}"#).unwrap();
+
+    // Tracks routed to a point are as wide as the net class says too, not as the options do.
+    let outcome = invoker
+        .execute(Command::Pointroute(
+            selection,
+            point! {x: 141000.0, y: -101000.0},
+            common::autorouter_options(),
+        ))
+        .unwrap();
+    assert!(matches!(outcome, ExecutionOutcome::Pointroute(..)));
+
+    let (autorouter, ..) = invoker.dissolve();
+    let drawing = autorouter.board().layout().drawing();
+    let mut loose_seg_count = 0;
+
+    for index in drawing.primitive_nodes() {
+        if let PrimitiveIndex::LoneLooseSeg(..) | PrimitiveIndex::SeqLooseSeg(..) = index {
+            assert_eq!(index.primitive(drawing).width(), 150.0);
+            loose_seg_count += 1;
+        }
+    }
+
+    assert!(loose_seg_count > 0);
+}
+
 #[test]
 fn test_4x_3rd_order_smd_lc_filters() {
     let mut invoker = common::load_design_and_assert(
@@ -92,3 +857,413 @@ fn test_tht_3pin_xlr_to_tht_3pin_xlr() {
     // FIXME: The routing result is pretty bad.
     common::assert_single_layer_groundless_autoroute(&mut autorouter, "F.Cu");
 }
+
+#[test]
+fn test_4x_3rd_order_smd_lc_filters_skip_failed_ratlines() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/single_layer/4x_3rd_order_smd_lc_filters/4x_3rd_order_smd_lc_filters.dsn",
+    );
+
+    // Routing every pin of the layer, grounds included, gets some ratlines stuck, but the
+    // autoroute goes on past them and reports them instead of failing.
+    let report = common::autoroute_layer(
+        &mut invoker,
+        0,
+        AutorouterOptions {
+            skip_failed_ratlines: true,
+            ..common::autorouter_options()
+        },
+    );
+    let autorouter = invoker.autorouter();
+
+    assert!(!report.routed_ratlines().is_empty());
+    assert!(!report.failed_ratlines().is_empty());
+    assert!(report.total_length() > &0.0);
+
+    for (ratline, band_termseg) in report.routed_ratlines() {
+        let weight = autorouter.ratsnest().graph().edge_weight(*ratline).unwrap();
+        assert_eq!(
+            weight.band_termseg.map(|termseg| termseg.petgraph_index()),
+            Some(band_termseg.petgraph_index())
+        );
+    }
+
+    for (ratline, err) in report.failed_ratlines() {
+        let weight = autorouter.ratsnest().graph().edge_weight(*ratline).unwrap();
+        assert!(weight.band_termseg.is_none());
+        assert!(matches!(
+            err,
+            AutorouterError::Astar(..) | AutorouterError::Navmesh(..)
+        ));
+    }
+}
+
+#[test]
+fn test_tht_de9_to_tht_de9_shove_bands() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/single_layer/tht_de9_to_tht_de9/tht_de9_to_tht_de9.dsn",
+    );
+    let unrouted_node_count = invoker.autorouter().board().layout().drawing().node_count();
+
+    // The pins are so close together that bands have to be shoved to route some ratlines.
+    let mut options = AutorouterOptions {
+        skip_failed_ratlines: true,
+        ..common::autorouter_options()
+    };
+    options.router_options.wrap_around_bands = false;
+    options.router_options.squeeze_through_under_bands = false;
+    options.router_options.shove_depth = 3;
+    let report = common::autoroute_layer(&mut invoker, 0, options);
+    assert!(!report.routed_ratlines().is_empty());
+
+    // Shoved bands are still whole bands.
+    for (.., band_termseg) in report.routed_ratlines() {
+        assert!(
+            band_termseg
+                .ref_(invoker.autorouter().board().layout().drawing())
+                .length()
+                > 0.0
+        );
+    }
+
+    invoker.undo().unwrap();
+    assert_eq!(
+        invoker.autorouter().board().layout().drawing().node_count(),
+        unrouted_node_count
+    );
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_lone_seg_band_is_not_shoved() {
+    let design_file = std::fs::File::open(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.dsn",
+    )
+    .unwrap();
+    let design = SpecctraDesign::load(BufReader::new(design_file)).unwrap();
+    let mut board = design.make_board();
+
+    let source = common::pin_dot(&mut board, "D1-1", 0);
+    let target = common::pin_dot(&mut board, "D2-2", 0);
+    let band = Draw::new(board.layout_mut())
+        .finish_in_dot(BareHead { face: source }.into(), target, 100.0)
+        .unwrap();
+    let BandTermsegIndex::Straight(seg) = band else {
+        panic!("band between adjacent pins isn't straight");
+    };
+    let node_count = board.layout().drawing().node_count();
+
+    // The cane from D1-2 around J1-2 crosses the band, which can't be shoved however deep
+    // shoving may go.
+    let from = common::pin_dot(&mut board, "D1-2", 0);
+    let around = common::pin_dot(&mut board, "J1-2", 0);
+    let result = Draw::with_shove_depth(board.layout_mut(), 3).shove_cane_around_dot(
+        BareHead { face: from }.into(),
+        around,
+        true,
+        100.0,
+    );
+    let Err(DrawException::CannotShoveLoneSeg(_, infringee)) = result else {
+        panic!("cane around J1-2 yielded {:?}", result);
+    };
+    assert_eq!(infringee, seg);
+    assert_eq!(board.layout().drawing().node_count(), node_count);
+}
+
+#[test]
+fn test_presort_by_pairwise_detours_option_is_read_as_ratline_ordering() {
+    let options = |presort: &str| -> AutorouterOptions {
+        serde_json::from_str(&format!(
+            r#"{{
+                "presort_by_pairwise_detours": {},
+                "router_options": {{
+                    "routed_band_width": 100.0,
+                    "wrap_around_bands": true,
+                    "squeeze_through_under_bands": true
+                }}
+            }}"#,
+            presort
+        ))
+        .unwrap()
+    };
+
+    assert_eq!(
+        options("true").ratline_ordering,
+        RatlineOrdering::PairwiseDetours
+    );
+    assert_eq!(
+        options("false").ratline_ordering,
+        RatlineOrdering::Unordered
+    );
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_shortest_ratlines_first() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.dsn",
+    );
+    let report = common::autoroute_layer(
+        &mut invoker,
+        0,
+        AutorouterOptions {
+            ratline_ordering: RatlineOrdering::ShortestFirst,
+            ..common::autorouter_options()
+        },
+    );
+
+    let graph = invoker.autorouter().ratsnest().graph();
+    let lengths: Vec<f64> = report
+        .routed_ratlines()
+        .iter()
+        .map(|(ratline, _)| {
+            let (source, target) = graph.edge_endpoints(*ratline).unwrap();
+            let source_pos = graph.node_weight(source).unwrap().pos;
+            let target_pos = graph.node_weight(target).unwrap().pos;
+            (target_pos - source_pos)
+                .x()
+                .hypot((target_pos - source_pos).y())
+        })
+        .collect();
+
+    assert_eq!(lengths.len(), 8);
+    assert!(lengths.windows(2).all(|pair| pair[0] <= pair[1]));
+}
+
+fn autoroute_tht_diff_pair(
+    diff_pair_options: DiffPairOptions,
+) -> (Invoker<SpecctraMesadata>, AutorouteReport) {
+    let mut invoker =
+        common::load_design_and_assert("tests/single_layer/tht_diff_pair/tht_diff_pair.dsn");
+    let report = common::autoroute_layer(
+        &mut invoker,
+        0,
+        AutorouterOptions {
+            skip_failed_ratlines: true,
+            diff_pair_options,
+            ..common::autorouter_options()
+        },
+    );
+
+    (invoker, report)
+}
+
+#[test]
+fn test_tht_diff_pair() {
+    let (invoker, report) = autoroute_tht_diff_pair(DiffPairOptions {
+        max_uncoupled_length: Some(1000.0),
+        max_skew: Some(1000.0),
+    });
+
+    let mesadata = invoker.autorouter().board().mesadata();
+    let positive = mesadata.netname_net("SIG_P").unwrap();
+    let negative = mesadata.netname_net("SIG_N").unwrap();
+
+    assert_eq!(mesadata.net_pair(positive), Some(negative));
+    assert_eq!(mesadata.net_pair(negative), Some(positive));
+    assert_eq!(complementary_netname("SIG_N").as_deref(), Some("SIG_P"));
+
+    // The gap of the `pair` descriptor takes the place of the clearance between the nets.
+    assert_eq!(
+        mesadata.clearance(
+            &Conditions {
+                maybe_net: Some(positive),
+                maybe_region: None,
+                maybe_layer: Some(0),
+                kind: ObjectKind::Wire,
+            },
+            &Conditions {
+                maybe_net: Some(negative),
+                maybe_region: None,
+                maybe_layer: Some(0),
+                kind: ObjectKind::Wire,
+            },
+        ),
+        300.0
+    );
+
+    assert_eq!(report.routed_ratlines().len(), 2);
+    assert!(report.failed_ratlines().is_empty());
+    assert_eq!(report.pair_skews().len(), 1);
+
+    let board = invoker.autorouter().board();
+    let drawing = board.layout().drawing();
+    let positive_band = board.band_between_pins("J1-1", "J2-1").unwrap();
+    let negative_band = board.band_between_pins("J1-2", "J2-2").unwrap();
+    let positive_length = positive_band.0.ref_(drawing).length();
+    let negative_length = negative_band.0.ref_(drawing).length();
+    let (_, _, skew) = report.pair_skews()[0];
+
+    assert!((skew - (positive_length - negative_length).abs()).abs() < 1e-6);
+
+    // Both bands go around the mounting hole, one of them wrapping around the bend of the
+    // other.
+    let positive_path = positive_band.0.ref_(drawing).path();
+    let negative_path = negative_band.0.ref_(drawing).path();
+
+    assert_eq!(positive_path.bends.len(), 1);
+    assert_eq!(negative_path.bends.len(), 1);
+    assert!(
+        positive_path.wraps[0].0 == GearIndex::LooseBend(negative_path.bends[0])
+            || negative_path.wraps[0].0 == GearIndex::LooseBend(positive_path.bends[0])
+    );
+}
+
+#[test]
+fn test_tht_diff_pair_skew_limit() {
+    let (invoker, report) = autoroute_tht_diff_pair(DiffPairOptions {
+        max_uncoupled_length: None,
+        max_skew: Some(100.0),
+    });
+
+    // The band on the outer side of the turn around the mounting hole comes out longer than
+    // the limit allows.
+    assert_eq!(report.routed_ratlines().len(), 1);
+    assert!(report.pair_skews().is_empty());
+    assert!(matches!(
+        report.failed_ratlines()[..],
+        [(_, AutorouterError::PairSkewTooLarge(..))]
+    ));
+
+    let (ratline, _) = report.failed_ratlines()[0];
+    assert!(invoker
+        .autorouter()
+        .ratsnest()
+        .graph()
+        .edge_weight(ratline)
+        .unwrap()
+        .band_termseg
+        .is_none());
+}
+
+#[test]
+fn test_tht_diff_pair_route_track() {
+    let design_file =
+        std::fs::File::open("tests/single_layer/tht_diff_pair/tht_diff_pair.dsn").unwrap();
+    let design = SpecctraDesign::load(BufReader::new(design_file)).unwrap();
+    let mut interactor = Interactor::new(design.make_board()).unwrap();
+    let unrouted_node_count = node_count(&interactor);
+    let selection: PinSelection =
+        serde_json::from_str(r#"{"selectors": [{"pin": "J1-1", "layer": "F.Cu"}]}"#).unwrap();
+    let options = common::autorouter_options();
+    let input = |x, y, is_pointer_clicked| InteractiveInput {
+        pointer_pos: point! {x: x, y: y},
+        is_pointer_clicked,
+        dt: 0.0,
+    };
+
+    // Aborting a track removes whatever was routed of it.
+    interactor.route_track(selection.clone(), options).unwrap();
+    assert!(interactor
+        .update(&input(5000.0, 3000.0, false))
+        .is_continue());
+    assert!(node_count(&interactor) > unrouted_node_count);
+    interactor.abort();
+    assert_eq!(node_count(&interactor), unrouted_node_count);
+
+    // The track follows the pointer and is laid down where it was last routed to on click.
+    interactor.route_track(selection, options).unwrap();
+    assert!(interactor
+        .update(&input(5000.0, 3000.0, false))
+        .is_continue());
+    assert!(interactor
+        .update(&input(8000.0, 4000.0, false))
+        .is_continue());
+    let routed_node_count = node_count(&interactor);
+    assert!(matches!(
+        interactor.update(&input(8000.0, 4000.0, true)),
+        ControlFlow::Break(Ok(()))
+    ));
+    assert_eq!(node_count(&interactor), routed_node_count);
+    assert!(matches!(
+        interactor.invoker().history().last_done().unwrap().command(),
+        Command::Pointroute(_, point, _) if *point == point! {x: 8000.0, y: 4000.0}
+    ));
+
+    // The track is undone like any other command.
+    interactor.undo().unwrap();
+    assert_eq!(node_count(&interactor), unrouted_node_count);
+    interactor.redo().unwrap();
+    assert_eq!(node_count(&interactor), routed_node_count);
+}
+
+fn node_count(interactor: &Interactor<SpecctraMesadata>) -> usize {
+    interactor
+        .invoker()
+        .autorouter()
+        .board()
+        .layout()
+        .drawing()
+        .node_count()
+}
+
+#[test]
+fn test_tht_diode_bridge_rectifier_move_component() {
+    let mut invoker = common::load_design_and_assert(
+        "tests/single_layer/tht_diode_bridge_rectifier/tht_diode_bridge_rectifier.dsn",
+    );
+    common::replay_and_assert(
+        &mut invoker,
+        "tests/single_layer/tht_diode_bridge_rectifier/autoroute_all.cmd",
+    );
+
+    let options = common::autorouter_options();
+    let component_positions = |invoker: &Invoker<SpecctraMesadata>| {
+        let board = invoker.autorouter().board();
+        let mut positions: Vec<(usize, Point)> = board
+            .component_nodes("J2")
+            .filter_map(|node| match node {
+                GenericNode::Primitive(primitive @ PrimitiveIndex::FixedDot(..)) => Some((
+                    primitive.petgraph_index().index(),
+                    primitive
+                        .primitive(board.layout().drawing())
+                        .shape()
+                        .center(),
+                )),
+                _ => None,
+            })
+            .collect();
+        positions.sort_by_key(|(index, _)| *index);
+        positions
+    };
+    let unmoved_positions = component_positions(&invoker);
+
+    let outcome = invoker
+        .execute(Command::MoveComponent(
+            "J2".to_string(),
+            point! {x: 2000.0, y: 0.0},
+            options,
+        ))
+        .unwrap();
+    let ExecutionOutcome::MoveComponent(report) = outcome else {
+        panic!("moving component yielded {:?}", outcome);
+    };
+
+    // The bands joined to the component follow it.
+    assert!(!report.routed_ratlines().is_empty());
+    assert!(report.failed_ratlines().is_empty());
+
+    for ((_, unmoved), (_, moved)) in unmoved_positions
+        .iter()
+        .zip(component_positions(&invoker).iter())
+    {
+        assert_eq!(*moved, *unmoved + point! {x: 2000.0, y: 0.0});
+    }
+
+    let (mut autorouter, history, ..) = invoker.dissolve();
+    common::assert_single_layer_groundless_autoroute(&mut autorouter, "F.Cu");
+
+    // Moving a component is undone like any other command.
+    let mut invoker = Invoker::new_with_history(autorouter, history);
+    invoker.undo().unwrap();
+    assert_eq!(component_positions(&invoker), unmoved_positions);
+
+    assert!(matches!(
+        invoker.execute(Command::MoveComponent(
+            "J3".to_string(),
+            point! {x: 2000.0, y: 0.0},
+            options,
+        )),
+        Err(InvokerError::Autorouter(AutorouterError::NoSuchComponent(
+            ..
+        )))
+    ));
+}